@@ -65,7 +65,7 @@ public:
         // 记录日志：只有当目标改进时写入 log
         if (obj < bestObj) {
             bestObj = obj;
-            log << elapsed << ": " << obj << std::endl;
+            log << elapsed << ": " << obj << " " << getBestObjValue() << std::endl;
         }
         // 如果提供了 pool 目录，则保存当前解
         if (!solutionPoolDir.empty()) {
@@ -118,6 +118,49 @@ void writePlainSolution(IloCplex& cplex, IloNumVarArray& vars, const std::string
     out.close();
 }
 
+// Runs the conflict refiner over all constraints and variable bounds and writes the
+// members of the conflict as the Gurobi wrapper writes an IIS: "row <constraint>",
+// "lb <variable>" or "ub <variable>" lines.
+void writeConflict(IloEnv env, IloCplex& cplex, IloModel& model, IloNumVarArray& vars, const std::string& iis_file) {
+    IloConstraintArray constraints(env);
+    std::vector<std::string> members;
+    for (IloModel::Iterator it(model); it.ok(); ++it) {
+        IloExtractable ext = *it;
+        if (ext.isConstraint()) {
+            const char* name = ext.getName();
+            constraints.add(ext.asConstraint());
+            members.push_back(std::string("row ") + (name ? name : "?"));
+        }
+    }
+    for (IloInt i = 0; i < vars.getSize(); i++) {
+        constraints.add(IloBound(vars[i], IloBound::Lower));
+        members.push_back(std::string("lb ") + vars[i].getName());
+        constraints.add(IloBound(vars[i], IloBound::Upper));
+        members.push_back(std::string("ub ") + vars[i].getName());
+    }
+    IloNumArray prefs(env, constraints.getSize());
+    for (IloInt i = 0; i < prefs.getSize(); i++) {
+        prefs[i] = 1.0;
+    }
+    if (!cplex.refineConflict(constraints, prefs)) {
+        std::cerr << "The conflict refiner found no conflict." << std::endl;
+        return;
+    }
+
+    IloCplex::ConflictStatusArray conflict = cplex.getConflict(constraints);
+    std::ofstream out(iis_file);
+    if (!out.is_open()) {
+        std::cerr << "Could not open file " << iis_file << " for writing the conflict." << std::endl;
+        return;
+    }
+    for (IloInt i = 0; i < constraints.getSize(); i++) {
+        if (conflict[i] == IloCplex::ConflictMember || conflict[i] == IloCplex::ConflictPossibleMember) {
+            out << members[i] << std::endl;
+        }
+    }
+    std::cout << "Conflict written to: " << iis_file << std::endl;
+}
+
 int main(int argc, char* argv[]) {
     try {
         // 解析命令行参数
@@ -133,7 +176,7 @@ int main(int argc, char* argv[]) {
         if (missingParams) {
             std::cerr << "Usage: " << argv[0] 
                       << " --lp_file <file> --output_file <file> --log_file <file> "
-                      << "[--mst_file <file>] [--time_limit <seconds>] [--solution_pool_dir <dir>]" 
+                      << "[--mst_file <file>] [--extra_mst_files <file,...>] [--time_limit <seconds>] [--solution_pool_dir <dir>] [--iis_file <file>]" 
                       << std::endl;
             return 1;
         }
@@ -144,6 +187,7 @@ int main(int argc, char* argv[]) {
         std::string mst_file = "";
         double time_limit = 1e+20;  // 默认无限制
         std::string solution_pool_dir = "";
+        std::string iis_file = params.count("iis_file") ? params["iis_file"] : "";
         
         if (params.find("mst_file") != params.end()) {
             mst_file = params["mst_file"];
@@ -252,32 +296,46 @@ int main(int argc, char* argv[]) {
         //     std::cout << "Loading Finished" << std::endl;
         // }
 
-        std::ifstream infile(mst_file);
-        if (!infile.is_open()) {
-            std::cerr << "Cannot open MIP start file: " << mst_file << std::endl;
-        } else {
-            IloNumVarArray startVars(env);
-            IloNumArray startVals(env);
-            std::string varName;
-            double val;
-            // 每行格式：变量名 初始值
-            while (infile >> varName >> val) {
-                auto it = varMap.find(varName);
-                if (it != varMap.end()) {
-                    startVars.add(it->second);
-                    startVals.add(val);
-                }
+        // The MST file first, then the extra starts, each as its own MIP start
+        std::vector<std::string> start_files;
+        if (!mst_file.empty()) {
+            start_files.push_back(mst_file);
+        }
+        if (params.find("extra_mst_files") != params.end()) {
+            std::stringstream files(params["extra_mst_files"]);
+            std::string file;
+            while (std::getline(files, file, ',')) {
+                start_files.push_back(file);
             }
-            infile.close();
-        
-            if (startVars.getSize() > 0) {
-                cplex.addMIPStart(startVars, startVals, IloCplex::MIPStartAuto);
-                std::cout << "MIP start loaded successfully with " << startVars.getSize() << " variables." << std::endl;
+        }
+        for (const std::string& start_file : start_files) {
+            std::ifstream infile(start_file);
+            if (!infile.is_open()) {
+                std::cerr << "Cannot open MIP start file: " << start_file << std::endl;
             } else {
-                std::cout << "No valid MIP start variables found in file." << mst_file << std::endl;
+                IloNumVarArray startVars(env);
+                IloNumArray startVals(env);
+                std::string varName;
+                double val;
+                // 每行格式：变量名 初始值
+                while (infile >> varName >> val) {
+                    auto it = varMap.find(varName);
+                    if (it != varMap.end()) {
+                        startVars.add(it->second);
+                        startVals.add(val);
+                    }
+                }
+                infile.close();
+        
+                if (startVars.getSize() > 0) {
+                    cplex.addMIPStart(startVars, startVals, IloCplex::MIPStartAuto);
+                    std::cout << "MIP start loaded successfully with " << startVars.getSize() << " variables." << std::endl;
+                } else {
+                    std::cout << "No valid MIP start variables found in file." << start_file << std::endl;
+                }
+                startVals.end();
+                startVars.end();
             }
-            startVals.end();
-            startVars.end();
         }
         
         
@@ -292,6 +350,9 @@ int main(int argc, char* argv[]) {
             std::cout << "Optimal solution found!" << std::endl;
         } else if (status == IloAlgorithm::Infeasible) {
             std::cout << "Model is infeasible." << std::endl;
+            if (!iis_file.empty()) {
+                writeConflict(env, cplex, model, vars, iis_file);
+            }
         } else if (status == IloAlgorithm::Unbounded) {
             std::cout << "Model is unbounded." << std::endl;
         } else {
@@ -319,6 +380,11 @@ int main(int argc, char* argv[]) {
         
         logStream.close();
         env.end();
+
+        // Same exit code as the Gurobi wrapper for an infeasible model.
+        if (status == IloAlgorithm::Infeasible || status == IloAlgorithm::InfeasibleOrUnbounded) {
+            return 2;
+        }
     }
     catch (IloException& e) {
         std::cerr << "Concert exception caught: " << e.getMessage() << std::endl;
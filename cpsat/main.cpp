@@ -323,7 +323,12 @@ int main(int argc, char* argv[]) {
     Model model;
     int num_solutions = 0;
     auto start = std::chrono::high_resolution_clock::now();
-    std::string log = "";
+    // Written as the incumbents come in, so that the log can be followed while solving.
+    std::ofstream log_fout(log_file);
+    if (!log_fout.is_open()) {
+      std::cerr << "Unable to open log file: " << log_file << std::endl;
+      return 1;
+    }
     model.Add(NewFeasibleSolutionObserver([&](const CpSolverResponse& r) {
         auto current_time = std::chrono::high_resolution_clock::now();
         double elapsed_seconds = std::chrono::duration_cast<std::chrono::duration<double>>(
@@ -331,7 +336,8 @@ int main(int argc, char* argv[]) {
         std::cout << "Incumbent Solution: " 
                         << elapsed_seconds 
                         << "s; objective: " << r.objective_value() << std::endl;
-        log += std::to_string(elapsed_seconds) + " " + std::to_string(r.objective_value()) + "\n";
+        log_fout << std::to_string(elapsed_seconds) << " " << std::to_string(r.objective_value())
+                 << " " << std::to_string(r.best_objective_bound()) << std::endl;
 
         // 如果指定了solution_pool目录，则保存当前解
         if (!solution_pool_dir.empty()) {
@@ -400,19 +406,15 @@ int main(int argc, char* argv[]) {
         }
         fout << output;
         fout.close();
-        // Save the log to the specified log file
-        std::cout << "Saving log to " << log_file << std::endl;
-        std::ofstream log_fout(log_file);
-        if (!log_fout.is_open()) {
-          std::cerr << "Unable to open log file: " << log_file << std::endl;
-          return 1;
-        }
-        log_fout << log;
         log_fout.close();
         std::cout << "Output saved." << std::endl;
         std::cout << "Objective: " << response.objective_value() << std::endl;
         std::cout << "Runtime:" << response.wall_time() << " s" << std::endl;
         std::cout << "Number of solutions found: " << num_solutions << std::endl;
+    } else if (response.status() == CpSolverStatus::INFEASIBLE) {
+        std::cout << "Model is infeasible." << std::endl;
+        // Exit code 2: infeasible model.
+        return 2;
     } else {
         std::cout << "No solution found." << std::endl;
     }
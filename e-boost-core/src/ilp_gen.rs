@@ -0,0 +1,790 @@
+use std::fs::File;
+use std::io::Write;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+use egraph_serialize::EGraph as SerializedEGraph;
+use egraph_serialize::{Class, ClassId};
+use egraph_serialize::NodeId;
+use extraction_gym::ExtractionResult;
+use rustc_hash::FxHashSet;
+use crate::ilp_model::{IlpModel, Sense, VarId, VarKind};
+use crate::warm_start::{self, Solver};
+
+/// How the ILP rules out cyclic selections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Acyclicity {
+    /// Level variables, with a big-M row per edge switched off through an Opp = 1 - N
+    /// variable when the node is not selected. Understood by every LP solver.
+    #[default]
+    BigM,
+    /// `N = 1 -> L_child - L_parent >= 1` indicator constraints: no Opp variables and
+    /// no big-M in the relaxation. Gurobi and CPLEX only.
+    Indicator,
+    /// The big-M rows, as lazy constraints the solver only adds once a candidate
+    /// violates them. Gurobi and CPLEX only.
+    Lazy,
+    /// No levels: the model is solved as is, and the cycles of its solution are cut
+    /// off (`add_cycle_cuts`) and the model solved again until the solution has none.
+    /// Understood by every LP solver, and the LP stays small when few cycles are cut.
+    Cuts,
+}
+
+impl Acyclicity {
+    /// Parses the `--acyclicity` values `bigM`, `indicator` and `lazy`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bigm" => Some(Acyclicity::BigM),
+            "indicator" => Some(Acyclicity::Indicator),
+            "lazy" => Some(Acyclicity::Lazy),
+            "cuts" => Some(Acyclicity::Cuts),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Acyclicity::BigM => "bigM",
+            Acyclicity::Indicator => "indicator",
+            Acyclicity::Lazy => "lazy",
+            Acyclicity::Cuts => "cuts",
+        }
+    }
+
+    /// Whether the model has level variables and LEVEL rows.
+    pub(crate) fn has_levels(&self) -> bool {
+        *self != Acyclicity::Cuts
+    }
+
+    /// Whether the LEVEL rows are switched off through Opp variables.
+    pub(crate) fn has_opp(&self) -> bool {
+        matches!(self, Acyclicity::BigM | Acyclicity::Lazy)
+    }
+}
+
+/// What the model holds besides the e-graph, shared by `build_model` and the
+/// functions writing its files.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ModelOptions<'a> {
+    /// Nodes that are fixed to 0, through their bounds.
+    pub pruned: Option<&'a [NodeId]>,
+    /// Choices that are already committed; their node is selected whenever the class is.
+    pub frozen: Option<&'a IndexMap<ClassId, NodeId>>,
+    pub acyclicity: Acyclicity,
+    /// With a weight, every op used by the selection adds it to the objective once, on
+    /// top of the node costs (`O_<k>` variables, see `build_model`).
+    pub op_weight: Option<f64>,
+}
+
+/// 对字符串进行简单处理，转换成只含字母数字和下划线的变量名
+pub(crate) fn sanitize(s: &ClassId) -> String {
+    s.to_string().chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// 辅助函数：获取某个候选节点的子类集合（封装 node.children 的逻辑）
+/// 这里假定 egraph 中每个节点都有 .children 字段，每个子节点可获得其 eclass。
+pub(crate) fn node_children_classes(egraph: &SerializedEGraph, node_id: &NodeId) -> IndexSet<ClassId> {
+    egraph[node_id].children.iter().cloned().collect()
+}
+
+/// 生成 ILP 文件（LP 格式），使得 CPLEX 或 Gurobi 能够调用该文件求解。
+/// 这里生成的模型与第一份代码（基于 coin‑cbc 的版本）功能完全等价。
+///
+/// 参数说明：
+/// - `egraph`: 输入的 e-graph 数据结构  
+/// - `roots`: 根 eclass 列表  
+/// - `file_path`: 要写入的 LP 文件路径
+/// - `options`: the pruned and frozen nodes, the acyclicity constraints and the op weight
+/// - `warm_start`: a selection to start from and the MST file to write it to, as values
+///   for every variable of the model. It is checked against the model first and not
+///   written if it violates it.
+///
+/// Returns the model, for the hint file.
+pub fn generate_ilp_file(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    file_path: &str,
+    options: ModelOptions,
+    warm_start: Option<(&ExtractionResult, &str)>,
+) -> IlpModel {
+    let mut file = File::create(file_path)
+        .expect("无法创建 ILP 文件");
+    let model = write_ilp(egraph, roots, &mut file, options, warm_start.map(|(start, _)| start))
+        .expect("写入 ILP 文件失败");
+    if let Some((_, mst_file)) = warm_start {
+        write_mst(&model, mst_file);
+    }
+    let symmetries = broken_symmetries(&model);
+    if symmetries > 0 {
+        println!("Symmetry breaking: {} duplicate nodes ruled out", symmetries);
+    }
+
+    println!("ILP 文件已生成：{}", file_path);
+    model
+}
+
+/// `generate_ilp_file` in free MPS format, for the solvers that don't read LP files.
+/// The variables have the same names, so the MST written with either file is a warm
+/// start of both.
+pub fn generate_mps_file(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    file_path: &str,
+    options: ModelOptions,
+    warm_start: Option<(&ExtractionResult, &str)>,
+) -> IlpModel {
+    let model = build_model(egraph, roots, options, warm_start.map(|(start, _)| start));
+    let name = std::path::Path::new(file_path).file_stem().map_or("extraction".into(), |stem| stem.to_string_lossy());
+    std::fs::write(file_path, model.to_mps(&name)).expect("写入 MPS 文件失败");
+    if let Some((_, mst_file)) = warm_start {
+        write_mst(&model, mst_file);
+    }
+    println!("MPS 文件已生成：{}", file_path);
+    model
+}
+
+/// `generate_ilp_file` without the filesystem: writes the LP to `out` (a `Vec<u8>`
+/// to keep it in memory) and returns the model. The warm start is `model.to_mst()`
+/// when `model.hint_violations()` is empty.
+pub fn write_ilp(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    out: &mut impl Write,
+    options: ModelOptions,
+    start: Option<&ExtractionResult>,
+) -> std::io::Result<IlpModel> {
+    let model = build_model(egraph, roots, options, start);
+    out.write_all(model.to_lp().as_bytes())?;
+    Ok(model)
+}
+
+/// Writes `model`, changed since `build_model` (by `add_cycle_cuts`, ...), as
+/// `generate_ilp_file` writes the model it builds.
+pub fn write_model_files(model: &IlpModel, file_path: &str, mst_file: Option<&str>) {
+    std::fs::write(file_path, model.to_lp()).expect("写入 ILP 文件失败");
+    if let Some(mst_file) = mst_file {
+        write_mst(model, mst_file);
+    }
+    println!("ILP 文件已生成：{}", file_path);
+}
+
+/// Most cycles `solution_cycles` returns, as the simple cycles of a selection may be
+/// exponentially many.
+const CYCLE_LIMIT: usize = 1000;
+
+/// The cycles of the selection `solution` makes from `roots`, each as the nodes
+/// selected along it. Empty for a valid extraction.
+pub fn solution_cycles(egraph: &SerializedEGraph, roots: &[ClassId], solution: &ExtractionResult) -> Vec<Vec<NodeId>> {
+    fn dfs(
+        egraph: &SerializedEGraph,
+        solution: &ExtractionResult,
+        cid: ClassId,
+        done: &mut FxHashSet<ClassId>,
+        stack: &mut Vec<ClassId>,
+        cycles: &mut Vec<Vec<NodeId>>,
+    ) {
+        if let Some(pos) = stack.iter().position(|on_stack| *on_stack == cid) {
+            cycles.push(stack[pos..].iter().map(|cid| solution.choices[cid]).collect());
+            return;
+        }
+        if done.contains(&cid) || cycles.len() >= CYCLE_LIMIT {
+            return;
+        }
+        // A class without choice is the extraction's problem, not a cycle.
+        let Some(node_id) = solution.choices.get(&cid) else {
+            return;
+        };
+        stack.push(cid);
+        for child in node_children_classes(egraph, node_id) {
+            dfs(egraph, solution, child, done, stack, cycles);
+        }
+        stack.pop();
+        done.insert(cid);
+    }
+
+    let mut done = FxHashSet::default();
+    let mut cycles = Vec::new();
+    for root in roots {
+        dfs(egraph, solution, *root, &mut done, &mut Vec::new(), &mut cycles);
+    }
+    cycles
+}
+
+/// `Acyclicity::Cuts`: rules out the `cycles` (from `solution_cycles`) by allowing at
+/// most all but one of the nodes of each, `CYCLE_<k>` rows numbered on from the cuts
+/// the model has. The hints of an acyclic start still satisfy them.
+pub fn add_cycle_cuts(model: &mut IlpModel, cycles: &[Vec<NodeId>]) {
+    let node_vars: IndexMap<&str, VarId> = model
+        .vars
+        .iter()
+        .enumerate()
+        .filter(|(_, var)| var.name.starts_with("N_"))
+        .map(|(i, var)| (var.name.as_str(), VarId(i)))
+        .collect();
+    let mut cuts = Vec::new();
+    for cycle in cycles {
+        let terms: Vec<(VarId, f64)> = cycle
+            .iter()
+            .map(|node_id| (node_vars[format!("N_{}_{}", node_id.0[0], node_id.0[1]).as_str()], 1.0))
+            .collect();
+        cuts.push((terms, (cycle.len() - 1) as f64));
+    }
+    let first = model.constraints.iter().filter(|constraint| constraint.name.starts_with("CYCLE_")).count();
+    for (k, (terms, rhs)) in cuts.into_iter().enumerate() {
+        model.add_constraint(format!("CYCLE_{}", first + k), terms, Sense::Le, rhs);
+    }
+}
+
+/// Writes the hints of `model` to `mst_file` if they satisfy it. Returns whether they did.
+fn write_mst(model: &IlpModel, mst_file: &str) -> bool {
+    let violations = model.hint_violations();
+    if violations.is_empty() {
+        warm_start::write_start(model, Solver::Gurobi, mst_file).expect("Unable to write file");
+        println!("MST file successfully generated at: {}", mst_file);
+        return true;
+    }
+    eprintln!(
+        "Warning: the warm start violates {} constraints or bounds of the model ({}), solving without it",
+        violations.len(),
+        violations.iter().take(5).cloned().collect::<Vec<_>>().join(", ")
+    );
+    // Don't let the solver pick up the start of an earlier run.
+    let _ = std::fs::remove_file(mst_file);
+    false
+}
+
+/// Writes `start` as a MIP start of the model `generate_ilp_file` builds from the same
+/// parameters, without writing the LP again. Returns whether the start satisfies the
+/// model, it is not written otherwise.
+pub fn generate_mst_file(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    mst_file: &str,
+    options: ModelOptions,
+    start: &ExtractionResult,
+) -> bool {
+    write_mst(&build_model(egraph, roots, options, Some(start)), mst_file)
+}
+
+/// Same model as `generate_ilp_file`, written in lp_solve's LP format, which cannot
+/// express `Acyclicity::Indicator`.
+pub fn generate_lpsolve_file(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    file_path: &str,
+    options: ModelOptions,
+) {
+    let model = build_model(egraph, roots, options, None);
+    std::fs::write(file_path, model.to_lpsolve()).expect("写入 lp_solve 文件失败");
+    println!("lp_solve 文件已生成：{}", file_path);
+}
+
+/// Builds the extraction ILP of `egraph`, see `generate_ilp_file` for the parameters.
+/// With a `start`, every variable gets the value it has in that selection as its hint.
+pub fn build_model(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    options: ModelOptions,
+    start: Option<&ExtractionResult>,
+) -> IlpModel {
+    let ModelOptions { pruned, frozen, acyclicity, op_weight } = options;
+    let mut model = IlpModel::default();
+
+    // Classes the roots cannot reach can never be selected and are left out of the model.
+    let reachable = extraction_gym::egraph_reachable_classes(egraph, roots);
+    let classes: Vec<&Class> = egraph.classes().values().filter(|class| reachable.contains(&class.id)).collect();
+    println!("ILP over {} of {} classes reachable from the roots", classes.len(), egraph.classes().len());
+
+    // ============================================
+    // 1. 为每个 eclass 和其中每个节点生成变量
+    // ============================================
+
+    // 每个 eclass 对应一个“激活变量”：A_<class_id>
+    let mut class_active_vars: IndexMap<ClassId, VarId> = IndexMap::new();
+    // 每个 eclass 中每个候选节点对应一个二进制变量：N_<class_id>_<node_index>
+    let mut node_vars: IndexMap<(ClassId, u32), VarId> = IndexMap::new();
+    // 有子类在同一强连通分量中的候选节点还需要一个“opposite”变量，用来配合层级约束：Opp_<class_id>_<node_index>
+    let mut opposite_vars: IndexMap<(ClassId, u32), VarId> = IndexMap::new();
+    // 为 block_cycles 部分，非平凡强连通分量中的每个 eclass 还需要一个“层级变量”：L_<class_id>
+    let mut level_vars: IndexMap<ClassId, VarId> = IndexMap::new();
+
+    // Apart from self loops (handled by SELF_LOOP), a cycle only runs through classes of
+    // a strongly connected component of the class graph with several classes. Most
+    // classes are in none, and get neither levels nor Opp variables.
+    let component_size = component_sizes(egraph);
+    let cyclic_children = |cid: ClassId, node_id: &NodeId| cyclic_children(egraph, &component_size, cid, node_id);
+
+    for class in &classes {
+        let cid = class.id;
+        class_active_vars.insert(cid, model.add_binary(format!("A_{}", sanitize(&cid))));
+    }
+    for class in &classes {
+        let cid = class.id;
+        for node_id in &class.nodes {
+            let nid = node_id.0;
+            assert!(nid[0] == cid.0);
+            node_vars.insert((cid, nid[1]), model.add_binary(format!("N_{}_{}", nid[0], nid[1])));
+        }
+    }
+    for class in &classes {
+        let cid = class.id;
+        for node_id in &class.nodes {
+            let nid = node_id.0;
+            if acyclicity.has_opp() && !cyclic_children(cid, node_id).is_empty() {
+                opposite_vars.insert((cid, nid[1]), model.add_binary(format!("Opp_{}_{}", nid[0], nid[1])));
+            }
+        }
+    }
+    // 层级变量的下界为 0，上界为所在强连通分量的 eclass 数减一：numbering the classes of
+    // a component in a topological order of any acyclic selection satisfies the LEVEL rows
+    for class in &classes {
+        let cid = class.id;
+        let size = component_size[&cid].1;
+        if size > 1 && acyclicity.has_levels() {
+            let l_var = model.add_var(format!("L_{}", sanitize(&cid)), VarKind::Continuous, 0.0, (size - 1) as f64);
+            level_vars.insert(cid, l_var);
+        }
+    }
+
+    // ============================================
+    // 2. 目标函数（Minimize）
+    // ============================================
+    for class in &classes {
+        let cid = class.id;
+        for node_id in &class.nodes {
+            let nid = node_id.0;
+            let cost = egraph[node_id].cost.into_inner();
+            // 只对非零成本项计入目标函数
+            if cost != 0.0 {
+                model.objective.push((node_vars[&(cid, nid[1])], cost));
+            }
+        }
+    }
+    // Distinct ops: O_<k> is 1 when a node with the k-th op (in class order) is
+    // selected, through N - O_<k> <= 0, and costs op_weight
+    let mut op_vars: IndexMap<&str, VarId> = IndexMap::new();
+    if let Some(weight) = op_weight {
+        for class in &classes {
+            for node_id in &class.nodes {
+                let nid = node_id.0;
+                let op = egraph[node_id].op.as_str();
+                let k = op_vars.len();
+                let op_var = *op_vars.entry(op).or_insert_with(|| model.add_binary(format!("O_{}", k)));
+                model.add_constraint(
+                    format!("USES_OP_{}_{}", nid[0], nid[1]),
+                    vec![(node_vars[&(class.id, nid[1])], 1.0), (op_var, -1.0)],
+                    Sense::Le,
+                    0.0,
+                );
+            }
+        }
+        model.objective.extend(op_vars.values().map(|&op_var| (op_var, weight)));
+    }
+
+    // ============================================
+    // 3. 约束（Subject To）
+    // ============================================
+
+    // 3.1 每个 eclass 必须满足：其所有候选节点之和等于该类激活变量
+    // 写成： N_i + N_j + ... - A_class = 0
+    for class in &classes {
+        let cid = class.id;
+        let mut terms: Vec<(VarId, f64)> = class
+            .nodes
+            .iter()
+            .map(|node_id| (node_vars[&(cid, node_id.0[1])], 1.0))
+            .collect();
+        terms.push((class_active_vars[&cid], -1.0));
+        model.add_constraint(format!("C_ACT_{}", sanitize(&cid)), terms, Sense::Eq, 0.0);
+    }
+
+    // 3.2 每个候选节点的激活必须“传递”到其子节点所在的 eclass：
+    // 对于每个候选节点，对于它所有子节点所属的 eclass，
+    // 添加约束： N_<class>_<i> - A_<child_class> <= 0
+    for class in &classes {
+        let cid = class.id;
+        for node_id in &class.nodes {
+            let nid = node_id.0;
+            let node_var = node_vars[&(cid, nid[1])];
+            // 收集当前候选节点所有子节点所在的 eclass（去重）
+            let child_classes: IndexSet<ClassId> = egraph[node_id].children.iter().cloned().collect();
+            for child_cid in child_classes {
+                model.add_constraint(
+                    format!("NODE_CHILD_{}_{}_{}", nid[0], nid[1], sanitize(&child_cid)),
+                    vec![(node_var, 1.0), (class_active_vars[&child_cid], -1.0)],
+                    Sense::Le,
+                    0.0,
+                );
+            }
+        }
+    }
+
+    // 3.3 对于每个根 eclass，要求其激活变量下界为 1
+    for root in roots {
+        model.add_constraint(format!("ROOT_{}", sanitize(root)), vec![(class_active_vars[root], 1.0)], Sense::Ge, 1.0);
+    }
+
+    // 3.4 额外的交集约束：若一个类的所有候选节点共有一部分子类，则该部分子类必须激活。
+    for class in &classes {
+        let cid = class.id;
+        if class.nodes.is_empty() { continue; }
+        // 先取第一个候选节点的子类集合作为初始交集
+        let mut intersection = node_children_classes(egraph, &class.nodes[0]);
+        for node_id in &class.nodes[1..] {
+            let child_set = node_children_classes(egraph, node_id);
+            intersection = intersection.intersection(&child_set).cloned().collect();
+        }
+        for child_cid in intersection {
+            model.add_constraint(
+                format!("INTERSECT_{}_{}", sanitize(&cid), sanitize(&child_cid)),
+                vec![(class_active_vars[&cid], 1.0), (class_active_vars[&child_cid], -1.0)],
+                Sense::Le,
+                0.0,
+            );
+        }
+    }
+
+    // 3.5 防止环路的约束（block_cycles 部分）
+    // 3.5.1 对于每个有 Opp 变量的候选节点，添加： N + Opp = 1
+    for (&(cid, nid), &opp_var) in &opposite_vars {
+        model.add_constraint(
+            format!("OPP_{}_{}", cid.0, nid),
+            vec![(node_vars[&(cid, nid)], 1.0), (opp_var, 1.0)],
+            Sense::Eq,
+            1.0,
+        );
+    }
+    // 3.5.2 如果候选节点出现自环（其子集中包含本类），则直接使该节点变量取 0
+    for class in &classes {
+        let cid = class.id;
+        for node_id in &class.nodes {
+            let nid = node_id.0;
+            if egraph[node_id].children.contains(&cid) {
+                model.add_constraint(
+                    format!("SELF_LOOP_{}_{}", nid[0], nid[1]),
+                    vec![(node_vars[&(cid, nid[1])], 1.0)],
+                    Sense::Eq,
+                    0.0,
+                );
+            }
+        }
+    }
+    // 3.5.3 对于每个候选节点和其每个同一强连通分量中的非自环子类，添加层级约束：
+    // -L_parent + L_child + M * Opp >= 1 (Acyclicity::Indicator: N = 1 -> L_child - L_parent >= 1)
+    // M is the smallest coefficient that leaves the row slack for any levels within the
+    // bounds when the node is not selected: upper(L_parent) - lower(L_child) + 1, i.e.
+    // the size of the component.
+    for class in &classes {
+        let cid = class.id;
+        let m_const = component_size[&cid].1 as f64;
+        for node_id in &class.nodes {
+            let nid = node_id.0;
+            let child_classes = cyclic_children(cid, node_id);
+            if child_classes.is_empty() || !acyclicity.has_levels() {
+                continue;
+            }
+            let level_var = level_vars[&cid];
+            for child_cid in child_classes {
+                let name = format!("LEVEL_{}_{}_{}", nid[0], nid[1], sanitize(&child_cid));
+                let levels = vec![(level_vars[&child_cid], 1.0), (level_var, -1.0)];
+                match acyclicity {
+                    Acyclicity::Indicator => {
+                        model.add_indicator(name, node_vars[&(cid, nid[1])], 1.0, levels, Sense::Ge, 1.0);
+                    }
+                    Acyclicity::BigM | Acyclicity::Lazy | Acyclicity::Cuts => {
+                        let mut terms = levels;
+                        terms.push((opposite_vars[&(cid, nid[1])], m_const));
+                        if acyclicity == Acyclicity::Lazy {
+                            model.add_lazy_constraint(name, terms, Sense::Ge, 1.0);
+                        } else {
+                            model.add_constraint(name, terms, Sense::Ge, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Pruned nodes: fixed to 0 by their bounds rather than by a row each, which presolve
+    // would only turn back into a fixing
+    if let Some(pruned) = pruned {
+        for node_id in pruned.iter().filter(|node_id| reachable.contains(egraph.nid_to_cid(node_id))) {
+            model.fix(node_vars[&(egraph[node_id].eclass, node_id.0[1])], 0.0);
+        }
+    }
+
+    // Frozen choices: N_<frozen node> - A_<class> = 0
+    if let Some(frozen) = frozen {
+        for (cid, node_id) in frozen.iter().filter(|(cid, _)| reachable.contains(*cid)) {
+            model.add_constraint(
+                format!("FROZEN_{}", sanitize(cid)),
+                vec![(node_vars[&(*cid, node_id.0[1])], 1.0), (class_active_vars[cid], -1.0)],
+                Sense::Eq,
+                0.0,
+            );
+        }
+    }
+
+    // Symmetry breaking: nodes of a class with the same op, children and cost are
+    // interchangeable, so every solution using one of them has twins the solver would
+    // branch on as well. Chain each group with N_prev - N_next >= 0: as a class selects
+    // at most one node, this leaves only the first node of the group selectable. The
+    // first node is the frozen one if any, else the one the start selects, else one that
+    // is not pruned.
+    let selected: FxHashSet<NodeId> = start.map(|start| start.activate_nodes(egraph, roots)).unwrap_or_default();
+    let pruned: IndexSet<&NodeId> = pruned.into_iter().flatten().collect();
+    for class in &classes {
+        let cid = class.id;
+        let mut groups: IndexMap<(&str, &[ClassId], u64), Vec<&NodeId>> = IndexMap::new();
+        for node_id in &class.nodes {
+            let node = &egraph[node_id];
+            let key = (node.op.as_str(), node.children.as_slice(), node.cost.into_inner().to_bits());
+            groups.entry(key).or_default().push(node_id);
+        }
+        for mut group in groups.into_values().filter(|group| group.len() > 1) {
+            let frozen_node = frozen.and_then(|frozen| frozen.get(&cid));
+            let first = group
+                .iter()
+                .position(|node_id| Some(*node_id) == frozen_node)
+                .or_else(|| group.iter().position(|node_id| selected.contains(*node_id)))
+                .or_else(|| group.iter().position(|node_id| !pruned.contains(node_id)))
+                .unwrap_or(0);
+            group.swap(0, first);
+            for pair in group.windows(2) {
+                let (prev, next) = (pair[0].0, pair[1].0);
+                model.add_constraint(
+                    format!("SYM_{}_{}", next[0], next[1]),
+                    vec![(node_vars[&(cid, prev[1])], 1.0), (node_vars[&(cid, next[1])], -1.0)],
+                    Sense::Ge,
+                    0.0,
+                );
+            }
+        }
+    }
+
+    // Hints: the start as a complete assignment. A class is active iff its chosen node is
+    // selected, Opp = 1 - N, and the levels number the selected classes of each component
+    // in a topological order of the selection (Kahn's algorithm over the selected edges
+    // that stay within a component).
+    if start.is_some() {
+        for &var in class_active_vars.values() {
+            model.set_hint(var, 0.0);
+        }
+        for (&(cid, nid), &var) in &node_vars {
+            let is_selected = selected.contains(&NodeId([cid.0, nid]));
+            model.set_hint(var, if is_selected { 1.0 } else { 0.0 });
+            if is_selected {
+                model.set_hint(class_active_vars[&cid], 1.0);
+            }
+        }
+        for (&(cid, nid), &var) in &opposite_vars {
+            let is_selected = selected.contains(&NodeId([cid.0, nid]));
+            model.set_hint(var, if is_selected { 0.0 } else { 1.0 });
+        }
+        let selected_ops: FxHashSet<&str> = selected.iter().map(|node_id| egraph[node_id].op.as_str()).collect();
+        for (op, &var) in &op_vars {
+            model.set_hint(var, if selected_ops.contains(op) { 1.0 } else { 0.0 });
+        }
+
+        let cyclic_selected: Vec<(ClassId, &NodeId)> = selected
+            .iter()
+            .map(|node_id| (*egraph.nid_to_cid(node_id), node_id))
+            .filter(|(cid, _)| level_vars.contains_key(cid))
+            .collect();
+        let mut children: IndexMap<ClassId, IndexSet<ClassId>> = IndexMap::new();
+        let mut indegree: IndexMap<ClassId, usize> = cyclic_selected.iter().map(|(cid, _)| (*cid, 0)).collect();
+        for (cid, node_id) in &cyclic_selected {
+            let node_children = cyclic_children(*cid, node_id);
+            for child in &node_children {
+                if let Some(degree) = indegree.get_mut(child) {
+                    *degree += 1;
+                }
+            }
+            children.insert(*cid, node_children);
+        }
+        let mut ready: Vec<ClassId> = indegree.iter().filter(|(_, degree)| **degree == 0).map(|(cid, _)| *cid).collect();
+        let mut next_level: IndexMap<usize, usize> = IndexMap::new();
+        while let Some(cid) = ready.pop() {
+            let level = next_level.entry(component_size[&cid].0).or_insert(0);
+            model.set_hint(level_vars[&cid], *level as f64);
+            *level += 1;
+            for child in &children[&cid] {
+                if let Some(degree) = indegree.get_mut(child) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push(*child);
+                    }
+                }
+            }
+        }
+        // Unselected classes, and the classes of a cyclic start, which `hint_violations` reports.
+        for &var in level_vars.values() {
+            if model.var(var).hint.is_none() {
+                model.set_hint(var, 0.0);
+            }
+        }
+    }
+
+    model
+}
+
+/// The strongly connected component of every class of the class graph, by index,
+/// and its number of classes.
+pub(crate) fn component_sizes(egraph: &SerializedEGraph) -> IndexMap<ClassId, (usize, usize)> {
+    let mut component_size = IndexMap::new();
+    for (i, scc) in class_sccs(egraph).into_iter().enumerate() {
+        for cid in &scc {
+            component_size.insert(*cid, (i, scc.len()));
+        }
+    }
+    component_size
+}
+
+/// The children of a node of class `cid` that may close a cycle with it: the other
+/// classes of its component.
+pub(crate) fn cyclic_children(
+    egraph: &SerializedEGraph,
+    component_size: &IndexMap<ClassId, (usize, usize)>,
+    cid: ClassId,
+    node_id: &NodeId,
+) -> IndexSet<ClassId> {
+    let (component, size) = component_size[&cid];
+    if size == 1 {
+        return IndexSet::new();
+    }
+    egraph[node_id]
+        .children
+        .iter()
+        .filter(|child| **child != cid && component_size[*child].0 == component)
+        .cloned()
+        .collect()
+}
+
+/// Strongly connected components of the class graph (an edge from every class to
+/// the children of its nodes), in the order of `sccs`.
+fn class_sccs(egraph: &SerializedEGraph) -> Vec<Vec<ClassId>> {
+    let classes = egraph.classes();
+    let successors: Vec<Vec<usize>> = classes
+        .values()
+        .map(|class| {
+            let mut children: Vec<usize> = class
+                .nodes
+                .iter()
+                .flat_map(|node_id| egraph[node_id].children.iter())
+                .map(|child| classes.get_index_of(child).unwrap())
+                .collect();
+            children.sort_unstable();
+            children.dedup();
+            children
+        })
+        .collect();
+    sccs(&successors)
+        .into_iter()
+        .map(|scc| scc.into_iter().map(|c| *classes.get_index(c).unwrap().0).collect())
+        .collect()
+}
+
+/// Strongly connected components of the graph of `successors`, found with
+/// Tarjan's algorithm and listed so that every edge between two components goes
+/// from an earlier to a later one.
+pub(crate) fn sccs(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = successors.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut sccs = Vec::new();
+    for start in 0..n {
+        if index[start] != UNVISITED {
+            continue;
+        }
+        // Explicit call stack of (class, next successor to visit): e-graphs are deep
+        // enough to overflow the thread stack with recursion.
+        let mut calls = vec![(start, 0)];
+        index[start] = next_index;
+        low[start] = next_index;
+        next_index += 1;
+        stack.push(start);
+        on_stack[start] = true;
+        while let Some((v, next)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&w) = successors[v].get(*next) {
+                *next += 1;
+                if index[w] == UNVISITED {
+                    index[w] = next_index;
+                    low[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut scc = Vec::new();
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    scc.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                sccs.push(scc);
+            }
+        }
+    }
+    // Tarjan finishes a component after everything reachable from it.
+    sccs.reverse();
+    sccs
+}
+
+/// Number of duplicate nodes `build_model` ruled out by symmetry breaking.
+fn broken_symmetries(model: &IlpModel) -> usize {
+    model.constraints.iter().filter(|constraint| constraint.name.starts_with("SYM_")).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egraph_serialize::{Data, Node};
+    use ordered_float::NotNan;
+
+    /// Root class 0 is `x` or `f(1)`, class 1 is `g(0)`.
+    fn loop_egraph() -> SerializedEGraph {
+        let mut nodes = IndexMap::new();
+        for (class, node, op, children) in [(0, 0, "f", vec![1]), (0, 1, "x", vec![]), (1, 0, "g", vec![0])] {
+            let id = NodeId::from((class, node));
+            let children = children.into_iter().map(ClassId::from).collect();
+            nodes.insert(id, Node { op: op.to_string(), id, children, eclass: ClassId::from(class), cost: NotNan::new(1.0).unwrap(), area: None });
+        }
+        SerializedEGraph::from_Data(&Data { nodes, root_eclasses: vec![ClassId::from(0)], class_data: IndexMap::new() }).unwrap()
+    }
+
+    #[test]
+    fn test_cycle_cuts() {
+        let egraph = loop_egraph();
+        let roots = egraph.root_eclasses.clone();
+        let choices = |nodes: &[(u32, u32)]| ExtractionResult::new(nodes.iter().map(|&(class, node)| (ClassId::from(class), NodeId::from((class, node)))).collect());
+        let cycles = solution_cycles(&egraph, &roots, &choices(&[(0, 0), (1, 0)]));
+        assert_eq!(cycles, [vec![NodeId::from((0, 0)), NodeId::from((1, 0))]]);
+        assert!(solution_cycles(&egraph, &roots, &choices(&[(0, 1), (1, 0)])).is_empty());
+
+        let mut model = build_model(&egraph, &roots, ModelOptions { acyclicity: Acyclicity::Cuts, ..Default::default() }, None);
+        assert!(!model.to_lp().contains("L_"));
+        add_cycle_cuts(&mut model, &cycles);
+        add_cycle_cuts(&mut model, &cycles);
+        let cuts: Vec<_> = model.constraints.iter().filter(|constraint| constraint.name.starts_with("CYCLE_")).collect();
+        assert_eq!(cuts.iter().map(|cut| cut.name.as_str()).collect::<Vec<_>>(), ["CYCLE_0", "CYCLE_1"]);
+        assert_eq!((cuts[0].terms.len(), cuts[0].sense, cuts[0].rhs), (2, Sense::Le, 1.0));
+    }
+}
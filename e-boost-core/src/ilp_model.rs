@@ -0,0 +1,420 @@
+// Solver-independent representation of the extraction ILP.
+//
+// `ilp_gen` walks the e-graph once to build an `IlpModel` (variables with their
+// bounds and integrality, named linear constraints, the objective and optional
+// solution hints); every output format is then written from the model, so all
+// of them encode exactly the same problem.
+
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VarId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VarKind {
+    Binary,
+    Integer,
+    Continuous,
+}
+
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub kind: VarKind,
+    pub lower: f64,
+    pub upper: f64,
+    /// Value suggested to the solver as a starting point.
+    pub hint: Option<f64>,
+}
+
+impl Variable {
+    /// A binary with its [0, 1] bounds, which the LP formats leave implicit.
+    fn is_plain_binary(&self) -> bool {
+        self.kind == VarKind::Binary && self.lower == 0.0 && self.upper == 1.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sense {
+    Le,
+    Ge,
+    Eq,
+}
+
+impl Sense {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Sense::Le => "<=",
+            Sense::Ge => ">=",
+            Sense::Eq => "=",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Constraint {
+    pub name: String,
+    pub terms: Vec<(VarId, f64)>,
+    pub sense: Sense,
+    pub rhs: f64,
+    /// Only enforced when the variable takes the value (an indicator constraint).
+    pub indicator: Option<(VarId, f64)>,
+    /// May be left out by the solver until a candidate solution violates it.
+    pub lazy: bool,
+}
+
+/// A minimization problem over `vars`.
+#[derive(Clone, Debug, Default)]
+pub struct IlpModel {
+    pub vars: Vec<Variable>,
+    pub constraints: Vec<Constraint>,
+    pub objective: Vec<(VarId, f64)>,
+}
+
+impl IlpModel {
+    pub fn add_binary(&mut self, name: String) -> VarId {
+        self.add_var(name, VarKind::Binary, 0.0, 1.0)
+    }
+
+    pub fn add_var(&mut self, name: String, kind: VarKind, lower: f64, upper: f64) -> VarId {
+        self.vars.push(Variable { name, kind, lower, upper, hint: None });
+        VarId(self.vars.len() - 1)
+    }
+
+    pub fn add_constraint(&mut self, name: String, terms: Vec<(VarId, f64)>, sense: Sense, rhs: f64) {
+        self.constraints.push(Constraint { name, terms, sense, rhs, indicator: None, lazy: false });
+    }
+
+    /// `var = value -> terms sense rhs`; needs a solver with indicator constraints
+    /// (Gurobi, CPLEX).
+    pub fn add_indicator(&mut self, name: String, var: VarId, value: f64, terms: Vec<(VarId, f64)>, sense: Sense, rhs: f64) {
+        self.constraints.push(Constraint { name, terms, sense, rhs, indicator: Some((var, value)), lazy: false });
+    }
+
+    pub fn add_lazy_constraint(&mut self, name: String, terms: Vec<(VarId, f64)>, sense: Sense, rhs: f64) {
+        self.constraints.push(Constraint { name, terms, sense, rhs, indicator: None, lazy: true });
+    }
+
+    /// Fixes `var` to `value` through its bounds.
+    pub fn fix(&mut self, var: VarId, value: f64) {
+        let var = &mut self.vars[var.0];
+        var.lower = value;
+        var.upper = value;
+    }
+
+    pub fn set_hint(&mut self, var: VarId, value: f64) {
+        self.vars[var.0].hint = Some(value);
+    }
+
+    pub fn var(&self, var: VarId) -> &Variable {
+        &self.vars[var.0]
+    }
+
+    /// The bounds (by variable name) and constraints (by name) that the hints violate,
+    /// or the variables without a hint. Indicator constraints only count when their
+    /// condition holds.
+    pub fn hint_violations(&self) -> Vec<String> {
+        const TOLERANCE: f64 = 1e-6;
+        let mut violations = Vec::new();
+        for var in &self.vars {
+            match var.hint {
+                None => violations.push(format!("{} (no value)", var.name)),
+                Some(value) if value < var.lower - TOLERANCE || value > var.upper + TOLERANCE => {
+                    violations.push(var.name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        if !violations.is_empty() {
+            return violations;
+        }
+        let value = |var: VarId| self.var(var).hint.unwrap();
+        for constraint in &self.constraints {
+            if let Some((var, condition)) = constraint.indicator {
+                if (value(var) - condition).abs() > TOLERANCE {
+                    continue;
+                }
+            }
+            let lhs: f64 = constraint.terms.iter().map(|(var, coef)| coef * value(*var)).sum();
+            let satisfied = match constraint.sense {
+                Sense::Le => lhs <= constraint.rhs + TOLERANCE,
+                Sense::Ge => lhs >= constraint.rhs - TOLERANCE,
+                Sense::Eq => (lhs - constraint.rhs).abs() <= TOLERANCE,
+            };
+            if !satisfied {
+                violations.push(constraint.name.clone());
+            }
+        }
+        violations
+    }
+
+    /// The hints as a MIP start in Gurobi's MST format (`<name> <value>` lines), which
+    /// the CPLEX wrapper is fed as well. See `warm_start` for the other formats.
+    pub fn to_mst(&self) -> String {
+        crate::warm_start::to_start(self, crate::warm_start::Solver::Gurobi)
+    }
+
+    /// The model in CPLEX LP format, as read by Gurobi, CPLEX and most other solvers.
+    pub fn to_lp(&self) -> String {
+        let mut lp = String::new();
+        lp.push_str("Minimize\n obj: ");
+        let obj_terms: Vec<String> = self
+            .objective
+            .iter()
+            .filter(|(_, coef)| *coef != 0.0)
+            .map(|(var, coef)| {
+                let name = &self.var(*var).name;
+                if *coef == 1.0 {
+                    name.clone()
+                } else {
+                    format!("{} {}", coef, name)
+                }
+            })
+            .collect();
+        lp.push_str(&obj_terms.join(" + "));
+        lp.push_str("\n\n");
+
+        lp.push_str("Subject To\n");
+        for constraint in self.constraints.iter().filter(|constraint| !constraint.lazy) {
+            self.write_lp_constraint(&mut lp, constraint);
+        }
+        if self.constraints.iter().any(|constraint| constraint.lazy) {
+            lp.push_str("\nLazy Constraints\n");
+            for constraint in self.constraints.iter().filter(|constraint| constraint.lazy) {
+                self.write_lp_constraint(&mut lp, constraint);
+            }
+        }
+
+        lp.push_str("\nBounds\n");
+        for var in self.vars.iter().filter(|var| !var.is_plain_binary()) {
+            if var.lower == var.upper {
+                writeln!(lp, "{} = {}", var.name, var.lower).unwrap();
+            } else {
+                writeln!(lp, "{} <= {} <= {}", var.lower, var.name, var.upper).unwrap();
+            }
+        }
+
+        // Readers disagree on whether declaring a variable binary resets its bounds, so
+        // binaries with other bounds (fixed ones) are declared as general integers.
+        let generals: Vec<&Variable> = self
+            .vars
+            .iter()
+            .filter(|var| var.kind == VarKind::Integer || (var.kind == VarKind::Binary && !var.is_plain_binary()))
+            .collect();
+        if !generals.is_empty() {
+            lp.push_str("\nGenerals\n");
+            for var in generals {
+                writeln!(lp, "{}", var.name).unwrap();
+            }
+        }
+
+        lp.push_str("\nBinaries\n");
+        for var in self.vars.iter().filter(|var| var.is_plain_binary()) {
+            writeln!(lp, "{}", var.name).unwrap();
+        }
+
+        lp.push_str("\nEnd\n");
+        lp
+    }
+
+    fn write_lp_constraint(&self, lp: &mut String, constraint: &Constraint) {
+        let name = |var: VarId| self.var(var).name.as_str();
+        let indicator = constraint.indicator.map(|(var, value)| (name(var), value));
+        let terms = constraint.terms.iter().map(|(var, coef)| (name(*var), *coef));
+        write_lp_row(lp, &constraint.name, indicator, terms, constraint.sense, constraint.rhs);
+    }
+
+    /// The model in lp_solve's LP format. lp_solve has no lazy constraints, they are
+    /// written as ordinary ones, nor indicator constraints, which panic.
+    pub fn to_lpsolve(&self) -> String {
+        let mut lp = String::new();
+        lp.push_str("/* Objective function */\nmin: ");
+        let objective: Vec<(VarId, f64)> = self.objective.iter().filter(|(_, coef)| *coef != 0.0).copied().collect();
+        self.write_lp_terms(&mut lp, &objective);
+        lp.push_str(";\n\n/* Constraints */\n");
+        for constraint in &self.constraints {
+            assert!(constraint.indicator.is_none(), "lp_solve's LP format has no indicator constraints");
+            // Labelled, so that single-variable rows stay constraints instead of becoming bounds.
+            write!(lp, "{}: ", constraint.name).unwrap();
+            self.write_lp_terms(&mut lp, &constraint.terms);
+            writeln!(lp, " {} {};", constraint.sense.as_str(), constraint.rhs).unwrap();
+        }
+
+        lp.push_str("\n/* Bounds */\n");
+        for var in self.vars.iter().filter(|var| !var.is_plain_binary()) {
+            writeln!(lp, "{} <= {} <= {};", var.lower, var.name, var.upper).unwrap();
+        }
+
+        // `bin` resets the bounds to [0, 1], fixed binaries are declared `int`.
+        let declarations = [
+            ("int", self.vars.iter().filter(|var| var.kind != VarKind::Continuous && !var.is_plain_binary()).collect::<Vec<_>>()),
+            ("bin", self.vars.iter().filter(|var| var.is_plain_binary()).collect()),
+        ];
+        for (keyword, vars) in declarations {
+            if !vars.is_empty() {
+                let names: Vec<&str> = vars.iter().map(|var| var.name.as_str()).collect();
+                writeln!(lp, "\n{} {};", keyword, names.join(", ")).unwrap();
+            }
+        }
+        lp
+    }
+
+    /// The model in free MPS format, for the solvers that only read MPS (SCIP
+    /// clusters, ...). The variables keep their LP names, so that the MST of the LP
+    /// is a warm start of this model too. Lazy constraints are written as ordinary
+    /// rows, indicator constraints in the `INDICATORS` section of CPLEX, Gurobi and
+    /// SCIP.
+    pub fn to_mps(&self, name: &str) -> String {
+        let mut mps = String::new();
+        writeln!(mps, "NAME {}", name).unwrap();
+        mps.push_str("ROWS\n N obj\n");
+        for constraint in &self.constraints {
+            let sense = match constraint.sense {
+                Sense::Le => 'L',
+                Sense::Ge => 'G',
+                Sense::Eq => 'E',
+            };
+            writeln!(mps, " {} {}", sense, constraint.name).unwrap();
+        }
+
+        // The rows of each column, merged where a row has a variable twice.
+        let mut columns: Vec<Vec<(&str, f64)>> = vec![Vec::new(); self.vars.len()];
+        let rows = self.objective.iter().filter(|(_, coef)| *coef != 0.0).map(|term| ("obj", term)).chain(
+            self.constraints.iter().flat_map(|constraint| constraint.terms.iter().map(move |term| (constraint.name.as_str(), term))),
+        );
+        for (row, (var, coef)) in rows {
+            let column = &mut columns[var.0];
+            match column.last_mut() {
+                Some((last, sum)) if *last == row => *sum += coef,
+                _ => column.push((row, *coef)),
+            }
+        }
+        mps.push_str("COLUMNS\n");
+        let mut integral = false;
+        for (var, column) in self.vars.iter().zip(&columns) {
+            if integral != (var.kind != VarKind::Continuous) {
+                integral = !integral;
+                writeln!(mps, " MARKER 'MARKER' '{}'", if integral { "INTORG" } else { "INTEND" }).unwrap();
+            }
+            if column.is_empty() {
+                writeln!(mps, " {} obj 0", var.name).unwrap();
+            }
+            for (row, coef) in column {
+                writeln!(mps, " {} {} {}", var.name, row, coef).unwrap();
+            }
+        }
+        if integral {
+            mps.push_str(" MARKER 'MARKER' 'INTEND'\n");
+        }
+
+        mps.push_str("RHS\n");
+        for constraint in self.constraints.iter().filter(|constraint| constraint.rhs != 0.0) {
+            writeln!(mps, " rhs {} {}", constraint.name, constraint.rhs).unwrap();
+        }
+
+        // Readers differ in the default upper bound of integer columns, so theirs are
+        // always written.
+        mps.push_str("BOUNDS\n");
+        for var in &self.vars {
+            if var.is_plain_binary() {
+                writeln!(mps, " BV bnd {}", var.name).unwrap();
+                continue;
+            }
+            if var.lower == var.upper {
+                writeln!(mps, " FX bnd {} {}", var.name, var.lower).unwrap();
+                continue;
+            }
+            if var.lower == f64::NEG_INFINITY {
+                writeln!(mps, " MI bnd {}", var.name).unwrap();
+            } else if var.lower != 0.0 || var.kind != VarKind::Continuous {
+                writeln!(mps, " LO bnd {} {}", var.name, var.lower).unwrap();
+            }
+            if var.upper != f64::INFINITY {
+                writeln!(mps, " UP bnd {} {}", var.name, var.upper).unwrap();
+            } else if var.kind != VarKind::Continuous {
+                writeln!(mps, " PL bnd {}", var.name).unwrap();
+            }
+        }
+
+        if self.constraints.iter().any(|constraint| constraint.indicator.is_some()) {
+            mps.push_str("INDICATORS\n");
+            for constraint in &self.constraints {
+                if let Some((var, value)) = constraint.indicator {
+                    writeln!(mps, " IF {} {} {}", constraint.name, self.var(var).name, value).unwrap();
+                }
+            }
+        }
+        mps.push_str("ENDATA\n");
+        mps
+    }
+
+    fn write_lp_terms(&self, lp: &mut String, terms: &[(VarId, f64)]) {
+        write_lp_terms(lp, terms.iter().map(|(var, coef)| (self.var(*var).name.as_str(), *coef)));
+    }
+}
+
+/// A row of the LP format's `Subject To` or `Lazy Constraints` section, by variable
+/// names; `IlpModel::to_lp` and `ilp_stream` write the same rows.
+pub(crate) fn write_lp_row<'a>(
+    lp: &mut String,
+    name: &str,
+    indicator: Option<(&str, f64)>,
+    terms: impl IntoIterator<Item = (&'a str, f64)>,
+    sense: Sense,
+    rhs: f64,
+) {
+    write!(lp, "{}: ", name).unwrap();
+    if let Some((var, value)) = indicator {
+        write!(lp, "{} = {} -> ", var, value).unwrap();
+    }
+    write_lp_terms(lp, terms);
+    writeln!(lp, " {} {}", sense.as_str(), rhs).unwrap();
+}
+
+pub(crate) fn write_lp_terms<'a>(lp: &mut String, terms: impl IntoIterator<Item = (&'a str, f64)>) {
+    for (i, (name, coef)) in terms.into_iter().enumerate() {
+        let magnitude = coef.abs();
+        match (i, coef < 0.0) {
+            (0, false) => {}
+            (0, true) => lp.push('-'),
+            (_, false) => lp.push_str(" + "),
+            (_, true) => lp.push_str(" - "),
+        }
+        if magnitude == 1.0 {
+            lp.push_str(name);
+        } else {
+            write!(lp, "{} {}", magnitude, name).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mps_sections() {
+        let mut model = IlpModel::default();
+        let n = model.add_binary("N_0_0".to_string());
+        let pruned = model.add_binary("N_0_1".to_string());
+        model.fix(pruned, 0.0);
+        let level = model.add_var("L_0".to_string(), VarKind::Continuous, 0.0, 2.0);
+        model.objective = vec![(n, 3.0), (pruned, 4.0)];
+        model.add_constraint("class_0".to_string(), vec![(n, 1.0), (pruned, 1.0), (n, 1.0)], Sense::Ge, 1.0);
+        model.add_indicator("cycle_0".to_string(), n, 1.0, vec![(level, 1.0)], Sense::Ge, 1.0);
+
+        let mps = model.to_mps("test");
+        let lines: Vec<&str> = mps.lines().collect();
+        assert_eq!(lines[..5], ["NAME test", "ROWS", " N obj", " G class_0", " G cycle_0"]);
+        // The two terms of N_0_0 in class_0 are one entry.
+        assert!(lines.contains(&" N_0_0 class_0 2"));
+        assert!(lines.contains(&" L_0 cycle_0 1"));
+        let markers: Vec<&&str> = lines.iter().filter(|line| line.contains("MARKER")).collect();
+        assert_eq!(markers, [&" MARKER 'MARKER' 'INTORG'", &" MARKER 'MARKER' 'INTEND'"]);
+        assert!(lines.contains(&" BV bnd N_0_0"));
+        assert!(lines.contains(&" FX bnd N_0_1 0"));
+        assert!(lines.contains(&" UP bnd L_0 2"));
+        assert!(lines.contains(&" IF cycle_0 N_0_0 1"));
+        assert_eq!(lines.last(), Some(&"ENDATA"));
+    }
+}
@@ -0,0 +1,464 @@
+// The extraction ILP written straight to the LP file, for e-graphs whose model
+// doesn't fit in memory.
+//
+// `ilp_gen::generate_ilp_file` builds the whole `IlpModel` (every variable and
+// row name as a string) before writing it. Here the file is written in one pass
+// over the e-graph per section of the LP, the objective, each constraint family
+// and the bound and type declarations, through a buffered writer: what stays in
+// memory is a few numbers per class, never a row more than the one being written.
+// The file is the same, byte for byte, as `IlpModel::to_lp` of the model
+// `build_model` would build without a warm start.
+//
+// After each section, `<lp>.checkpoint` records it with the length of the file so
+// far. A run that stops halfway (killed, out of disk) is resumed by the next one
+// with the same e-graph and parameters, from the end of the last section
+// written; the checkpoint is removed once the file is complete.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Seek, Write};
+use indexmap::{IndexMap, IndexSet};
+use egraph_serialize::EGraph as SerializedEGraph;
+use egraph_serialize::{Class, ClassId, NodeId};
+use crate::ilp_gen::{component_sizes, cyclic_children, node_children_classes, sanitize, Acyclicity, ModelOptions};
+use crate::ilp_model::{write_lp_row, Sense};
+
+/// What `stream_ilp_file` wrote.
+#[derive(Clone, Debug, Default)]
+pub struct Streamed {
+    /// Constraints, lazy ones included.
+    pub rows: usize,
+    /// Sections taken from the checkpoint of an earlier run rather than written.
+    pub resumed: usize,
+}
+
+/// The sections of the LP file, in order.
+const SECTIONS: [&str; 16] = [
+    "objective",
+    "uses_op",
+    "c_act",
+    "node_child",
+    "root",
+    "intersect",
+    "opp",
+    "self_loop",
+    "level",
+    "frozen",
+    "sym",
+    "lazy",
+    "bounds",
+    "generals",
+    "binaries",
+    "end",
+];
+
+/// The model's parameters and the few numbers per class each section needs.
+struct Context<'a> {
+    egraph: &'a SerializedEGraph,
+    roots: &'a [ClassId],
+    classes: Vec<&'a Class>,
+    component_size: IndexMap<ClassId, (usize, usize)>,
+    /// Reachable pruned nodes, fixed to 0.
+    pruned: IndexSet<NodeId>,
+    frozen: Option<&'a IndexMap<ClassId, NodeId>>,
+    acyclicity: Acyclicity,
+    op_weight: Option<f64>,
+    /// `O_<k>` of every op, in the order the classes first use them.
+    ops: IndexMap<&'a str, usize>,
+}
+
+impl Context<'_> {
+    fn node_var(node_id: &NodeId) -> String {
+        format!("N_{}_{}", node_id.0[0], node_id.0[1])
+    }
+
+    fn has_opp(&self, cid: ClassId, node_id: &NodeId) -> bool {
+        self.acyclicity.has_opp() && !cyclic_children(self.egraph, &self.component_size, cid, node_id).is_empty()
+    }
+
+    fn nodes(&self) -> impl Iterator<Item = (ClassId, &NodeId)> {
+        self.classes.iter().flat_map(|class| class.nodes.iter().map(move |node_id| (class.id, node_id)))
+    }
+
+    /// Writes one section, returning its number of rows. `line` is the only
+    /// buffer, emptied into `out` after every row.
+    fn write_section(&self, section: &str, out: &mut impl Write) -> std::io::Result<usize> {
+        let mut line = String::new();
+        let mut rows = 0;
+        let mut row = |line: &mut String, out: &mut dyn Write| -> std::io::Result<()> {
+            out.write_all(line.as_bytes())?;
+            line.clear();
+            rows += 1;
+            Ok(())
+        };
+        match section {
+            "objective" => {
+                out.write_all(b"Minimize\n obj: ")?;
+                let mut first = true;
+                let costs = self.nodes().map(|(_, node_id)| (Self::node_var(node_id), self.egraph[node_id].cost.into_inner()));
+                let ops = self.op_weight.into_iter().flat_map(|weight| (0..self.ops.len()).map(move |k| (format!("O_{}", k), weight)));
+                for (name, coef) in costs.chain(ops).filter(|(_, coef)| *coef != 0.0) {
+                    if !first {
+                        out.write_all(b" + ")?;
+                    }
+                    first = false;
+                    if coef == 1.0 {
+                        write!(out, "{}", name)?;
+                    } else {
+                        write!(out, "{} {}", coef, name)?;
+                    }
+                }
+                out.write_all(b"\n\nSubject To\n")?;
+            }
+            "uses_op" if self.op_weight.is_some() => {
+                for (_, node_id) in self.nodes() {
+                    let op = format!("O_{}", self.ops[self.egraph[node_id].op.as_str()]);
+                    let name = format!("USES_OP_{}_{}", node_id.0[0], node_id.0[1]);
+                    write_lp_row(&mut line, &name, None, [(Self::node_var(node_id).as_str(), 1.0), (op.as_str(), -1.0)], Sense::Le, 0.0);
+                    row(&mut line, out)?;
+                }
+            }
+            "c_act" => {
+                for class in &self.classes {
+                    let nodes: Vec<String> = class.nodes.iter().map(Self::node_var).collect();
+                    let active = format!("A_{}", sanitize(&class.id));
+                    let terms = nodes.iter().map(|name| (name.as_str(), 1.0)).chain([(active.as_str(), -1.0)]);
+                    write_lp_row(&mut line, &format!("C_ACT_{}", sanitize(&class.id)), None, terms, Sense::Eq, 0.0);
+                    row(&mut line, out)?;
+                }
+            }
+            "node_child" => {
+                for (_, node_id) in self.nodes() {
+                    let node_var = Self::node_var(node_id);
+                    for child in node_children_classes(self.egraph, node_id) {
+                        let name = format!("NODE_CHILD_{}_{}_{}", node_id.0[0], node_id.0[1], sanitize(&child));
+                        let active = format!("A_{}", sanitize(&child));
+                        write_lp_row(&mut line, &name, None, [(node_var.as_str(), 1.0), (active.as_str(), -1.0)], Sense::Le, 0.0);
+                        row(&mut line, out)?;
+                    }
+                }
+            }
+            "root" => {
+                for root in self.roots {
+                    let active = format!("A_{}", sanitize(root));
+                    write_lp_row(&mut line, &format!("ROOT_{}", sanitize(root)), None, [(active.as_str(), 1.0)], Sense::Ge, 1.0);
+                    row(&mut line, out)?;
+                }
+            }
+            "intersect" => {
+                for class in self.classes.iter().filter(|class| !class.nodes.is_empty()) {
+                    let mut intersection = node_children_classes(self.egraph, &class.nodes[0]);
+                    for node_id in &class.nodes[1..] {
+                        let child_set = node_children_classes(self.egraph, node_id);
+                        intersection = intersection.intersection(&child_set).cloned().collect();
+                    }
+                    let active = format!("A_{}", sanitize(&class.id));
+                    for child in intersection {
+                        let name = format!("INTERSECT_{}_{}", sanitize(&class.id), sanitize(&child));
+                        let child_active = format!("A_{}", sanitize(&child));
+                        write_lp_row(&mut line, &name, None, [(active.as_str(), 1.0), (child_active.as_str(), -1.0)], Sense::Le, 0.0);
+                        row(&mut line, out)?;
+                    }
+                }
+            }
+            "opp" => {
+                for (cid, node_id) in self.nodes().filter(|(cid, node_id)| self.has_opp(*cid, node_id)) {
+                    let (node_var, opp) = (Self::node_var(node_id), format!("Opp_{}_{}", node_id.0[0], node_id.0[1]));
+                    let name = format!("OPP_{}_{}", cid.0, node_id.0[1]);
+                    write_lp_row(&mut line, &name, None, [(node_var.as_str(), 1.0), (opp.as_str(), 1.0)], Sense::Eq, 1.0);
+                    row(&mut line, out)?;
+                }
+            }
+            "self_loop" => {
+                for (_, node_id) in self.nodes().filter(|(cid, node_id)| self.egraph[*node_id].children.contains(cid)) {
+                    let name = format!("SELF_LOOP_{}_{}", node_id.0[0], node_id.0[1]);
+                    write_lp_row(&mut line, &name, None, [(Self::node_var(node_id).as_str(), 1.0)], Sense::Eq, 0.0);
+                    row(&mut line, out)?;
+                }
+            }
+            // The LEVEL rows, in `Subject To` or as lazy constraints.
+            "level" | "lazy" if self.acyclicity.has_levels() && (section == "lazy") == (self.acyclicity == Acyclicity::Lazy) => {
+                let mut header = section == "lazy";
+                for (cid, node_id) in self.nodes() {
+                    let m_const = self.component_size[&cid].1 as f64;
+                    let node_var = Self::node_var(node_id);
+                    let opp = format!("Opp_{}_{}", node_id.0[0], node_id.0[1]);
+                    let level = format!("L_{}", sanitize(&cid));
+                    for child in cyclic_children(self.egraph, &self.component_size, cid, node_id) {
+                        if header {
+                            out.write_all(b"\nLazy Constraints\n")?;
+                            header = false;
+                        }
+                        let name = format!("LEVEL_{}_{}_{}", node_id.0[0], node_id.0[1], sanitize(&child));
+                        let child_level = format!("L_{}", sanitize(&child));
+                        let levels = [(child_level.as_str(), 1.0), (level.as_str(), -1.0)];
+                        if self.acyclicity == Acyclicity::Indicator {
+                            write_lp_row(&mut line, &name, Some((node_var.as_str(), 1.0)), levels, Sense::Ge, 1.0);
+                        } else {
+                            write_lp_row(&mut line, &name, None, levels.into_iter().chain([(opp.as_str(), m_const)]), Sense::Ge, 1.0);
+                        }
+                        row(&mut line, out)?;
+                    }
+                }
+            }
+            "frozen" => {
+                let reachable: IndexSet<ClassId> = self.classes.iter().map(|class| class.id).collect();
+                for (cid, node_id) in self.frozen.into_iter().flatten().filter(|(cid, _)| reachable.contains(*cid)) {
+                    let (node_var, active) = (Self::node_var(node_id), format!("A_{}", sanitize(cid)));
+                    let terms = [(node_var.as_str(), 1.0), (active.as_str(), -1.0)];
+                    write_lp_row(&mut line, &format!("FROZEN_{}", sanitize(cid)), None, terms, Sense::Eq, 0.0);
+                    row(&mut line, out)?;
+                }
+            }
+            // As `build_model` without a start: the first node of a group is the
+            // frozen one if any, else one that is not pruned.
+            "sym" => {
+                for class in &self.classes {
+                    let mut groups: IndexMap<(&str, &[ClassId], u64), Vec<&NodeId>> = IndexMap::new();
+                    for node_id in &class.nodes {
+                        let node = &self.egraph[node_id];
+                        groups.entry((node.op.as_str(), node.children.as_slice(), node.cost.into_inner().to_bits())).or_default().push(node_id);
+                    }
+                    let frozen_node = self.frozen.and_then(|frozen| frozen.get(&class.id));
+                    for mut group in groups.into_values().filter(|group| group.len() > 1) {
+                        let first = group
+                            .iter()
+                            .position(|node_id| Some(*node_id) == frozen_node)
+                            .or_else(|| group.iter().position(|node_id| !self.pruned.contains(*node_id)))
+                            .unwrap_or(0);
+                        group.swap(0, first);
+                        for pair in group.windows(2) {
+                            let (prev, next) = (Self::node_var(pair[0]), Self::node_var(pair[1]));
+                            let name = format!("SYM_{}_{}", pair[1].0[0], pair[1].0[1]);
+                            write_lp_row(&mut line, &name, None, [(prev.as_str(), 1.0), (next.as_str(), -1.0)], Sense::Ge, 0.0);
+                            row(&mut line, out)?;
+                        }
+                    }
+                }
+            }
+            "bounds" => {
+                out.write_all(b"\nBounds\n")?;
+                for (_, node_id) in self.nodes().filter(|(_, node_id)| self.pruned.contains(*node_id)) {
+                    writeln!(out, "{} = 0", Self::node_var(node_id))?;
+                }
+                for class in &self.classes {
+                    let size = self.component_size[&class.id].1;
+                    if size > 1 && self.acyclicity.has_levels() {
+                        writeln!(out, "0 <= L_{} <= {}", sanitize(&class.id), size - 1)?;
+                    }
+                }
+            }
+            // Fixed binaries are declared as general integers, see `IlpModel::to_lp`.
+            "generals" if !self.pruned.is_empty() => {
+                out.write_all(b"\nGenerals\n")?;
+                for (_, node_id) in self.nodes().filter(|(_, node_id)| self.pruned.contains(*node_id)) {
+                    writeln!(out, "{}", Self::node_var(node_id))?;
+                }
+            }
+            "binaries" => {
+                out.write_all(b"\nBinaries\n")?;
+                for class in &self.classes {
+                    writeln!(out, "A_{}", sanitize(&class.id))?;
+                }
+                for (_, node_id) in self.nodes().filter(|(_, node_id)| !self.pruned.contains(*node_id)) {
+                    writeln!(out, "{}", Self::node_var(node_id))?;
+                }
+                for (_, node_id) in self.nodes().filter(|(cid, node_id)| self.has_opp(*cid, node_id)) {
+                    writeln!(out, "Opp_{}_{}", node_id.0[0], node_id.0[1])?;
+                }
+                if self.op_weight.is_some() {
+                    for k in 0..self.ops.len() {
+                        writeln!(out, "O_{}", k)?;
+                    }
+                }
+            }
+            "end" => out.write_all(b"\nEnd\n")?,
+            _ => {}
+        }
+        Ok(rows)
+    }
+}
+
+/// Identifies the e-graph and the parameters a checkpoint was written for.
+fn fingerprint(egraph: &SerializedEGraph, roots: &[ClassId], options: ModelOptions) -> u64 {
+    let ModelOptions { pruned, frozen, acyclicity, op_weight } = options;
+    let mut hasher = DefaultHasher::new();
+    for (node_id, node) in &egraph.nodes {
+        (node_id, &node.op, &node.children, node.cost.into_inner().to_bits()).hash(&mut hasher);
+    }
+    roots.hash(&mut hasher);
+    pruned.hash(&mut hasher);
+    frozen.map(|frozen| frozen.iter().collect::<Vec<_>>()).hash(&mut hasher);
+    acyclicity.name().hash(&mut hasher);
+    op_weight.map(f64::to_bits).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The sections of the checkpoint at `path` that were written for `fingerprint`,
+/// with the length of the file and the rows written after each.
+fn read_checkpoint(path: &str, fingerprint: u64) -> Vec<(String, u64, usize)> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut lines = text.lines();
+    if lines.next() != Some(format!("fingerprint {:x}", fingerprint).as_str()) {
+        return Vec::new();
+    }
+    // A line cut short by the interruption ends the sections that count.
+    lines
+        .map_while(|line| {
+            let mut fields = line.split(' ');
+            let section = fields.next()?.to_string();
+            let length = fields.next()?.parse().ok()?;
+            let rows = fields.next()?.parse().ok()?;
+            Some((section, length, rows))
+        })
+        .zip(SECTIONS)
+        .take_while(|((section, _, _), expected)| section == expected)
+        .map(|(done, _)| done)
+        .collect()
+}
+
+/// Writes the model `ilp_gen::generate_ilp_file` writes, without a warm start, to
+/// `file_path` one section at a time, resuming the sections a stopped run left in
+/// its checkpoint. The parameters are those of `generate_ilp_file`.
+pub fn stream_ilp_file(egraph: &SerializedEGraph, roots: &[ClassId], file_path: &str, options: ModelOptions) -> std::io::Result<Streamed> {
+    let checkpoint_path = format!("{}.checkpoint", file_path);
+    let fingerprint = fingerprint(egraph, roots, options);
+    let ModelOptions { pruned, frozen, acyclicity, op_weight } = options;
+
+    let reachable = extraction_gym::egraph_reachable_classes(egraph, roots);
+    let classes: Vec<&Class> = egraph.classes().values().filter(|class| reachable.contains(&class.id)).collect();
+    println!("Streaming the ILP over {} of {} classes reachable from the roots", classes.len(), egraph.classes().len());
+    let mut ops = IndexMap::new();
+    if op_weight.is_some() {
+        for node_id in classes.iter().flat_map(|class| class.nodes.iter()) {
+            let k = ops.len();
+            ops.entry(egraph[node_id].op.as_str()).or_insert(k);
+        }
+    }
+    let context = Context {
+        egraph,
+        roots,
+        component_size: component_sizes(egraph),
+        pruned: pruned.into_iter().flatten().filter(|node_id| reachable.contains(egraph.nid_to_cid(node_id))).copied().collect(),
+        classes,
+        frozen,
+        acyclicity,
+        op_weight,
+        ops,
+    };
+
+    let mut done = read_checkpoint(&checkpoint_path, fingerprint);
+    let file = match done.last() {
+        Some((section, length, _)) if std::fs::metadata(file_path).is_ok_and(|metadata| metadata.len() >= *length) => {
+            println!("Resuming {} after section {} of the checkpoint", file_path, section);
+            let file = OpenOptions::new().write(true).open(file_path)?;
+            file.set_len(*length)?;
+            file
+        }
+        _ => {
+            done.clear();
+            File::create(file_path)?
+        }
+    };
+    let mut out = BufWriter::new(file);
+    out.seek(std::io::SeekFrom::End(0))?;
+    let mut checkpoint = File::create(&checkpoint_path)?;
+    writeln!(checkpoint, "fingerprint {:x}", fingerprint)?;
+    for (section, length, rows) in &done {
+        writeln!(checkpoint, "{} {} {}", section, length, rows)?;
+    }
+
+    let mut streamed = Streamed { rows: done.iter().map(|(_, _, rows)| rows).sum(), resumed: done.len() };
+    for section in &SECTIONS[done.len()..] {
+        let rows = context.write_section(section, &mut out)?;
+        // `stream_position` flushes the buffer first.
+        let length = out.stream_position()?;
+        out.get_ref().sync_data()?;
+        writeln!(checkpoint, "{} {} {}", section, length, rows)?;
+        streamed.rows += rows;
+    }
+    drop(checkpoint);
+    std::fs::remove_file(&checkpoint_path)?;
+    println!("LP file streamed to {}: {} rows", file_path, streamed.rows);
+    Ok(streamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilp_gen::build_model;
+    use egraph_serialize::{Data, Node};
+    use ordered_float::NotNan;
+
+    /// Classes 0 to 3, root 3. Class 1 and 2 use each other, class 2 has twin
+    /// nodes, class 0 a self loop.
+    fn cyclic() -> SerializedEGraph {
+        let mut nodes = IndexMap::new();
+        let mut add = |class: u32, node: u32, op: &str, children: &[u32], cost: f64| {
+            let id = NodeId::from((class, node));
+            let children = children.iter().map(|child| ClassId::from(*child)).collect();
+            nodes.insert(id, Node { op: op.to_string(), id, children, eclass: ClassId::from(class), cost: NotNan::new(cost).unwrap(), area: None });
+        };
+        add(0, 0, "x", &[], 1.0);
+        add(0, 1, "loop", &[0], 0.0);
+        add(1, 0, "f", &[2], 2.0);
+        add(1, 1, "g", &[0], 3.0);
+        add(2, 0, "h", &[1, 0], 1.0);
+        add(2, 1, "h", &[1, 0], 1.0);
+        add(2, 2, "x", &[], 4.0);
+        add(3, 0, "add", &[1, 2], 1.0);
+        SerializedEGraph::from_Data(&Data { nodes, root_eclasses: vec![ClassId::from(3)], class_data: IndexMap::new() }).unwrap()
+    }
+
+    fn path(name: &str) -> String {
+        std::env::temp_dir().join(format!("e_boost_ilp_stream_{}_{}.lp", std::process::id(), name)).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_stream_matches_model() {
+        let egraph = cyclic();
+        let roots = egraph.root_eclasses.clone();
+        let frozen: IndexMap<ClassId, NodeId> = [(ClassId::from(0), NodeId::from((0, 0)))].into_iter().collect();
+        for acyclicity in [Acyclicity::BigM, Acyclicity::Indicator, Acyclicity::Lazy, Acyclicity::Cuts] {
+            for (pruned, op_weight) in [(None, None), (Some(&[NodeId::from((1, 1)), NodeId::from((2, 0))][..]), Some(2.0))] {
+                let file = path(acyclicity.name());
+                let options = ModelOptions { pruned, frozen: Some(&frozen), acyclicity, op_weight };
+                stream_ilp_file(&egraph, &roots, &file, options).unwrap();
+                let model = build_model(&egraph, &roots, options, None);
+                assert_eq!(std::fs::read_to_string(&file).unwrap(), model.to_lp());
+                assert!(!std::path::Path::new(&format!("{}.checkpoint", file)).exists());
+                std::fs::remove_file(&file).unwrap();
+            }
+        }
+    }
+
+    /// A run stopped after a few sections, and with a half-written one, is resumed
+    /// into the same file.
+    #[test]
+    fn test_stream_resumes_from_checkpoint() {
+        let egraph = cyclic();
+        let roots = egraph.root_eclasses.clone();
+        let file = path("resume");
+        stream_ilp_file(&egraph, &roots, &file, ModelOptions::default()).unwrap();
+        let complete = std::fs::read_to_string(&file).unwrap();
+
+        // objective, uses_op (empty), c_act and node_child, then a cut line.
+        let sections = 4;
+        let length = complete.find("ROOT_").unwrap();
+        let mut checkpoint = format!("fingerprint {:x}\n", fingerprint(&egraph, &roots, ModelOptions::default()));
+        for (section, offset) in SECTIONS.iter().zip([complete.find("C_ACT").unwrap(), complete.find("C_ACT").unwrap(), complete.find("NODE_CHILD").unwrap(), length]) {
+            checkpoint.push_str(&format!("{} {} 0\n", section, offset));
+        }
+        checkpoint.push_str("root 12");
+        std::fs::write(format!("{}.checkpoint", file), checkpoint).unwrap();
+        std::fs::write(&file, format!("{}ROOT_3: A_", &complete[..length])).unwrap();
+
+        let streamed = stream_ilp_file(&egraph, &roots, &file, ModelOptions::default()).unwrap();
+        assert_eq!(streamed.resumed, sections);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), complete);
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,15 @@
+//! The parts of E-boost that work on e-graphs and solutions in memory, without
+//! running an extractor or a solver: the pruning of the heuristic's result, the
+//! ILP model with its LP files and warm starts, the e-graph preprocessing and
+//! the parsing of the solvers' solution files.
+
+pub mod ilp_gen;
+pub mod ilp_model;
+pub mod ilp_stream;
+pub mod partition;
+pub mod pruning;
+pub mod redundancy;
+pub mod solution;
+pub mod warm_start;
+
+pub use pruning::collect_results;
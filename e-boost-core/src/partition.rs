@@ -0,0 +1,194 @@
+use crate::ilp_gen::sccs;
+use egraph_serialize::{ClassId, Data, EGraph, Node, NodeId};
+use extraction_gym::ExtractionResult;
+use indexmap::{IndexMap, IndexSet};
+use ordered_float::NotNan;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The class of the `pseudo_root` node joining several roots.
+pub const PSEUDO_ROOT: u32 = u32::MAX;
+
+fn pseudo_root(op: String, children: Vec<ClassId>) -> Node {
+    Node {
+        op,
+        id: NodeId::from((PSEUDO_ROOT, 0)),
+        children,
+        eclass: ClassId::from(PSEUDO_ROOT),
+        cost: NotNan::new(0.0).unwrap(),
+        area: Some(NotNan::new(0.0).unwrap()),
+    }
+}
+
+/// Splits the e-graph into at most `1 / factor` sub-e-graphs of whole classes, in
+/// breadth-first order from its root (a `pseudo_root` over the classes without
+/// parents when there are several). The children outside a sub-e-graph are
+/// dropped, and each has a single root, a `pseudo_root_<i>` node again where needed.
+pub fn partition(data: &Data, factor: f32) -> Vec<Data> {
+    let mut nodes = data.nodes.clone();
+    let mut parents: HashMap<ClassId, Vec<NodeId>> = HashMap::new();
+    for (node_id, node) in nodes.iter() {
+        for child in node.children.iter() {
+            parents.entry(*child).or_default().push(*node_id);
+        }
+    }
+
+    let mut roots: IndexSet<ClassId> = nodes.values().map(|node| node.eclass).filter(|class| !parents.contains_key(class)).collect();
+    if roots.len() > 1 {
+        nodes.insert(NodeId::from((PSEUDO_ROOT, 0)), pseudo_root("pseudo_root".to_string(), roots.into_iter().collect()));
+        roots = IndexSet::from([ClassId::from(PSEUDO_ROOT)]);
+    }
+
+    let mut class_nodes: HashMap<ClassId, Vec<NodeId>> = HashMap::new();
+    for (node_id, node) in nodes.iter() {
+        class_nodes.entry(node.eclass).or_default().push(*node_id);
+    }
+
+    let partition_num = (1.0 / factor).round() as usize;
+    assert!(nodes.len() > partition_num, "fewer nodes than partitions");
+    let per_partition = nodes.len() as f32 / partition_num as f32;
+
+    let mut visited = HashSet::new();
+    let mut queue: VecDeque<ClassId> = roots.into_iter().collect();
+    let mut subgraphs = Vec::new();
+    let mut current = IndexSet::new();
+    let mut current_count = 0;
+    while let Some(class_id) = queue.pop_front() {
+        current.insert(class_id);
+        // The last partition takes whatever is left.
+        if current_count as f32 >= per_partition && subgraphs.len() + 1 < partition_num {
+            subgraphs.push(std::mem::take(&mut current));
+            current_count = 0;
+        }
+        let node_ids = class_nodes.get(&class_id).unwrap_or_else(|| panic!("class_id not found:{:?}", class_id));
+        for node_id in node_ids {
+            current_count += 1;
+            for child in nodes[node_id].children.iter() {
+                if visited.insert(*child) {
+                    queue.push_back(*child);
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        subgraphs.push(current);
+    }
+
+    subgraphs
+        .iter()
+        .enumerate()
+        .map(|(idx, subgraph)| {
+            let mut subgraph_nodes: IndexMap<NodeId, Node> = subgraph
+                .iter()
+                .flat_map(|class_id| class_nodes.get(class_id).into_iter().flatten())
+                .map(|node_id| (*node_id, nodes[node_id].clone()))
+                .collect();
+            for node in subgraph_nodes.values_mut() {
+                node.children.retain(|child| subgraph.contains(child));
+            }
+
+            let children: HashSet<ClassId> = subgraph_nodes.values().flat_map(|node| node.children.iter().cloned()).collect();
+            let mut subgraph_roots: IndexSet<ClassId> = subgraph_nodes.values().map(|node| node.eclass).filter(|class| !children.contains(class)).collect();
+            if subgraph_roots.len() > 1 {
+                let root = pseudo_root(format!("pseudo_root_{:?}", idx), subgraph_roots.into_iter().collect());
+                subgraph_nodes.insert(NodeId::from((PSEUDO_ROOT, 0)), root);
+                subgraph_roots = IndexSet::from([ClassId::from(PSEUDO_ROOT)]);
+            }
+
+            Data {
+                nodes: subgraph_nodes,
+                root_eclasses: subgraph_roots.into_iter().collect(),
+                class_data: data.class_data.clone(),
+            }
+        })
+        .collect()
+}
+
+/// The union of the choices of the extractions of the parts, without their
+/// pseudo roots, and the classes of that union that are on a cycle of it. The
+/// children a partition drops can close cycles across the parts; the other
+/// choices can be kept while the classes on cycles are extracted again
+/// (`Extractor::extract_frozen`).
+pub fn stitch<'a>(egraph: &EGraph, parts: impl IntoIterator<Item = &'a ExtractionResult>) -> (ExtractionResult, Vec<ClassId>) {
+    let mut stitched = ExtractionResult::default();
+    for part in parts {
+        for (cid, nid) in &part.choices {
+            if egraph.nodes.get(nid).is_some_and(|node| node.eclass == *cid) {
+                stitched.choose(*cid, *nid);
+            }
+        }
+    }
+    let successors: Vec<Vec<usize>> = stitched
+        .choices
+        .values()
+        .map(|nid| egraph[nid].children.iter().filter_map(|child| stitched.choices.get_index_of(child)).collect())
+        .collect();
+    let cyclic = sccs(&successors)
+        .into_iter()
+        .filter(|scc| scc.len() > 1 || successors[scc[0]].contains(&scc[0]))
+        .flatten()
+        .map(|c| *stitched.choices.get_index(c).unwrap().0)
+        .collect();
+    (stitched, cyclic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chain 0 <- 1 <- ... <- 7 of one node per class, 7 the root.
+    fn chain() -> Data {
+        let nodes = (0..8u32)
+            .map(|class| {
+                let id = NodeId::from((class, 0));
+                let node = Node {
+                    op: format!("op{}", class),
+                    id,
+                    children: if class == 0 { vec![] } else { vec![ClassId::from(class - 1)] },
+                    eclass: ClassId::from(class),
+                    cost: NotNan::new(1.0).unwrap(),
+                    area: None,
+                };
+                (id, node)
+            })
+            .collect();
+        Data { nodes, root_eclasses: vec![ClassId::from(7)], class_data: IndexMap::new() }
+    }
+
+    /// Parts of the chain choosing a node of class 4 that uses class 6 close a
+    /// cycle 4 -> 6 -> 5 -> 4 once stitched.
+    #[test]
+    fn test_stitch_finds_cycles() {
+        let mut data = chain();
+        let id = NodeId::from((4, 1));
+        let node = Node { op: "loop".to_string(), id, children: vec![ClassId::from(6)], eclass: ClassId::from(4), cost: NotNan::new(0.0).unwrap(), area: None };
+        data.nodes.insert(id, node);
+        let egraph = EGraph::from_Data(&data).unwrap();
+        let choices = |nodes: &[(u32, u32)]| ExtractionResult::new(nodes.iter().map(|&(class, node)| (ClassId::from(class), NodeId::from((class, node)))).collect());
+
+        let (stitched, cyclic) = stitch(&egraph, &[choices(&[(7, 0), (6, 0), (5, 0), (4, 0)]), choices(&[(3, 0), (2, 0), (1, 0), (0, 0)])]);
+        assert!(stitched.is_valid(&egraph));
+        assert!(cyclic.is_empty());
+
+        let (stitched, mut cyclic) = stitch(&egraph, &[choices(&[(7, 0), (6, 0), (5, 0), (PSEUDO_ROOT, 0)]), choices(&[(4, 1), (3, 0), (2, 0), (1, 0), (0, 0)])]);
+        assert_eq!(stitched.choices.len(), 8);
+        cyclic.sort();
+        assert_eq!(cyclic, vec![ClassId::from(4), ClassId::from(5), ClassId::from(6)]);
+    }
+
+    #[test]
+    fn test_partitions_cover_all_classes() {
+        let data = chain();
+        let parts = partition(&data, 0.5);
+        assert_eq!(parts.len(), 2);
+        let mut classes: Vec<ClassId> = parts.iter().flat_map(|part| part.nodes.values().map(|node| node.eclass)).collect();
+        classes.sort();
+        assert_eq!(classes, (0..8).map(ClassId::from).collect::<Vec<_>>());
+        for part in &parts {
+            assert_eq!(part.root_eclasses.len(), 1);
+            // No child outside the part.
+            for node in part.nodes.values() {
+                assert!(node.children.iter().all(|child| part.nodes.values().any(|other| other.eclass == *child)));
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use egraph_serialize::EGraph as SerializedEGraph;
+use egraph_serialize::{ClassId, NodeId};
+use extraction_gym::{ExtractionResult, INFINITY};
+use ordered_float::NotNan;
+use indexmap::IndexSet;
+use rustc_hash::FxHashSet;
+use crate::ilp_gen::{component_sizes, cyclic_children};
+
+/// Appends to `zero_node` the nodes of `result` that cost more than `bound` times
+/// the best cost of their class, the ones the warm-started LP fixes to 0.
+pub fn collect_results(egraph: &SerializedEGraph, result: &ExtractionResult, bound:f32, zero_node: &mut Vec<NodeId>) {
+    // assert!(bound >= 1.0);
+    let bound = NotNan::new(bound as f64).expect("bound is not NaN");
+    for (node_id, cost) in result.cost.iter() {
+        // Nodes costing more than bound times the best cost of their class are pruned
+        if let Some(class_cost) = result.class_costs.get(egraph.nid_to_cid(node_id)) {
+            if *cost > *class_cost * bound {
+                zero_node.push(*node_id);
+            }
+        }
+    }
+}
+
+/// Binaries of the LP `ilp_gen` generates with `pruned` fixed to 0: the class and
+/// unpruned node variables of the classes the roots reach, and the `Opp` variables
+/// of `Acyclicity::BigM` and `Lazy`. The `--op-weight` variables are left out.
+pub fn lp_binaries(egraph: &SerializedEGraph, roots: &[ClassId], pruned: &[NodeId]) -> usize {
+    let reachable = extraction_gym::egraph_reachable_classes(egraph, roots);
+    let pruned: FxHashSet<&NodeId> = pruned.iter().filter(|node_id| reachable.contains(egraph.nid_to_cid(node_id))).collect();
+    fixed_binaries(egraph, &reachable) + reachable_nodes(egraph, &reachable) - pruned.len()
+}
+
+/// The binaries no bound prunes: the class variables and the `Opp` variables.
+fn fixed_binaries(egraph: &SerializedEGraph, reachable: &IndexSet<ClassId>) -> usize {
+    let component_size = component_sizes(egraph);
+    let opp = reachable
+        .iter()
+        .flat_map(|cid| egraph[cid].nodes.iter().map(move |node_id| (*cid, node_id)))
+        .filter(|(cid, node_id)| !cyclic_children(egraph, &component_size, *cid, node_id).is_empty())
+        .count();
+    reachable.len() + opp
+}
+
+fn reachable_nodes(egraph: &SerializedEGraph, reachable: &IndexSet<ClassId>) -> usize {
+    reachable.iter().map(|cid| egraph[cid].nodes.len()).sum()
+}
+
+/// LP size budget of `--bound auto` without `--lp-budget`, in binaries.
+pub const DEFAULT_LP_BUDGET: usize = 2_000_000;
+
+/// The bound `auto_bound` picked and what it was picked from.
+#[derive(Clone, Debug)]
+pub struct AutoBound {
+    pub bound: f32,
+    /// Binaries of the LP with `bound`, see `lp_binaries`.
+    pub binaries: usize,
+    /// Binaries without pruning.
+    pub unpruned: usize,
+    /// Nodes per class, and the coefficient of variation (standard deviation over
+    /// mean) of the node costs.
+    pub candidates: f64,
+    pub cost_cv: f64,
+    /// Trial prunes run.
+    pub trials: usize,
+}
+
+/// The largest `--bound` whose LP has at most `budget` binaries, from the per-node
+/// costs of the heuristic's `result`.
+///
+/// Every bound above the largest ratio of a node's cost to the best of its class
+/// keeps the same nodes, so that ratio is the largest bound worth trying, and the
+/// one picked when the LP fits without pruning. Otherwise the e-graph statistics
+/// give a first guess, the spread of the costs times the share of the nodes the
+/// budget leaves, and trial prunes bisect from there to a thousandth. A budget too
+/// small even for bound 1 gives bound 1.
+pub fn auto_bound(egraph: &SerializedEGraph, result: &ExtractionResult, budget: usize) -> AutoBound {
+    let roots = &egraph.root_eclasses;
+    let costs: Vec<f64> = egraph.nodes.values().map(|node| node.cost.into_inner()).collect();
+    let mean = costs.iter().sum::<f64>() / costs.len().max(1) as f64;
+    let variance = costs.iter().map(|cost| (cost - mean).powi(2)).sum::<f64>() / costs.len().max(1) as f64;
+    let cost_cv = if mean > 0.0 { variance.sqrt() / mean } else { 0.0 };
+    let candidates = egraph.nodes.len() as f64 / egraph.classes().len().max(1) as f64;
+
+    // What a trial prune changes is the number of node variables.
+    let reachable = extraction_gym::egraph_reachable_classes(egraph, roots);
+    let fixed = fixed_binaries(egraph, &reachable);
+    let unpruned = fixed + reachable_nodes(egraph, &reachable);
+    let mut trials = 0;
+    let mut binaries = |bound: f32| {
+        trials += 1;
+        let mut pruned = Vec::new();
+        collect_results(egraph, result, bound, &mut pruned);
+        unpruned - pruned.iter().filter(|node_id| reachable.contains(egraph.nid_to_cid(node_id))).count()
+    };
+    let max_ratio = result
+        .cost
+        .iter()
+        .filter(|(_, cost)| **cost != INFINITY)
+        .filter_map(|(node_id, cost)| {
+            let best = result.class_costs.get(egraph.nid_to_cid(node_id))?;
+            (best.into_inner() > 0.0).then(|| (*cost / *best).into_inner())
+        })
+        .fold(1.0f64, f64::max) as f32;
+    let at_max = binaries(max_ratio);
+    if at_max <= budget {
+        return AutoBound { bound: max_ratio, binaries: at_max, unpruned, candidates, cost_cv, trials };
+    }
+    let at_one = binaries(1.0);
+    if at_one > budget {
+        return AutoBound { bound: 1.0, binaries: at_one, unpruned, candidates, cost_cv, trials };
+    }
+
+    // Bisection over [fits, too large], starting from the guess.
+    let (mut fits, mut too_large) = (1.0f32, max_ratio);
+    let share = budget.saturating_sub(fixed) as f64 / egraph.nodes.len().max(1) as f64;
+    let guess = (1.0 + cost_cv * share) as f32;
+    let mut probe = if guess > fits && guess < too_large { guess } else { (fits + too_large) / 2.0 };
+    let mut fits_binaries = at_one;
+    while too_large - fits > 0.001 {
+        let count = binaries(probe);
+        if count <= budget {
+            fits = probe;
+            fits_binaries = count;
+        } else {
+            too_large = probe;
+        }
+        probe = (fits + too_large) / 2.0;
+    }
+    // Rounded down, which prunes at least as much.
+    let bound = (fits * 1000.0).floor() / 1000.0;
+    let binaries = if bound == fits { fits_binaries } else { binaries(bound) };
+    AutoBound { bound, binaries, unpruned, candidates, cost_cv, trials }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egraph_serialize::{ClassId, Data, Node};
+    use indexmap::IndexMap;
+
+    fn node(class: u32, index: u32) -> Node {
+        Node {
+            op: format!("op{}", index),
+            id: NodeId::from((class, index)),
+            children: vec![],
+            eclass: ClassId::from(class),
+            cost: NotNan::new(1.0).unwrap(),
+            area: None,
+        }
+    }
+
+    #[test]
+    fn test_prunes_above_bound() {
+        let nodes: IndexMap<NodeId, Node> = (0..3).map(|i| (NodeId::from((0, i)), node(0, i))).collect();
+        let egraph = SerializedEGraph::from_Data(&Data { nodes, root_eclasses: vec![ClassId::from(0)], class_data: IndexMap::new() }).unwrap();
+        let mut result = ExtractionResult::new(IndexMap::new());
+        result.class_costs.insert(ClassId::from(0), NotNan::new(4.0).unwrap());
+        for (i, cost) in [4.0, 5.0, 6.0].into_iter().enumerate() {
+            result.cost.insert(NodeId::from((0, i as u32)), NotNan::new(cost).unwrap());
+        }
+
+        let mut zero_node = Vec::new();
+        collect_results(&egraph, &result, 1.25, &mut zero_node);
+        assert_eq!(zero_node, vec![NodeId::from((0, 2))]);
+    }
+    /// One class of 3 nodes costing 4, 5 and 6: 4 binaries with every node, 3 with
+    /// bound 1.25 and 2 with bound 1.
+    #[test]
+    fn test_auto_bound_fits_budget() {
+        let nodes: IndexMap<NodeId, Node> = (0..3).map(|i| (NodeId::from((0, i)), node(0, i))).collect();
+        let egraph = SerializedEGraph::from_Data(&Data { nodes, root_eclasses: vec![ClassId::from(0)], class_data: IndexMap::new() }).unwrap();
+        let mut result = ExtractionResult::new(IndexMap::new());
+        result.class_costs.insert(ClassId::from(0), NotNan::new(4.0).unwrap());
+        for (i, cost) in [4.0, 5.0, 6.0].into_iter().enumerate() {
+            result.cost.insert(NodeId::from((0, i as u32)), NotNan::new(cost).unwrap());
+        }
+
+        let tuned = auto_bound(&egraph, &result, 10);
+        assert_eq!((tuned.bound, tuned.binaries, tuned.unpruned), (1.5, 4, 4));
+        let tuned = auto_bound(&egraph, &result, 3);
+        assert!((1.25..1.5).contains(&tuned.bound), "{}", tuned.bound);
+        assert_eq!(tuned.binaries, 3);
+        assert_eq!(auto_bound(&egraph, &result, 1).bound, 1.0);
+    }
+}
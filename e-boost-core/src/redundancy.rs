@@ -0,0 +1,166 @@
+use egraph_serialize::{ClassId, Cost, Data, NodeId};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Removes the nodes that have the same children as another node of their class,
+/// in any order and whatever their op, keeping the cheapest (the first of equal
+/// cost). Such nodes compute the same value from the same classes, so the others
+/// never improve an extraction. Returns how many nodes were removed.
+pub fn remove_redundant_nodes(data: &mut Data) -> usize {
+    // The children of a node as a multiset, the key of its group within its class.
+    let mut groups: HashMap<(ClassId, BTreeMap<ClassId, usize>), Vec<&NodeId>> = HashMap::new();
+    for (node_id, node) in data.nodes.iter() {
+        let mut children = BTreeMap::new();
+        for child in &node.children {
+            *children.entry(*child).or_insert(0) += 1;
+        }
+        groups.entry((node.eclass, children)).or_default().push(node_id);
+    }
+
+    let mut redundant = HashSet::new();
+    for node_ids in groups.values().filter(|node_ids| node_ids.len() > 1) {
+        // min_by_key keeps the first of equal cost.
+        let kept = node_ids.iter().min_by_key(|node_id| data.nodes[**node_id].cost).unwrap();
+        redundant.extend(node_ids.iter().filter(|node_id| *node_id != kept).map(|node_id| **node_id));
+    }
+    data.nodes.retain(|node_id, _| !redundant.contains(node_id));
+    redundant.len()
+}
+
+/// Op, child classes, cost and area.
+type NodeKey<'a> = (&'a str, Vec<ClassId>, Cost, Option<Cost>);
+
+/// Merges the classes whose nodes are the same (ops, child classes, costs and
+/// areas) once the classes merged so far are identified, until no more merge:
+/// the nodes of all but the first of them are removed, and the children and
+/// roots that used them use the first. Nodes of a class that become identical are
+/// removed too, keeping the first. Returns how many classes were merged away.
+pub fn merge_duplicate_classes(data: &mut Data) -> usize {
+    let mut class_nodes: IndexMap<ClassId, Vec<&NodeId>> = IndexMap::new();
+    for (node_id, node) in data.nodes.iter() {
+        class_nodes.entry(node.eclass).or_default().push(node_id);
+    }
+    // Every merged class to the class it was merged into, which may have been
+    // merged later itself.
+    let mut merged_into: HashMap<ClassId, ClassId> = HashMap::new();
+    let find = |merged_into: &HashMap<ClassId, ClassId>, mut class: ClassId| {
+        while let Some(next) = merged_into.get(&class) {
+            class = *next;
+        }
+        class
+    };
+    loop {
+        let mut firsts: HashMap<Vec<NodeKey>, ClassId> = HashMap::new();
+        let before = merged_into.len();
+        for (class, node_ids) in &class_nodes {
+            if merged_into.contains_key(class) {
+                continue;
+            }
+            let mut nodes: Vec<_> = node_ids
+                .iter()
+                .map(|node_id| {
+                    let node = &data.nodes[*node_id];
+                    let children = node.children.iter().map(|child| find(&merged_into, *child)).collect();
+                    (node.op.as_str(), children, node.cost, node.area)
+                })
+                .collect();
+            nodes.sort();
+            nodes.dedup();
+            match firsts.get(&nodes) {
+                Some(first) => {
+                    merged_into.insert(*class, *first);
+                }
+                None => {
+                    firsts.insert(nodes, *class);
+                }
+            }
+        }
+        if merged_into.len() == before {
+            break;
+        }
+    }
+    if merged_into.is_empty() {
+        return 0;
+    }
+
+    let canonical: HashMap<ClassId, ClassId> = merged_into.keys().map(|class| (*class, find(&merged_into, *class))).collect();
+    data.nodes.retain(|_, node| !canonical.contains_key(&node.eclass));
+    for node in data.nodes.values_mut() {
+        for child in node.children.iter_mut() {
+            *child = canonical.get(child).copied().unwrap_or(*child);
+        }
+    }
+    let mut seen = HashSet::new();
+    data.nodes.retain(|_, node| seen.insert((node.eclass, node.op.clone(), node.children.clone(), node.cost, node.area)));
+    // Roots stay in place, an output that becomes another's is still an output.
+    for root in data.root_eclasses.iter_mut() {
+        *root = canonical.get(root).copied().unwrap_or(*root);
+    }
+    data.class_data.retain(|class, _| !canonical.contains_key(class));
+    canonical.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egraph_serialize::Node;
+    use indexmap::IndexMap;
+    use ordered_float::NotNan;
+
+    fn node(class: u32, index: u32, op: &str, children: &[u32], cost: f64) -> (NodeId, Node) {
+        let id = NodeId::from((class, index));
+        let node = Node {
+            op: op.to_string(),
+            id,
+            children: children.iter().map(|child| ClassId::from(*child)).collect(),
+            eclass: ClassId::from(class),
+            cost: NotNan::new(cost).unwrap(),
+            area: None,
+        };
+        (id, node)
+    }
+
+    #[test]
+    fn test_keeps_cheapest_duplicate() {
+        let nodes: IndexMap<NodeId, Node> = [
+            node(0, 0, "a", &[], 1.0),
+            node(1, 0, "b", &[], 1.0),
+            node(2, 0, "add", &[0, 1], 2.0),
+            node(2, 1, "add", &[1, 0], 1.0),
+            node(2, 2, "mul", &[0, 0], 1.0),
+            node(2, 3, "sub", &[0, 1], 1.0),
+        ]
+        .into_iter()
+        .collect();
+        let mut data = Data { nodes, root_eclasses: vec![ClassId::from(2)], class_data: IndexMap::new() };
+
+        assert_eq!(remove_redundant_nodes(&mut data), 2);
+        let left: Vec<NodeId> = data.nodes.keys().cloned().collect();
+        assert_eq!(left, vec![NodeId::from((0, 0)), NodeId::from((1, 0)), NodeId::from((2, 1)), NodeId::from((2, 2))]);
+    }
+
+    /// Classes 1 and 2 are the same `a`, which makes 3 and 4 the same `f` of it
+    /// and the two nodes of 5 identical.
+    #[test]
+    fn test_merges_duplicate_classes() {
+        let nodes: IndexMap<NodeId, Node> = [
+            node(0, 0, "x", &[], 1.0),
+            node(1, 0, "a", &[], 1.0),
+            node(2, 0, "a", &[], 1.0),
+            node(3, 0, "f", &[1, 0], 2.0),
+            node(4, 0, "f", &[2, 0], 2.0),
+            node(5, 0, "g", &[3], 1.0),
+            node(5, 1, "g", &[4], 1.0),
+            node(6, 0, "f", &[2, 0], 3.0),
+        ]
+        .into_iter()
+        .collect();
+        let mut data = Data { nodes, root_eclasses: vec![ClassId::from(5), ClassId::from(4), ClassId::from(6)], class_data: IndexMap::new() };
+
+        assert_eq!(merge_duplicate_classes(&mut data), 2);
+        let left: Vec<NodeId> = data.nodes.keys().cloned().collect();
+        assert_eq!(left, vec![NodeId::from((0, 0)), NodeId::from((1, 0)), NodeId::from((3, 0)), NodeId::from((5, 0)), NodeId::from((6, 0))]);
+        assert_eq!(data.nodes[&NodeId::from((6, 0))].children, vec![ClassId::from(1), ClassId::from(0)]);
+        assert_eq!(data.root_eclasses, vec![ClassId::from(5), ClassId::from(3), ClassId::from(6)]);
+    }
+}
@@ -0,0 +1,339 @@
+//! Reading the solvers' solutions: which node of every class the `N_<cid>_<nid>`
+//! variables select. `parse_solution` reads a solution file of any back end,
+//! telling the formats apart with `SolutionFormat::detect`.
+
+use egraph_serialize::{ClassId, NodeId};
+use extraction_gym::ExtractionResult;
+use indexmap::IndexMap;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+/// The solution files `parse_solution` reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolutionFormat {
+    /// `<name> <value>` lines after `#` comments, as written by Gurobi
+    /// (`ResultFile=*.sol`), by the Gurobi and CPLEX wrappers and by `highs
+    /// --solution_file`, whose dual values are skipped.
+    Gurobi,
+    /// A status line, then `<index> <name> <value> <objective>` lines, as written
+    /// by `cbc -solu`.
+    Cbc,
+    /// A `<CPLEXSolution>` XML file, as written by CPLEX's `write`. Of a solution
+    /// pool file, the first solution.
+    Cplex,
+    /// `<name> <value>` lines without a header, as written by the CP-SAT wrapper
+    /// (`cpsat/cpsat`), or `<name>: <value>` ones.
+    CpSat,
+}
+
+impl SolutionFormat {
+    /// The format of a solution file, by its first line.
+    pub fn detect(contents: &str) -> SolutionFormat {
+        let first = contents.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+        let fields: Vec<&str> = first.split_whitespace().collect();
+        if first.starts_with('<') {
+            SolutionFormat::Cplex
+        } else if first.contains(" - objective value") || (fields.len() > 2 && fields[0].parse::<usize>().is_ok()) {
+            SolutionFormat::Cbc
+        } else if first.starts_with('#') || first.starts_with("Model status") {
+            SolutionFormat::Gurobi
+        } else {
+            SolutionFormat::CpSat
+        }
+    }
+}
+
+/// Why a solution couldn't be read.
+#[derive(Debug)]
+pub enum SolutionError {
+    Io(std::io::Error),
+    /// The solution file has nothing but whitespace.
+    Empty,
+    /// The value of a variable, on that line of the file, isn't a number.
+    InvalidValue { line: usize, value: String },
+    /// A variable named like a node variable that isn't an `N_<cid>_<nid>` one.
+    InvalidVariable(String),
+    /// More than one node of the class is selected.
+    SeveralNodes(ClassId),
+    /// A CPLEX solution that isn't well-formed XML or has no `<variables>`.
+    Xml(String),
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolutionError::Io(err) => write!(f, "failed to read the solution: {}", err),
+            SolutionError::Empty => write!(f, "the solution is empty"),
+            SolutionError::InvalidValue { line, value } => write!(f, "line {}: invalid value {:?}", line, value),
+            SolutionError::InvalidVariable(name) => write!(f, "invalid node variable {}", name),
+            SolutionError::SeveralNodes(class) => write!(f, "class {} has several selected nodes", class),
+            SolutionError::Xml(err) => write!(f, "invalid CPLEX solution: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SolutionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SolutionError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SolutionError {
+    fn from(err: std::io::Error) -> Self {
+        SolutionError::Io(err)
+    }
+}
+
+/// The selected nodes of the solution file at `path`, in any `SolutionFormat`.
+pub fn parse_solution(path: impl AsRef<Path>) -> Result<ExtractionResult, SolutionError> {
+    parse_solution_str(&std::fs::read_to_string(path)?)
+}
+
+/// `parse_solution` of the contents of a solution file.
+pub fn parse_solution_str(contents: &str) -> Result<ExtractionResult, SolutionError> {
+    if contents.trim().is_empty() {
+        return Err(SolutionError::Empty);
+    }
+    parse_solution_as(contents, SolutionFormat::detect(contents))
+}
+
+/// `parse_solution_str` of a solution known to be in `format`.
+pub fn parse_solution_as(contents: &str, format: SolutionFormat) -> Result<ExtractionResult, SolutionError> {
+    match format {
+        SolutionFormat::Cplex => {
+            let values = parse_cplex_solution(contents)?;
+            from_values(values.iter().map(|(name, value)| (name.as_str(), *value)))
+        }
+        SolutionFormat::Cbc => parse_lines(contents, |fields| match fields {
+            [index, name, value, ..] if index.parse::<usize>().is_ok() => Some((name, value)),
+            _ => None,
+        }),
+        SolutionFormat::Gurobi => parse_lines(contents, |fields| match fields {
+            [name, value] => Some((name, value)),
+            _ => None,
+        }),
+        SolutionFormat::CpSat => parse_lines(contents, |fields| match fields {
+            [name, value] => Some((name.strip_suffix(':').unwrap_or(name), value)),
+            _ => None,
+        }),
+    }
+}
+
+/// The selected nodes of a solution of one variable per line, whose name and
+/// value `variable` picks out of the line's fields. Other lines and variables are
+/// skipped.
+fn parse_lines<'a>(
+    contents: &'a str,
+    variable: impl Fn(&[&'a str]) -> Option<(&'a str, &'a str)>,
+) -> Result<ExtractionResult, SolutionError> {
+    let mut ilp_solution = ExtractionResult::new(IndexMap::new());
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        // HiGHS lists the primal values first; its dual section reuses the column names.
+        if line.starts_with("# Dual solution values") {
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let Some((var_name, value)) = variable(&fields) {
+            if var_name.starts_with("N_") {
+                let value = value
+                    .parse::<f64>()
+                    .map_err(|_| SolutionError::InvalidValue { line: i + 1, value: value.to_string() })?;
+                choose_node_var(&mut ilp_solution, var_name, value)?;
+            }
+        }
+    }
+    Ok(ilp_solution)
+}
+
+/// The selected nodes of a solution given as variable values, e.g. read from a
+/// solver through its API. Variables other than the `N_<cid>_<nid>` ones are
+/// ignored.
+pub fn from_values<'a>(values: impl IntoIterator<Item = (&'a str, f64)>) -> Result<ExtractionResult, SolutionError> {
+    let mut ilp_solution = ExtractionResult::new(IndexMap::new());
+    for (var_name, value) in values {
+        if var_name.starts_with("N_") {
+            choose_node_var(&mut ilp_solution, var_name, value)?;
+        }
+    }
+    Ok(ilp_solution)
+}
+
+/// Selects the node of the `N_<cid>_<nid>` variable if it is set to 1.
+fn choose_node_var(ilp_solution: &mut ExtractionResult, var_name: &str, value: f64) -> Result<(), SolutionError> {
+    let (cid, nid) = var_name["N_".len()..]
+        .split_once('_')
+        .and_then(|(cid, nid)| Some((cid.parse::<u32>().ok()?, nid.parse::<u32>().ok()?)))
+        .ok_or_else(|| SolutionError::InvalidVariable(var_name.to_string()))?;
+    if value.round() as i32 == 1 {
+        if ilp_solution.choices.contains_key(&ClassId::from(cid)) {
+            return Err(SolutionError::SeveralNodes(ClassId::from(cid)));
+        }
+        ilp_solution.choose(ClassId::from(cid), NodeId::from((cid, nid)));
+    }
+    Ok(())
+}
+
+/// `parse_solution_str` of a solution that isn't in a file, e.g. received from a
+/// solver running elsewhere.
+pub fn read_solution(mut reader: impl Read) -> Result<ExtractionResult, SolutionError> {
+    let mut sol_contents = String::new();
+    reader.read_to_string(&mut sol_contents)?;
+    parse_solution_str(&sol_contents)
+}
+
+/// The values of the `N_` variables of a CPLEX XML solution, the `name` and
+/// `value` attributes of the `<variable>` elements of its first `<variables>`.
+pub fn parse_cplex_solution(contents: &str) -> Result<HashMap<String, f64>, SolutionError> {
+    let mut reader = Reader::from_str(contents);
+    reader.trim_text(true);
+    let line = |position: usize| contents[..position.min(contents.len())].matches('\n').count() + 1;
+    let mut variables = HashMap::new();
+    let mut in_variables = false;
+    let mut buf = Vec::new();
+    loop {
+        let event = reader
+            .read_event(&mut buf)
+            .map_err(|err| SolutionError::Xml(format!("line {}: {}", line(reader.buffer_position()), err)))?;
+        match event {
+            Event::Start(tag) if tag.name() == b"variables" => in_variables = true,
+            Event::End(tag) if tag.name() == b"variables" => return Ok(variables),
+            Event::Start(tag) | Event::Empty(tag) if in_variables && tag.name() == b"variable" => {
+                let (mut name, mut value) = (None, None);
+                for attribute in tag.attributes() {
+                    let attribute = attribute.map_err(|err| SolutionError::Xml(err.to_string()))?;
+                    let text = attribute.unescaped_value().map_err(|err| SolutionError::Xml(err.to_string()))?;
+                    let text = String::from_utf8_lossy(&text).into_owned();
+                    match attribute.key {
+                        b"name" => name = Some(text),
+                        b"value" => value = Some(text),
+                        _ => {}
+                    }
+                }
+                let at = line(reader.buffer_position());
+                let name = name.ok_or_else(|| SolutionError::Xml(format!("line {}: a variable has no name", at)))?;
+                if name.starts_with("N_") {
+                    let value = value.unwrap_or_default();
+                    let parsed = value.parse::<f64>().map_err(|_| SolutionError::InvalidValue { line: at, value })?;
+                    variables.insert(name, parsed);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Err(SolutionError::Xml("no <variables> section".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapper_solution() {
+        let solution = parse_solution_str("# Objective value = 3\nN_0_1 1\nN_1_0 0\nN_1_1 0.9999999\nA_0 1\n").unwrap();
+        assert_eq!(solution.choices.len(), 2);
+        assert_eq!(solution.choices[&ClassId::from(0)], NodeId::from((0, 1)));
+        assert_eq!(solution.choices[&ClassId::from(1)], NodeId::from((1, 1)));
+    }
+
+    #[test]
+    fn test_cbc_solution() {
+        let solution = parse_solution_str("Optimal - objective value 3\n      0 N_0_1  1  1\n      1 N_2_0  1  2\n").unwrap();
+        assert_eq!(solution.choices.len(), 2);
+        assert_eq!(solution.choices[&ClassId::from(2)], NodeId::from((2, 0)));
+    }
+
+    #[test]
+    fn test_highs_dual_section_is_skipped() {
+        let solution = parse_solution_str("# Primal solution values\nN_0_0 1\n# Dual solution values\nN_0_1 1\n").unwrap();
+        assert_eq!(solution.choices[&ClassId::from(0)], NodeId::from((0, 0)));
+    }
+
+    #[test]
+    fn test_two_nodes_of_a_class() {
+        assert!(parse_solution_str("N_0_0 1\nN_0_1 1\n").is_err());
+    }
+
+    #[test]
+    fn test_read_solution() {
+        let solution = read_solution("N_0_0 1\nN_1_2 1\n".as_bytes()).unwrap();
+        assert_eq!(solution.choices[&ClassId::from(1)], NodeId::from((1, 2)));
+    }
+
+    #[test]
+    fn test_solution_from_values() {
+        let solution = from_values([("N_0_1", 0.9999), ("N_0_0", 1e-7), ("A_0", 1.0), ("N_4_2", 1.0)]).unwrap();
+        assert_eq!(solution.choices.len(), 2);
+        assert_eq!(solution.choices[&ClassId::from(4)], NodeId::from((4, 2)));
+        assert!(from_values([("N_0_x", 1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_cplex_solution() {
+        let xml = "<CPLEXSolution>\n <variables>\n  <variable name=\"N_3_1\" index=\"0\" value=\"1\"/>\n  <variable name=\"A_3\" index=\"1\" value=\"1\"/>\n </variables>\n</CPLEXSolution>";
+        let variables = parse_cplex_solution(xml).unwrap();
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables["N_3_1"], 1.0);
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(SolutionFormat::detect("# Solution for model obj\nN_0_0 1\n"), SolutionFormat::Gurobi);
+        assert_eq!(SolutionFormat::detect("Model status\nOptimal\n"), SolutionFormat::Gurobi);
+        assert_eq!(SolutionFormat::detect("Stopped on time - objective value 7\n"), SolutionFormat::Cbc);
+        assert_eq!(SolutionFormat::detect("\n<?xml version = \"1.0\"?>\n<CPLEXSolution/>"), SolutionFormat::Cplex);
+        assert_eq!(SolutionFormat::detect("N_0_0 1\n"), SolutionFormat::CpSat);
+    }
+
+    #[test]
+    fn test_cpsat_solution() {
+        let solution = parse_solution_str("N_0_0 0\nN_0_1 1\nN_1_0: 1\n").unwrap();
+        assert_eq!(solution.choices[&ClassId::from(0)], NodeId::from((0, 1)));
+        assert_eq!(solution.choices[&ClassId::from(1)], NodeId::from((1, 0)));
+    }
+
+    #[test]
+    fn test_cplex_solution_file() {
+        let xml = "<?xml version = \"1.0\" standalone=\"yes\"?>\n<CPLEXSolutions>\n<CPLEXSolution version=\"1.2\">\n <variables>\n  \
+                   <variable index=\"0\" value=\"1\"\n   name=\"N_2_4\"/>\n  <variable name=\"N_2_0\" index=\"1\" value=\"-0\"/>\n </variables>\n\
+                   </CPLEXSolution>\n<CPLEXSolution>\n <variables>\n  <variable name=\"N_2_0\" value=\"1\"/>\n </variables>\n</CPLEXSolution>\n</CPLEXSolutions>\n";
+        let solution = parse_solution_str(xml).unwrap();
+        assert_eq!(solution.choices.len(), 1);
+        assert_eq!(solution.choices[&ClassId::from(2)], NodeId::from((2, 4)));
+        assert!(matches!(parse_cplex_solution("<CPLEXSolution></CPLEXSolution>"), Err(SolutionError::Xml(_))));
+        assert!(matches!(parse_cplex_solution("<CPLEXSolution><variables>"), Err(SolutionError::Xml(_))));
+    }
+
+    #[test]
+    fn test_solution_errors() {
+        assert!(matches!(parse_solution_str(" \n"), Err(SolutionError::Empty)));
+        assert!(matches!(
+            parse_solution_str("# Objective value = 1\nN_0_0 1\nN_1_0 x\n"),
+            Err(SolutionError::InvalidValue { line: 3, .. })
+        ));
+        assert!(matches!(parse_solution_str("N_0 1\n"), Err(SolutionError::InvalidVariable(_))));
+        assert!(matches!(parse_solution_str("N_5_0 1\nN_5_1 1\n"), Err(SolutionError::SeveralNodes(class)) if class == ClassId::from(5)));
+        assert!(matches!(parse_solution("no/such/solution.sol"), Err(SolutionError::Io(_))));
+    }
+
+    #[test]
+    fn test_parse_solution_file() {
+        let path = std::env::temp_dir().join(format!("e-boost-solution-{}.sol", std::process::id()));
+        std::fs::write(&path, "# Objective value = 2\nN_7_1 1\n").unwrap();
+        let solution = parse_solution(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(solution.unwrap().choices[&ClassId::from(7)], NodeId::from((7, 1)));
+    }
+}
@@ -0,0 +1,123 @@
+//! MIP starts in the formats the solvers read, written from the hints of an
+//! `IlpModel`. With a start, `ilp_gen::build_model` hints every variable, so the
+//! starts are complete: the class variables `A_<cid>` are set with the nodes, and
+//! no solver has to complete (or reject) a partial assignment.
+//!
+//! - Gurobi: an MST file, `<name> <value>` lines. The Gurobi and CPLEX wrappers
+//!   read it as well, CBC a rewrite of it (`-mipstart`).
+//! - CPLEX: CPLEX's own MST format, the XML of its solution files, as the CPLEX
+//!   interactive optimizer reads it (`read start.mst`).
+//! - CP-SAT: `<name> <0|1>` lines of the node and class variables, the only ones
+//!   the CP-SAT wrapper (`--total_gurobi_mst`) knows; it rebuilds the rest.
+
+use crate::ilp_model::IlpModel;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The solvers whose warm start format `to_start` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Solver {
+    Gurobi,
+    Cplex,
+    CpSat,
+}
+
+impl Solver {
+    /// The solver of a `--solver` name. HiGHS takes no start and CBC takes the
+    /// Gurobi one, so `None` for those.
+    pub fn from_name(name: &str) -> Option<Solver> {
+        match name {
+            "gurobi" => Some(Solver::Gurobi),
+            "cplex" => Some(Solver::Cplex),
+            "cpsat" => Some(Solver::CpSat),
+            _ => None,
+        }
+    }
+
+    /// The extension of the solver's start file, next to the MST of the LP.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Solver::Gurobi => "mst",
+            Solver::Cplex => "cplex.mst",
+            Solver::CpSat => "cpsat.mst",
+        }
+    }
+}
+
+/// The hints of `model` as a start in the format of `solver`. Variables without a
+/// hint are left out.
+pub fn to_start(model: &IlpModel, solver: Solver) -> String {
+    let hinted = || model.vars.iter().filter_map(|var| var.hint.map(|value| (var.name.as_str(), value)));
+    let mut start = String::new();
+    match solver {
+        Solver::Gurobi => {
+            for (name, value) in hinted() {
+                writeln!(start, "{} {}", name, value).unwrap();
+            }
+        }
+        Solver::Cplex => {
+            // Names are enough for CPLEX to match the columns, whose indices depend on
+            // the order of the LP file.
+            let objective: f64 = model.objective.iter().map(|(var, coef)| coef * model.var(*var).hint.unwrap_or(0.0)).sum();
+            start.push_str("<?xml version = \"1.0\" standalone=\"yes\"?>\n<CPLEXSolutions version=\"1.2\">\n <CPLEXSolution version=\"1.2\">\n");
+            writeln!(start, "  <header\n    solutionName=\"e-boost\"\n    objectiveValue=\"{}\"/>", objective).unwrap();
+            start.push_str("  <variables>\n");
+            for (name, value) in hinted() {
+                writeln!(start, "   <variable name=\"{}\" value=\"{}\"/>", name, value).unwrap();
+            }
+            start.push_str("  </variables>\n </CPLEXSolution>\n</CPLEXSolutions>\n");
+        }
+        Solver::CpSat => {
+            for (name, value) in hinted().filter(|(name, _)| name.starts_with("N_") || name.starts_with("A_")) {
+                writeln!(start, "{} {}", name, value.round() as i64).unwrap();
+            }
+        }
+    }
+    start
+}
+
+/// Writes `to_start(model, solver)` to `path`.
+pub fn write_start(model: &IlpModel, solver: Solver, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, to_start(model, solver))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ilp_gen::{build_model, ModelOptions};
+    use crate::solution;
+    use egraph_serialize::EGraphBuilder;
+    use extraction_gym::ExtractionResult;
+
+    #[test]
+    fn test_starts() {
+        // Root class `f(x)` or `y`, the start takes `y`.
+        let mut builder = EGraphBuilder::new();
+        let x = builder.leaf("x");
+        let root = builder.new_class();
+        builder.add_node(root, "f", [x]);
+        let y = builder.add_node(root, "y", []);
+        let egraph = builder.root(root).build().unwrap();
+        let start = ExtractionResult::new([(root, y)].into_iter().collect());
+        let model = build_model(&egraph, &egraph.root_eclasses, ModelOptions::default(), Some(&start));
+
+        let gurobi = to_start(&model, Solver::Gurobi);
+        assert_eq!(gurobi, model.to_mst());
+        assert!(gurobi.lines().any(|line| line == format!("A_{} 0", x)));
+        let cpsat = to_start(&model, Solver::CpSat);
+        assert!(cpsat.lines().all(|line| line.starts_with("N_") || line.starts_with("A_")));
+        assert!(cpsat.contains(&format!("A_{} 1\n", root)) && cpsat.contains(&format!("A_{} 0\n", x)));
+        assert_eq!(cpsat.lines().count(), 3 + 2);
+
+        let cplex = to_start(&model, Solver::Cplex);
+        assert!(cplex.contains("objectiveValue=\"1\""));
+        let values = solution::parse_cplex_solution(&cplex).unwrap();
+        assert_eq!(values.len(), 3);
+        for format in [gurobi, cplex, cpsat] {
+            let parsed = solution::parse_solution_str(&format).unwrap();
+            assert_eq!(parsed.choices.into_iter().collect::<Vec<_>>(), [(root, y)]);
+        }
+        assert_eq!(Solver::from_name("cpsat").map(Solver::extension), Some("cpsat.mst"));
+        assert_eq!(Solver::from_name("highs"), None);
+    }
+}
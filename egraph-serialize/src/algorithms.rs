@@ -1,4 +1,5 @@
-use crate::EGraph;
+use crate::{ClassId, EGraph};
+use std::collections::{HashMap, HashSet};
 
 pub const MISSING_ARG_VALUE: &str = "·";
 
@@ -111,4 +112,41 @@ impl EGraph {
     pub fn saturate_inline_leaves(&mut self) {
         while self.inline_leaves() > 0 {}
     }
+
+    /// Removes the classes not reachable from `roots` and the nodes with a child
+    /// class that has no node, until neither is left: removing a node can leave its
+    /// class without nodes and other classes unreachable. `root_eclasses` is kept,
+    /// so a root whose nodes were all removed is still listed. Returns how many
+    /// nodes were removed.
+    pub fn prune_unreachable(&mut self, roots: &[ClassId]) -> usize {
+        let before = self.nodes.len();
+        loop {
+            let count = self.nodes.len();
+            let classes: HashSet<ClassId> = self.nodes.values().map(|node| node.eclass).collect();
+            self.nodes.retain(|_, node| node.children.iter().all(|child| classes.contains(child)));
+
+            let mut class_nodes: HashMap<ClassId, Vec<&crate::Node>> = HashMap::new();
+            for node in self.nodes.values() {
+                class_nodes.entry(node.eclass).or_default().push(node);
+            }
+            let mut reachable: HashSet<ClassId> = HashSet::new();
+            let mut todo: Vec<ClassId> = roots.to_vec();
+            while let Some(class) = todo.pop() {
+                if !reachable.insert(class) {
+                    continue;
+                }
+                for node in class_nodes.get(&class).into_iter().flatten() {
+                    todo.extend(node.children.iter().filter(|child| !reachable.contains(child)));
+                }
+            }
+            self.nodes.retain(|_, node| reachable.contains(&node.eclass));
+            if self.nodes.len() == count {
+                break;
+            }
+        }
+        let classes: HashSet<ClassId> = self.nodes.values().map(|node| node.eclass).collect();
+        self.class_data.retain(|class, _| classes.contains(class));
+        self.once_cell_classes = Default::default();
+        before - self.nodes.len()
+    }
 }
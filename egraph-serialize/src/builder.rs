@@ -0,0 +1,146 @@
+use crate::{ClassData, ClassId, Cost, EGraph, Node, NodeId};
+
+/// Builds an [`EGraph`] in code, handing out the class and node ids.
+///
+/// Classes are numbered from 0 in the order they are created, and the nodes of
+/// a class from 0 in the order they are added, as `NodeId([class, index])`. A
+/// class can be used as a child before it has nodes, which is how cycles are
+/// built; [`build`](EGraphBuilder::build) then checks that every class used as
+/// a child or root has a node.
+///
+/// ```
+/// use egraph_serialize::EGraphBuilder;
+///
+/// let mut builder = EGraphBuilder::new();
+/// let a = builder.leaf("a");
+/// let b = builder.leaf("b");
+/// let sum = builder.term("+", [a, b]);
+/// builder.add_node(sum, "+", [b, a]);
+/// let egraph = builder.root(sum).build().unwrap();
+/// assert_eq!(egraph.classes()[&sum].nodes.len(), 2);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct EGraphBuilder {
+    egraph: EGraph,
+    /// Nodes added to each class so far, by class id.
+    class_sizes: Vec<u32>,
+    /// Nodes added with a NaN cost, reported by `build`.
+    nan_costs: Vec<NodeId>,
+}
+
+/// Why [`EGraphBuilder::build`] refused an e-graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// The node has a child class the builder never created.
+    UnknownChild { node: NodeId, child: ClassId },
+    /// The node has a child class without nodes.
+    EmptyChild { node: NodeId, child: ClassId },
+    /// A root the builder never created, or without nodes.
+    InvalidRoot(ClassId),
+    NanCost(NodeId),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::UnknownChild { node, child } => write!(f, "node {} has child class {}, which was never created", node, child),
+            BuildError::EmptyChild { node, child } => write!(f, "node {} has child class {}, which has no node", node, child),
+            BuildError::InvalidRoot(class) => write!(f, "root class {} has no node", class),
+            BuildError::NanCost(node) => write!(f, "node {} has a NaN cost", node),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl EGraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new class without nodes.
+    pub fn new_class(&mut self) -> ClassId {
+        self.class_sizes.push(0);
+        ClassId(self.class_sizes.len() as u32 - 1)
+    }
+
+    /// Adds a node of cost 1 to `class`.
+    pub fn add_node(&mut self, class: ClassId, op: impl Into<String>, children: impl IntoIterator<Item = ClassId>) -> NodeId {
+        self.add_node_with_cost(class, op, children, 1.0)
+    }
+
+    /// Adds a node to `class`. Panics if the builder didn't create `class`.
+    pub fn add_node_with_cost(
+        &mut self,
+        class: ClassId,
+        op: impl Into<String>,
+        children: impl IntoIterator<Item = ClassId>,
+        cost: f64,
+    ) -> NodeId {
+        let size = self
+            .class_sizes
+            .get_mut(class.0 as usize)
+            .unwrap_or_else(|| panic!("class {} was not created by this builder", class));
+        let id = NodeId([class.0, *size]);
+        *size += 1;
+        let cost = Cost::new(cost).unwrap_or_else(|_| {
+            self.nan_costs.push(id);
+            Cost::default()
+        });
+        let node = Node { op: op.into(), id, children: children.into_iter().collect(), eclass: class, cost, area: None };
+        self.egraph.add_node(id, node);
+        id
+    }
+
+    /// A new class with a single node of cost 1.
+    pub fn term(&mut self, op: impl Into<String>, children: impl IntoIterator<Item = ClassId>) -> ClassId {
+        let class = self.new_class();
+        self.add_node(class, op, children);
+        class
+    }
+
+    /// A new class with a single node without children, of cost 1.
+    pub fn leaf(&mut self, op: impl Into<String>) -> ClassId {
+        self.term(op, [])
+    }
+
+    /// Sets the area of a node added to the builder, see `Node::area`.
+    pub fn area(&mut self, node: NodeId, area: Cost) -> &mut Self {
+        self.egraph.nodes.get_mut(&node).unwrap_or_else(|| panic!("node {} was not added to this builder", node)).area = Some(area);
+        self
+    }
+
+    /// Adds `class` to the root e-classes.
+    pub fn root(&mut self, class: ClassId) -> &mut Self {
+        self.egraph.root_eclasses.push(class);
+        self
+    }
+
+    pub fn class_data(&mut self, class: ClassId, data: ClassData) -> &mut Self {
+        self.egraph.class_data.insert(class, data);
+        self
+    }
+
+    /// The e-graph, once every child and root class has a node. The builder is
+    /// left as it was, to build more of the e-graph on.
+    pub fn build(&self) -> Result<EGraph, BuildError> {
+        if let Some(node) = self.nan_costs.first() {
+            return Err(BuildError::NanCost(*node));
+        }
+        let has_nodes = |class: &ClassId| self.class_sizes.get(class.0 as usize).is_some_and(|size| *size > 0);
+        for (id, node) in &self.egraph.nodes {
+            for child in &node.children {
+                if child.0 as usize >= self.class_sizes.len() {
+                    return Err(BuildError::UnknownChild { node: *id, child: *child });
+                }
+                if !has_nodes(child) {
+                    return Err(BuildError::EmptyChild { node: *id, child: *child });
+                }
+            }
+        }
+        if let Some(root) = self.egraph.root_eclasses.iter().find(|root| !has_nodes(root)) {
+            return Err(BuildError::InvalidRoot(*root));
+        }
+        Ok(self.egraph.clone())
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fmt, io::Write};
 
-use crate::EGraph;
+use crate::{ClassId, Cost, EGraph};
 use graphviz_rust::{
     attributes::*,
     dot_generator::*,
@@ -34,23 +34,19 @@ impl EGraph {
     }
 
     fn to_graphviz(&self) -> Graph {
-        // 1. Group nodes by type and class (use BTreeMap to keep sorted so colors are consistent)
+        // 1. Group classes by type (use BTreeMap to keep sorted so colors are consistent)
         let mut class_nodes = std::collections::BTreeMap::new();
-        //  and create mapping from each node ID to its class
-        let mut node_to_class = std::collections::HashMap::new();
-        for (node_id, node) in &self.nodes {
+        for (class_id, class) in self.classes() {
             let typ = self
                 .class_data
-                .get(&node.eclass)
+                .get(class_id)
                 .and_then(|data| data.typ.clone());
-            node_to_class.insert(node_id.clone(), node.eclass.clone());
             class_nodes
                 .entry(typ)
-                .or_insert_with(std::collections::HashMap::new)
-                .entry(node.eclass.clone())
                 .or_insert_with(Vec::new)
-                .push((node_id.clone(), node));
+                .push((class_id.clone(), class));
         }
+        let min_costs = self.min_class_costs();
         // 2. Start with configuration
         let mut stmts = vec![
             // Set to compound so we can have edge to clusters
@@ -80,26 +76,27 @@ impl EGraph {
         // Mapping of sort names to color index
         let mut typ_colors = std::collections::HashMap::new();
 
-        for (typ, class_to_node) in class_nodes {
+        for (typ, classes) in class_nodes {
             let next_color = (typ_colors.len() + INITIAL_COLOR) % N_COLORS;
             let color = typ_colors.entry(typ).or_insert(next_color);
             stmts.push(stmt!(attr!("fillcolor", color)));
-            for (class_id, nodes) in class_to_node {
+            for (class_id, class) in classes {
                 let mut inner_stmts = vec![];
 
                 // Add nodes
-                for (node_id, node) in nodes {
-                    let label = node.op.as_ref();
-                    let tooltip = format!("{}: {}", class_id, node_id);
-                    let html_label = html_label(label, node.children.len());
+                for node_id in &class.nodes {
+                    let node = &self[node_id];
+                    let tooltip = format!("{}: {} (cost {})", class_id, node_id, node.cost);
+                    let html_label = html_label(&node.op, node.children.len());
                     let quoted_tooltip = quote(&tooltip);
-                    let quoted_node_id = quote(node_id.as_ref());
+                    let quoted_node_id = quote(&node_id.to_string());
                     // Add edges
                     for (i, child) in node.children.iter().enumerate() {
+                        // Children are e-classes: point at one of their nodes and clip the edge
+                        // at the border of the child's cluster
                         let source = node_id!(quoted_node_id, port!(id!(i), "s"));
-                        let target = node_id!(quote(child.as_ref()));
-                        let child_eclass = node_to_class.get(child).unwrap();
-                        let child_subgraph_id = format!("cluster_{}", child_eclass);
+                        let target = node_id!(quote(&self[child].nodes[0].to_string()));
+                        let child_subgraph_id = format!("cluster_{}", child);
                         let edge = edge!(source => target; EdgeAttributes::lhead(quote(&child_subgraph_id)));
                         // Make sure edge is part of outer statements so it doesn't add nodes to the subgraph which
                         // don't belong there
@@ -112,14 +109,19 @@ impl EGraph {
                 let subgraph_id = format!("cluster_{}", class_id);
                 let outer_subgraph_id = quote(&format!("outer_{}", subgraph_id));
                 let quoted_subgraph_id = quote(&subgraph_id);
+                let class_label = match min_costs.get(&class_id) {
+                    Some(cost) => format!("{}: min cost {}", class_id, cost),
+                    // Only reachable through cycles, nothing can be extracted from it
+                    None => format!("{}: no finite term", class_id),
+                };
 
                 let subgraph = subgraph!(outer_subgraph_id;
-                    // Disable label for now, to reduce size
-                    // NodeAttributes::label(subgraph_html_label(&typ)),
-
                     // Nest in empty sub-graph so that we can use rank=same
                     // https://stackoverflow.com/a/55562026/907060
-                    subgraph!(quoted_subgraph_id; subgraph!("", inner_stmts)),
+                    subgraph!(quoted_subgraph_id;
+                        SubgraphAttributes::label(quote(&class_label)),
+                        subgraph!("", inner_stmts)
+                    ),
 
                     // Make outer subgraph a cluster but make it invisible, so just used for padding
                     // https://forum.graphviz.org/t/how-to-add-space-between-clusters/1209/3
@@ -138,6 +140,33 @@ impl EGraph {
         stmts.push(stmt!(GraphAttributes::margin(0.0)));
         graph!(di id!(), stmts)
     }
+
+    /// Cost of the cheapest term of each e-class (tree cost, shared subterms counted
+    /// every time). Classes whose every node depends on a cycle get no entry.
+    fn min_class_costs(&self) -> std::collections::HashMap<ClassId, Cost> {
+        let mut costs = std::collections::HashMap::new();
+        // Bellman-Ford style: costs only settle further after a round that changed
+        // something, and with non-negative costs a class settles within a round per class.
+        for _ in 0..=self.classes().len() {
+            let mut changed = false;
+            for node in self.nodes.values() {
+                let cost = node
+                    .children
+                    .iter()
+                    .try_fold(node.cost, |sum, child| Some(sum + *costs.get(child)?));
+                if let Some(cost) = cost {
+                    if costs.get(&node.eclass).map_or(true, |current| cost < *current) {
+                        costs.insert(node.eclass.clone(), cost);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        costs
+    }
 }
 
 // Number of colors in the graphviz color scheme
@@ -146,10 +175,13 @@ const N_COLORS: usize = 12;
 // Initial color to use for the first type
 const INITIAL_COLOR: usize = 2;
 
-/// Returns an html label for the node with the function name and ports for each argumetn
+/// Returns an html label for the node with the function name and ports for each argumetn.
+/// Leaves get a grey background, so that the terms' inputs stand out.
 fn html_label(label: &str, n_args: usize) -> String {
+    let bgcolor = if n_args == 0 { "gray90" } else { "white" };
     format!(
-        "<<TABLE BGCOLOR=\"white\" CELLBORDER=\"0\" CELLSPACING=\"0\" CELLPADDING=\"0\" style=\"rounded\"><tr><td BALIGN=\"left\" CELLPADDING=\"4\" WIDTH=\"30\" HEIGHT=\"30\"{}>{}</td></tr>{}</TABLE>>",
+        "<<TABLE BGCOLOR=\"{}\" CELLBORDER=\"0\" CELLSPACING=\"0\" CELLPADDING=\"0\" style=\"rounded\"><tr><td BALIGN=\"left\" CELLPADDING=\"4\" WIDTH=\"30\" HEIGHT=\"30\"{}>{}</td></tr>{}</TABLE>>",
+        bgcolor,
         (if n_args  == 0 {"".to_string()} else {format!(" colspan=\"{}\"", n_args)}),
         Escape(label),
         (if n_args == 0 {
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+
+use crate::{one, ClassData, ClassId, Cost, Data, Node, NodeId};
+
+/// The legacy "a.b" keyed JSON, read straight into `Data`: node ids are parsed
+/// as the map is read, so only the converted node map is ever held, instead of
+/// a `Data_old` and its conversion side by side.
+#[derive(serde::Deserialize)]
+pub(crate) struct LegacyData {
+    nodes: LegacyNodes,
+    root_eclasses: Vec<ClassId>,
+    #[serde(default)]
+    class_data: IndexMap<ClassId, ClassData>,
+}
+
+impl From<LegacyData> for Data {
+    fn from(legacy: LegacyData) -> Self {
+        Data { nodes: legacy.nodes.0, root_eclasses: legacy.root_eclasses, class_data: legacy.class_data }
+    }
+}
+
+struct LegacyNodes(IndexMap<NodeId, Node>);
+
+/// A node without its id: the inner "id" duplicates the map key, which is the
+/// one used (as the conversion from `Data_old` did), so it is skipped unread.
+#[derive(serde::Deserialize)]
+struct LegacyNode {
+    op: String,
+    #[serde(default)]
+    children: Vec<ClassId>,
+    eclass: ClassId,
+    #[serde(default = "one")]
+    cost: Cost,
+    #[serde(default)]
+    area: Option<Cost>,
+}
+
+/// A "a.b" node id.
+struct LegacyNodeId(NodeId);
+
+impl<'de> Deserialize<'de> for LegacyNodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl Visitor<'_> for IdVisitor {
+            type Value = LegacyNodeId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a node id of the form \"a.b\"")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                let (a, b) = s.split_once('.').ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))?;
+                match (a.parse(), b.parse()) {
+                    (Ok(a), Ok(b)) => Ok(LegacyNodeId(NodeId([a, b]))),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(IdVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for LegacyNodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodesVisitor;
+
+        impl<'de> Visitor<'de> for NodesVisitor {
+            type Value = LegacyNodes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of \"a.b\" node ids to nodes")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut nodes = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((LegacyNodeId(id), node)) = map.next_entry::<LegacyNodeId, LegacyNode>()? {
+                    let LegacyNode { op, children, eclass, cost, area } = node;
+                    nodes.insert(id, Node { op, id, children, eclass, cost, area });
+                }
+                Ok(LegacyNodes(nodes))
+            }
+        }
+
+        deserializer.deserialize_map(NodesVisitor)
+    }
+}
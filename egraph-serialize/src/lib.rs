@@ -2,6 +2,10 @@
 mod graphviz;
 
 mod algorithms;
+mod builder;
+mod legacy;
+
+pub use builder::{BuildError, EGraphBuilder};
 
 use core::panic;
 use std::sync::Arc;
@@ -86,6 +90,13 @@ mod id_impls {
     }
 }
 
+/// A serialized e-graph.
+///
+/// `EGraph` is `Send + Sync`, but the classes are grouped on the first call to
+/// `classes` (or indexing by a `ClassId`), through a `OnceCell`. An e-graph shared
+/// between threads before that has its workers block on whichever of them builds
+/// the map, and one modified afterwards has stale classes. Threads should get a
+/// `FrozenEGraph` instead.
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct EGraph {
@@ -117,6 +128,41 @@ impl EGraph {
         };
     }
 
+    /// `add_node`, with an error instead of a panic when the id is taken; the
+    /// e-graph is then left as it was.
+    pub fn try_add_node(&mut self, node_id: impl Into<NodeId>, node: Node) -> Result<(), ValidationError> {
+        match self.nodes.entry(node_id.into()) {
+            Entry::Occupied(e) => Err(ValidationError::DuplicateNode(*e.key())),
+            Entry::Vacant(e) => {
+                e.insert(node);
+                Ok(())
+            }
+        }
+    }
+
+    /// Indexing by a `NodeId`, with an error instead of a panic for an unknown node.
+    pub fn get_node(&self, node_id: &NodeId) -> Result<&Node, ValidationError> {
+        self.nodes.get(node_id).ok_or(ValidationError::UnknownNode(*node_id))
+    }
+
+    /// Indexing by a `ClassId`, with an error instead of a panic for a class
+    /// without nodes.
+    pub fn get_class(&self, class_id: &ClassId) -> Result<&Class, ValidationError> {
+        self.classes().get(class_id).ok_or(ValidationError::UnknownClass(*class_id))
+    }
+
+    /// Checks that the e-graph has roots and that they and the children of every
+    /// node are classes with nodes, so that indexing by them doesn't panic.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.root_eclasses.is_empty() {
+            return Err(ValidationError::NoRoots);
+        }
+        for class_id in self.root_eclasses.iter().chain(self.nodes.values().flat_map(|node| &node.children)) {
+            self.get_class(class_id)?;
+        }
+        Ok(())
+    }
+
     pub fn nid_to_cid(&self, node_id: &NodeId) -> &ClassId {
         &self[node_id].eclass
     }
@@ -155,21 +201,26 @@ impl EGraph {
     }
 
     pub fn from_Data(data: &Data) -> std::io::Result<Self> {
-        let Data { nodes, root_eclasses } = data;
+        let Data { nodes, root_eclasses, class_data } = data;
         let mut egraph = Self {
             nodes: nodes.clone(),
             root_eclasses: root_eclasses.clone(),
+            class_data: class_data.clone(),
             ..Default::default()
         };
         egraph.once_cell_classes = Default::default();
         Ok(egraph)
     }
 
+    /// Writes the e-graph as compact JSON; see `to_json_file_pretty` for a readable file.
     #[cfg(feature = "serde")]
     pub fn to_json_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
-        let file = std::fs::File::create(path)?;
-        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
-        Ok(())
+        write_json_file(path, self, false)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json_file_pretty(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        write_json_file(path, self, true)
     }
 
     #[cfg(feature = "serde")]
@@ -180,10 +231,43 @@ impl EGraph {
     }
 }
 
+/// An `EGraph` with all its caches built, that cannot be modified while it is held.
+/// It derefs to the e-graph and is cheap to copy into every thread.
+#[derive(Debug, Clone, Copy)]
+pub struct FrozenEGraph<'a> {
+    egraph: &'a EGraph,
+}
+
+impl<'a> FrozenEGraph<'a> {
+    pub fn new(egraph: &'a EGraph) -> Self {
+        egraph.classes();
+        FrozenEGraph { egraph }
+    }
+
+    pub fn egraph(&self) -> &'a EGraph {
+        self.egraph
+    }
+}
+
+impl std::ops::Deref for FrozenEGraph<'_> {
+    type Target = EGraph;
+
+    fn deref(&self) -> &EGraph {
+        self.egraph
+    }
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<FrozenEGraph<'static>>();
+};
+
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct Data_old {
     pub nodes: IndexMap<NodeId_old, Node_old>,
     pub root_eclasses: Vec<ClassId>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub class_data: IndexMap<ClassId, ClassData>,
 }
 
 impl Data_old {
@@ -199,35 +283,43 @@ impl Data_old {
 pub struct Data {
     pub nodes: IndexMap<NodeId, Node>,
     pub root_eclasses: Vec<ClassId>,
+    #[serde(default)]
+    pub class_data: IndexMap<ClassId, ClassData>,
 }
 
 impl Data {
     pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
         let file = std::fs::File::open(path)?;
-        let data_old: Data_old = serde_json::from_reader(std::io::BufReader::new(&file))?;
-        
-        let mut new_nodes = IndexMap::new();
-        for (old_id, old_node) in data_old.nodes.into_iter() {
-            let new_id = convert_nodeid_old(&old_id);
-            // For internal node ids, convert them as well
-            let new_node = Node {
-                op: old_node.op,
-                id: new_id.clone(),
-                children: old_node.children, // children and eclass remain unchanged
-                eclass: old_node.eclass,
-                cost: old_node.cost,
-            };
-            new_nodes.insert(new_id, new_node);
-        }
-        
-        let data = Data {
-            nodes: new_nodes,
-            root_eclasses: data_old.root_eclasses,
-        };
-        Ok(data)
+        Self::from_json_reader(&file)
+    }
+
+    /// Same as `from_json_file`, but reads the legacy "a.b" keyed JSON from any reader
+    /// (e.g. a request body).
+    pub fn from_json_reader(reader: impl std::io::Read) -> std::io::Result<Self> {
+        let data: legacy::LegacyData = serde_json::from_reader(std::io::BufReader::new(reader))?;
+        Ok(data.into())
     }
 
+    /// Writes the legacy "a.b" keyed JSON without any whitespace, which is what the
+    /// intermediate files (partitions, the redundancy file) use: indenting doubles
+    /// their size and write time. `to_json_file_pretty` writes it indented.
     pub fn to_json_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        println!("{}", path.as_ref().display());
+        write_json_file(path, &self.to_old(), false)
+    }
+
+    pub fn to_json_file_pretty(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        println!("{}", path.as_ref().display());
+        write_json_file(path, &self.to_old(), true)
+    }
+
+    /// The legacy JSON of `to_json_file` to any writer (e.g. a socket), on a single
+    /// line; `from_json_reader` reads it back.
+    pub fn to_json_writer(&self, writer: impl std::io::Write) -> std::io::Result<()> {
+        Ok(serde_json::to_writer(writer, &self.to_old())?)
+    }
+
+    fn to_old(&self) -> Data_old {
         // Iterate through self.nodes, convert each node to old format
         let mut nodes_old = IndexMap::new();
         for (node_id, node) in &self.nodes {
@@ -238,20 +330,27 @@ impl Data {
                 children: node.children.clone(), // Other fields remain unchanged
                 eclass: node.eclass.clone(),
                 cost: node.cost,
+                area: node.area,
             };
             nodes_old.insert(old_id, node_old);
         }
-        let data_old = Data_old {
+        Data_old {
             nodes: nodes_old,
             root_eclasses: self.root_eclasses.clone(),
-        };
-        // Serialize to JSON string and write to file
-        let new_file_content =
-            serde_json::to_string_pretty(&data_old).expect("Unable to serialize JSON");
-        println!("{}", path.as_ref().display());
-        std::fs::write(path, new_file_content).expect("Unable to write file");
-        Ok(())
+            class_data: self.class_data.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn write_json_file<T: serde::Serialize>(path: impl AsRef<std::path::Path>, value: &T, pretty: bool) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, value)?;
+    } else {
+        serde_json::to_writer(&mut writer, value)?;
     }
+    std::io::Write::flush(&mut writer)
 }
 
 fn convert_nodeid_to_old(node_id: &NodeId) -> NodeId_old {
@@ -259,19 +358,47 @@ fn convert_nodeid_to_old(node_id: &NodeId) -> NodeId_old {
     NodeId_old(Arc::from(format!("{}.{}", node_id.0[0], node_id.0[1])))
 }
 
-fn convert_nodeid_old(old: &NodeId_old) -> NodeId {
-    // Assume NodeId_old internally stores strings in "a.b" format
-    let s: &str = &old.0;
-    let parts: Vec<&str> = s.split('.').collect();
-    if parts.len() != 2 {
-        panic!("Invalid NodeId_old format: {}", s);
-    }
-    let a = parts[0].parse::<u32>().expect("failed to parse first part");
-    let b = parts[1].parse::<u32>().expect("failed to parse second part");
-    NodeId([a, b])
+/// What is wrong with a node, a class or an extraction, from the fallible
+/// counterparts of the calls that panic on it (`EGraph::try_add_node`,
+/// `EGraph::get_node`, `EGraph::get_class`, and the e-graph and extraction
+/// checks built on them).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    DuplicateNode(NodeId),
+    UnknownNode(NodeId),
+    UnknownClass(ClassId),
+    /// The e-graph has no root class to extract.
+    NoRoots,
+    /// No node is chosen for `class`, which `parent` (or the roots, for `None`)
+    /// depends on.
+    MissingChoice { class: ClassId, parent: Option<NodeId> },
+    /// The node chosen for `class` belongs to another class.
+    WrongClass { class: ClassId, node: NodeId },
+    /// The chosen nodes depend on themselves through these classes.
+    Cycle(Vec<ClassId>),
 }
 
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::DuplicateNode(node) => write!(f, "duplicate node with id {}", node),
+            ValidationError::UnknownNode(node) => write!(f, "no node with id {}", node),
+            ValidationError::UnknownClass(class) => write!(f, "no class with id {}", class),
+            ValidationError::NoRoots => write!(f, "the e-graph has no root class"),
+            ValidationError::MissingChoice { class, parent: None } => write!(f, "no node chosen for root class {}", class),
+            ValidationError::MissingChoice { class, parent: Some(parent) } => {
+                write!(f, "no node chosen for class {}, a child of node {}", class, parent)
+            }
+            ValidationError::WrongClass { class, node } => write!(f, "node {} chosen for class {} is not in it", node, class),
+            ValidationError::Cycle(classes) => {
+                let classes: Vec<String> = classes.iter().map(|class| class.to_string()).collect();
+                write!(f, "the chosen nodes form a cycle through classes {}", classes.join(" -> "))
+            }
+        }
+    }
+}
 
+impl std::error::Error for ValidationError {}
 
 impl std::ops::Index<&NodeId> for EGraph {
     type Output = Node;
@@ -303,6 +430,9 @@ pub struct Node_old {
     pub eclass: ClassId,
     #[cfg_attr(feature = "serde", serde(default = "one"))]
     pub cost: Cost,
+    /// Area of the node, when it is not the cost being optimized.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub area: Option<Cost>,
 }
 
 
@@ -316,12 +446,20 @@ pub struct Node {
     pub eclass: ClassId,
     #[cfg_attr(feature = "serde", serde(default = "one"))]
     pub cost: Cost,
+    /// Area of the node, when it is not the cost being optimized.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub area: Option<Cost>,
 }
 
 impl Node {
     pub fn is_leaf(&self) -> bool {
         self.children.is_empty()
     }
+
+    /// The node's area, falling back to its cost when none is given.
+    pub fn area(&self) -> Cost {
+        self.area.unwrap_or(self.cost)
+    }
 }
 
 fn one() -> Cost {
@@ -339,4 +477,7 @@ pub struct Class {
 pub struct ClassData {
     #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub typ: Option<String>,
+    /// Latency of the nodes of this class, used when the delay model has no entry for their op.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub delay: Option<Cost>,
 }
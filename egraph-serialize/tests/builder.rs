@@ -0,0 +1,40 @@
+use egraph_serialize::*;
+
+/// `x * 2` and `x << 1` in one class, with `x` defined through itself.
+#[test]
+fn test_builder_allocates_ids() {
+    let mut builder = EGraphBuilder::new();
+    let x = builder.new_class();
+    let two = builder.leaf("2");
+    let one = builder.leaf("1");
+    let product = builder.term("*", [x, two]);
+    let shift = builder.add_node_with_cost(product, "<<", [x, one], 0.5);
+    builder.add_node(x, "x", []);
+    builder.add_node(x, "/", [product, two]);
+    let egraph = builder.root(product).build().unwrap();
+
+    assert_eq!(shift, NodeId::from((product.0, 1)));
+    assert_eq!(egraph.root_eclasses, vec![product]);
+    assert_eq!(egraph.classes().len(), 4);
+    assert_eq!(egraph.classes()[&x].nodes, vec![NodeId::from((0, 0)), NodeId::from((0, 1))]);
+    assert_eq!(egraph[&shift].cost, Cost::new(0.5).unwrap());
+    assert_eq!(egraph[&shift].children, vec![x, one]);
+}
+
+#[test]
+fn test_builder_validates_references() {
+    let mut builder = EGraphBuilder::new();
+    let empty = builder.new_class();
+    let f = builder.term("f", [empty]);
+    assert_eq!(builder.build(), Err(BuildError::EmptyChild { node: NodeId::from((f.0, 0)), child: empty }));
+
+    builder.add_node(empty, "x", []);
+    builder.add_node(f, "g", [ClassId::from(7)]);
+    assert_eq!(builder.build(), Err(BuildError::UnknownChild { node: NodeId::from((f.0, 1)), child: ClassId::from(7) }));
+
+    let mut builder = EGraphBuilder::new();
+    let root = builder.new_class();
+    assert_eq!(builder.root(root).build(), Err(BuildError::InvalidRoot(root)));
+    builder.add_node_with_cost(root, "nan", [], f64::NAN);
+    assert!(matches!(builder.build(), Err(BuildError::NanCost(_))));
+}
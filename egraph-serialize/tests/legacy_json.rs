@@ -0,0 +1,39 @@
+use egraph_serialize::*;
+
+/// The map key is the node id; the inner "id" and missing fields fall back as
+/// they did with `Data_old`.
+#[test]
+fn test_legacy_json_reader() {
+    let json = r#"{
+        "nodes": {
+            "0.0": {"op": "x", "id": "ignored", "eclass": 0},
+            "1.0": {"op": "f", "id": "1.0", "children": [0, 0], "eclass": 1, "cost": 2.5, "area": 3.0},
+            "1.12": {"op": "g", "id": "1.12", "children": [1], "eclass": 1, "cost": 0.0}
+        },
+        "root_eclasses": [1]
+    }"#;
+    let data = Data::from_json_reader(json.as_bytes()).unwrap();
+
+    let ids: Vec<NodeId> = data.nodes.keys().cloned().collect();
+    assert_eq!(ids, vec![NodeId::from((0, 0)), NodeId::from((1, 0)), NodeId::from((1, 12))]);
+    assert_eq!(data.nodes[&NodeId::from((0, 0))].id, NodeId::from((0, 0)));
+    assert_eq!(data.nodes[&NodeId::from((0, 0))].cost, Cost::new(1.0).unwrap());
+    assert_eq!(data.nodes[&NodeId::from((1, 0))].area, Some(Cost::new(3.0).unwrap()));
+    assert_eq!(data.nodes[&NodeId::from((1, 12))].children, vec![ClassId::from(1)]);
+    assert_eq!(data.root_eclasses, vec![ClassId::from(1)]);
+    assert!(data.class_data.is_empty());
+
+    let mut written = Vec::new();
+    data.to_json_writer(&mut written).unwrap();
+    let read = Data::from_json_reader(written.as_slice()).unwrap();
+    assert_eq!(read.nodes, data.nodes);
+}
+
+#[test]
+fn test_legacy_json_rejects_bad_ids() {
+    for id in ["3", "3.x", "-1.0", "1.2.3"] {
+        let json = format!(r#"{{"nodes": {{"{id}": {{"op": "x", "id": "{id}", "eclass": 0}}}}, "root_eclasses": []}}"#);
+        let err = Data::from_json_reader(json.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("a node id of the form"), "{}: {}", id, err);
+    }
+}
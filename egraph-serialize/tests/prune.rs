@@ -0,0 +1,31 @@
+use egraph_serialize::*;
+
+fn node(op: &str, id: (u32, u32), children: &[u32]) -> Node {
+    Node {
+        op: op.to_string(),
+        id: NodeId::from(id),
+        children: children.iter().map(|child| ClassId::from(*child)).collect(),
+        eclass: ClassId::from(id.0),
+        cost: Cost::new(1.0).unwrap(),
+        area: None,
+    }
+}
+
+/// `f` has a child without nodes, which leaves `g` without its class `1`; `x` is
+/// then only used by `f`, and `z` never was.
+#[test]
+fn test_prune_unreachable() {
+    let mut egraph = EGraph::default();
+    for node in [node("x", (0, 0), &[]), node("f", (1, 0), &[0, 3]), node("g", (2, 0), &[1]), node("y", (4, 0), &[])] {
+        egraph.add_node(node.id, node);
+    }
+    egraph.add_node(NodeId::from((2, 1)), node("h", (2, 1), &[4]));
+    egraph.add_node(NodeId::from((5, 0)), node("z", (5, 0), &[]));
+    let roots = [ClassId::from(2)];
+
+    assert_eq!(egraph.prune_unreachable(&roots), 4);
+    let left: Vec<NodeId> = egraph.nodes.keys().cloned().collect();
+    assert_eq!(left, vec![NodeId::from((4, 0)), NodeId::from((2, 1))]);
+    assert_eq!(egraph.classes().len(), 2);
+    assert_eq!(egraph.prune_unreachable(&roots), 0);
+}
@@ -0,0 +1,64 @@
+// cargo bench --bench queue
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use extraction_gym::queue::{UniquePriorityQueue, UniqueQueue};
+
+const N: u32 = 100_000;
+
+// Worklist traffic of the extractors: every item is pushed a few times while pending.
+fn items() -> Vec<u32> {
+    (0..N).chain((0..N).step_by(3)).chain((0..N).rev().step_by(7)).collect()
+}
+
+fn unique_queue(c: &mut Criterion) {
+    let items = items();
+    c.bench_function("unique_queue/pop", |b| {
+        b.iter_batched(
+            || items.clone(),
+            |items| {
+                let mut queue = UniqueQueue::default();
+                queue.extend(items);
+                while let Some(t) = queue.pop() {
+                    black_box(t);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    for batch_size in [1024, 8192, 16384] {
+        c.bench_function(&format!("unique_queue/pop_batch_{}", batch_size), |b| {
+            b.iter_batched(
+                || items.clone(),
+                |items| {
+                    let mut queue = UniqueQueue::with_batch_size(batch_size);
+                    queue.extend(items);
+                    while !queue.is_empty() {
+                        black_box(queue.pop_batch());
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+fn priority_queue(c: &mut Criterion) {
+    let items = items();
+    c.bench_function("unique_priority_queue/pop", |b| {
+        b.iter_batched(
+            || items.clone(),
+            |items| {
+                let mut queue = UniquePriorityQueue::default();
+                for t in items {
+                    queue.insert(t, t % 1024);
+                }
+                while let Some(t) = queue.pop() {
+                    black_box(t);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, unique_queue, priority_queue);
+criterion_main!(benches);
@@ -14,6 +14,7 @@ impl Extractor for BottomUpExtractor {
             for class in egraph.classes().values() {
                 for node in &class.nodes {
                     let cost = result.node_sum_cost(egraph, &egraph[node], &costs);
+                    result.record_cost(node.clone(), cost);
                     if &cost < costs.get(&class.id).unwrap_or(&INFINITY) {
                         result.choose(class.id.clone(), node.clone());
                         costs.insert(class.id.clone(), cost);
@@ -29,6 +30,11 @@ impl Extractor for BottomUpExtractor {
             }
         }
 
+        result.class_costs.extend(costs);
         result
     }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,121 @@
+//! JSON format for extraction results.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "choices": { "<class id>": [<class id>, <node index>], ... },
+//!   "costs": { "tree": 12.0, "dag": 7.0, "depth": 3, "area": 7.0 },
+//!   "meta": { "extractor": "faster-greedy-dag", ... }
+//! }
+//! ```
+//!
+//! `choices` maps every chosen e-class to the two parts of its selected node id
+//! (node `"12.1"` of the e-graph JSON is `[12, 1]`). `costs` holds the cost
+//! measures of the extraction over its roots; a measure that was
+//! infinite is written as `null`. `meta` is free-form information about the run
+//! and is carried over unchanged by the reader.
+
+use crate::*;
+use serde_json::{json, Map, Value};
+
+pub const VERSION: u64 = 1;
+
+#[derive(Clone, Debug, Default)]
+pub struct ChoicesFile {
+    pub choices: IndexMap<ClassId, NodeId>,
+    pub costs: IndexMap<String, Cost>,
+    pub meta: Map<String, Value>,
+}
+
+impl ChoicesFile {
+    /// The choices of `result` with its tree, DAG and depth costs over `roots`
+    /// and the area of the DAG selected from the e-graph roots.
+    pub fn from_result(egraph: &EGraph, result: &ExtractionResult, roots: &[ClassId]) -> Self {
+        let mut costs = IndexMap::new();
+        costs.insert("tree".to_string(), result.tree_cost(egraph, roots));
+        costs.insert("dag".to_string(), result.dag_cost(egraph, roots));
+        costs.insert("depth".to_string(), Cost::from(result.depth_cost(egraph, roots)));
+        costs.insert("area".to_string(), result.area_cost(egraph));
+        Self {
+            choices: result.choices.clone(),
+            costs,
+            meta: Map::new(),
+        }
+    }
+
+    pub fn with_meta(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.meta.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn to_result(&self) -> ExtractionResult {
+        ExtractionResult::new(self.choices.clone())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let choices: Map<String, Value> = self
+            .choices
+            .iter()
+            .map(|(cid, nid)| (cid.to_string(), json!([nid.0[0], nid.0[1]])))
+            .collect();
+        let costs: Map<String, Value> = self
+            .costs
+            .iter()
+            .map(|(name, cost)| (name.clone(), json!(cost.into_inner())))
+            .collect();
+        json!({
+            "version": VERSION,
+            "choices": choices,
+            "costs": costs,
+            "meta": self.meta,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        match value.get("version").and_then(Value::as_u64) {
+            Some(VERSION) => {}
+            Some(version) => return Err(format!("unsupported choices file version {}", version)),
+            None => return Err("missing version".to_string()),
+        }
+
+        let mut file = ChoicesFile::default();
+        let choices = value.get("choices").and_then(Value::as_object).ok_or("missing choices")?;
+        for (cid, nid) in choices {
+            let cid = cid.parse::<u32>().map_err(|_| format!("invalid class id {}", cid))?;
+            let parts = nid
+                .as_array()
+                .filter(|parts| parts.len() == 2)
+                .and_then(|parts| Some([parts[0].as_u64()? as u32, parts[1].as_u64()? as u32]))
+                .ok_or_else(|| format!("invalid node id for class {}", cid))?;
+            file.choices.insert(ClassId(cid), NodeId(parts));
+        }
+        if let Some(costs) = value.get("costs").and_then(Value::as_object) {
+            for (name, cost) in costs {
+                let cost = match cost {
+                    // JSON has no infinity, serde_json writes it as null.
+                    Value::Null => INFINITY,
+                    cost => cost
+                        .as_f64()
+                        .and_then(|cost| Cost::new(cost).ok())
+                        .ok_or_else(|| format!("invalid {} cost", name))?,
+                };
+                file.costs.insert(name.clone(), cost);
+            }
+        }
+        if let Some(meta) = value.get("meta").and_then(Value::as_object) {
+            file.meta = meta.clone();
+        }
+        Ok(file)
+    }
+
+    pub fn to_json_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        std::fs::write(path, json)
+    }
+
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let value: Value = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Self::from_json(&value).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+}
@@ -0,0 +1,62 @@
+use crate::*;
+
+/// Latency of each node, for depth measured along the critical path instead of
+/// in number of nodes.
+///
+/// The delay of a node is looked up by op in `op_delays`, then in the `delay` of
+/// its class data, and falls back to `default_delay`, or to the node's cost with
+/// `cost_as_delay` (for technology-mapped gates, whose cost is their delay).
+#[derive(Clone, Debug)]
+pub struct DelayModel {
+    pub op_delays: HashMap<String, Cost>,
+    pub default_delay: Cost,
+    pub cost_as_delay: bool,
+}
+
+impl Default for DelayModel {
+    fn default() -> Self {
+        Self {
+            op_delays: HashMap::new(),
+            default_delay: Cost::new(1.0).unwrap(),
+            cost_as_delay: false,
+        }
+    }
+}
+
+impl DelayModel {
+    /// Node costs as delays.
+    pub fn node_costs() -> Self {
+        Self {
+            cost_as_delay: true,
+            ..Default::default()
+        }
+    }
+
+    /// Reads a JSON object mapping ops to their delay, e.g. `{"*": 1.0, "maj": 2.5}`.
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let delays: HashMap<String, f64> = serde_json::from_reader(std::io::BufReader::new(file))?;
+        let mut op_delays = HashMap::new();
+        for (op, delay) in delays {
+            let delay = Cost::new(delay).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, format!("delay of {} is NaN", op))
+            })?;
+            op_delays.insert(op, delay);
+        }
+        Ok(Self {
+            op_delays,
+            ..Default::default()
+        })
+    }
+
+    pub fn delay(&self, egraph: &EGraph, node: &Node) -> Cost {
+        if let Some(delay) = self.op_delays.get(&node.op) {
+            return *delay;
+        }
+        egraph
+            .class_data
+            .get(&node.eclass)
+            .and_then(|data| data.delay)
+            .unwrap_or(if self.cost_as_delay { node.cost } else { self.default_delay })
+    }
+}
@@ -0,0 +1,144 @@
+//! Comparing two extractions of the same e-graph, e.g. the solutions of two
+//! solvers: which classes they extract differently, and which ops their tree,
+//! DAG and depth costs differ by.
+
+use crate::*;
+use serde_json::{json, Map, Value};
+
+/// A class that one extraction selects with another node than the other, or that
+/// only one of them selects. `None` for the side whose DAG doesn't use the class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChoiceChange {
+    pub class: ClassId,
+    pub a: Option<NodeId>,
+    pub b: Option<NodeId>,
+}
+
+/// The share of one op in the costs of an extraction: the tree cost of its
+/// occurrences in the tree, the DAG cost of its selected nodes, and the number of
+/// them on the critical path. Over all ops, these add up to the costs of the
+/// extraction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OpCosts {
+    pub tree: Cost,
+    pub dag: Cost,
+    pub depth: u32,
+}
+
+/// See `compare`.
+#[derive(Clone, Debug)]
+pub struct ResultDiff {
+    pub a: RootCosts,
+    pub b: RootCosts,
+    /// By class id.
+    pub changed: Vec<ChoiceChange>,
+    /// The costs of every op either extraction uses, `a`'s then `b`'s, by op.
+    pub by_op: IndexMap<String, (OpCosts, OpCosts)>,
+}
+
+/// Compares the extractions `a` and `b` of `roots`, both of which have to be
+/// valid, see `ExtractionResult::validate`. Only the classes below the roots
+/// count, whatever else the extractions choose.
+pub fn compare(egraph: &EGraph, roots: &[ClassId], a: &ExtractionResult, b: &ExtractionResult) -> ResultDiff {
+    let (a_classes, b_classes) = (a.postorder(egraph, roots), b.postorder(egraph, roots));
+    let mut classes: Vec<ClassId> = a_classes.iter().chain(&b_classes).copied().collect::<IndexSet<_>>().into_iter().collect();
+    classes.sort();
+    let changed = classes
+        .into_iter()
+        .map(|class| {
+            let a = a_classes.contains(&class).then(|| a.choices[&class]);
+            let b = b_classes.contains(&class).then(|| b.choices[&class]);
+            ChoiceChange { class, a, b }
+        })
+        .filter(|change| change.a != change.b)
+        .collect();
+
+    let (a_ops, b_ops) = (a.op_costs(egraph, roots, &a_classes), b.op_costs(egraph, roots, &b_classes));
+    let mut by_op: IndexMap<String, (OpCosts, OpCosts)> = IndexMap::new();
+    for (op, costs) in a_ops {
+        by_op.entry(op).or_default().0 = costs;
+    }
+    for (op, costs) in b_ops {
+        by_op.entry(op).or_default().1 = costs;
+    }
+    by_op.sort_keys();
+
+    let costs = |result: &ExtractionResult| RootCosts {
+        tree: result.tree_cost(egraph, roots),
+        dag: result.dag_cost(egraph, roots),
+        depth: result.depth_cost(egraph, roots),
+    };
+    ResultDiff { a: costs(a), b: costs(b), changed, by_op }
+}
+
+impl ExtractionResult {
+    /// The classes below `roots`, every one after its children.
+    fn postorder(&self, egraph: &EGraph, roots: &[ClassId]) -> IndexSet<ClassId> {
+        fn visit(result: &ExtractionResult, egraph: &EGraph, cid: ClassId, seen: &mut FxHashSet<ClassId>, order: &mut IndexSet<ClassId>) {
+            if !seen.insert(cid) {
+                return;
+            }
+            for child in &egraph[&result.choices[&cid]].children {
+                visit(result, egraph, *child, seen, order);
+            }
+            order.insert(cid);
+        }
+        let mut seen = FxHashSet::default();
+        let mut order = IndexSet::new();
+        for root in roots {
+            visit(self, egraph, *root, &mut seen, &mut order);
+        }
+        order
+    }
+
+    fn op_costs(&self, egraph: &EGraph, roots: &[ClassId], postorder: &IndexSet<ClassId>) -> IndexMap<String, OpCosts> {
+        let mut by_op = IndexMap::<String, OpCosts>::new();
+        // How often every class occurs in the tree, parents before children.
+        let mut occurrences = FxHashMap::<ClassId, f64>::default();
+        for root in roots {
+            *occurrences.entry(*root).or_default() += 1.0;
+        }
+        for cid in postorder.iter().rev() {
+            let node = &egraph[&self.choices[cid]];
+            let count = occurrences[cid];
+            for child in &node.children {
+                *occurrences.entry(*child).or_default() += count;
+            }
+            let costs = by_op.entry(node.op.clone()).or_default();
+            costs.tree += node.cost * count;
+            costs.dag += node.cost;
+        }
+
+        // One deepest path, from the deepest root down.
+        let mut depths = HashMap::new();
+        let mut next = roots.iter().max_by_key(|root| self.depth_cost_rec(egraph, root, &mut depths)).copied();
+        while let Some(cid) = next {
+            let node = &egraph[&self.choices[&cid]];
+            by_op.entry(node.op.clone()).or_default().depth += 1;
+            next = node.children.iter().max_by_key(|child| depths[child]).copied();
+        }
+        by_op
+    }
+}
+
+impl ResultDiff {
+    pub fn to_json(&self, egraph: &EGraph) -> Value {
+        let costs = |costs: &RootCosts| json!({ "tree": costs.tree.into_inner(), "dag": costs.dag.into_inner(), "depth": costs.depth });
+        let op_costs = |costs: &OpCosts| json!({ "tree": costs.tree.into_inner(), "dag": costs.dag.into_inner(), "depth": costs.depth });
+        let side = |nid: &Option<NodeId>| match nid {
+            Some(nid) => json!({ "node": nid.to_string(), "op": egraph[nid].op }),
+            None => Value::Null,
+        };
+        let changed: Vec<Value> = self
+            .changed
+            .iter()
+            .map(|change| json!({ "class": change.class.to_string(), "a": side(&change.a), "b": side(&change.b) }))
+            .collect();
+        let by_op: Map<String, Value> = self
+            .by_op
+            .iter()
+            .map(|(op, (a, b))| (op.clone(), json!({ "a": op_costs(a), "b": op_costs(b) })))
+            .collect();
+        json!({ "a": costs(&self.a), "b": costs(&self.b), "changed": changed, "by_op": by_op })
+    }
+}
@@ -0,0 +1,92 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use crate::*;
+use crate::queue::UniqueQueue;
+
+/// Few distinct ops first, cost second: a greedy take on minimising the cells of
+/// an extracted netlist.
+///
+/// Starting from a bottom-up (tree cost) extraction, the ops it uses are dropped
+/// one at a time, least used first, as long as the roots can still be extracted
+/// without them; the result is the bottom-up extraction over the ops that are
+/// left. Every op is tried once, so the op set is minimal in the sense that no
+/// single op of it can go, not necessarily the smallest. The ILP counts the ops
+/// exactly with `--op-weight`.
+pub struct DistinctOpsExtractor;
+
+impl Extractor for DistinctOpsExtractor {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        let mut allowed: FxHashSet<&str> = egraph.nodes.values().map(|node| node.op.as_str()).collect();
+        let Some(mut best) = extract_with_ops(egraph, roots, &allowed) else {
+            // Some root has no term at all; leave it to `check` to report.
+            return ExtractionResult::default();
+        };
+        let mut tried = FxHashSet::<&str>::default();
+        loop {
+            // Ops that an earlier removal brought in are tried as well.
+            let mut uses = FxHashMap::<&str, usize>::default();
+            for nid in best.activate_nodes(egraph, roots) {
+                *uses.entry(egraph[&nid].op.as_str()).or_default() += 1;
+            }
+            let Some((op, _)) = uses
+                .into_iter()
+                .filter(|(op, _)| !tried.contains(op))
+                .min_by(|(a_op, a_uses), (b_op, b_uses)| a_uses.cmp(b_uses).then(a_op.cmp(b_op)))
+            else {
+                break;
+            };
+            tried.insert(op);
+            allowed.remove(op);
+            match extract_with_ops(egraph, roots, &allowed) {
+                Some(result) => best = result,
+                None => {
+                    allowed.insert(op);
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Bottom-up extraction by tree cost over the nodes with an `allowed` op, `None`
+/// if a root has no term made of them.
+fn extract_with_ops(egraph: &EGraph, roots: &[ClassId], allowed: &FxHashSet<&str>) -> Option<ExtractionResult> {
+    let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+    let mut pending = UniqueQueue::default();
+    for class in egraph.classes().values() {
+        parents.insert(class.id, Vec::new());
+    }
+    for class in egraph.classes().values() {
+        for node in &class.nodes {
+            if !allowed.contains(egraph[node].op.as_str()) {
+                continue;
+            }
+            for child in &egraph[node].children {
+                parents[child].push(*node);
+            }
+            if egraph[node].is_leaf() {
+                pending.insert(*node);
+            }
+        }
+    }
+
+    let mut result = ExtractionResult::default();
+    let mut costs = FxHashMap::<ClassId, Cost>::default();
+    while let Some(node_id) = pending.pop() {
+        let class_id = egraph.nid_to_cid(&node_id);
+        let node = &egraph[&node_id];
+        let cost = node
+            .children
+            .iter()
+            .fold(node.cost, |sum, child| sum + *costs.get(child).unwrap_or(&INFINITY));
+        if cost < *costs.get(class_id).unwrap_or(&INFINITY) {
+            result.choose(*class_id, node_id);
+            costs.insert(*class_id, cost);
+            pending.extend(parents[class_id].iter().cloned());
+        }
+    }
+    if !roots.iter().all(|root| costs.contains_key(root)) {
+        return None;
+    }
+    result.class_costs.extend(costs);
+    Some(result)
+}
@@ -0,0 +1,58 @@
+use crate::*;
+use std::fmt::Write;
+
+impl ExtractionResult {
+    /// Graphviz rendering of the extracted DAG: one box per selected node,
+    /// labelled with its op and cost, and an edge per child. Roots are filled.
+    /// Unlike `EGraph::to_dot`, unselected nodes are left out entirely, which
+    /// keeps the graph readable for e-graphs of realistic size.
+    pub fn to_dot(&self, egraph: &EGraph) -> String {
+        let roots = &egraph.root_eclasses;
+        let mut active: Vec<NodeId> = self.activate_nodes(egraph, roots).into_iter().collect();
+        active.sort();
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph extraction {{").unwrap();
+        writeln!(dot, "  rankdir=BT;").unwrap();
+        writeln!(dot, "  node [shape=box, fontname=\"Helvetica\"];").unwrap();
+        for nid in &active {
+            let node = &egraph[nid];
+            let style = if roots.contains(&node.eclass) {
+                ", style=filled, fillcolor=\"lightblue\", peripheries=2"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "  c{} [label=\"{}\\nclass {}  cost {}\"{}];",
+                node.eclass,
+                escape(&node.op),
+                node.eclass,
+                node.cost,
+                style
+            )
+            .unwrap();
+        }
+        for nid in &active {
+            let node = &egraph[nid];
+            for (i, child) in node.children.iter().enumerate() {
+                // Argument positions only matter for ops with several children.
+                if node.children.len() > 1 {
+                    writeln!(dot, "  c{} -> c{} [taillabel=\"{}\"];", child, node.eclass, i).unwrap();
+                } else {
+                    writeln!(dot, "  c{} -> c{};", child, node.eclass).unwrap();
+                }
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    pub fn to_dot_file(&self, egraph: &EGraph, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_dot(egraph))
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
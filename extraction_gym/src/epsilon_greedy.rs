@@ -0,0 +1,161 @@
+//! Stochastic variant of `faster_greedy_dag` for diversified warm starts.
+//!
+//! The sweep is the same, except that a node whose sub-DAG costs at most `slack`
+//! (relative) more than the current choice of its class is accepted anyway with
+//! probability epsilon. Every class takes at most one such detour, so the sweep
+//! still terminates, and the random draws come from a seeded generator: the same
+//! seed gives the same extraction, and different seeds give different ones, e.g.
+//! one per `--starts` entry.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt;
+
+use crate::*;
+use crate::faster_greedy_dag::{CostSet, FasterGreedyDagExtractor};
+use crate::queue::UniqueQueue;
+
+/// Probability of accepting a worse choice, by pass of the work list (a pass
+/// being the nodes queued when the previous one ended).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EpsilonSchedule {
+    Constant(f64),
+    /// `start`, multiplied by `decay` after every pass, so that the detours are
+    /// mostly taken near the leaves and the last passes are greedy.
+    Geometric { start: f64, decay: f64 },
+}
+
+impl EpsilonSchedule {
+    pub fn epsilon(&self, pass: usize) -> f64 {
+        let epsilon = match *self {
+            EpsilonSchedule::Constant(epsilon) => epsilon,
+            EpsilonSchedule::Geometric { start, decay } => start * decay.powi(pass.min(i32::MAX as usize) as i32),
+        };
+        epsilon.clamp(0.0, 1.0)
+    }
+}
+
+impl std::str::FromStr for EpsilonSchedule {
+    type Err = String;
+
+    /// `<epsilon>` or `<start>:<decay>`.
+    fn from_str(schedule: &str) -> Result<Self, String> {
+        let number = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| (0.0..=1.0).contains(value))
+                .ok_or_else(|| format!("'{}' is not a probability between 0 and 1", value))
+        };
+        match schedule.split_once(':') {
+            None => Ok(EpsilonSchedule::Constant(number(schedule)?)),
+            Some((start, decay)) => Ok(EpsilonSchedule::Geometric { start: number(start)?, decay: number(decay)? }),
+        }
+    }
+}
+
+impl fmt::Display for EpsilonSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpsilonSchedule::Constant(epsilon) => write!(f, "{}", epsilon),
+            EpsilonSchedule::Geometric { start, decay } => write!(f, "{}:{}", start, decay),
+        }
+    }
+}
+
+pub struct EpsilonGreedyDagExtractor {
+    pub seed: u64,
+    pub epsilon: EpsilonSchedule,
+    /// How much more than the current choice, relative to its cost, an accepted
+    /// worse choice may cost.
+    pub slack: f64,
+}
+
+impl Default for EpsilonGreedyDagExtractor {
+    fn default() -> Self {
+        EpsilonGreedyDagExtractor { seed: 0, epsilon: EpsilonSchedule::Constant(0.1), slack: 0.1 }
+    }
+}
+
+impl Extractor for EpsilonGreedyDagExtractor {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::NONE)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::after(budget))
+    }
+}
+
+impl EpsilonGreedyDagExtractor {
+    fn extract_until(&self, egraph: &EGraph, _roots: &[ClassId], deadline: Deadline) -> ExtractionResult {
+        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
+        let mut analysis_pending = UniqueQueue::default();
+
+        for class in egraph.classes().values() {
+            parents.insert(class.id, Vec::new());
+        }
+        for class in egraph.classes().values() {
+            for node in &class.nodes {
+                for c in &egraph[node].children {
+                    parents[c].push(*node);
+                }
+                if egraph[node].is_leaf() {
+                    analysis_pending.insert(*node);
+                }
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut costs = FxHashMap::<ClassId, CostSet>::with_capacity_and_hasher(egraph.classes().len(), Default::default());
+        let mut detoured = FxHashSet::<ClassId>::default();
+        let mut pass = 0;
+        let mut pass_left = analysis_pending.len();
+
+        while let Some(node_id) = analysis_pending.pop() {
+            if deadline.passed() {
+                break;
+            }
+            if pass_left == 0 {
+                pass += 1;
+                pass_left = analysis_pending.len() + 1;
+            }
+            pass_left -= 1;
+            let class_id = n2c(&node_id);
+            if !egraph[&node_id].children.iter().all(|c| costs.contains_key(c)) {
+                continue;
+            }
+            let prev = costs.get(class_id);
+            let prev_cost = prev.map_or(INFINITY, |prev| prev.total);
+            let limit = if prev_cost == INFINITY { INFINITY } else { Cost::new(prev_cost.into_inner() * (1.0 + self.slack)).unwrap() };
+            let mut cost_set = FasterGreedyDagExtractor::calculate_cost_set(egraph, node_id, &costs, limit, None);
+            if cost_set.total == INFINITY {
+                continue;
+            }
+            // Summed in class order, so that the draws below only depend on the seed.
+            cost_set.total = sum_in_class_order(&cost_set.costs);
+            let accept = cost_set.total < prev_cost
+                || (cost_set.total <= limit
+                    && prev.is_some_and(|prev| prev.choice != node_id)
+                    && !detoured.contains(class_id)
+                    && rng.gen_bool(self.epsilon.epsilon(pass))
+                    && detoured.insert(*class_id));
+            if accept {
+                costs.insert(*class_id, cost_set);
+                analysis_pending.extend(parents[class_id].iter().cloned());
+            }
+        }
+
+        let mut result = ExtractionResult::default();
+        for class in egraph.classes().values() {
+            if let Some(cost_set) = costs.get(&class.id) {
+                result.class_costs.insert(class.id, cost_set.total);
+                result.choose(class.id, cost_set.choice);
+            }
+        }
+        result
+    }
+}
@@ -1,7 +1,8 @@
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
 use crate::*;
+use crate::queue::UniqueQueue;
 pub const U32INFINITY: u32 = std::u32::MAX-1;
-/// 一个基于 AST 大小（节点总数）的快速提取器
+/// 一个基于 AST 深度（子节点深度的最大值）的快速提取器，AST 大小见 `faster_ast_size`
 pub struct FasterAstSizeExtractor;
 
 impl Extractor for FasterAstSizeExtractor {
@@ -42,6 +43,9 @@ impl Extractor for FasterAstSizeExtractor {
             let cost = 1 + node.children.iter().fold(0, |max, child_id| {
                 max.max(*costs.get(&child_id).unwrap_or(&U32INFINITY))
             });
+            if cost < U32INFINITY {
+                result.record_cost(node_id.clone(), Cost::from(cost));
+            }
             // println!("node_id:{:?},cost:{:?}",node_id.0,cost);
             // for nodee in &node.children {
             //     println!("{:?},{:?}",nodee,costs.get(&nodee).unwrap_or(&U32INFINITY));
@@ -55,61 +59,12 @@ impl Extractor for FasterAstSizeExtractor {
         }
 
         result
-    }
-}
-
-/// 保证队列中元素唯一的队列结构，实现了 O(1) 期望均摊插入/弹出复杂度。
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>,
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
-    }
-}
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
+            .class_costs
+            .extend(costs.into_iter().map(|(cid, depth)| (cid, Cost::from(depth))));
+        result
     }
 
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
+    fn fills_node_costs(&self) -> bool {
+        true
     }
 }
@@ -1,15 +1,34 @@
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
 use dashmap::DashMap;
 use std::sync::Arc;
 use rayon::prelude::*;
 
 use crate::*;
+use crate::queue::UniqueQueue;
 pub const U32INFINITY: u32 = std::u32::MAX-1;
-/// 一个基于 AST 大小（节点总数）的快速提取器
+/// 一个基于 AST 深度（子节点深度的最大值）的快速提取器，AST 大小见 `faster_ast_size`
 pub struct FasterAstSizeExtractor;
 
 impl Extractor for FasterAstSizeExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_parallel(FrozenEGraph::new(egraph), roots)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        let deadline = Deadline::after(budget);
+        self.extract_until(FrozenEGraph::new(egraph), roots, deadline)
+    }
+}
+
+impl ParallelExtractor for FasterAstSizeExtractor {
+    fn extract_parallel(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::NONE)
+    }
+}
+
+impl FasterAstSizeExtractor {
+    /// `extract_parallel`, stopping between batches once `deadline` has passed.
+    fn extract_until(&self, egraph: FrozenEGraph, _roots: &[ClassId], deadline: Deadline) -> ExtractionResult {
         // 构造每个等价类对应的父节点列表
         let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
         let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
@@ -36,8 +55,8 @@ impl Extractor for FasterAstSizeExtractor {
         let costs_all: Arc<DashMap<ClassId, (NodeId,u32)>> = Arc::new(DashMap::with_capacity_and_hasher(
             egraph.classes().len(), Default::default()));
 
-        while !analysis_pending.is_empty() {
-            let vec_node_id = analysis_pending.pop_32();
+        while !analysis_pending.is_empty() && !deadline.passed() {
+            let vec_node_id = analysis_pending.pop_batch();
             let costs_all_clone: Arc<DashMap<ClassId, (NodeId,u32)>> = Arc::clone(&costs_all);
             let should_insert: Vec<_> = vec_node_id.into_par_iter().map(|node_id| {
                 let costs_all = Arc::clone(&costs_all_clone);
@@ -83,81 +102,9 @@ impl Extractor for FasterAstSizeExtractor {
             let cid = entry.key();
             let cost_set = entry.value();
             result.choose(cid.clone(), cost_set.0);
+            result.class_costs.insert(*cid, Cost::from(cost_set.1));
         }
         
         result
     }
 }
-
-/// 保证队列中元素唯一的队列结构，实现了 O(1) 期望均摊插入/弹出复杂度。
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>,
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
-    }
-}
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    pub fn pop_32(&mut self) -> Vec<T> {
-        let k = 4096*2;
-        let mut popped_items = Vec::with_capacity(k);
-        
-        for _ in 0..k {
-            if let Some(item) = self.queue.pop_front() {
-                self.set.remove(&item);
-                popped_items.push(item);
-            } else {
-                break; // 队列已空，退出循环
-            }
-        }
-        
-        popped_items
-    }
-
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
@@ -0,0 +1,90 @@
+use rustc_hash::FxHashMap;
+use dashmap::DashMap;
+use rayon::prelude::*;
+
+use crate::*;
+use crate::faster_ast_size::node_size;
+use crate::queue::UniqueQueue;
+
+/// `faster_ast_size` with each batch of the worklist evaluated in parallel, as
+/// `faster_ast_depth_mt` does for depth.
+pub struct FasterAstSizeExtractor;
+
+impl Extractor for FasterAstSizeExtractor {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_parallel(FrozenEGraph::new(egraph), roots)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        let deadline = Deadline::after(budget);
+        self.extract_until(FrozenEGraph::new(egraph), roots, deadline)
+    }
+}
+
+impl ParallelExtractor for FasterAstSizeExtractor {
+    fn extract_parallel(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::NONE)
+    }
+}
+
+impl FasterAstSizeExtractor {
+    /// `extract_parallel`, stopping between batches once `deadline` has passed.
+    fn extract_until(&self, egraph: FrozenEGraph, _roots: &[ClassId], deadline: Deadline) -> ExtractionResult {
+        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
+        let mut analysis_pending = UniqueQueue::default();
+
+        for class in egraph.classes().values() {
+            parents.insert(class.id, Vec::new());
+        }
+
+        for class in egraph.classes().values() {
+            for node in &class.nodes {
+                for child in &egraph[node].children {
+                    parents[child].push(*node);
+                }
+                if egraph[node].is_leaf() {
+                    analysis_pending.insert(*node);
+                }
+            }
+        }
+
+        let sizes: DashMap<ClassId, (NodeId, Cost)> = DashMap::with_capacity(egraph.classes().len());
+
+        while !analysis_pending.is_empty() && !deadline.passed() {
+            let batch = analysis_pending.pop_batch();
+            let improved: Vec<(NodeId, Cost)> = batch
+                .into_par_iter()
+                .filter_map(|node_id| {
+                    let class_id = n2c(&node_id);
+                    let prev_size = sizes.get(class_id).map_or(INFINITY, |entry| entry.1);
+                    let size = node_size(&egraph[&node_id], |child| sizes.get(child).map_or(INFINITY, |entry| entry.1));
+                    (size < prev_size).then_some((node_id, size))
+                })
+                .collect();
+            // The smallest improvement of each class in the batch wins.
+            let mut grouped: FxHashMap<ClassId, (NodeId, Cost)> = FxHashMap::default();
+            for (node_id, size) in improved {
+                grouped
+                    .entry(*n2c(&node_id))
+                    .and_modify(|best| {
+                        if size < best.1 {
+                            *best = (node_id, size);
+                        }
+                    })
+                    .or_insert((node_id, size));
+            }
+            for (class_id, best) in grouped {
+                sizes.insert(class_id, best);
+                analysis_pending.extend(parents[&class_id].iter().cloned());
+            }
+        }
+
+        let mut result = ExtractionResult::default();
+        for entry in sizes.iter() {
+            result.choose(*entry.key(), entry.value().0);
+            result.class_costs.insert(*entry.key(), entry.value().1);
+        }
+        result
+    }
+}
@@ -1,6 +1,7 @@
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::*;
+use crate::queue::{Frontier, Worklist};
 
 /// A faster bottom up extractor inspired by the faster-greedy-dag extractor.
 /// It should return an extraction result with the same cost as the bottom-up extractor.
@@ -13,111 +14,178 @@ use crate::*;
 /// of the fixed point.
 /// This algorithm instead only visits the nodes whose current cost estimate may change:
 /// it does this by tracking parent-child relationships and storing relevant nodes
-/// in a work list (UniqueQueue).
-pub struct FasterBottomUpExtractor;
+/// in a work list, processed in the order given by `frontier`.
+#[derive(Default)]
+pub struct FasterBottomUpExtractor {
+    pub frontier: Frontier,
+}
 
-impl Extractor for FasterBottomUpExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
-        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
-        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
-        let mut analysis_pending = UniqueQueue::default();
+impl FasterBottomUpExtractor {
+    pub fn extract_with_stats(&self, egraph: &EGraph, _roots: &[ClassId]) -> (ExtractionResult, ExtractionStats) {
+        bottom_up(egraph, |node| node.cost, None, self.frontier)
+    }
 
-        for class in egraph.classes().values() {
-            parents.insert(class.id.clone(), Vec::new());
-        }
+    /// Re-extracts `egraph` after `delta` was applied to it, starting from
+    /// `previous`, this extractor's result on the e-graph before the change.
+    ///
+    /// Only the new and changed nodes, and the classes whose chosen term went
+    /// through a changed node, are put back on the worklist; everything else keeps
+    /// its settled cost.
+    pub fn extract_incremental(
+        &self,
+        egraph: &EGraph,
+        previous: &ExtractionResult,
+        delta: &EGraphDelta,
+    ) -> (ExtractionResult, ExtractionStats) {
+        bottom_up(egraph, |node| node.cost, Some((previous, delta)), self.frontier)
+    }
+}
 
-        for class in egraph.classes().values() {
-            for node in &class.nodes {
-                for c in &egraph[node].children {
-                    parents[c].push(node.clone());
-                }
+impl Extractor for FasterBottomUpExtractor {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_with_stats(egraph, roots).0
+    }
 
-                // start the analysis from leaves
-                if egraph[node].is_leaf() {
-                    analysis_pending.insert(node.clone());
-                }
-            }
-        }
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
 
-        let mut result = ExtractionResult::default();
-        let mut costs = FxHashMap::<ClassId, Cost>::with_capacity_and_hasher(
-            egraph.classes().len(),
-            Default::default(),
-        );
-
-        while let Some(node_id) = analysis_pending.pop() {
-            let class_id = n2c(&node_id);
-            let node = &egraph[&node_id];
-            let prev_cost = costs.get(class_id).unwrap_or(&INFINITY);
-            let cost = result.node_sum_cost(egraph, node, &costs);
-            if cost < *prev_cost {
-                result.choose(class_id.clone(), node_id.clone());
-                costs.insert(class_id.clone(), cost);
-                analysis_pending.extend(parents[class_id].iter().cloned());
-            }
+/// The faster bottom-up extraction, with node costs looked up by op instead of
+/// read from `Node::cost`.
+///
+/// This lets one serialized e-graph be extracted under several cost models
+/// (e.g. one per technology library) without rewriting its JSON. Note that the
+/// costs reported by `ExtractionResult` (`tree_cost`, `dag_cost`, ...) are still
+/// computed from `Node::cost`.
+pub struct OpCostBottomUpExtractor {
+    op_cost: Box<dyn Fn(&str) -> Cost + Send + Sync>,
+}
+
+impl OpCostBottomUpExtractor {
+    pub fn new(op_cost: impl Fn(&str) -> Cost + Send + Sync + 'static) -> Self {
+        Self {
+            op_cost: Box::new(op_cost),
         }
+    }
 
-        result
+    /// Costs from a table of ops, `default` for ops that are not listed.
+    pub fn from_table(table: HashMap<String, Cost>, default: Cost) -> Self {
+        Self::new(move |op| table.get(op).copied().unwrap_or(default))
     }
 }
 
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
+impl Extractor for OpCostBottomUpExtractor {
+    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+        bottom_up(egraph, |node| (self.op_cost)(&node.op), None, Frontier::Fifo).0
+    }
 
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
 }
 
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
+fn bottom_up(
+    egraph: &EGraph,
+    node_cost: impl Fn(&Node) -> Cost,
+    start: Option<(&ExtractionResult, &EGraphDelta)>,
+    frontier: Frontier,
+) -> (ExtractionResult, ExtractionStats) {
+    let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+    let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
+    let mut analysis_pending = Worklist::new(frontier);
+
+    for class in egraph.classes().values() {
+        parents.insert(class.id.clone(), Vec::new());
     }
-}
 
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
+    for class in egraph.classes().values() {
+        for node in &class.nodes {
+            for c in &egraph[node].children {
+                parents[c].push(node.clone());
+            }
+
+            // start the analysis from leaves
+            if start.is_none() && egraph[node].is_leaf() {
+                analysis_pending.insert(node.clone(), node_cost(&egraph[node]));
+            }
         }
     }
 
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
+    let mut result = ExtractionResult::default();
+    let mut stats = StatsRecorder::default();
+    let mut costs = FxHashMap::<ClassId, Cost>::with_capacity_and_hasher(
+        egraph.classes().len(),
+        Default::default(),
+    );
+
+    if let Some((previous, delta)) = start {
+        result.choices = previous.choices.clone();
+        result.cost = previous.cost.clone();
+        costs.extend(previous.class_costs.iter().map(|(cid, cost)| (*cid, *cost)));
+
+        // Classes whose chosen term contains a changed node (or that have no settled
+        // cost) must be recomputed, and so must the classes choosing through them.
+        let mut dirty: FxHashSet<ClassId> = result
+            .choices
+            .iter()
+            .filter(|(cid, _)| !costs.contains_key(*cid))
+            .map(|(cid, _)| *cid)
+            .collect();
+        for node_id in &delta.changed_costs {
+            let cid = n2c(node_id);
+            if result.choices.get(cid) == Some(node_id) {
+                dirty.insert(*cid);
+            }
+        }
+        let mut todo: Vec<ClassId> = dirty.iter().copied().collect();
+        while let Some(cid) = todo.pop() {
+            for parent in &parents[&cid] {
+                result.cost.remove(parent);
+                let parent_cid = n2c(parent);
+                if result.choices.get(parent_cid) == Some(parent) && dirty.insert(*parent_cid) {
+                    todo.push(*parent_cid);
+                }
+            }
+        }
+
+        for cid in &dirty {
+            result.choices.swap_remove(cid);
+            costs.remove(cid);
+            for node_id in &egraph[cid].nodes {
+                result.cost.remove(node_id);
+                analysis_pending.insert(node_id.clone(), Cost::default());
+            }
+        }
+        for node_id in delta.added_nodes.iter().chain(&delta.changed_costs) {
+            result.cost.remove(node_id);
+            analysis_pending.insert(node_id.clone(), Cost::default());
         }
     }
 
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
+    while let Some(node_id) = analysis_pending.pop() {
+        stats.popped(analysis_pending.len() + 1);
+        stats.relaxed();
+        let class_id = n2c(&node_id);
+        let node = &egraph[&node_id];
+        let prev_cost = costs.get(class_id).unwrap_or(&INFINITY);
+        let cost = node_cost(node)
+            + node
+                .children
+                .iter()
+                .map(|c| costs.get(c).unwrap_or(&INFINITY))
+                .sum::<Cost>();
+        result.record_cost(node_id.clone(), cost);
+        if cost < *prev_cost {
+            if result.choices.get(class_id) != Some(&node_id) {
+                stats.choice_changed(*class_id);
+            }
+            result.choose(class_id.clone(), node_id.clone());
+            costs.insert(class_id.clone(), cost);
+            analysis_pending.extend(parents[class_id].iter().cloned(), cost);
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
+    result.class_costs.extend(costs);
+    (result, stats.finish())
 }
@@ -1,9 +1,10 @@
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
 use dashmap::DashMap;
 use std::sync::Arc;
 use rayon::prelude::*;
 
 use crate::*;
+use crate::queue::UniqueQueue;
 
 /// A faster bottom up extractor inspired by the faster-greedy-dag extractor.
 /// It should return an extraction result with the same cost as the bottom-up extractor.
@@ -20,7 +21,25 @@ use crate::*;
 pub struct FasterBottomUpExtractor;
 
 impl Extractor for FasterBottomUpExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_parallel(FrozenEGraph::new(egraph), roots)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        let deadline = Deadline::after(budget);
+        self.extract_until(FrozenEGraph::new(egraph), roots, deadline)
+    }
+}
+
+impl ParallelExtractor for FasterBottomUpExtractor {
+    fn extract_parallel(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::NONE)
+    }
+}
+
+impl FasterBottomUpExtractor {
+    /// `extract_parallel`, stopping between batches once `deadline` has passed.
+    fn extract_until(&self, egraph: FrozenEGraph, _roots: &[ClassId], deadline: Deadline) -> ExtractionResult {
         let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
         let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
         let mut analysis_pending = UniqueQueue::default();
@@ -53,8 +72,8 @@ impl Extractor for FasterBottomUpExtractor {
             egraph.classes().len(), Default::default()));
 
 
-        while !analysis_pending.is_empty() {
-            let vec_node_id = analysis_pending.pop_32();
+        while !analysis_pending.is_empty() && !deadline.passed() {
+            let vec_node_id = analysis_pending.pop_batch();
             let costs_all_clone: Arc<DashMap<ClassId, (NodeId,Cost)>> = Arc::clone(&costs_all);
             let should_insert: Vec<_> = vec_node_id.into_par_iter().map(|node_id| {
                 let costs_all = Arc::clone(&costs_all_clone);
@@ -105,85 +124,9 @@ impl Extractor for FasterBottomUpExtractor {
             let cid = entry.key();
             let cost_set = entry.value();
             result.choose(cid.clone(), cost_set.0);
+            result.class_costs.insert(*cid, cost_set.1);
         }
 
         result
     }
 }
-
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
-
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
-    }
-}
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    pub fn pop_32(&mut self) -> Vec<T> {
-        let k = 4096*2;
-        let mut popped_items = Vec::with_capacity(k);
-        
-        for _ in 0..k {
-            if let Some(item) = self.queue.pop_front() {
-                self.set.remove(&item);
-                popped_items.push(item);
-            } else {
-                break; // 队列已空，退出循环
-            }
-        }
-        
-        popped_items
-    }
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
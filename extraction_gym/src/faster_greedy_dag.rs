@@ -3,23 +3,43 @@
 // included in the cost.
 
 use crate::*;
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+use crate::policy::{self, Candidate, ChoicePolicy};
+use crate::queue::{Frontier, Worklist};
 
-struct CostSet {
+pub(crate) struct CostSet {
     // It's slightly faster if this is an HashMap rather than an fxHashMap.
-    costs: HashMap<ClassId, Cost>,
-    total: Cost,
-    choice: NodeId,
+    pub(crate) costs: HashMap<ClassId, Cost>,
+    pub(crate) total: Cost,
+    pub(crate) choice: NodeId,
+    pub(crate) depth: usize,
 }
 
-pub struct FasterGreedyDagExtractor;
+impl CostSet {
+    pub(crate) fn candidate<'a>(&self, egraph: &'a EGraph) -> Candidate<'a> {
+        Candidate { node: &egraph[&self.choice], total: self.total, depth: self.depth, size: self.costs.len() }
+    }
+}
+
+/// Processes its pending nodes in the order given by `frontier`. With a `fanout`
+/// penalty, the cost of a sub-DAG is its DAG cost plus the penalty of the uses
+/// of its classes by the nodes currently chosen for the classes of the sub-DAG.
+/// A `policy` decides which of two cost sets of a class wins, strict cost by default.
+#[derive(Default)]
+pub struct FasterGreedyDagExtractor {
+    pub frontier: Frontier,
+    pub fanout: Option<FanoutPenalty>,
+    pub policy: Option<Arc<dyn ChoicePolicy>>,
+}
 
 impl FasterGreedyDagExtractor {
-    fn calculate_cost_set(
+    pub(crate) fn calculate_cost_set(
         egraph: &EGraph,
         node_id: NodeId,
         costs: &FxHashMap<ClassId, CostSet>,
         best_cost: Cost,
+        fanout: Option<&FanoutPenalty>,
     ) -> CostSet {
         let node = &egraph[&node_id];
         let cid = egraph.nid_to_cid(&node_id);
@@ -29,6 +49,7 @@ impl FasterGreedyDagExtractor {
                 costs: HashMap::from([(cid.clone(), node.cost)]),
                 total: node.cost,
                 choice: node_id.clone(),
+                depth: 1,
             };
         }
 
@@ -48,6 +69,7 @@ impl FasterGreedyDagExtractor {
                 costs: Default::default(),
                 total: INFINITY,
                 choice: node_id.clone(),
+                depth: 0,
             };
         }
 
@@ -71,25 +93,53 @@ impl FasterGreedyDagExtractor {
         let contains = result.contains_key(&cid);
         result.insert(cid.clone(), node.cost);
 
-        let result_cost = if contains {
+        let mut result_cost = if contains {
             INFINITY
         } else {
             result.values().sum()
         };
+        // Only ever grows as nodes are added, so the shortcut above still holds.
+        if let (Some(penalty), false) = (fanout, contains) {
+            let mut uses = FxHashMap::<ClassId, usize>::default();
+            for class in result.keys() {
+                let choice = if class == cid { &node_id } else { &costs[class].choice };
+                for child in &egraph[choice].children {
+                    *uses.entry(*child).or_default() += 1;
+                }
+            }
+            result_cost += penalty.penalty(uses.into_values());
+        }
+
+        let depth = 1 + childrens_classes.iter().map(|c| costs[c].depth).max().unwrap();
 
         return CostSet {
             costs: result,
             total: result_cost,
             choice: node_id.clone(),
+            depth,
         };
     }
 }
 
 impl Extractor for FasterGreedyDagExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_with_stats(egraph, roots, Deadline::NONE).0
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        self.extract_with_stats(egraph, roots, Deadline::after(budget)).0
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
+
+impl FasterGreedyDagExtractor {
+    pub fn extract_with_stats(&self, egraph: &EGraph, _roots: &[ClassId], deadline: Deadline) -> (ExtractionResult, ExtractionStats) {
         let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
         let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
-        let mut analysis_pending = UniqueQueue::default();
+        let mut analysis_pending = Worklist::new(self.frontier);
         for class in egraph.classes().values() {
             parents.insert(class.id.clone(), Vec::new());
         }
@@ -103,13 +153,14 @@ impl Extractor for FasterGreedyDagExtractor {
 
                 // start the analysis from leaves
                 if egraph[node].is_leaf() {
-                    analysis_pending.insert(node.clone());
+                    analysis_pending.insert(node.clone(), egraph[node].cost);
                 }
             }
         }
 
 
         let mut result = ExtractionResult::default();
+        let mut stats = StatsRecorder::default();
         let mut costs = FxHashMap::<ClassId, CostSet>::with_capacity_and_hasher(
             egraph.classes().len(),
             Default::default(),
@@ -117,16 +168,21 @@ impl Extractor for FasterGreedyDagExtractor {
 
 
         while let Some(node_id) = analysis_pending.pop() {
+            if deadline.passed() {
+                break;
+            }
+            stats.popped(analysis_pending.len() + 1);
             let class_id = n2c(&node_id);
             let node = &egraph[&node_id];
             if node.children.iter().all(|c| costs.contains_key(c)) {
+                stats.relaxed();
                 let lookup = costs.get(class_id);
                 let mut prev_cost = INFINITY;
                 if lookup.is_some() {
                     prev_cost = lookup.unwrap().total;
                 }
 
-                let cost_set = Self::calculate_cost_set(egraph, node_id.clone(), &costs, prev_cost);
+                let cost_set = Self::calculate_cost_set(egraph, node_id.clone(), &costs, prev_cost, self.fanout.as_ref());
                 match result.cost.get(&node_id) {
                     Some(existing) if cost_set.total < *existing => {
                         result.cost.insert(node_id, cost_set.total);
@@ -136,79 +192,24 @@ impl Extractor for FasterGreedyDagExtractor {
                     }
                     _ => {}
                 }
-                if cost_set.total < prev_cost {
+                let current = lookup.map(|old| old.candidate(egraph));
+                if policy::prefers(self.policy.as_deref(), &cost_set.candidate(egraph), current.as_ref()) {
+                    if lookup.map(|old| &old.choice) != Some(&cost_set.choice) {
+                        stats.choice_changed(*class_id);
+                    }
+                    let total = cost_set.total;
                     costs.insert(class_id.clone(), cost_set);
-                    analysis_pending.extend(parents[class_id].iter().cloned());
+                    analysis_pending.extend(parents[class_id].iter().cloned(), total);
                 }
             }
         }
 
 
         for (cid, cost_set) in costs {
+            result.class_costs.insert(cid, cost_set.total);
             result.choose(cid, cost_set.choice);
         }
 
-        result
+        (result, stats.finish())
     }
 }
-
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
-
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
-    }
-}
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
\ No newline at end of file
@@ -1,25 +1,139 @@
-// Calculates the cost where shared nodes are just costed once,
-// For example (+ (* x x ) (* x x )) has one mulitplication
-// included in the cost.
+// Calculates the cost where shared nodes are just costed once, like
+// faster_greedy_dag, but aware of full adders: `fst(fa(a, b, c))` and
+// `snd(fa(a, b, c))` share their `fa`, so choosing one of them for a class also
+// chooses its partner for the other output of the adder when that is cheaper than
+// computing the two outputs separately.
+//
+// Each projection class keeps two cost sets: the best one, and the best one that
+// doesn't use a projection, which the class falls back to when its adder is given
+// up by the other output.
 
 use crate::*;
+use crate::queue::UniqueQueue;
 use rustc_hash::{FxHashMap, FxHashSet};
 
 #[derive(Clone, Debug)]
-struct CostSet {
+pub(crate) struct CostSet {
     // It's slightly faster if this is an HashMap rather than an fxHashMap.
     costs: HashMap<ClassId, Cost>,
-    total: Cost,
-    choice: NodeId,
+    pub(crate) total: Cost,
+    pub(crate) choice: Option<NodeId>,
+}
+
+impl CostSet {
+    fn unset() -> Self {
+        CostSet {
+            costs: Default::default(),
+            total: INFINITY,
+            choice: None,
+        }
+    }
+
+    fn op<'a>(&self, egraph: &'a EGraph) -> Option<&'a str> {
+        self.choice.as_ref().map(|choice| egraph[choice].op.as_str())
+    }
+}
+
+/// The best and the best projection-free cost set of each class.
+pub(crate) type CostSets = FxHashMap<ClassId, (CostSet, CostSet)>;
+
+/// The `fst` and `snd` nodes of the same full adder, i.e. with the same children,
+/// each mapped to the other.
+pub struct AdderPairs {
+    partner: FxHashMap<NodeId, NodeId>,
+    fst_classes: FxHashSet<ClassId>,
+    snd_classes: FxHashSet<ClassId>,
+}
+
+impl AdderPairs {
+    pub fn new(egraph: &EGraph) -> Self {
+        let mut fst_by_children = FxHashMap::<&[ClassId], NodeId>::default();
+        let mut snds = Vec::new();
+        let mut pairs = AdderPairs {
+            partner: Default::default(),
+            fst_classes: Default::default(),
+            snd_classes: Default::default(),
+        };
+        for (node_id, node) in &egraph.nodes {
+            if node.op == "fst" {
+                fst_by_children.insert(&node.children, *node_id);
+                pairs.fst_classes.insert(*egraph.nid_to_cid(node_id));
+            } else if node.op == "snd" {
+                snds.push(node_id);
+                pairs.snd_classes.insert(*egraph.nid_to_cid(node_id));
+            }
+        }
+        for snd in snds {
+            if let Some(fst) = fst_by_children.get(egraph[snd].children.as_slice()) {
+                pairs.partner.insert(*fst, *snd);
+                pairs.partner.insert(*snd, *fst);
+            }
+        }
+        pairs
+    }
+
+    /// The other projection of the adder of the `fst` or `snd` node `node`.
+    pub fn partner(&self, node: &NodeId) -> Option<&NodeId> {
+        self.partner.get(node)
+    }
+
+    /// Whether `class` is an output of some adder, i.e. has a projection node.
+    pub fn is_output(&self, class: &ClassId) -> bool {
+        self.fst_classes.contains(class) || self.snd_classes.contains(class)
+    }
+}
+
+/// The full-adder-aware extraction together with the alternatives it kept: for
+/// the classes of projections, the best choice that doesn't use one.
+#[derive(Clone, Default)]
+pub struct DualExtractionResult {
+    pub result: ExtractionResult,
+    /// Best projection-free node and the cost of its sub-DAG, for the classes of
+    /// projections where it isn't the chosen node.
+    pub secondary: IndexMap<ClassId, (NodeId, Cost)>,
+}
+
+impl DualExtractionResult {
+    pub(crate) fn new(egraph: &EGraph, pairs: &AdderPairs, costs_all: CostSets, mut result: ExtractionResult) -> Self {
+        let mut secondary = IndexMap::new();
+        for class in egraph.classes().values() {
+            let Some((best, fallback)) = costs_all.get(&class.id) else {
+                continue;
+            };
+            if let Some(choice) = best.choice {
+                result.class_costs.insert(class.id, best.total);
+                result.choose(class.id, choice);
+            }
+            let projections = pairs.is_output(&class.id);
+            if let Some(alternative) = fallback.choice.filter(|alternative| projections && best.choice != Some(*alternative)) {
+                secondary.insert(class.id, (alternative, fallback.total));
+            }
+        }
+        DualExtractionResult { result, secondary }
+    }
+
+    /// The extraction with the secondary choices instead, i.e. with the outputs
+    /// of the adders computed separately, if that is a valid extraction too.
+    pub fn alternative(&self, egraph: &EGraph) -> Option<ExtractionResult> {
+        if self.secondary.is_empty() {
+            return None;
+        }
+        let mut alternative = self.result.clone();
+        for (cid, (nid, cost)) in &self.secondary {
+            alternative.choose(*cid, *nid);
+            alternative.class_costs.insert(*cid, *cost);
+        }
+        alternative.is_valid(egraph).then_some(alternative)
+    }
 }
 
 pub struct FasterGreedyDagExtractor;
 
 impl FasterGreedyDagExtractor {
-    fn calculate_cost_set(
+    pub(crate) fn calculate_cost_set(
         egraph: &EGraph,
         node_id: NodeId,
-        costs_all: &FxHashMap::<ClassId, (CostSet,CostSet)>,
+        costs_all: &CostSets,
         best_cost: Cost,
     ) -> CostSet {
         let node = &egraph[&node_id];
@@ -29,44 +143,40 @@ impl FasterGreedyDagExtractor {
             return CostSet {
                 costs: HashMap::from([(cid.clone(), node.cost)]),
                 total: node.cost,
-                choice: node_id.clone(),
+                choice: Some(node_id),
             };
         }
 
         // Get unique classes of children.
-        let mut childrens_classes = node
-            .children
-            .iter()
-            .map(|c| egraph.nid_to_cid(&c).clone())
-            .collect::<Vec<ClassId>>();
+        let mut childrens_classes = node.children.clone();
         childrens_classes.sort();
         childrens_classes.dedup();
 
-        let first_cost = costs_all.get(&childrens_classes[0]).unwrap();
+        let first_cost = &costs_all[&childrens_classes[0]].0;
 
         if childrens_classes.contains(cid)
-            || (childrens_classes.len() == 1 && (node.cost + first_cost.0.total < best_cost))
+            || (childrens_classes.len() == 1 && (node.cost + first_cost.total > best_cost))
         {
             // Shortcut. Can't be cheaper so return junk.
             return CostSet {
                 costs: Default::default(),
-                total: -INFINITY,
-                choice: node_id.clone(),
+                total: INFINITY,
+                choice: Some(node_id),
             };
         }
 
         // Clone the biggest set and insert the others into it.
         let id_of_biggest = childrens_classes
             .iter()
-            .max_by_key(|s| costs_all.get(s).unwrap().0.costs.len())
+            .max_by_key(|s| costs_all[*s].0.costs.len())
             .unwrap();
-        let mut result = costs_all.get(&id_of_biggest).unwrap().0.costs.clone();
+        let mut result = costs_all[id_of_biggest].0.costs.clone();
         for child_cid in &childrens_classes {
             if child_cid == id_of_biggest {
                 continue;
             }
 
-            let next_cost = &costs_all.get(child_cid).unwrap().0.costs;
+            let next_cost = &costs_all[child_cid].0.costs;
             for (key, value) in next_cost.iter() {
                 result.insert(key.clone(), value.clone());
             }
@@ -76,7 +186,7 @@ impl FasterGreedyDagExtractor {
         result.insert(cid.clone(), node.cost);
 
         let result_cost = if contains {
-            -INFINITY
+            INFINITY
         } else {
             result.values().sum()
         };
@@ -84,104 +194,170 @@ impl FasterGreedyDagExtractor {
         return CostSet {
             costs: result,
             total: result_cost,
-            choice: node_id.clone(),
+            choice: Some(node_id),
         };
     }
-}
 
-fn combined_costset(costset1: &CostSet, cid2: &ClassId, costs_all: &FxHashMap::<ClassId, (CostSet,CostSet)>, mode: bool) -> (Cost,CostSet) {
+    /// The cost above which a node of `class_id` can't change anything. The two
+    /// outputs of an adder are compared to the cost of both, and in the classes of
+    /// projections the other nodes can still be the projection-free set.
+    pub(crate) fn shortcut_bound(pairs: &AdderPairs, costs_all: &CostSets, node_id: &NodeId, class_id: &ClassId) -> Cost {
+        let Some((best, fallback)) = costs_all.get(class_id) else {
+            return INFINITY;
+        };
+        if pairs.partner.contains_key(node_id) {
+            INFINITY
+        } else if pairs.is_output(class_id) {
+            best.total.max(fallback.total)
+        } else {
+            best.total
+        }
+    }
+
+    /// Offers `cost_set`, of one of the nodes of its class, and returns the classes
+    /// whose choice changed, whose parents have to be looked at again.
+    pub(crate) fn relax(egraph: &EGraph, pairs: &AdderPairs, costs_all: &mut CostSets, cost_set: CostSet) -> Vec<ClassId> {
+        let node_id = cost_set.choice.unwrap();
+        let node = &egraph[&node_id];
+        let class_id = *egraph.nid_to_cid(&node_id);
+        let (prev_costset0, prev_costset1) = costs_all.get(&class_id).cloned().unwrap_or_else(|| (CostSet::unset(), CostSet::unset()));
 
-    let prev_costs1 = costset1.costs.clone();
+        let (own, other) = if pairs.snd_classes.contains(&class_id) {
+            ("snd", "fst")
+        } else if pairs.fst_classes.contains(&class_id) {
+            ("fst", "snd")
+        } else {
+            if cost_set.total < prev_costset0.total {
+                costs_all.insert(class_id, (cost_set, prev_costset0));
+                return vec![class_id];
+            }
+            return vec![];
+        };
 
-    let mut prev_costs2;
-    if costs_all.contains_key(cid2) {
-        if mode {
-            prev_costs2 = costs_all.get(&cid2).unwrap().0.clone();
-        }
-        else{
-            prev_costs2 = costs_all.get(&cid2).unwrap().1.clone();
+        let mut changed = vec![];
+        if node.op == own {
+            let Some(partner) = pairs.partner.get(&node_id) else {
+                // Not half of an adder, so nothing else to choose.
+                if cost_set.total < prev_costset0.total {
+                    costs_all.insert(class_id, (cost_set, prev_costset1));
+                    changed.push(class_id);
+                }
+                return changed;
+            };
+            let cid2 = *egraph.nid_to_cid(partner);
+            let previous_adder = if prev_costset0.op(egraph) == Some(own) { prev_costset0.choice.and_then(|choice| pairs.partner.get(&choice)) } else { None };
+            // Case 1: the class doesn't use an adder yet, so this one has to pay for
+            // both outputs. Case 2: it already uses one, only the cheaper one is kept.
+            let improves = match previous_adder {
+                None => cost_set.total < combined_total(&prev_costset0, &cid2, costs_all, false),
+                Some(_) => cost_set.total < prev_costset0.total,
+            };
+            if !improves {
+                return changed;
+            }
+            // The adder that the partner's class used, whose other output loses it.
+            let cid4 = costs_all
+                .get(&cid2)
+                .and_then(|(best, _)| best.choice.filter(|choice| egraph[choice].op == other))
+                .and_then(|choice| pairs.partner.get(&choice))
+                .map(|other_partner| *egraph.nid_to_cid(other_partner))
+                .filter(|cid4| *cid4 != class_id);
+
+            let mut costs2 = cost_set.costs.clone();
+            costs2.remove(&class_id);
+            costs2.insert(cid2, egraph[partner].cost);
+            let costset2 = CostSet {
+                total: costs2.values().sum(),
+                costs: costs2,
+                choice: Some(*partner),
+            };
+            let fallback2 = costs_all.get(&cid2).map_or_else(CostSet::unset, |(_, fallback)| fallback.clone());
+            costs_all.insert(class_id, (cost_set, prev_costset1));
+            costs_all.insert(cid2, (costset2, fallback2));
+            changed.extend([class_id, cid2]);
+
+            if let Some(cid3) = previous_adder.map(|previous| *egraph.nid_to_cid(previous)).filter(|cid3| *cid3 != cid2) {
+                changed.extend(fall_back(costs_all, cid3));
+            }
+            if let Some(cid4) = cid4 {
+                changed.extend(fall_back(costs_all, cid4));
+            }
+        } else if node.op != other {
+            // Case 3: the class doesn't use an adder, so this node only has to be
+            // cheaper.
+            let mut updated = false;
+            if prev_costset0.op(egraph) != Some(own) {
+                if cost_set.total < prev_costset0.total {
+                    costs_all.insert(class_id, (cost_set.clone(), cost_set.clone()));
+                    changed.push(class_id);
+                    updated = true;
+                }
+            }
+            // Case 4: it uses one, so this node has to be cheaper than the adder with
+            // the partner computed without it.
+            else if let Some(partner) = prev_costset0.choice.and_then(|choice| pairs.partner.get(&choice)) {
+                let cid2 = *egraph.nid_to_cid(partner);
+                if combined_total(&cost_set, &cid2, costs_all, false) < prev_costset0.total {
+                    costs_all.insert(class_id, (cost_set.clone(), cost_set.clone()));
+                    changed.push(class_id);
+                    changed.extend(fall_back(costs_all, cid2));
+                    updated = true;
+                }
+            }
+            // Otherwise it may still be the best projection-free set.
+            if !updated && prev_costset1.op(egraph) != Some(own) && cost_set.total < prev_costset1.total {
+                costs_all.insert(class_id, (prev_costset0, cost_set));
+            }
         }
+        changed
     }
-    else {
-        prev_costs2 = CostSet {
-            costs: Default::default(),
-            total: -INFINITY,
-            choice: NodeId::new(),
-        };
+}
+
+/// The cost of `costset1` and the best (`best`) or projection-free set of `cid2`
+/// together, with their shared classes costed once.
+fn combined_total(costset1: &CostSet, cid2: &ClassId, costs_all: &CostSets, best: bool) -> Cost {
+    if costset1.choice.is_none() {
+        return INFINITY;
     }
-    let mut combined_costs = prev_costs1.clone();
-    for (key, value) in prev_costs2.costs.iter() {
-        combined_costs.insert(key.clone(), *value);
+    let mut combined_costs = costset1.costs.clone();
+    if let Some((best_set, fallback)) = costs_all.get(cid2) {
+        let costset2 = if best { best_set } else { fallback };
+        for (key, value) in costset2.costs.iter() {
+            combined_costs.insert(*key, *value);
+        }
     }
+    combined_costs.values().sum()
+}
 
-    // let cost1 = costset1.total;
-    // let cost2 = costs_all.get(cid2).unwrap().0.total;
-    return (combined_costs.values().sum(),prev_costs2);
+/// Makes `class_id` use its projection-free set, if it has one.
+fn fall_back(costs_all: &mut CostSets, class_id: ClassId) -> Option<ClassId> {
+    let (_, fallback) = costs_all.get(&class_id)?;
+    fallback.choice?;
+    let fallback = fallback.clone();
+    costs_all.insert(class_id, (fallback.clone(), fallback));
+    Some(class_id)
 }
 
 impl Extractor for FasterGreedyDagExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
-        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
-        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
-        let mut analysis_pending = UniqueQueue::default();
-
-        let mut xor_op: FxHashSet<NodeId> = FxHashSet::default();
-        let mut xor_op_class: FxHashSet<&ClassId> = FxHashSet::default();
-        let mut xor_nmap: FxHashMap<NodeId, NodeId> = FxHashMap::default();
-        let mut maj_op: FxHashSet<NodeId> = FxHashSet::default();
-        let mut maj_op_class: FxHashSet<&ClassId> = FxHashSet::default();
-        let mut maj_nmap: FxHashMap<NodeId, NodeId> = FxHashMap::default();
-        let mut fa_op: FxHashSet<NodeId> = FxHashSet::default();
-        let mut fa_op_class: FxHashSet<&ClassId> = FxHashSet::default();
-        let mut fst_op: FxHashSet<NodeId> = FxHashSet::default();
-        let mut snd_op: FxHashSet<NodeId> = FxHashSet::default();
-        let mut fst_nmap: FxHashMap<NodeId, NodeId> = FxHashMap::default();
-        let mut fst_op_class: FxHashSet<&ClassId> = FxHashSet::default();
-        let mut snd_nmap: FxHashMap<NodeId, NodeId> = FxHashMap::default();
-        let mut snd_op_class: FxHashSet<&ClassId> = FxHashSet::default();
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_dual(egraph, roots, Deadline::NONE).result
+    }
 
-        for (node_id, node) in &egraph.nodes {
-            if node.op == "xor3" {
-                xor_op.insert(node_id.clone());
-                xor_op_class.insert(n2c(node_id));
-            } else if node.op == "maj" {
-                maj_op.insert(node_id.clone());
-                maj_op_class.insert(n2c(node_id));
-            } else if node.op == "fa" {
-                fa_op.insert(node_id.clone());
-                fa_op_class.insert(n2c(node_id));
-            } else if node.op == "fst" {
-                fst_op.insert(node_id.clone());
-                fst_op_class.insert(n2c(node_id));
-            } else if node.op == "snd" {
-                snd_op.insert(node_id.clone());
-                snd_op_class.insert(n2c(node_id));
-            }
-        }
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        self.extract_dual(egraph, roots, Deadline::after(budget)).result
+    }
 
-        let mut i = 0;
-        for xor in &xor_op {
-            for maj in &maj_op {
-                i=i+1;
-                if egraph.nodes[xor].children == egraph.nodes[maj].children {
-                    xor_nmap.insert(xor.clone(), maj.clone());
-                    maj_nmap.insert(maj.clone(), xor.clone());
-                }
-            }
-        }
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
 
-        for fst in &fst_op {
-            for snd in &snd_op {
-                if egraph.nodes[fst].children == egraph.nodes[snd].children {
-                    fst_nmap.insert(fst.clone(), snd.clone());
-                    snd_nmap.insert(snd.clone(), fst.clone());
-                }
-            }
-        }
-        // println!("XOR: {}", xor_map);
-        // println!("XOR: {}", maj_op.len());
-        // println!("XOR: {}", maj_op.len());
-        // panic!("XOR: {:?}", xor_map);
+impl FasterGreedyDagExtractor {
+    pub fn extract_dual(&self, egraph: &EGraph, _roots: &[ClassId], deadline: Deadline) -> DualExtractionResult {
+        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
+        let mut analysis_pending = UniqueQueue::default();
+        let pairs = AdderPairs::new(egraph);
 
         for class in egraph.classes().values() {
             parents.insert(class.id.clone(), Vec::new());
@@ -191,7 +367,7 @@ impl Extractor for FasterGreedyDagExtractor {
             for node in &class.nodes {
                 for c in &egraph[node].children {
                     // compute parents of this enode
-                    parents[n2c(c)].push(node.clone());
+                    parents[c].push(node.clone());
                 }
 
                 // start the analysis from leaves
@@ -202,368 +378,23 @@ impl Extractor for FasterGreedyDagExtractor {
         }
 
         let mut result = ExtractionResult::default();
-        let mut costs_all = FxHashMap::<ClassId, (CostSet,CostSet)>::with_capacity_and_hasher(
-            egraph.classes().len(),
-            Default::default(),
-        );
-
-        // println!("fst_op_class: {:?}", fst_op_class);
-        // println!("snd_op_class: {:?}", snd_op_class);
+        let mut costs_all = CostSets::with_capacity_and_hasher(egraph.classes().len(), Default::default());
 
         while let Some(node_id) = analysis_pending.pop() {
-            let class_id = n2c(&node_id);
+            if deadline.passed() {
+                break;
+            }
             let node = &egraph[&node_id];
-            // println!("{:?}", node.op);
-            if node.children.iter().all(|c| costs_all.contains_key(n2c(c))) {
-                let lookup = costs_all.get(class_id);
-                // let mut prev_cost = -INFINITY;
-                let mut prev_costset0 = CostSet {
-                    costs: Default::default(),
-                    total: -INFINITY,
-                    choice: NodeId::new(),
-                };
-
-                if lookup.is_some() {
-                    prev_costset0 = lookup.unwrap().0.clone();
-                }
-
-                let mut prev_costset1 = CostSet {
-                    costs: Default::default(),
-                    total: -INFINITY,
-                    choice: NodeId::new(),
-                };
-
-                if lookup.is_some() {
-                    prev_costset1 = lookup.unwrap().1.clone();
-                }
-                let cost_set = Self::calculate_cost_set(egraph, node_id.clone(), &costs_all, prev_costset0.total);
-                // if node class is maj_class
-                if snd_op_class.contains(class_id) {
-                    // println!("{:?}", node.op);
-                    if node.op == "snd" {
-                        // Case 1: Node is snd and the previous node is not snd
-                        if prev_costset0.choice.as_ref() == "None" || egraph.nodes[&prev_costset0.choice].op != "snd"{
-                            // let cid2= maj_map.get(n2c(&node_id)).unwrap();
-                            let cid2 = n2c(snd_nmap.get(&node_id).unwrap());
-                            let mut cid4 = n2c(&node_id);
-                            if fst_nmap.contains_key(&costs_all.get(&cid2).unwrap().0.choice){
-                                cid4 = n2c(fst_nmap.get(&costs_all.get(&cid2).unwrap().0.choice).unwrap());
-                            }
-                            let total1 = cost_set.total;
-                            let (total2,prev_costset2) = combined_costset(&prev_costset0, cid2, &costs_all, false);
-                            if total1 > total2 {
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),prev_costset1.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                let mut costs2=cost_set.clone().costs;
-                                costs2.insert(cid2.clone(), egraph.nodes[&node_id].cost);
-                                costs2.remove(class_id);
-                                let CostSet2=CostSet {
-                                    costs: costs2,
-                                    total: cost_set.total,
-                                    choice: snd_nmap.get(&node_id).unwrap().clone(),
-                                };
-                                costs_all.insert(cid2.clone(), (CostSet2.clone(),costs_all.get(cid2).unwrap().1.clone()));
-                                analysis_pending.extend(parents[cid2].iter().cloned());
-                                // print!("11 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,CostSet2.choice);
-
-                                if cid4 != n2c(&node_id) {
-                                    let costset4=costs_all.get(cid4).unwrap().1.clone();
-                                    costs_all.insert(cid4.clone(), (costset4.clone(),costset4.clone()));
-                                    analysis_pending.extend(parents[cid4].iter().cloned());
-                                    // print!(" {:?}-{:?}", cid4,costset4.choice);
-                                }
-
-                                // println!();
-                            }
-                        }
-                        // Case 2: Node is snd and the previous node is snd
-                        else{
-                            if cost_set.total > prev_costset0.total {
-                                let cid2 = n2c(snd_nmap.get(&node_id).unwrap());
-                                let cid3 = n2c(snd_nmap.get(&prev_costset0.choice).unwrap());
-                                let mut cid4 = n2c(&node_id);
-                                if fst_nmap.contains_key(&costs_all.get(&cid2).unwrap().0.choice){
-                                    cid4 = n2c(fst_nmap.get(&costs_all.get(&cid2).unwrap().0.choice).unwrap());
-                                }
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),prev_costset1.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                let mut costs2=cost_set.clone().costs;
-                                costs2.insert(cid2.clone(), egraph.nodes[&node_id].cost);
-                                costs2.remove(class_id);
-                                let CostSet2=CostSet {
-                                    costs: costs2,
-                                    total: cost_set.total,
-                                    choice: snd_nmap.get(&node_id).unwrap().clone(),
-                                };
-                                costs_all.insert(cid2.clone(), (CostSet2.clone(),costs_all.get(cid2).unwrap().1.clone()));
-                                analysis_pending.extend(parents[cid2].iter().cloned());
-                                // print!("12 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,CostSet2.choice);
-                                if cid2 != cid3 {
-                                    let costset3=costs_all.get(cid3).unwrap().1.clone();
-                                    costs_all.insert(cid3.clone(), (costset3.clone(),costset3.clone()));
-                                    analysis_pending.extend(parents[cid3].iter().cloned());
-                                    // print!(" {:?}-{:?}", cid3,costset3.choice);
-                                }
-
-                                if cid4 != n2c(&node_id) {
-                                    let costset4=costs_all.get(cid4).unwrap().1.clone();
-                                    costs_all.insert(cid4.clone(), (costset4.clone(),costset4.clone()));
-                                    analysis_pending.extend(parents[cid4].iter().cloned());
-                                    // print!(" {:?}-{:?}", cid4,costset4.choice);
-                                }
-
-                                // println!();
-                            }
-                            // let cid1 = n2c(&node_id);
-                            // let cid2= maj_map.get(n2c(&node_id)).unwrap();
-                            // let cid3 = maj_map.get(n2c(&prev_costset0.choice)).unwrap();
-                            // if cid2 == cid3 {
-                            //     if cost_set.total > prev_costset0.total {
-                            //         costs_all.insert(class_id.clone(), (cost_set.clone(),prev_costset1.clone()));
-                            //         analysis_pending.extend(parents[class_id].iter().cloned());
-                            //         let mut costs2=cost_set.clone().costs;
-                            //         costs2.insert(cid2.clone(), egraph.nodes[&node_id].cost);
-                            //         costs2.remove(class_id);
-                            //         let CostSet2=CostSet {
-                            //             costs: costs2,
-                            //             total: cost_set.total,
-                            //             choice: snd_nmap.get(&node_id).unwrap().clone(),
-                            //         };
-                            //         costs_all.insert(cid2.clone(), (CostSet2.clone(),costs_all.get(cid2).unwrap().1.clone()));
-                            //         analysis_pending.extend(parents[cid2].iter().cloned());
-                            //         println!("12 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,CostSet2.choice);
-                            //     }
-                            // }
-                            // else {
-                            // }
-                        }
-                    }
-                    else if node.op != "fst" {
-                        // Case 3: Node is not snd and the previous node 0 is not snd
-                        let mut flag = true;
-                        if prev_costset0.choice.as_ref() == "None" || egraph.nodes[&prev_costset0.choice].op != "snd"{
-                            if cost_set.total > prev_costset0.total {
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),cost_set.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                flag = false;
-                                // println!("13 {:?}-{:?}", class_id,cost_set.choice);
-                            }
-                        }
-                        // Case 4: Node is not snd and the previous node is snd
-                        else{
-                            let cid2 = n2c(snd_nmap.get(&prev_costset0.choice).unwrap());
-                            let (total1,prev_costset2) = combined_costset(&cost_set, cid2, &costs_all, true);
-                            if total1 > prev_costset0.total {
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),cost_set.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                // update the costset of xor
-                                let costset2=costs_all.get(cid2).unwrap().1.clone();
-                                costs_all.insert(cid2.clone(),(costset2.clone(),costset2.clone()));
-                                analysis_pending.extend(parents[cid2].iter().cloned());
-                                flag = false;
-                                // println!("14 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,costset2.choice);
-                            }
-                        }
-                        // If the node total is less than the previous node 0 total, then we need to check the previous node 1, if it is not snd, then we can update the costset.
-                        if flag {
-                            if prev_costset1.choice.as_ref() == "None" || egraph.nodes[&prev_costset1.choice].op != "snd"{
-                                if cost_set.total > prev_costset1.total {
-                                    costs_all.insert(class_id.clone(), (prev_costset0.clone(),cost_set));
-                                    // println!("15 {:?}-{:?}", class_id,prev_costset0.choice);
-                                }
-                            }
-                        }
-                    }
-                }
-                else if fst_op_class.contains(class_id) {
-                    // println!("{:?}", node.op);
-                    if node.op == "fst" {
-                        // Case 1: Node is fst and the previous node is not fst
-                        if prev_costset0.choice.as_ref() == "None" || egraph.nodes[&prev_costset0.choice].op != "fst"{
-                            let cid2 = n2c(fst_nmap.get(&node_id).unwrap());
-                            let mut cid4 = n2c(&node_id);
-                            if fst_nmap.contains_key(&costs_all.get(&cid2).unwrap().0.choice){
-                                cid4 = n2c(fst_nmap.get(&costs_all.get(&cid2).unwrap().0.choice).unwrap());
-                            }
-                            let total1 = cost_set.total;
-                            let (total2,_) = combined_costset(&prev_costset0, cid2, &costs_all, false);
-                            if total1 > total2 {
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),prev_costset1.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                let mut costs2=cost_set.clone().costs;
-                                costs2.insert(cid2.clone(), egraph.nodes[&node_id].cost);
-                                costs2.remove(class_id);
-                                let CostSet2=CostSet {
-                                    costs: costs2,
-                                    total: cost_set.total,
-                                    choice: fst_nmap.get(&node_id).unwrap().clone(),
-                                };
-                                costs_all.insert(cid2.clone(), (CostSet2.clone(),costs_all.get(cid2).unwrap().1.clone()));
-                                analysis_pending.extend(parents[cid2].iter().cloned());
-                                // print!("21 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,CostSet2.choice);
-                                
-
-                                if cid4 != n2c(&node_id) {
-                                    let costset4=costs_all.get(cid4).unwrap().1.clone();
-                                    costs_all.insert(cid4.clone(), (costset4.clone(),costset4.clone()));
-                                    analysis_pending.extend(parents[cid4].iter().cloned());
-                                    // print!(" {:?}-{:?}", cid4,costset4.choice);
-                                }
-
-                                // println!();
-                            }
-                        }
-                        // Case 2: Node is fst and the previous node is fst
-                        else{
-                            if cost_set.total > prev_costset0.total {
-                                let cid2 = n2c(fst_nmap.get(&node_id).unwrap());
-                                let cid3 = n2c(fst_nmap.get(&prev_costset0.choice).unwrap());
-                                let mut cid4 = n2c(&node_id);
-                                if fst_nmap.contains_key(&costs_all.get(&cid2).unwrap().0.choice){
-                                    cid4 = n2c(fst_nmap.get(&costs_all.get(&cid2).unwrap().0.choice).unwrap());
-                                }
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),prev_costset1.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                let mut costs2=cost_set.clone().costs;
-                                costs2.insert(cid2.clone(), egraph.nodes[&node_id].cost);
-                                costs2.remove(class_id);
-                                let CostSet2=CostSet {
-                                    costs: costs2,
-                                    total: cost_set.total,
-                                    choice: fst_nmap.get(&node_id).unwrap().clone(),
-                                };
-                                costs_all.insert(cid2.clone(), (CostSet2.clone(),costs_all.get(cid2).unwrap().1.clone()));
-                                analysis_pending.extend(parents[cid2].iter().cloned());
-                                // print!("22 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,CostSet2.choice);
-                                if cid2 != cid3 {
-                                    let costset3=costs_all.get(cid3).unwrap().1.clone();
-                                    costs_all.insert(cid3.clone(), (costset3.clone(),costset3.clone()));
-                                    analysis_pending.extend(parents[cid3].iter().cloned());
-                                    // print!(" {:?}-{:?}", cid3,costset3.choice);
-                                }
-
-                                if cid4 != n2c(&node_id) {
-                                    let costset4=costs_all.get(cid4).unwrap().1.clone();
-                                    costs_all.insert(cid4.clone(), (costset4.clone(),costset4.clone()));
-                                    analysis_pending.extend(parents[cid4].iter().cloned());
-                                    // print!(" {:?}-{:?}", cid4,costset4.choice);
-                                }
-
-                                // println!();
-                            }
-                        }
-
-                    }
-                    else if node.op != "snd"{
-                        // Case 3: Node is not fst and the previous node 0 is not fst
-                        let mut flag = true;
-                        if prev_costset0.choice.as_ref() == "None" || egraph.nodes[&prev_costset0.choice].op != "fst"{
-                            if cost_set.total > prev_costset0.total {
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),cost_set.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                flag = false;
-                                // println!("23 {:?}-{:?}", class_id,cost_set.choice);
-                            }
-                        }
-                        // Case 4: Node is not fst and the previous node is fst
-                        else{
-                            let cid2 = n2c(fst_nmap.get(&prev_costset0.choice).unwrap());
-                            let (total1,prev_costset2) = combined_costset(&cost_set, cid2, &costs_all, true);
-                            if total1 > prev_costset0.total {
-                                costs_all.insert(class_id.clone(), (cost_set.clone(),cost_set.clone()));
-                                analysis_pending.extend(parents[class_id].iter().cloned());
-                                // update the costset of xor
-                                let costset2=costs_all.get(cid2).unwrap().1.clone();
-                                costs_all.insert(cid2.clone(),(costset2.clone(),costset2.clone()));
-                                analysis_pending.extend(parents[cid2].iter().cloned());
-                                flag = false;
-                                // println!("24 {:?}-{:?} {:?}-{:?}", class_id,cost_set.choice, cid2,costset2.choice);
-                            }
-                        }
-                        // If the node total is less than the previous node 0 total, then we need to check the previous node 1, if it is not fst, then we can update the costset.
-                        if flag {
-                            if prev_costset1.choice.as_ref() == "None" || egraph.nodes[&prev_costset1.choice].op != "fst"{
-                                if cost_set.total > prev_costset1.total {
-                                    costs_all.insert(class_id.clone(), (prev_costset0.clone(),cost_set));
-                                    // println!("25 {:?}-{:?}", class_id,prev_costset0.choice);
-                                }
-                            }
-                        }
-                    }
-                }
-                else if cost_set.total > prev_costset0.total {
-                    costs_all.insert(class_id.clone(), (cost_set,prev_costset0));
-                    analysis_pending.extend(parents[class_id].iter().cloned());
+            if node.children.iter().all(|c| costs_all.contains_key(c)) {
+                let bound = Self::shortcut_bound(&pairs, &costs_all, &node_id, n2c(&node_id));
+                let cost_set = Self::calculate_cost_set(egraph, node_id, &costs_all, bound);
+                result.record_cost(node_id, cost_set.total);
+                for class_id in Self::relax(egraph, &pairs, &mut costs_all, cost_set) {
+                    analysis_pending.extend(parents[&class_id].iter().cloned());
                 }
             }
         }
 
-        for (cid, cost_set) in costs_all {
-            result.choose(cid, cost_set.0.choice);
-        }
-
-
-        result
-    }
-}
-
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
-
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
+        DualExtractionResult::new(egraph, &pairs, costs_all, result)
     }
 }
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
\ No newline at end of file
@@ -7,9 +7,11 @@ use rayon::vec;
 use rustc_hash::{FxHashMap, FxHashSet};
 use core::panic;
 use std::{hash::Hash, os::unix::process, sync::{Arc, Mutex,RwLock}};
-use rand::seq::SliceRandom;
 use dashmap::DashMap;
 use std::time::Instant;
+use crate::passes::{MultiPassExtractor, PassLog, PassSchedule};
+use crate::policy::{self, Candidate, ChoicePolicy};
+use crate::queue::UniqueQueue;
 
 #[derive(Clone, Debug)]
 struct CostSet {
@@ -17,16 +19,32 @@ struct CostSet {
     costs: HashMap<ClassId, Cost>,
     total: Cost,
     choice: NodeId,
+    depth: usize,
 }
 
-pub struct FasterGreedyDagExtractor;
+impl CostSet {
+    fn candidate<'a>(&self, egraph: &'a EGraph) -> Candidate<'a> {
+        Candidate { node: &egraph[&self.choice], total: self.total, depth: self.depth, size: self.costs.len() }
+    }
+}
+
+#[derive(Default)]
+pub struct FasterGreedyDagExtractor {
+    /// Visit classes and nodes in id order instead of shuffling them, and sum cost
+    /// sets in class order, so repeated runs give the same extraction.
+    pub deterministic: bool,
+    pub passes: PassSchedule,
+    /// Which of two cost sets of a class wins, strict cost by default.
+    pub policy: Option<Arc<dyn ChoicePolicy>>,
+}
 
 impl FasterGreedyDagExtractor {
     fn calculate_cost_set(
-        egraph: &EGraph,
+        egraph: FrozenEGraph,
         node_id: NodeId,
         costs: &Arc<DashMap<ClassId, Arc<CostSet>>>,
         best_cost: Cost,
+        deterministic: bool,
     ) -> Arc<CostSet> {
         let node = &egraph[&node_id];
         let cid = egraph.nid_to_cid(&node_id);
@@ -36,6 +54,7 @@ impl FasterGreedyDagExtractor {
                 costs: HashMap::from([(cid.clone(), node.cost)]),
                 total: node.cost,
                 choice: node_id.clone(),
+                depth: 1,
             });
         }
 
@@ -59,6 +78,7 @@ impl FasterGreedyDagExtractor {
                 costs: Default::default(),
                 total: INFINITY,
                 choice: node_id.clone(),
+                depth: 0,
             });
         }
 
@@ -72,6 +92,7 @@ impl FasterGreedyDagExtractor {
                 costs: Default::default(),
                 total: INFINITY,
                 choice: node_id.clone(),
+                depth: 0,
             });
         }
 
@@ -100,22 +121,28 @@ impl FasterGreedyDagExtractor {
 
         let result_cost = if contains {
             INFINITY
+        } else if deterministic {
+            sum_in_class_order(&result)
         } else {
             result.values().sum()
         };
+        let depth = 1 + child_costs.iter().map(|(_, cost)| cost.depth).max().unwrap();
 
         return Arc::new(CostSet {
             costs: result,
             total: result_cost,
             choice: node_id.clone(),
+            depth,
         });
     }
 }
 
 fn process_item(
-    egraph: &EGraph,
+    egraph: FrozenEGraph,
     node_id: &NodeId,
     costs: &Arc<DashMap<ClassId, Arc<CostSet>>>,
+    deterministic: bool,
+    policy: Option<&dyn ChoicePolicy>,
     // costs: &HashMap<ClassId, Arc<CostSet>>,
 )  -> (FxHashMap<ClassId, Arc<CostSet>>, NotNan<f64>, NodeId) {
     let class_id = egraph.nid_to_cid(&node_id);
@@ -123,15 +150,13 @@ fn process_item(
     let mut should_insert = FxHashMap::default();
     let mut total= INFINITY;
     if node.children.iter().all(|c| costs.contains_key(c)) {
-        let lookup = costs.get(&class_id);
-        let mut prev_cost = INFINITY;
-        if lookup.is_some() {
-            prev_cost = lookup.unwrap().total;
-        }
+        let prev = costs.get(&class_id).map(|prev| Arc::clone(prev.value()));
+        let prev_cost = prev.as_ref().map_or(INFINITY, |prev| prev.total);
 
-        let cost_set = FasterGreedyDagExtractor::calculate_cost_set(egraph, node_id.clone(), &costs, prev_cost);
+        let cost_set = FasterGreedyDagExtractor::calculate_cost_set(egraph, node_id.clone(), &costs, prev_cost, deterministic);
         total = cost_set.total;
-        if cost_set.total < prev_cost {
+        let current = prev.map(|prev| prev.candidate(&egraph));
+        if policy::prefers(policy, &cost_set.candidate(&egraph), current.as_ref()) {
             should_insert.insert(class_id.clone(), cost_set);
         }
     }
@@ -139,7 +164,35 @@ fn process_item(
 }
 
 impl Extractor for FasterGreedyDagExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_parallel(FrozenEGraph::new(egraph), roots)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        let deadline = Deadline::after(budget);
+        self.extract_passes_until(FrozenEGraph::new(egraph), roots, deadline).0
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
+
+impl ParallelExtractor for FasterGreedyDagExtractor {
+    fn extract_parallel(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_with_passes(egraph, roots).0
+    }
+}
+
+impl MultiPassExtractor for FasterGreedyDagExtractor {
+    fn extract_with_passes(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> (ExtractionResult, PassLog) {
+        self.extract_passes_until(egraph, roots, Deadline::NONE)
+    }
+}
+
+impl FasterGreedyDagExtractor {
+    /// `extract_with_passes`, stopping between batches once `deadline` has passed.
+    fn extract_passes_until(&self, egraph: FrozenEGraph, _roots: &[ClassId], deadline: Deadline) -> (ExtractionResult, PassLog) {
         let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
         let mut analysis_pending = UniqueQueue::default();
 
@@ -166,24 +219,15 @@ impl Extractor for FasterGreedyDagExtractor {
         let num_threads = 64; // 可以根据你的机器进行调整
         
 
-        for i in 0..2 {
-            let mut classes: Vec<&Class> = egraph.classes().values().collect();
-            classes.shuffle(&mut rand::thread_rng());
-            for class in classes {
-                for node in &class.nodes {
-                    if i == 0{
-                        if egraph[node].is_leaf() {
-                            analysis_pending.insert(node.clone());
-                        }
-                    }
-                    else{
-                        analysis_pending.insert(node.clone());
-                    }
-                }
-            }
+        let mut passes = self.passes.run();
+        while let Some(pass) = passes.next() {
+            pass.seed(&egraph, self.deterministic, &mut analysis_pending);
+            let mut batches = 0;
+            let mut improved = FxHashSet::default();
 
-            while !analysis_pending.is_empty() {
-                let vec_node_id = analysis_pending.pop_32();
+            while !analysis_pending.is_empty() && !passes.stops(batches) && !deadline.passed() {
+                batches += 1;
+                let vec_node_id = analysis_pending.pop_batch();
 
                 // 使用 crossbeam 作用域线程
                 let costs_all_clone = Arc::clone(&costs_all);
@@ -205,7 +249,7 @@ impl Extractor for FasterGreedyDagExtractor {
                         let handle = s.spawn(move |_| {
                             let mut thread_results = vec![];
                             for node_id in chunk {
-                                let result = process_item(egraph, &node_id, &costs_all);
+                                let result = process_item(egraph, &node_id, &costs_all, self.deterministic, self.policy.as_deref());
                                 thread_results.push(result);
                             }
                             thread_results
@@ -231,7 +275,7 @@ impl Extractor for FasterGreedyDagExtractor {
                         if value.total != INFINITY {
                             grouped.entry(key)
                                 .and_modify(|existing| {
-                                    if value.total < existing.total {
+                                    if policy::prefers(self.policy.as_deref(), &value.candidate(&egraph), Some(&existing.candidate(&egraph))) {
                                         *existing = value.clone();
                                     }
                                 })
@@ -251,12 +295,17 @@ impl Extractor for FasterGreedyDagExtractor {
 
 
                 // 更新全局状态并添加父节点到队列
+                improved.extend(grouped.keys().cloned());
                 for (cid, cost_set) in grouped {
                     costs_all.insert(cid.clone(), cost_set);
                     analysis_pending.extend(parents[&cid].iter().cloned());
                 }
 
             }
+            passes.end(improved.len(), !analysis_pending.is_empty());
+            if deadline.passed() {
+                break;
+            }
         }
 
 
@@ -265,85 +314,13 @@ impl Extractor for FasterGreedyDagExtractor {
             let cid = entry.key();
             let cost_set = entry.value();
             result.choose(cid.clone(), cost_set.choice.clone());
+            result.class_costs.insert(*cid, cost_set.total);
         }
 
-        result
-    }
-}
-
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
-
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
+        if self.deterministic {
+            result.choices.sort_keys();
+            result.class_costs.sort_keys();
         }
+        (result, passes.finish())
     }
 }
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    pub fn pop_32(&mut self) -> Vec<T> {
-        let k = 4096*2;
-        let mut popped_items = Vec::with_capacity(k);
-        
-        for _ in 0..k {
-            if let Some(item) = self.queue.pop_front() {
-                self.set.remove(&item);
-                popped_items.push(item);
-            } else {
-                break; // 队列已空，退出循环
-            }
-        }
-        
-        popped_items
-    }
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
\ No newline at end of file
@@ -7,10 +7,12 @@ use rayon::vec;
 use rustc_hash::{FxHashMap, FxHashSet};
 use core::panic;
 use std::{os::unix::process, sync::{Arc, Mutex,RwLock}};
-use rand::seq::SliceRandom;
 use dashmap::DashMap;
 use std::time::Instant;
 use rayon::prelude::*;
+use crate::passes::{MultiPassExtractor, PassLog, PassSchedule};
+use crate::policy::{self, Candidate, ChoicePolicy};
+use crate::queue::UniqueQueue;
 
 
 #[derive(Clone, Debug)]
@@ -19,16 +21,32 @@ struct CostSet {
     costs: HashMap<ClassId, Cost>,
     total: Cost,
     choice: NodeId,
+    depth: usize,
 }
 
-pub struct FasterGreedyDagExtractor;
+impl CostSet {
+    fn candidate<'a>(&self, egraph: &'a EGraph) -> Candidate<'a> {
+        Candidate { node: &egraph[&self.choice], total: self.total, depth: self.depth, size: self.costs.len() }
+    }
+}
+
+#[derive(Default)]
+pub struct FasterGreedyDagExtractor {
+    /// Visit classes and nodes in id order instead of shuffling them, and sum cost
+    /// sets in class order, so repeated runs give the same extraction.
+    pub deterministic: bool,
+    pub passes: PassSchedule,
+    /// Which of two cost sets of a class wins, strict cost by default.
+    pub policy: Option<Arc<dyn ChoicePolicy>>,
+}
 
 impl FasterGreedyDagExtractor {
     fn calculate_cost_set(
-        egraph: &EGraph,
+        egraph: FrozenEGraph,
         node_id: NodeId,
         costs: &Arc<DashMap<ClassId, Arc<CostSet>>>,
         best_cost: Cost,
+        deterministic: bool,
     ) -> Arc<CostSet> {
         let node = &egraph[&node_id];
         let cid = egraph.nid_to_cid(&node_id);
@@ -38,6 +56,7 @@ impl FasterGreedyDagExtractor {
                 costs: HashMap::from([(cid.clone(), node.cost)]),
                 total: node.cost,
                 choice: node_id.clone(),
+                depth: 1,
             });
         }
 
@@ -60,6 +79,7 @@ impl FasterGreedyDagExtractor {
                 costs: Default::default(),
                 total: INFINITY,
                 choice: node_id.clone(),
+                depth: 0,
             });
         }
 
@@ -72,6 +92,7 @@ impl FasterGreedyDagExtractor {
                 costs: Default::default(),
                 total: INFINITY,
                 choice: node_id.clone(),
+                depth: 0,
             });
         }
 
@@ -94,36 +115,40 @@ impl FasterGreedyDagExtractor {
 
         let result_cost = if contains {
             INFINITY
+        } else if deterministic {
+            sum_in_class_order(&result)
         } else {
             result.values().sum()
         };
+        let depth = 1 + child_costs.iter().map(|(_, cost)| cost.depth).max().unwrap();
 
         Arc::new(CostSet {
             costs: result,
             total: result_cost,
             choice: node_id.clone(),
+            depth,
         })
     }
 }
 
 fn process_item(
-    egraph: &EGraph,
+    egraph: FrozenEGraph,
     node_id: &NodeId,
     costs: &Arc<DashMap<ClassId, Arc<CostSet>>>,
+    deterministic: bool,
+    policy: Option<&dyn ChoicePolicy>,
 ) -> (FxHashMap<ClassId, Arc<CostSet>>, NotNan<f64>, NodeId) {
     let class_id = egraph.nid_to_cid(&node_id);
     let node = &egraph[node_id];
     let mut should_insert = FxHashMap::default();
     let mut total = INFINITY;
     if node.children.iter().all(|c| costs.contains_key(c)) {
-        let lookup = costs.get(&class_id);
-        let mut prev_cost = INFINITY;
-        if lookup.is_some() {
-            prev_cost = lookup.unwrap().total;
-        }
-        let cost_set = FasterGreedyDagExtractor::calculate_cost_set(egraph, node_id.clone(), &costs, prev_cost);
+        let prev = costs.get(&class_id).map(|prev| Arc::clone(prev.value()));
+        let prev_cost = prev.as_ref().map_or(INFINITY, |prev| prev.total);
+        let cost_set = FasterGreedyDagExtractor::calculate_cost_set(egraph, node_id.clone(), &costs, prev_cost, deterministic);
         total = cost_set.total;
-        if cost_set.total < prev_cost {
+        let current = prev.map(|prev| prev.candidate(&egraph));
+        if policy::prefers(policy, &cost_set.candidate(&egraph), current.as_ref()) {
             should_insert.insert(class_id.clone(), cost_set);
         }
     }
@@ -131,9 +156,37 @@ fn process_item(
 }
 
 impl Extractor for FasterGreedyDagExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_parallel(FrozenEGraph::new(egraph), roots)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        let deadline = Deadline::after(budget);
+        self.extract_passes_until(FrozenEGraph::new(egraph), roots, deadline).0
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
+
+impl ParallelExtractor for FasterGreedyDagExtractor {
+    fn extract_parallel(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_with_passes(egraph, roots).0
+    }
+}
+
+impl MultiPassExtractor for FasterGreedyDagExtractor {
+    fn extract_with_passes(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> (ExtractionResult, PassLog) {
+        self.extract_passes_until(egraph, roots, Deadline::NONE)
+    }
+}
+
+impl FasterGreedyDagExtractor {
+    /// `extract_with_passes`, stopping between batches once `deadline` has passed.
+    fn extract_passes_until(&self, egraph: FrozenEGraph, _roots: &[ClassId], deadline: Deadline) -> (ExtractionResult, PassLog) {
         let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
-        let mut analysis_pending = UniqueQueue::default();
+        let mut analysis_pending = UniqueQueue::with_batch_size(16384);
 
         let costs_all: Arc<DashMap<ClassId, Arc<CostSet>>> = Arc::new(DashMap::with_capacity_and_hasher(
             egraph.classes().len(), Default::default()));
@@ -161,24 +214,15 @@ impl Extractor for FasterGreedyDagExtractor {
         // while !arc_queue.lock().unwrap().is_empty() {
 
 
-        for i in 0..5 {
-            let mut classes: Vec<&Class> = egraph.classes().values().collect();
-            classes.shuffle(&mut rand::thread_rng());
-            for class in classes {
-                for node in &class.nodes {
-                    if i == 0{
-                        if egraph[node].is_leaf() {
-                            analysis_pending.insert(node.clone());
-                        }
-                    }
-                    else{
-                        analysis_pending.insert(node.clone());
-                    }
-                }
-            }
+        let mut passes = self.passes.run();
+        while let Some(pass) = passes.next() {
+            pass.seed(&egraph, self.deterministic, &mut analysis_pending);
+            let mut batches = 0;
+            let mut improved = FxHashSet::default();
 
-            while !analysis_pending.is_empty() {
-                let vec_node_id = analysis_pending.pop_32();
+            while !analysis_pending.is_empty() && !passes.stops(batches) && !deadline.passed() {
+                batches += 1;
+                let vec_node_id = analysis_pending.pop_batch();
 
 
                 let costs_all_clone = Arc::clone(&costs_all);
@@ -187,7 +231,7 @@ impl Extractor for FasterGreedyDagExtractor {
 
                 let should_insert: Vec<_> = vec_node_id.into_par_iter().map(|node_id| {
                     let costs_all = Arc::clone(&costs_all_clone);
-                    process_item(egraph, &node_id, &costs_all)
+                    process_item(egraph, &node_id, &costs_all, self.deterministic, self.policy.as_deref())
                 }).collect();
 
 
@@ -198,7 +242,7 @@ impl Extractor for FasterGreedyDagExtractor {
                         if value.total != INFINITY {
                             grouped.entry(key)
                                 .and_modify(|existing| {
-                                    if value.total < existing.total {
+                                    if policy::prefers(self.policy.as_deref(), &value.candidate(&egraph), Some(&existing.candidate(&egraph))) {
                                         *existing = value.clone();
                                     }
                                 })
@@ -215,11 +259,16 @@ impl Extractor for FasterGreedyDagExtractor {
                         _ => {}
                     }
                 });
+                improved.extend(grouped.keys().cloned());
                 for (cid, cost_set) in grouped {
                     costs_all.insert(cid, cost_set);
                     analysis_pending.extend(parents[&cid].iter().cloned());
                 }
             }
+            passes.end(improved.len(), !analysis_pending.is_empty());
+            if deadline.passed() {
+                break;
+            }
         }
 
 
@@ -227,84 +276,12 @@ impl Extractor for FasterGreedyDagExtractor {
             let cid = entry.key();
             let cost_set = entry.value();
             result.choose(cid.clone(), cost_set.choice);
+            result.class_costs.insert(*cid, cost_set.total);
         }
-        result
-    }
-}
-
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
-
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
+        if self.deterministic {
+            result.choices.sort_keys();
+            result.class_costs.sort_keys();
         }
+        (result, passes.finish())
     }
 }
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    pub fn pop_32(&mut self) -> Vec<T> {
-        let k = 16384;
-        let mut popped_items = Vec::with_capacity(k);
-        
-        for _ in 0..k {
-            if let Some(item) = self.queue.pop_front() {
-                self.set.remove(&item);
-                popped_items.push(item);
-            } else {
-                break; // 队列已空，退出循环
-            }
-        }
-        
-        popped_items
-    }
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
\ No newline at end of file
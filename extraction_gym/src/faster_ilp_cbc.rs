@@ -233,7 +233,7 @@ fn extract(
         })
         .collect();
 
-    let initial_result = super::faster_greedy_dag::FasterGreedyDagExtractor.extract(egraph, &roots);
+    let initial_result = super::faster_greedy_dag::FasterGreedyDagExtractor::default().extract(egraph, &roots);
     let initial_result_cost = initial_result.dag_cost(egraph, &roots);
 
     // For classes where we know the choice already, we set the nodes early.
@@ -669,8 +669,9 @@ fn remove_unreachable_classes(
     config: &Config,
 ) {
     if config.remove_unreachable_classes {
-        let mut reachable_classes: IndexSet<ClassId> = IndexSet::default();
-        reachable(&*vars, roots, &mut reachable_classes);
+        let reachable_classes = crate::reachable_classes(roots, |class| {
+            vars.get(class).unwrap().childrens_classes.iter().flatten().cloned().collect::<Vec<_>>()
+        });
         let initial_size = vars.len();
         vars.retain(|class_id, _| reachable_classes.contains(class_id));
         log::info!("Unreachable classes: {}", initial_size - vars.len());
@@ -1016,24 +1017,6 @@ fn classes_with_single_parent(vars: &IndexMap<ClassId, ClassILP>) -> IndexMap<Cl
         .collect()
 }
 
-//Set of classes that can be reached from the [classes]
-fn reachable(
-    vars: &IndexMap<ClassId, ClassILP>,
-    classes: &[ClassId],
-    is_reachable: &mut IndexSet<ClassId>,
-) {
-    for class in classes {
-        if is_reachable.insert(class.clone()) {
-            let class_vars = vars.get(class).unwrap();
-            for kids in &class_vars.childrens_classes {
-                for child_class in kids {
-                    reachable(vars, &[child_class.clone()], is_reachable);
-                }
-            }
-        }
-    }
-}
-
 // Adds constraints to stop the cycle.
 fn block_cycle(model: &mut Model, cycle: &Vec<ClassId>, vars: &IndexMap<ClassId, ClassILP>) {
     if cycle.is_empty() {
@@ -0,0 +1,69 @@
+use rustc_hash::FxHashMap;
+use crate::*;
+use crate::delay::DelayModel;
+use crate::queue::UniqueQueue;
+
+/// Same worklist as `faster_ast_depth`, but a node's depth is its delay plus the
+/// deepest child, so the extracted DAG minimises the weighted critical path.
+#[derive(Default)]
+pub struct FasterWeightedDepthExtractor {
+    pub delays: DelayModel,
+}
+
+impl FasterWeightedDepthExtractor {
+    pub fn new(delays: DelayModel) -> Self {
+        Self { delays }
+    }
+}
+
+impl Extractor for FasterWeightedDepthExtractor {
+    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
+        let mut analysis_pending = UniqueQueue::default();
+
+        for class in egraph.classes().values() {
+            parents.insert(class.id.clone(), Vec::new());
+        }
+
+        for class in egraph.classes().values() {
+            for node in &class.nodes {
+                for child in &egraph[node].children {
+                    parents[child].push(node.clone());
+                }
+                if egraph[node].is_leaf() {
+                    analysis_pending.insert(node.clone());
+                }
+            }
+        }
+
+        let mut result = ExtractionResult::default();
+        let mut costs = FxHashMap::<ClassId, Cost>::with_capacity_and_hasher(
+            egraph.classes().len(),
+            Default::default(),
+        );
+
+        while let Some(node_id) = analysis_pending.pop() {
+            let class_id = n2c(&node_id);
+            let node = &egraph[&node_id];
+            let prev_cost = costs.get(class_id).unwrap_or(&INFINITY);
+            let cost = self.delays.delay(egraph, node)
+                + node.children.iter().fold(Cost::default(), |max, child_id| {
+                    max.max(*costs.get(child_id).unwrap_or(&INFINITY))
+                });
+            result.record_cost(node_id.clone(), cost);
+            if cost < *prev_cost {
+                result.choose(class_id.clone(), node_id.clone());
+                costs.insert(class_id.clone(), cost);
+                analysis_pending.extend(parents[class_id].iter().cloned());
+            }
+        }
+
+        result.class_costs.extend(costs);
+        result
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
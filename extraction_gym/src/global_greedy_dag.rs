@@ -153,20 +153,38 @@ impl TermDag {
 
 pub struct GlobalGreedyDagExtractor;
 impl Extractor for GlobalGreedyDagExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::NONE)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::after(budget))
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
+
+impl GlobalGreedyDagExtractor {
+    fn extract_until(&self, egraph: &EGraph, _roots: &[ClassId], deadline: Deadline) -> ExtractionResult {
         let mut keep_going = true;
 
         let nodes = egraph.nodes.clone();
         let mut termdag = TermDag::default();
         let mut best_in_class: HashMap<ClassId, TermId> = HashMap::default();
 
+        let mut result = ExtractionResult::default();
         let mut i = 0;
-        while keep_going {
+        'sweeps: while keep_going {
             i += 1;
             // println!("iteration {}", i);
             keep_going = false;
 
             'node_loop: for (node_id, node) in &nodes {
+                if deadline.passed() {
+                    break 'sweeps;
+                }
                 let mut children: Vec<TermId> = vec![];
                 // compute the cost set from the children
                 for child in &node.children {
@@ -185,6 +203,7 @@ impl Extractor for GlobalGreedyDagExtractor {
 
                 if let Some(candidate) = termdag.make(node_id.clone(), node, children, old_cost) {
                     let cadidate_cost = termdag.total_cost(candidate);
+                    result.record_cost(node_id.clone(), cadidate_cost);
 
                     if cadidate_cost < old_cost {
                         best_in_class.insert(node.eclass.clone(), candidate);
@@ -194,73 +213,10 @@ impl Extractor for GlobalGreedyDagExtractor {
             }
         }
 
-        let mut result = ExtractionResult::default();
         for (class, term) in best_in_class {
             result.choose(class, termdag.info[term].node.clone());
+            result.class_costs.insert(class, termdag.total_cost(term));
         }
         result
     }
 }
-
-
-/** A data structure to maintain a queue of unique elements.
-
-Notably, insert/pop operations have O(1) expected amortized runtime complexity.
-
-Thanks @Bastacyclop for the implementation!
-*/
-#[derive(Clone)]
-#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
-pub(crate) struct UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    set: FxHashSet<T>, // hashbrown::
-    queue: std::collections::VecDeque<T>,
-}
-
-impl<T> Default for UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    fn default() -> Self {
-        UniqueQueue {
-            set: Default::default(),
-            queue: std::collections::VecDeque::new(),
-        }
-    }
-}
-
-impl<T> UniqueQueue<T>
-where
-    T: Eq + std::hash::Hash + Clone,
-{
-    pub fn insert(&mut self, t: T) {
-        if self.set.insert(t.clone()) {
-            self.queue.push_back(t);
-        }
-    }
-
-    pub fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
-        for t in iter.into_iter() {
-            self.insert(t);
-        }
-    }
-
-    pub fn pop(&mut self) -> Option<T> {
-        let res = self.queue.pop_front();
-        res.as_ref().map(|t| self.set.remove(t));
-        res
-    }
-
-    #[allow(dead_code)]
-    pub fn is_empty(&self) -> bool {
-        let r = self.queue.is_empty();
-        debug_assert_eq!(r, self.set.is_empty());
-        r
-    }
-}
-
@@ -0,0 +1,261 @@
+//! Bottom-up extraction with the cost relaxation run on the GPU (experimental).
+//!
+//! The e-graph is flattened into tables of node costs, child classes and the
+//! node range of every class, and the rounds of `gpu_bottom_up.wgsl` run over
+//! them until no class gets cheaper: one thread per node, then one per class.
+//! That is the fixed point `faster_bottom_up` reaches with a work list, computed
+//! in 32-bit floats. Without the `gpu` feature, without an adapter, or when the
+//! tables exceed the adapter's buffer limits, the extraction runs on the CPU
+//! with `FasterBottomUpExtractor` instead.
+
+use crate::*;
+use crate::faster_bottom_up::FasterBottomUpExtractor;
+
+#[derive(Default)]
+pub struct GpuBottomUpExtractor;
+
+impl Extractor for GpuBottomUpExtractor {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        #[cfg(feature = "gpu")]
+        if let Some(result) = device::extract(egraph, roots) {
+            return result;
+        }
+        FasterBottomUpExtractor::default().extract(egraph, roots)
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "gpu")]
+mod device {
+    use super::*;
+    use std::sync::mpsc;
+    use wgpu::util::DeviceExt;
+
+    const WORKGROUP: u32 = 256;
+    const MAX_WORKGROUPS: u32 = 65535;
+    /// Rounds submitted between two reads of the `changed` flag.
+    const ROUNDS_PER_CHECK: usize = 32;
+    const UNREACHED: f32 = 3.0e38;
+
+    /// The e-graph as flat tables, with the nodes numbered class by class.
+    struct Tables {
+        nodes: Vec<NodeId>,
+        classes: Vec<ClassId>,
+        node_cost: Vec<f32>,
+        child_start: Vec<u32>,
+        children: Vec<u32>,
+        class_start: Vec<u32>,
+    }
+
+    impl Tables {
+        fn new(egraph: &EGraph) -> Self {
+            let class_index: FxHashMap<ClassId, u32> =
+                egraph.classes().keys().enumerate().map(|(i, cid)| (*cid, i as u32)).collect();
+            let mut tables = Tables {
+                nodes: Vec::with_capacity(egraph.nodes.len()),
+                classes: egraph.classes().keys().cloned().collect(),
+                node_cost: Vec::with_capacity(egraph.nodes.len()),
+                child_start: Vec::with_capacity(egraph.nodes.len() + 1),
+                children: Vec::new(),
+                class_start: Vec::with_capacity(egraph.classes().len() + 1),
+            };
+            for class in egraph.classes().values() {
+                tables.class_start.push(tables.nodes.len() as u32);
+                for nid in &class.nodes {
+                    let node = &egraph[nid];
+                    tables.nodes.push(*nid);
+                    tables.node_cost.push(node.cost.into_inner() as f32);
+                    tables.child_start.push(tables.children.len() as u32);
+                    tables.children.extend(node.children.iter().map(|child| class_index[child]));
+                }
+            }
+            tables.class_start.push(tables.nodes.len() as u32);
+            tables.child_start.push(tables.children.len() as u32);
+            tables
+        }
+    }
+
+    /// The extraction from the converged node costs, `None` if the relaxation
+    /// couldn't run on a GPU or its choices have a cycle, which ties between
+    /// zero-cost nodes can make.
+    pub(super) fn extract(egraph: &EGraph, roots: &[ClassId]) -> Option<ExtractionResult> {
+        if egraph.nodes.is_empty() {
+            return None;
+        }
+        let tables = Tables::new(egraph);
+        let (node_total, class_total) = pollster::block_on(relax(&tables))?;
+
+        let mut result = ExtractionResult::default();
+        for (c, cid) in tables.classes.iter().enumerate() {
+            if class_total[c] >= UNREACHED {
+                continue;
+            }
+            let range = tables.class_start[c] as usize..tables.class_start[c + 1] as usize;
+            let best = range.clone().min_by(|a, b| node_total[*a].total_cmp(&node_total[*b])).unwrap();
+            for n in range {
+                if node_total[n] < UNREACHED {
+                    result.record_cost(tables.nodes[n], Cost::new(node_total[n] as f64).unwrap());
+                }
+            }
+            result.class_costs.insert(*cid, Cost::new(class_total[c] as f64).unwrap());
+            result.choose(*cid, tables.nodes[best]);
+        }
+        result.find_cycles(egraph, roots).is_empty().then_some(result)
+    }
+
+    /// Workgroups for one thread per item, continued in y past the x limit.
+    fn workgroups(items: usize) -> (u32, u32) {
+        let groups = (items as u32).div_ceil(WORKGROUP).max(1);
+        let x = groups.min(MAX_WORKGROUPS);
+        (x, groups.div_ceil(x))
+    }
+
+    /// Node and class costs at the fixed point.
+    async fn relax(tables: &Tables) -> Option<(Vec<f32>, Vec<f32>)> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions { power_preference: wgpu::PowerPreference::HighPerformance, ..Default::default() })
+            .await?;
+        let limits = adapter.limits();
+        let largest = tables.children.len().max(tables.child_start.len()) * 4;
+        if largest as u64 > (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size) {
+            return None;
+        }
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("gpu-bottom-up"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: limits,
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .ok()?;
+
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST;
+        let buffer = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some(label), contents, usage })
+        };
+        // Bindings can't be empty, e.g. the children of an e-graph of leaves.
+        let padded = |values: &[u32]| if values.is_empty() { vec![0] } else { values.to_vec() };
+        let buffers = [
+            buffer("node_cost", bytemuck::cast_slice(&tables.node_cost), usage),
+            buffer("child_start", bytemuck::cast_slice(&tables.child_start), usage),
+            buffer("children", bytemuck::cast_slice(&padded(&tables.children)), usage),
+            buffer("class_start", bytemuck::cast_slice(&tables.class_start), usage),
+            buffer("node_total", bytemuck::cast_slice(&vec![UNREACHED; tables.nodes.len()]), usage),
+            buffer("class_total", bytemuck::cast_slice(&vec![UNREACHED; tables.classes.len()]), usage),
+            buffer("changed", bytemuck::cast_slice(&[0u32]), usage),
+            buffer(
+                "sizes",
+                bytemuck::cast_slice(&[tables.nodes.len() as u32, tables.classes.len() as u32, 0, 0]),
+                wgpu::BufferUsages::UNIFORM,
+            ),
+        ];
+
+        let layout_entries: Vec<wgpu::BindGroupLayoutEntry> = (0..buffers.len() as u32)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: match binding {
+                        7 => wgpu::BufferBindingType::Uniform,
+                        _ => wgpu::BufferBindingType::Storage { read_only: binding < 4 },
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect();
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: Some("gpu-bottom-up"), entries: &layout_entries });
+        let bind_group_entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry { binding: binding as u32, resource: buffer.as_entire_binding() })
+            .collect();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu-bottom-up"),
+            layout: &bind_group_layout,
+            entries: &bind_group_entries,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gpu-bottom-up"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gpu-bottom-up"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu_bottom_up.wgsl").into()),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let relax_nodes = pipeline("relax_nodes");
+        let relax_classes = pipeline("relax_classes");
+
+        let (node_x, node_y) = workgroups(tables.nodes.len());
+        let (class_x, class_y) = workgroups(tables.classes.len());
+        let changed = &buffers[6];
+        // Every round that changes something lowers a class to the cost of a
+        // sub-term one node taller, so this many rounds settle every class.
+        let max_rounds = tables.classes.len() + 1;
+        let mut rounds = 0;
+        while rounds < max_rounds {
+            queue.write_buffer(changed, 0, bytemuck::cast_slice(&[0u32]));
+            let mut encoder = device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_bind_group(0, &bind_group, &[]);
+                for _ in 0..ROUNDS_PER_CHECK {
+                    pass.set_pipeline(&relax_nodes);
+                    pass.dispatch_workgroups(node_x, node_y, 1);
+                    pass.set_pipeline(&relax_classes);
+                    pass.dispatch_workgroups(class_x, class_y, 1);
+                }
+            }
+            queue.submit([encoder.finish()]);
+            rounds += ROUNDS_PER_CHECK;
+            if read::<u32>(&device, &queue, changed)?[0] == 0 {
+                break;
+            }
+        }
+        Some((read(&device, &queue, &buffers[4])?, read(&device, &queue, &buffers[5])?))
+    }
+
+    /// The contents of `source`, through a mappable copy.
+    fn read<T: bytemuck::Pod>(device: &wgpu::Device, queue: &wgpu::Queue, source: &wgpu::Buffer) -> Option<Vec<T>> {
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: source.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(source, 0, &staging, 0, source.size());
+        queue.submit([encoder.finish()]);
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+        let values = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        staging.unmap();
+        Some(values)
+    }
+}
@@ -0,0 +1,55 @@
+// Bottom-up relaxation of gpu_bottom_up.rs. A round computes the cost of every
+// node from the costs of its children's classes, then lowers the cost of every
+// class to its cheapest node, flagging `changed` when one goes down. Nodes are
+// numbered class by class, so the nodes of class c are class_start[c] until
+// class_start[c + 1].
+
+const UNREACHED: f32 = 3.0e38;
+
+@group(0) @binding(0) var<storage, read> node_cost: array<f32>;
+@group(0) @binding(1) var<storage, read> child_start: array<u32>;
+@group(0) @binding(2) var<storage, read> children: array<u32>;
+@group(0) @binding(3) var<storage, read> class_start: array<u32>;
+@group(0) @binding(4) var<storage, read_write> node_total: array<f32>;
+@group(0) @binding(5) var<storage, read_write> class_total: array<f32>;
+@group(0) @binding(6) var<storage, read_write> changed: atomic<u32>;
+@group(0) @binding(7) var<uniform> sizes: vec4<u32>;
+
+// Dispatches wider than the 65535 workgroups of a dimension continue in y.
+fn index(id: vec3<u32>, groups: vec3<u32>) -> u32 {
+    return id.x + id.y * groups.x * 256u;
+}
+
+@compute @workgroup_size(256)
+fn relax_nodes(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let n = index(id, groups);
+    if (n >= sizes.x) {
+        return;
+    }
+    var total = node_cost[n];
+    for (var i = child_start[n]; i < child_start[n + 1u]; i = i + 1u) {
+        let child = class_total[children[i]];
+        if (child >= UNREACHED) {
+            node_total[n] = UNREACHED;
+            return;
+        }
+        total = total + child;
+    }
+    node_total[n] = min(total, UNREACHED);
+}
+
+@compute @workgroup_size(256)
+fn relax_classes(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let c = index(id, groups);
+    if (c >= sizes.y) {
+        return;
+    }
+    var best = class_total[c];
+    for (var n = class_start[c]; n < class_start[c + 1u]; n = n + 1u) {
+        best = min(best, node_total[n]);
+    }
+    if (best < class_total[c]) {
+        class_total[c] = best;
+        atomicStore(&changed, 1u);
+    }
+}
@@ -7,23 +7,47 @@ struct CostSet {
     choice: NodeId,
 }
 
-pub struct GreedyDagExtractor;
+#[derive(Default)]
+pub struct GreedyDagExtractor {
+    /// Visit nodes in id order and emit choices in class order, so repeated runs
+    /// give the same extraction.
+    pub deterministic: bool,
+}
+
 impl Extractor for GreedyDagExtractor {
-    fn extract(&self, egraph: &EGraph, _roots: &[ClassId]) -> ExtractionResult {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::NONE)
+    }
+
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], budget: Duration) -> ExtractionResult {
+        self.extract_until(egraph, roots, Deadline::after(budget))
+    }
+}
+
+impl GreedyDagExtractor {
+    fn extract_until(&self, egraph: &EGraph, _roots: &[ClassId], deadline: Deadline) -> ExtractionResult {
         let mut costs = FxHashMap::<ClassId, CostSet>::with_capacity_and_hasher(
             egraph.classes().len(),
             Default::default(),
         );
 
+        let mut nodes: Vec<(&NodeId, &Node)> = egraph.nodes.iter().collect();
+        if self.deterministic {
+            nodes.sort_by_key(|(node_id, _)| *node_id);
+        }
+
         let mut keep_going = true;
 
         let mut i = 0;
-        while keep_going {
+        'sweeps: while keep_going {
             i += 1;
             println!("iteration {}", i);
             keep_going = false;
 
-            'node_loop: for (node_id, node) in &egraph.nodes {
+            'node_loop: for &(node_id, node) in &nodes {
+                if deadline.passed() {
+                    break 'sweeps;
+                }
                 let cid = egraph.nid_to_cid(node_id);
                 let mut cost_set = CostSet {
                     costs: Default::default(),
@@ -65,8 +89,13 @@ impl Extractor for GreedyDagExtractor {
 
         let mut result = ExtractionResult::default();
         for (cid, cost_set) in costs {
+            result.class_costs.insert(cid, cost_set.total);
             result.choose(cid, cost_set.choice);
         }
+        if self.deterministic {
+            result.choices.sort_keys();
+            result.class_costs.sort_keys();
+        }
         result
     }
 }
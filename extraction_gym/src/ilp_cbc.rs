@@ -111,7 +111,7 @@ fn extract(egraph: &EGraph, roots: &[ClassId], timeout_seconds: u32) -> Extracti
         assert!(timeout_seconds != std::u32::MAX);
 
         let initial_result =
-            super::faster_greedy_dag::FasterGreedyDagExtractor.extract(egraph, roots);
+            super::faster_greedy_dag::FasterGreedyDagExtractor::default().extract(egraph, roots);
         log::info!("Unfinished CBC solution");
         return initial_result;
     }
@@ -1,20 +1,44 @@
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{collections::HashMap, default};
+use std::time::{Duration, Instant};
 use egraph_serialize::*;
 use ordered_float::NotNan;
 pub const INFINITY: Cost = unsafe { NotNan::new_unchecked(std::f64::INFINITY) };
 pub mod bottom_up;
+pub mod choices_file;
+pub mod delay;
+pub mod diff;
+pub mod distinct_ops;
+mod dot;
+pub mod epsilon_greedy;
 pub mod faster_ast_depth;
 pub mod faster_ast_depth_mt;
+pub mod faster_ast_size;
+pub mod faster_ast_size_mt;
 pub mod faster_bottom_up;
 pub mod faster_bottom_up_mt;
 pub mod faster_greedy_dag;
+pub mod faster_greedy_dag_fa;
+pub mod faster_greedy_dag_fa_mt;
 pub mod faster_greedy_dag_mt1;
 pub mod faster_greedy_dag_mt2;
+pub mod faster_weighted_depth;
+pub mod gpu_bottom_up;
+pub mod moves;
 pub mod my_ilp;
-// pub mod faster_greedy_dag_fa;
-// pub mod faster_greedy_dag_fa_mt;
+pub mod netlist;
+pub mod pareto;
+pub mod passes;
+pub mod policy;
+pub mod queue;
+pub mod ref_counts;
+pub mod registry;
+pub mod root_weights;
+pub mod salience;
+pub mod sensitivity;
+pub mod signatures;
+pub mod term;
 #[cfg(feature = "ilp-cbc")]
 pub mod faster_ilp_cbc;
 pub mod global_greedy_dag;
@@ -25,9 +49,46 @@ pub mod ilp_cbc;
 // Allowance for floating point values to be considered equal
 pub const EPSILON_ALLOWANCE: f64 = 0.00001;
 
+/// An extraction algorithm.
+///
+/// Extractors that return `true` from [`Extractor::fills_node_costs`] also fill
+/// `ExtractionResult::cost` with, for every node they evaluated, the best cost
+/// they found for a term rooted at that node (via [`ExtractionResult::record_cost`]),
+/// and `ExtractionResult::class_costs` with the cost of the node chosen for each
+/// class. Costs only need to be comparable between nodes of the same class; they
+/// are what the warm start + pruning flow uses to drop nodes far above the
+/// cheapest alternative of their class.
 pub trait Extractor: Sync {
     fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult;
 
+    fn fills_node_costs(&self) -> bool {
+        false
+    }
+
+    /// Extraction within a time budget. The greedy and multi-threaded extractors
+    /// check it between the items (or batches) of their worklist, and once it has
+    /// run out return the choices they made so far, the best they found for every
+    /// class they reached. The classes they did not reach have no choice yet, so a
+    /// result cut short may not `validate`. Other extractors run to completion.
+    fn extract_with_deadline(&self, egraph: &EGraph, roots: &[ClassId], _budget: Duration) -> ExtractionResult {
+        self.extract(egraph, roots)
+    }
+
+    /// Extraction that keeps the `frozen` choices, e.g. for modules that are
+    /// already committed. By default this extracts from a copy of the e-graph in
+    /// which every frozen class only contains its frozen node.
+    fn extract_frozen(
+        &self,
+        egraph: &EGraph,
+        roots: &[ClassId],
+        frozen: &IndexMap<ClassId, NodeId>,
+    ) -> ExtractionResult {
+        if frozen.is_empty() {
+            return self.extract(egraph, roots);
+        }
+        self.extract(&freeze_choices(egraph, frozen), roots)
+    }
+
     fn boxed(self) -> Box<dyn Extractor>
     where
         Self: Sized + 'static,
@@ -36,6 +97,64 @@ pub trait Extractor: Sync {
     }
 }
 
+/// An extractor whose work is split between threads sharing the e-graph. They get
+/// it as a `FrozenEGraph`, whose caches are built before any of them starts; its
+/// `Extractor::extract` freezes the e-graph and calls `extract_parallel`.
+pub trait ParallelExtractor: Extractor {
+    fn extract_parallel(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> ExtractionResult;
+}
+
+/// Copy of `egraph` without the nodes that compete with a frozen choice.
+pub fn freeze_choices(egraph: &EGraph, frozen: &IndexMap<ClassId, NodeId>) -> EGraph {
+    for (cid, nid) in frozen {
+        assert!(egraph[nid].eclass == *cid, "frozen node {} is not in class {}", nid, cid);
+    }
+    let data = Data {
+        nodes: egraph
+            .nodes
+            .iter()
+            .filter(|(nid, node)| frozen.get(&node.eclass).map_or(true, |kept| kept == *nid))
+            .map(|(nid, node)| (*nid, node.clone()))
+            .collect(),
+        root_eclasses: egraph.root_eclasses.clone(),
+        class_data: egraph.class_data.clone(),
+    };
+    EGraph::from_Data(&data).expect("failed to rebuild the frozen e-graph")
+}
+
+/// Sum of a cost set, added up in class order so the floating point result does
+/// not depend on the map's iteration order.
+pub(crate) fn sum_in_class_order(costs: &HashMap<ClassId, Cost>) -> Cost {
+    let mut costs: Vec<(&ClassId, &Cost)> = costs.iter().collect();
+    costs.sort_unstable_by_key(|(cid, _)| **cid);
+    costs.into_iter().map(|(_, cost)| *cost).sum()
+}
+
+/// The classes reachable from `roots`, where `children` lists the child classes of a
+/// class, in the order they are reached (roots first).
+pub fn reachable_classes<I>(roots: &[ClassId], mut children: impl FnMut(&ClassId) -> I) -> IndexSet<ClassId>
+where
+    I: IntoIterator<Item = ClassId>,
+{
+    let mut reached: IndexSet<ClassId> = roots.iter().cloned().collect();
+    let mut next = 0;
+    while let Some(cid) = reached.get_index(next).cloned() {
+        reached.extend(children(&cid));
+        next += 1;
+    }
+    reached
+}
+
+/// `reachable_classes` through all the nodes of `egraph`.
+pub fn egraph_reachable_classes(egraph: &EGraph, roots: &[ClassId]) -> IndexSet<ClassId> {
+    reachable_classes(roots, |cid| {
+        egraph[cid]
+            .nodes
+            .iter()
+            .flat_map(|node_id| egraph[node_id].children.iter().cloned())
+    })
+}
+
 pub trait MapGet<K, V> {
     fn get(&self, key: &K) -> Option<&V>;
 }
@@ -69,10 +188,123 @@ where
 
 
 
+/// When an extraction has to stop, see [`Extractor::extract_with_deadline`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// No deadline: the extraction runs to completion.
+    pub const NONE: Deadline = Deadline(None);
+
+    /// `budget` from now. A budget too large to represent is no deadline.
+    pub fn after(budget: Duration) -> Self {
+        Deadline(Instant::now().checked_add(budget))
+    }
+
+    pub fn passed(&self) -> bool {
+        self.0.is_some_and(|at| Instant::now() >= at)
+    }
+}
+
+/// Fanout pressure, a proxy for the buffering a netlist needs: every use of a
+/// class by a selected node beyond `limit` costs `weight`. Reported with
+/// [`ExtractionResult::fanout_cost`], and a secondary term of the objective of
+/// `faster_greedy_dag` when set on it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FanoutPenalty {
+    pub limit: usize,
+    pub weight: f64,
+}
+
+impl FanoutPenalty {
+    pub fn penalty(&self, fanouts: impl IntoIterator<Item = usize>) -> Cost {
+        let excess: usize = fanouts.into_iter().map(|fanout| fanout.saturating_sub(self.limit)).sum();
+        Cost::new(self.weight * excess as f64).unwrap()
+    }
+}
+
+/// Costs of the term extracted for one root, see [`ExtractionResult::per_root_costs`].
+#[derive(Clone, Debug)]
+pub struct RootCosts {
+    pub tree: Cost,
+    pub dag: Cost,
+    pub depth: u32,
+}
+
+/// Changes made to an e-graph since it was last extracted, for incremental
+/// re-extraction. Removing nodes is not supported.
+#[derive(Default, Clone, Debug)]
+pub struct EGraphDelta {
+    /// New nodes, possibly in new classes.
+    pub added_nodes: Vec<NodeId>,
+    /// Existing nodes whose cost changed.
+    pub changed_costs: Vec<NodeId>,
+}
+
+/// How a worklist extractor converged.
+///
+/// A pass is one sweep over the nodes that were queued when it started; the
+/// nodes those re-queue are handled in the next pass.
+#[derive(Default, Clone, Debug)]
+pub struct ExtractionStats {
+    /// Number of passes until the worklist ran empty.
+    pub iterations: usize,
+    /// Number of node cost evaluations.
+    pub relaxations: usize,
+    /// Largest number of nodes waiting in the worklist.
+    pub max_queue_len: usize,
+    /// Number of classes whose chosen node changed, for each pass.
+    pub changed_per_pass: Vec<usize>,
+}
+
+#[derive(Default)]
+pub(crate) struct StatsRecorder {
+    stats: ExtractionStats,
+    pass_left: usize,
+    changed: FxHashSet<ClassId>,
+}
+
+impl StatsRecorder {
+    /// A node was taken off a worklist that held `queue_len` nodes.
+    pub(crate) fn popped(&mut self, queue_len: usize) {
+        self.stats.max_queue_len = self.stats.max_queue_len.max(queue_len);
+        if self.pass_left == 0 {
+            self.end_pass();
+            self.stats.iterations += 1;
+            self.pass_left = queue_len;
+        }
+        self.pass_left -= 1;
+    }
+
+    pub(crate) fn relaxed(&mut self) {
+        self.stats.relaxations += 1;
+    }
+
+    pub(crate) fn choice_changed(&mut self, class_id: ClassId) {
+        self.changed.insert(class_id);
+    }
+
+    pub(crate) fn finish(mut self) -> ExtractionStats {
+        self.end_pass();
+        self.stats
+    }
+
+    fn end_pass(&mut self) {
+        if self.stats.iterations > self.stats.changed_per_pass.len() {
+            self.stats.changed_per_pass.push(self.changed.len());
+            self.changed.clear();
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct ExtractionResult {
     pub choices: IndexMap<ClassId, NodeId>,
     pub cost: HashMap<NodeId, Cost>,
+    /// Settled best cost of each class, in the extractor's own cost metric.
+    pub class_costs: IndexMap<ClassId, Cost>,
+    /// Reference counts of the selected classes, once `track_refs` started them.
+    pub refs: Option<ref_counts::RefCounts>,
 }
 
 #[derive(Clone, Copy)]
@@ -97,6 +329,8 @@ impl ExtractionResult {
         Self {
             choices: IndexMap::<ClassId, NodeId>::default(),
             cost: HashMap::new(),
+            class_costs: IndexMap::new(),
+            refs: None,
         }
     }
 
@@ -104,54 +338,74 @@ impl ExtractionResult {
         Self {
             choices: choices,
             cost: HashMap::new(),
+            class_costs: IndexMap::new(),
+            refs: None,
         }
     }
 
+    /// Panics on an invalid extraction, see `validate`.
     pub fn check(&self, egraph: &EGraph) {
-        // should be a root
-        assert!(!egraph.root_eclasses.is_empty());
-
-        // All roots should be selected.
-        for cid in egraph.root_eclasses.iter() {
-            // println!("cid:{:?}",cid);
-            assert!(self.choices.contains_key(cid));
+        if let Err(err) = self.validate(egraph) {
+            panic!("Invalid extraction: {}", err);
         }
+    }
 
+    /// Whether this is an extraction of the e-graph's roots: every class the roots
+    /// depend on has a node of its own, and there is no cycle. The first problem
+    /// found is returned rather than panicked on as `check` does.
+    pub fn validate(&self, egraph: &EGraph) -> Result<(), ValidationError> {
+        if egraph.root_eclasses.is_empty() {
+            return Err(ValidationError::NoRoots);
+        }
 
         // Nodes should match the class they are selected into.
         for (cid, nid) in &self.choices {
-            let node = &egraph[nid];
-            assert!(node.eclass == *cid);
+            if egraph.get_node(nid)?.eclass != *cid {
+                return Err(ValidationError::WrongClass { class: *cid, node: *nid });
+            }
         }
 
         // All the nodes the roots depend upon should be selected.
-        let mut todo: Vec<ClassId> = egraph.root_eclasses.to_vec();
+        let mut todo: Vec<(ClassId, Option<NodeId>)> = egraph.root_eclasses.iter().map(|cid| (*cid, None)).collect();
+        todo.reverse();
         let mut visited: FxHashSet<ClassId> = Default::default();
-        while let Some(cid) = todo.pop() {
-            if !visited.insert(cid.clone()) {
+        while let Some((cid, parent)) = todo.pop() {
+            if !visited.insert(cid) {
                 continue;
             }
-            assert!(self.choices.contains_key(&cid));
-
-            for child in &egraph[&self.choices[&cid]].children {
-                todo.push(child.clone());
-            }
+            let Some(nid) = self.choices.get(&cid) else {
+                return Err(ValidationError::MissingChoice { class: cid, parent });
+            };
+            todo.extend(egraph[nid].children.iter().map(|child| (*child, Some(*nid))));
         }
 
-
-        if !self.find_cycles(&egraph, &egraph.root_eclasses).is_empty() {
-            if let Some(shortest_cycle) = self.find_shortest_cycle(&egraph, &egraph.root_eclasses) {
-                println!("shortest cycle: {:?}", shortest_cycle);
-            }
-            assert!(false);
+        if self.find_cycles(egraph, &egraph.root_eclasses).is_empty() {
+            return Ok(());
         }
-        
+        let cycle = self.find_shortest_cycle(egraph, &egraph.root_eclasses).unwrap_or_default();
+        Err(ValidationError::Cycle(cycle))
+    }
+
+    /// `validate` as a `bool`.
+    pub fn is_valid(&self, egraph: &EGraph) -> bool {
+        self.validate(egraph).is_ok()
     }
 
     pub fn choose(&mut self, class_id: ClassId, node_id: NodeId) {
+        self.refs = None;
         self.choices.insert(class_id, node_id);
     }
 
+    /// Keeps the lowest cost seen so far for `node_id`.
+    pub fn record_cost(&mut self, node_id: NodeId, cost: Cost) {
+        match self.cost.get(&node_id) {
+            Some(existing) if cost >= *existing => {}
+            _ => {
+                self.cost.insert(node_id, cost);
+            }
+        }
+    }
+
     pub fn find_cycles(&self, egraph: &EGraph, roots: &[ClassId]) -> Vec<ClassId> {
         // let mut status = vec![Status::Todo; egraph.classes().len()];
         let mut status = IndexMap::<ClassId, Status>::default();
@@ -257,6 +511,57 @@ impl ExtractionResult {
         cost
     }
 
+    /// Critical-path depth where each node counts with its delay instead of 1.
+    pub fn weighted_depth_cost(&self, egraph: &EGraph, roots: &[ClassId], delays: &delay::DelayModel) -> Cost {
+        let mut memo = HashMap::<ClassId, Cost>::new();
+        roots
+            .iter()
+            .map(|cid| self.weighted_depth_cost_rec(egraph, cid, delays, &mut memo))
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn weighted_depth_cost_rec(
+        &self,
+        egraph: &EGraph,
+        cid: &ClassId,
+        delays: &delay::DelayModel,
+        memo: &mut HashMap<ClassId, Cost>,
+    ) -> Cost {
+        if let Some(&cost) = memo.get(cid) {
+            return cost;
+        }
+        let node = &egraph[&self.choices[cid]];
+        let child_max = node
+            .children
+            .iter()
+            .map(|child_cid| self.weighted_depth_cost_rec(egraph, child_cid, delays, memo))
+            .max()
+            .unwrap_or_default();
+        let cost = delays.delay(egraph, node) + child_max;
+        memo.insert(cid.clone(), cost);
+        cost
+    }
+
+    /// Tree cost, DAG cost and depth of the term chosen for each root class, to
+    /// see which output dominates the cost of a multi-output design.
+    pub fn per_root_costs(&self, egraph: &EGraph) -> IndexMap<ClassId, RootCosts> {
+        let mut tree_memo = HashMap::new();
+        let mut depth_memo = HashMap::new();
+        egraph
+            .root_eclasses
+            .iter()
+            .map(|root| {
+                let costs = RootCosts {
+                    tree: self.tree_cost_rec(egraph, &[*root], &mut tree_memo),
+                    dag: self.dag_cost(egraph, &[*root]),
+                    depth: self.depth_cost_rec(egraph, root, &mut depth_memo),
+                };
+                (*root, costs)
+            })
+            .collect()
+    }
+
     pub fn tree_cost(&self, egraph: &EGraph, roots: &[ClassId]) -> Cost {
         let node_roots = roots
             .iter()
@@ -332,6 +637,40 @@ impl ExtractionResult {
         costs.values().sum()
     }
 
+    /// Sum of the node areas over the DAG selected from the e-graph roots.
+    pub fn area_cost(&self, egraph: &EGraph) -> Cost {
+        self.activate_nodes(egraph, &egraph.root_eclasses)
+            .iter()
+            .map(|nid| egraph[nid].area())
+            .sum()
+    }
+
+    /// The ops of the DAG selected from `roots`, the cells of an extracted netlist.
+    pub fn distinct_ops<'a>(&self, egraph: &'a EGraph, roots: &[ClassId]) -> FxHashSet<&'a str> {
+        self.activate_nodes(egraph, roots)
+            .iter()
+            .map(|nid| egraph[nid].op.as_str())
+            .collect()
+    }
+
+    /// How many times the selected nodes use each class (an `x * x` uses `x`
+    /// twice), for the classes used at least once.
+    pub fn fanout(&self, egraph: &EGraph, roots: &[ClassId]) -> IndexMap<ClassId, usize> {
+        let mut active: Vec<NodeId> = self.activate_nodes(egraph, roots).into_iter().collect();
+        active.sort();
+        let mut fanout = IndexMap::new();
+        for nid in &active {
+            for child in &egraph[nid].children {
+                *fanout.entry(*child).or_insert(0) += 1;
+            }
+        }
+        fanout
+    }
+
+    pub fn fanout_cost(&self, egraph: &EGraph, roots: &[ClassId], penalty: &FanoutPenalty) -> Cost {
+        penalty.penalty(self.fanout(egraph, roots).into_values())
+    }
+
     pub fn node_sum_cost<M>(&self, egraph: &EGraph, node: &Node, costs: &M) -> Cost
     where
         M: MapGet<ClassId, Cost>,
@@ -0,0 +1,207 @@
+//! Neighborhood moves over an `ExtractionResult`, shared by the local search
+//! extractors: each one changes some choices through `ExtractionResult::swap`,
+//! so that its DAG cost delta comes from the reference counts, and returns the
+//! delta with the `Undo` that puts the previous choices back.
+//!
+//! The reference counts are started from the e-graph roots if the result doesn't
+//! track them yet.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::*;
+use crate::faster_greedy_dag_fa::AdderPairs;
+
+/// The previous choices of the classes a move changed, in the order it changed
+/// them. A class that had no choice keeps the one the move gave it, which is
+/// outside the DAG again once the move is undone.
+#[derive(Clone, Debug, Default)]
+pub struct Undo {
+    previous: Vec<(ClassId, NodeId)>,
+}
+
+impl Undo {
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty()
+    }
+
+    /// Puts the previous choices back, returning the DAG cost delta of that, the
+    /// opposite of the move's.
+    pub fn undo(self, result: &mut ExtractionResult, egraph: &EGraph) -> Cost {
+        let mut delta = Cost::default();
+        for (cid, nid) in self.previous.into_iter().rev() {
+            let step = result.swap(egraph, &cid, &nid);
+            assert!(step != INFINITY, "undoing a move went through an invalid extraction");
+            delta += step;
+        }
+        delta
+    }
+}
+
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Move {
+    /// Change of the DAG cost.
+    pub delta: Cost,
+    pub undo: Undo,
+}
+
+/// Swaps in `choices` in turn, leaving out the ones that would close a cycle or
+/// select a class without a choice, or undoing all of them at the first such one
+/// if `all_or_nothing`.
+fn apply(
+    result: &mut ExtractionResult,
+    egraph: &EGraph,
+    choices: impl IntoIterator<Item = (ClassId, NodeId)>,
+    all_or_nothing: bool,
+) -> Option<Move> {
+    if result.refs.is_none() {
+        result.track_refs(egraph, &egraph.root_eclasses);
+    }
+    let mut applied = Move { delta: Cost::default(), undo: Undo::default() };
+    for (cid, nid) in choices {
+        let previous = result.choices.get(&cid).copied();
+        if previous == Some(nid) {
+            continue;
+        }
+        let delta = result.swap(egraph, &cid, &nid);
+        if delta == INFINITY {
+            if all_or_nothing {
+                applied.undo.undo(result, egraph);
+                return None;
+            }
+            continue;
+        }
+        applied.delta += delta;
+        if let Some(previous) = previous {
+            applied.undo.previous.push((cid, previous));
+        }
+    }
+    Some(applied)
+}
+
+/// Chooses `node` for its class; `None` if that closes a cycle or selects a class
+/// without a choice.
+pub fn swap_class(result: &mut ExtractionResult, egraph: &EGraph, node: &NodeId) -> Option<Move> {
+    apply(result, egraph, [(*egraph.nid_to_cid(node), *node)], true)
+}
+
+/// The classes selected at most `depth` edges below `class`, children before
+/// their parents.
+pub fn region(result: &ExtractionResult, egraph: &EGraph, class: &ClassId, depth: usize) -> Vec<ClassId> {
+    let mut within = FxHashMap::<ClassId, usize>::default();
+    let mut todo = vec![(*class, 0)];
+    while let Some((cid, level)) = todo.pop() {
+        if within.get(&cid).is_some_and(|seen| *seen <= level) {
+            continue;
+        }
+        within.insert(cid, level);
+        if level < depth {
+            if let Some(nid) = result.choices.get(&cid) {
+                todo.extend(egraph[nid].children.iter().map(|child| (*child, level + 1)));
+            }
+        }
+    }
+    post_order(result, egraph, class, |cid| within.contains_key(cid))
+}
+
+/// The classes `result` selects from `class` for which `within` holds, reached
+/// through such classes only, children before their parents.
+fn post_order(result: &ExtractionResult, egraph: &EGraph, class: &ClassId, within: impl Fn(&ClassId) -> bool) -> Vec<ClassId> {
+    let mut order = Vec::new();
+    let mut visited = FxHashSet::<ClassId>::default();
+    let mut todo = vec![(*class, false)];
+    while let Some((cid, leaving)) = todo.pop() {
+        if leaving {
+            order.push(cid);
+            continue;
+        }
+        if !within(&cid) || !visited.insert(cid) {
+            continue;
+        }
+        todo.push((cid, true));
+        if let Some(nid) = result.choices.get(&cid) {
+            todo.extend(egraph[nid].children.iter().map(|child| (*child, false)));
+        }
+    }
+    order
+}
+
+/// Extracts the sub-DAG of the classes within `depth` edges below `class` again
+/// with `extractor`, keeping the choices of the other selected classes. `None` if
+/// the new sub-DAG can't replace the old one. This extracts from a copy of the
+/// e-graph, so it's a move for the occasional big step.
+pub fn reextract(
+    result: &mut ExtractionResult,
+    egraph: &EGraph,
+    class: &ClassId,
+    depth: usize,
+    extractor: &dyn Extractor,
+) -> Option<Move> {
+    if result.refs.is_none() {
+        result.track_refs(egraph, &egraph.root_eclasses);
+    }
+    let region: FxHashSet<ClassId> = region(result, egraph, class, depth).into_iter().collect();
+    let refs = result.refs.as_ref().unwrap();
+    let frozen: IndexMap<ClassId, NodeId> = result
+        .choices
+        .iter()
+        .filter(|(cid, _)| refs.count(cid) > 0 && !region.contains(*cid))
+        .map(|(cid, nid)| (*cid, *nid))
+        .collect();
+    let extracted = extractor.extract_frozen(egraph, &[*class], &frozen);
+    if !extracted.choices.contains_key(class) {
+        return None;
+    }
+    let choices: Vec<(ClassId, NodeId)> = post_order(&extracted, egraph, class, |cid| !frozen.contains_key(cid))
+        .into_iter()
+        .filter_map(|cid| extracted.choices.get(&cid).map(|nid| (cid, *nid)))
+        .collect();
+    apply(result, egraph, choices, true)
+}
+
+/// Exchanges the adder of the `fst` or `snd` node `node` for separate gates, or
+/// separate gates for it: if the class of `node` chooses a projection, both
+/// outputs of the adder take the node of their class that adds the least to the
+/// DAG cost among the ones that aren't projections; otherwise they take `node`
+/// and its partner. `None` if `node` has no partner or the exchange can't be made.
+pub fn exchange_adder(result: &mut ExtractionResult, egraph: &EGraph, pairs: &AdderPairs, node: &NodeId) -> Option<Move> {
+    let partner = *pairs.partner(node)?;
+    let class = *egraph.nid_to_cid(node);
+    let partner_class = *egraph.nid_to_cid(&partner);
+    let projection = |nid: &NodeId| pairs.partner(nid).is_some();
+    if !result.choices.get(&class).is_some_and(projection) {
+        return apply(result, egraph, [(class, *node), (partner_class, partner)], true);
+    }
+    let mut exchanged = Move { delta: Cost::default(), undo: Undo::default() };
+    for cid in [class, partner_class] {
+        let best = egraph.classes()[&cid]
+            .nodes
+            .iter()
+            .filter(|nid| !projection(nid))
+            .map(|nid| (result.swap_cost_delta(egraph, &cid, nid), *nid))
+            .filter(|(delta, _)| *delta != INFINITY)
+            .min();
+        let Some(step) = best.and_then(|(_, nid)| swap_class(result, egraph, &nid)) else {
+            exchanged.undo.undo(result, egraph);
+            return None;
+        };
+        exchanged.delta += step.delta;
+        exchanged.undo.previous.extend(step.undo.previous);
+    }
+    Some(exchanged)
+}
+
+/// Chooses a random node for each class within `depth` edges below `class`,
+/// children first, leaving out the choices that would close a cycle or select a
+/// class without a choice.
+pub fn random_restart(result: &mut ExtractionResult, egraph: &EGraph, class: &ClassId, depth: usize, rng: &mut impl Rng) -> Move {
+    if result.refs.is_none() {
+        result.track_refs(egraph, &egraph.root_eclasses);
+    }
+    let choices: Vec<(ClassId, NodeId)> = region(result, egraph, class, depth)
+        .into_iter()
+        .filter_map(|cid| egraph.classes()[&cid].nodes.choose(rng).map(|nid| (cid, *nid)))
+        .collect();
+    apply(result, egraph, choices, false).unwrap()
+}
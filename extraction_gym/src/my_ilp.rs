@@ -51,11 +51,8 @@ use crate::*;
 use indexmap::IndexSet;
 use std::fmt;
 use std::time::SystemTime;
-use std::fs;
 use std::fs::File;
 use std::io::Write;
-use serde_json::json;
-use rand::Rng;
 
 #[derive(Debug)]
 pub struct Config {
@@ -254,9 +251,12 @@ fn extract(
         })
         .collect();
 
-    let initial_result = super::faster_greedy_dag::FasterGreedyDagExtractor.extract(egraph, &roots);
+    let initial_result = super::faster_greedy_dag::FasterGreedyDagExtractor::default().extract(egraph, &roots);
     let initial_result_cost = initial_result.dag_cost(egraph, &roots);
-    save_inital_solution("initial_solution.json", &initial_result);
+    choices_file::ChoicesFile::from_result(egraph, &initial_result, &roots)
+        .with_meta("extractor", "faster-greedy-dag")
+        .to_json_file("initial_solution.json")
+        .expect("Unable to write file");
 
     // For classes where we know the choice already, we set the nodes early.
     let mut result = ExtractionResult::default();
@@ -520,25 +520,6 @@ Using this caused wrong results from the solver. I don't have a good idea why.
 // }
 
 
-fn save_inital_solution(
-    file_path: &str,
-    initial_result: &ExtractionResult,
-) {
-    let mut rng = rand::thread_rng();
-    let mut file = std::fs::File::create(file_path).unwrap();
-
-    // 假设 initial_result.choices 是一个 IndexMap<ClassId, NodeId>
-    let modified: IndexMap<String, serde_json::Value> = initial_result.choices
-    .iter()
-    .map(|(k, v)| {
-        let random_num: u8 = rng.gen_range(0..=1);
-        (k.to_string(), json!([v, 0]))
-    })
-    .collect();
-    let json = serde_json::to_string_pretty(&modified).unwrap();
-    fs::write(file_path, json).expect("Unable to write file");
-}
-
 /* If a class has one node, and that node is zero cost, and it has no children, then we
 can fill the answer into the extraction result without doing any more work. If it
 has children, we need to setup the dependencies.
@@ -675,8 +656,9 @@ fn remove_unreachable_classes(
     config: &Config,
 ) {
     if config.remove_unreachable_classes {
-        let mut reachable_classes: IndexSet<ClassId> = IndexSet::default();
-        reachable(&*vars, roots, &mut reachable_classes);
+        let reachable_classes = crate::reachable_classes(roots, |class| {
+            vars.get(class).unwrap().childrens_classes.iter().flatten().cloned().collect::<Vec<_>>()
+        });
         let initial_size = vars.len();
         vars.retain(|class_id, _| reachable_classes.contains(class_id));
         log::info!("Unreachable classes: {}", initial_size - vars.len());
@@ -1022,24 +1004,6 @@ fn classes_with_single_parent(vars: &IndexMap<ClassId, ClassILP>) -> IndexMap<Cl
         .collect()
 }
 
-//Set of classes that can be reached from the [classes]
-fn reachable(
-    vars: &IndexMap<ClassId, ClassILP>,
-    classes: &[ClassId],
-    is_reachable: &mut IndexSet<ClassId>,
-) {
-    for class in classes {
-        if is_reachable.insert(class.clone()) {
-            let class_vars = vars.get(class).unwrap();
-            for kids in &class_vars.childrens_classes {
-                for child_class in kids {
-                    reachable(vars, &[child_class.clone()], is_reachable);
-                }
-            }
-        }
-    }
-}
-
 // // Adds constraints to stop the cycle.
 // fn block_cycle(model: &mut Model, cycle: &Vec<ClassId>, vars: &IndexMap<ClassId, ClassILP>) {
 //     if cycle.is_empty() {
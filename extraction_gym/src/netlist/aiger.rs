@@ -0,0 +1,278 @@
+// ASCII AIGER ("aag") for an extracted netlist, e.g. for `cec` against the
+// original design in ABC.
+//
+// Primitive gates are decomposed into AND gates and inverters. Other ops
+// need a gadget in the `AigMapping`, given as a boolean expression over the
+// node's inputs `$0, $1, ...` with `!`, `&`, `^`, `|` and parentheses, e.g.
+//
+//     { "fa_sum": "$0 ^ $1 ^ $2", "fa_carry": "($0 & $1) | ($2 & ($0 ^ $1))" }
+//
+// A mapping entry also overrides the built-in decomposition of that op.
+
+use super::{Gate, Netlist};
+use crate::ExtractionResult;
+use egraph_serialize::*;
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+#[derive(Clone, Debug)]
+pub enum Gadget {
+    Const(bool),
+    Input(usize),
+    Not(Box<Gadget>),
+    And(Box<Gadget>, Box<Gadget>),
+    Or(Box<Gadget>, Box<Gadget>),
+    Xor(Box<Gadget>, Box<Gadget>),
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct AigMapping {
+    pub gadgets: HashMap<String, Gadget>,
+}
+
+impl AigMapping {
+    pub fn insert(&mut self, op: &str, expr: &str) -> Result<(), String> {
+        let gadget = GadgetParser { chars: expr.chars().collect(), pos: 0 }.parse()?;
+        self.gadgets.insert(op.to_string(), gadget);
+        Ok(())
+    }
+
+    /// Reads a JSON object mapping op names to gadget expressions.
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(|e| e.to_string())?;
+        let entries: HashMap<String, String> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let mut mapping = Self::default();
+        for (op, expr) in &entries {
+            mapping.insert(op, expr).map_err(|e| format!("gadget for {}: {}", op, e))?;
+        }
+        Ok(mapping)
+    }
+}
+
+struct GadgetParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl GadgetParser {
+    fn parse(mut self) -> Result<Gadget, String> {
+        let gadget = self.or()?;
+        self.skip_ws();
+        if self.pos != self.chars.len() {
+            return Err(format!("unexpected '{}' at {}", self.chars[self.pos], self.pos));
+        }
+        Ok(gadget)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.chars.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Gadget, String> {
+        let mut lhs = self.xor()?;
+        while self.eat('|') {
+            lhs = Gadget::Or(Box::new(lhs), Box::new(self.xor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn xor(&mut self) -> Result<Gadget, String> {
+        let mut lhs = self.and()?;
+        while self.eat('^') {
+            lhs = Gadget::Xor(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Gadget, String> {
+        let mut lhs = self.unary()?;
+        while self.eat('&') {
+            lhs = Gadget::And(Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Gadget, String> {
+        if self.eat('!') {
+            return Ok(Gadget::Not(Box::new(self.unary()?)));
+        }
+        if self.eat('(') {
+            let inner = self.or()?;
+            if !self.eat(')') {
+                return Err(format!("expected ')' at {}", self.pos));
+            }
+            return Ok(inner);
+        }
+        if self.eat('0') {
+            return Ok(Gadget::Const(false));
+        }
+        if self.eat('1') {
+            return Ok(Gadget::Const(true));
+        }
+        if self.eat('$') {
+            let start = self.pos;
+            while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() {
+                self.pos += 1;
+            }
+            let digits: String = self.chars[start..self.pos].iter().collect();
+            return digits.parse().map(Gadget::Input).map_err(|_| format!("expected input index at {}", start));
+        }
+        Err(format!("unexpected end or token at {}", self.pos))
+    }
+}
+
+// Literals are 2 * variable + negation, 0 and 1 being the constants.
+struct Aig {
+    num_inputs: u32,
+    ands: Vec<(u32, u32, u32)>,
+    strash: FxHashMap<(u32, u32), u32>,
+}
+
+impl Aig {
+    fn and(&mut self, a: u32, b: u32) -> u32 {
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        if a == 0 || a == (b ^ 1) {
+            return 0;
+        }
+        if a == 1 || a == b {
+            return b;
+        }
+        if let Some(&lit) = self.strash.get(&(a, b)) {
+            return lit;
+        }
+        let lit = 2 * (self.num_inputs + self.ands.len() as u32 + 1);
+        self.ands.push((lit, b, a));
+        self.strash.insert((a, b), lit);
+        lit
+    }
+
+    fn or(&mut self, a: u32, b: u32) -> u32 {
+        self.and(a ^ 1, b ^ 1) ^ 1
+    }
+
+    fn xor(&mut self, a: u32, b: u32) -> u32 {
+        let l = self.and(a, b ^ 1);
+        let r = self.and(a ^ 1, b);
+        self.or(l, r)
+    }
+
+    fn gadget(&mut self, gadget: &Gadget, ins: &[u32]) -> Result<u32, String> {
+        Ok(match gadget {
+            Gadget::Const(value) => *value as u32,
+            Gadget::Input(i) => *ins
+                .get(*i)
+                .ok_or_else(|| format!("gadget uses ${} but the node has {} inputs", i, ins.len()))?,
+            Gadget::Not(a) => self.gadget(a, ins)? ^ 1,
+            Gadget::And(a, b) => {
+                let (a, b) = (self.gadget(a, ins)?, self.gadget(b, ins)?);
+                self.and(a, b)
+            }
+            Gadget::Or(a, b) => {
+                let (a, b) = (self.gadget(a, ins)?, self.gadget(b, ins)?);
+                self.or(a, b)
+            }
+            Gadget::Xor(a, b) => {
+                let (a, b) = (self.gadget(a, ins)?, self.gadget(b, ins)?);
+                self.xor(a, b)
+            }
+        })
+    }
+}
+
+/// Fails if a chosen op is neither a primitive gate nor in `mapping`.
+pub fn to_aiger(netlist: &Netlist, mapping: &AigMapping) -> Result<String, String> {
+    let mut aig = Aig {
+        num_inputs: netlist.inputs.len() as u32,
+        ands: vec![],
+        strash: FxHashMap::default(),
+    };
+    let mut lits: FxHashMap<ClassId, u32> = FxHashMap::default();
+    for (i, cid) in netlist.inputs.iter().enumerate() {
+        lits.insert(*cid, 2 * (i as u32 + 1));
+    }
+
+    for cid in &netlist.order {
+        if lits.contains_key(cid) {
+            continue;
+        }
+        let node = netlist.node(*cid);
+        let ins: Vec<u32> = node.children.iter().map(|c| lits[c]).collect();
+        let lit = if let Some(gadget) = mapping.gadgets.get(&node.op) {
+            aig.gadget(gadget, &ins)?
+        } else {
+            match netlist.gate(*cid) {
+                Gate::Const(value) => value as u32,
+                Gate::Buf => ins[0],
+                Gate::Not => ins[0] ^ 1,
+                Gate::And | Gate::Nand => {
+                    let lit = ins[1..].iter().fold(ins[0], |acc, &x| aig.and(acc, x));
+                    lit ^ (netlist.gate(*cid) == Gate::Nand) as u32
+                }
+                Gate::Or | Gate::Nor => {
+                    let lit = ins[1..].iter().fold(ins[0], |acc, &x| aig.or(acc, x));
+                    lit ^ (netlist.gate(*cid) == Gate::Nor) as u32
+                }
+                Gate::Xor | Gate::Xnor => {
+                    let lit = ins[1..].iter().fold(ins[0], |acc, &x| aig.xor(acc, x));
+                    lit ^ (netlist.gate(*cid) == Gate::Xnor) as u32
+                }
+                Gate::Maj => {
+                    let ab = aig.and(ins[0], ins[1]);
+                    let ac = aig.and(ins[0], ins[2]);
+                    let bc = aig.and(ins[1], ins[2]);
+                    let lit = aig.or(ab, ac);
+                    aig.or(lit, bc)
+                }
+                Gate::Cell(op) => return Err(format!("op '{}' has no AIG gadget", op)),
+                Gate::Input(_) => unreachable!(),
+            }
+        };
+        lits.insert(*cid, lit);
+    }
+
+    let mut out = String::new();
+    let num_inputs = aig.num_inputs;
+    let max_var = num_inputs + aig.ands.len() as u32;
+    writeln!(out, "aag {} {} 0 {} {}", max_var, num_inputs, netlist.outputs.len(), aig.ands.len()).unwrap();
+    for i in 0..num_inputs {
+        writeln!(out, "{}", 2 * (i + 1)).unwrap();
+    }
+    for cid in &netlist.outputs {
+        writeln!(out, "{}", lits[cid]).unwrap();
+    }
+    for (lhs, rhs0, rhs1) in &aig.ands {
+        writeln!(out, "{} {} {}", lhs, rhs0, rhs1).unwrap();
+    }
+    for (i, cid) in netlist.inputs.iter().enumerate() {
+        writeln!(out, "i{} {}", i, netlist.input_name(*cid)).unwrap();
+    }
+    for i in 0..netlist.outputs.len() {
+        writeln!(out, "o{} po{}", i, i).unwrap();
+    }
+    Ok(out)
+}
+
+pub fn write_aiger_file(
+    egraph: &EGraph,
+    result: &ExtractionResult,
+    roots: &[ClassId],
+    mapping: &AigMapping,
+    file_path: &str,
+) -> std::io::Result<()> {
+    let netlist = Netlist::new(egraph, result, roots);
+    let aag = to_aiger(&netlist, mapping).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(file_path, aag)
+}
@@ -0,0 +1,138 @@
+// BLIF for an extracted netlist, one `.subckt` per chosen node.
+//
+// Cells are named after the gate (AND2, OR3, INV, MAJ3, ...) or, for any other
+// op, after the op itself, with input pins i0, i1, ... and output pin o.
+// Models for the primitive cells are appended as `.names` covers so the file
+// reads stand-alone in ABC or Yosys; other ops are declared `.blackbox`.
+
+use super::{Gate, Netlist};
+use crate::ExtractionResult;
+use egraph_serialize::*;
+use indexmap::IndexMap;
+use std::fmt::Write as _;
+
+pub fn to_blif(netlist: &Netlist, model_name: &str) -> String {
+    let mut out = String::new();
+    let inputs: Vec<String> = netlist.inputs.iter().map(|cid| net(netlist, *cid)).collect();
+    let outputs: Vec<String> = (0..netlist.outputs.len()).map(|i| format!("po{}", i)).collect();
+
+    writeln!(out, ".model {}", name(model_name)).unwrap();
+    writeln!(out, ".inputs {}", inputs.join(" ")).unwrap();
+    writeln!(out, ".outputs {}", outputs.join(" ")).unwrap();
+
+    // cell name -> (gate, number of inputs)
+    let mut cells: IndexMap<String, (Gate, usize)> = IndexMap::new();
+    for cid in &netlist.order {
+        let node = netlist.node(*cid);
+        let gate = netlist.gate(*cid);
+        match gate {
+            Gate::Input(_) => {}
+            Gate::Const(value) => {
+                writeln!(out, ".names n{}", cid).unwrap();
+                if value {
+                    out.push_str("1\n");
+                }
+            }
+            gate => {
+                let cell = cell_name(&gate, node.children.len());
+                let mut line = format!(".subckt {}", cell);
+                for (i, child) in node.children.iter().enumerate() {
+                    write!(line, " i{}={}", i, net(netlist, *child)).unwrap();
+                }
+                write!(line, " o=n{}", cid).unwrap();
+                writeln!(out, "{}", line).unwrap();
+                cells.entry(cell).or_insert((gate, node.children.len()));
+            }
+        }
+    }
+
+    for (i, cid) in netlist.outputs.iter().enumerate() {
+        writeln!(out, ".names {} po{}\n1 1", net(netlist, *cid), i).unwrap();
+    }
+    out.push_str(".end\n");
+
+    for (cell, (gate, arity)) in &cells {
+        out.push('\n');
+        let pins: Vec<String> = (0..*arity).map(|i| format!("i{}", i)).collect();
+        writeln!(out, ".model {}\n.inputs {}\n.outputs o", cell, pins.join(" ")).unwrap();
+        match cover(gate, *arity) {
+            Some(rows) => {
+                writeln!(out, ".names {} o", pins.join(" ")).unwrap();
+                for row in rows {
+                    writeln!(out, "{} 1", row).unwrap();
+                }
+            }
+            None => out.push_str(".blackbox\n"),
+        }
+        out.push_str(".end\n");
+    }
+    out
+}
+
+pub fn write_blif_file(
+    egraph: &EGraph,
+    result: &ExtractionResult,
+    roots: &[ClassId],
+    model_name: &str,
+    file_path: &str,
+) -> std::io::Result<()> {
+    let netlist = Netlist::new(egraph, result, roots);
+    std::fs::write(file_path, to_blif(&netlist, model_name))
+}
+
+fn cell_name(gate: &Gate, arity: usize) -> String {
+    match gate {
+        Gate::Buf => String::from("BUF"),
+        Gate::Not => String::from("INV"),
+        Gate::And => format!("AND{}", arity),
+        Gate::Or => format!("OR{}", arity),
+        Gate::Xor => format!("XOR{}", arity),
+        Gate::Nand => format!("NAND{}", arity),
+        Gate::Nor => format!("NOR{}", arity),
+        Gate::Xnor => format!("XNOR{}", arity),
+        Gate::Maj => String::from("MAJ3"),
+        Gate::Cell(op) => name(op),
+        Gate::Const(_) | Gate::Input(_) => unreachable!(),
+    }
+}
+
+/// On-set of a primitive cell, `None` for black boxes.
+fn cover(gate: &Gate, arity: usize) -> Option<Vec<String>> {
+    let one_hot = |bit: char, rest: char| -> Vec<String> {
+        (0..arity)
+            .map(|i| (0..arity).map(|j| if i == j { bit } else { rest }).collect())
+            .collect()
+    };
+    let parity = |odd: bool| -> Vec<String> {
+        (0..1u32 << arity)
+            .filter(|m| (m.count_ones() % 2 == 1) == odd)
+            .map(|m| (0..arity).map(|j| if m >> j & 1 == 1 { '1' } else { '0' }).collect())
+            .collect()
+    };
+    Some(match gate {
+        Gate::Buf => vec![String::from("1")],
+        Gate::Not => vec![String::from("0")],
+        Gate::And => vec!["1".repeat(arity)],
+        Gate::Or => one_hot('1', '-'),
+        Gate::Nand => one_hot('0', '-'),
+        Gate::Nor => vec!["0".repeat(arity)],
+        Gate::Xor => parity(true),
+        Gate::Xnor => parity(false),
+        Gate::Maj => vec![String::from("11-"), String::from("1-1"), String::from("-11")],
+        Gate::Cell(_) | Gate::Const(_) | Gate::Input(_) => return None,
+    })
+}
+
+fn net(netlist: &Netlist, cid: ClassId) -> String {
+    match netlist.gate(cid) {
+        Gate::Input(_) => name(netlist.input_name(cid)),
+        _ => format!("n{}", cid),
+    }
+}
+
+// BLIF names are whitespace separated, and '=' / '#' are taken by the syntax.
+fn name(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_whitespace() || c == '=' || c == '#' || c == '\\' { '_' } else { c })
+        .collect()
+}
@@ -0,0 +1,150 @@
+// Netlist view of an extracted gate-level e-graph, shared by the exporters.
+//
+// Leaves become primary inputs (or constants for "0"/"1"), every other chosen
+// node becomes one gate driving the wire of its class, so a class used by
+// several parents is emitted once and shared. Root classes are the primary
+// outputs; a root built from the concatenation op "&" (as in the E-Syn
+// benchmarks) is flattened into one output per concatenated bit. Inputs are
+// named after their op, with the class id appended when several input classes
+// share an op.
+
+pub mod aiger;
+pub mod blif;
+pub mod verilog;
+
+use crate::ExtractionResult;
+use egraph_serialize::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Gate {
+    Const(bool),
+    Input(String),
+    Buf,
+    Not,
+    And,
+    Or,
+    Xor,
+    Nand,
+    Nor,
+    Xnor,
+    Maj,
+    /// Anything else is kept as an instance of a cell named after the op.
+    Cell(String),
+}
+
+impl Gate {
+    pub fn from_node(node: &Node) -> Self {
+        let op = node.op.as_str();
+        if node.children.is_empty() {
+            return match op {
+                "0" | "false" => Gate::Const(false),
+                "1" | "true" => Gate::Const(true),
+                _ => Gate::Input(node.op.clone()),
+            };
+        }
+        // Strip an arity suffix, e.g. and2 / xor3 / maj3.
+        let base = op.trim_end_matches(|c: char| c.is_ascii_digit());
+        match (base.to_lowercase().as_str(), node.children.len()) {
+            ("buf", 1) | ("id", 1) => Gate::Buf,
+            ("!", 1) | ("~", 1) | ("not", 1) | ("inv", 1) => Gate::Not,
+            ("*", _) | ("and", _) => Gate::And,
+            ("+", _) | ("or", _) => Gate::Or,
+            ("^", _) | ("xor", _) => Gate::Xor,
+            ("nand", _) => Gate::Nand,
+            ("nor", _) => Gate::Nor,
+            ("xnor", _) => Gate::Xnor,
+            ("maj", 3) => Gate::Maj,
+            _ => Gate::Cell(node.op.clone()),
+        }
+    }
+}
+
+pub const CONCAT_OP: &str = "&";
+
+pub struct Netlist<'a> {
+    pub egraph: &'a EGraph,
+    pub result: &'a ExtractionResult,
+    /// Chosen classes in topological order, fanins before fanouts.
+    pub order: Vec<ClassId>,
+    /// Input classes, in the order they were first reached.
+    pub inputs: Vec<ClassId>,
+    /// Name of each input class.
+    pub input_names: FxHashMap<ClassId, String>,
+    /// Output classes; a class may drive several outputs.
+    pub outputs: Vec<ClassId>,
+}
+
+impl<'a> Netlist<'a> {
+    pub fn new(egraph: &'a EGraph, result: &'a ExtractionResult, roots: &[ClassId]) -> Self {
+        let mut outputs = vec![];
+        for root in roots {
+            flatten_outputs(egraph, result, *root, &mut outputs);
+        }
+
+        // Iterative post-order DFS, the chosen DAGs can be very deep.
+        let mut order = vec![];
+        let mut inputs = vec![];
+        let mut visited: FxHashSet<ClassId> = FxHashSet::default();
+        for out in &outputs {
+            let mut stack = vec![(*out, false)];
+            while let Some((cid, expanded)) = stack.pop() {
+                if expanded {
+                    order.push(cid);
+                    continue;
+                }
+                if !visited.insert(cid) {
+                    continue;
+                }
+                let node = &egraph[&result.choices[&cid]];
+                if let Gate::Input(_) = Gate::from_node(node) {
+                    inputs.push(cid);
+                }
+                stack.push((cid, true));
+                for child in node.children.iter().rev() {
+                    if !visited.contains(child) {
+                        stack.push((*child, false));
+                    }
+                }
+            }
+        }
+
+        let mut ops: FxHashMap<&str, usize> = FxHashMap::default();
+        for cid in &inputs {
+            *ops.entry(egraph[&result.choices[cid]].op.as_str()).or_default() += 1;
+        }
+        let input_names = inputs
+            .iter()
+            .map(|cid| {
+                let op = &egraph[&result.choices[cid]].op;
+                let name = if ops[op.as_str()] > 1 { format!("{}_{}", op, cid) } else { op.clone() };
+                (*cid, name)
+            })
+            .collect();
+
+        Netlist { egraph, result, order, inputs, input_names, outputs }
+    }
+
+    pub fn node(&self, cid: ClassId) -> &'a Node {
+        &self.egraph[&self.result.choices[&cid]]
+    }
+
+    pub fn gate(&self, cid: ClassId) -> Gate {
+        Gate::from_node(self.node(cid))
+    }
+
+    pub fn input_name(&self, cid: ClassId) -> &str {
+        &self.input_names[&cid]
+    }
+}
+
+fn flatten_outputs(egraph: &EGraph, result: &ExtractionResult, cid: ClassId, outputs: &mut Vec<ClassId>) {
+    let node = &egraph[&result.choices[&cid]];
+    if node.op == CONCAT_OP {
+        for child in &node.children {
+            flatten_outputs(egraph, result, *child, outputs);
+        }
+    } else {
+        outputs.push(cid);
+    }
+}
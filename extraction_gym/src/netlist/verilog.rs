@@ -0,0 +1,105 @@
+// Structural Verilog for an extracted netlist.
+//
+// Every gate gets a wire `n<class id>`; primitive gates become continuous
+// assignments, other ops become instances `<op> g<class id> (out, in...)`
+// of a cell with that name, to be provided by the cell library.
+
+use super::{Gate, Netlist};
+use crate::ExtractionResult;
+use egraph_serialize::*;
+use std::fmt::Write as _;
+
+pub fn to_verilog(netlist: &Netlist, module_name: &str) -> String {
+    let mut out = String::new();
+    let inputs: Vec<String> = netlist.inputs.iter().map(|cid| input_name(netlist, *cid)).collect();
+    let outputs: Vec<String> = (0..netlist.outputs.len()).map(|i| format!("po{}", i)).collect();
+
+    let ports: Vec<&str> = inputs.iter().chain(outputs.iter()).map(|s| s.as_str()).collect();
+    writeln!(out, "module {} ({});", identifier(module_name), ports.join(", ")).unwrap();
+    for name in &inputs {
+        writeln!(out, "  input {};", name).unwrap();
+    }
+    for name in &outputs {
+        writeln!(out, "  output {};", name).unwrap();
+    }
+
+    let gates: Vec<ClassId> = netlist
+        .order
+        .iter()
+        .copied()
+        .filter(|cid| !matches!(netlist.gate(*cid), Gate::Input(_)))
+        .collect();
+    for cid in &gates {
+        writeln!(out, "  wire n{};", cid).unwrap();
+    }
+    out.push('\n');
+
+    for cid in &gates {
+        let node = netlist.node(*cid);
+        let ins: Vec<String> = node.children.iter().map(|c| signal(netlist, *c)).collect();
+        let expr = match netlist.gate(*cid) {
+            Gate::Const(value) => String::from(if value { "1'b1" } else { "1'b0" }),
+            Gate::Buf => ins[0].clone(),
+            Gate::Not => format!("~{}", ins[0]),
+            Gate::And => ins.join(" & "),
+            Gate::Or => ins.join(" | "),
+            Gate::Xor => ins.join(" ^ "),
+            Gate::Nand => format!("~({})", ins.join(" & ")),
+            Gate::Nor => format!("~({})", ins.join(" | ")),
+            Gate::Xnor => format!("~({})", ins.join(" ^ ")),
+            Gate::Maj => format!(
+                "({a} & {b}) | ({a} & {c}) | ({b} & {c})",
+                a = ins[0],
+                b = ins[1],
+                c = ins[2]
+            ),
+            Gate::Cell(op) => {
+                writeln!(out, "  {} g{} (n{}, {});", identifier(&op), cid, cid, ins.join(", ")).unwrap();
+                continue;
+            }
+            Gate::Input(_) => unreachable!(),
+        };
+        writeln!(out, "  assign n{} = {};", cid, expr).unwrap();
+    }
+
+    out.push('\n');
+    for (i, cid) in netlist.outputs.iter().enumerate() {
+        writeln!(out, "  assign po{} = {};", i, signal(netlist, *cid)).unwrap();
+    }
+    out.push_str("endmodule\n");
+    out
+}
+
+pub fn write_verilog_file(
+    egraph: &EGraph,
+    result: &ExtractionResult,
+    roots: &[ClassId],
+    module_name: &str,
+    file_path: &str,
+) -> std::io::Result<()> {
+    let netlist = Netlist::new(egraph, result, roots);
+    std::fs::write(file_path, to_verilog(&netlist, module_name))
+}
+
+fn signal(netlist: &Netlist, cid: ClassId) -> String {
+    match netlist.gate(cid) {
+        Gate::Input(_) => input_name(netlist, cid),
+        _ => format!("n{}", cid),
+    }
+}
+
+fn input_name(netlist: &Netlist, cid: ClassId) -> String {
+    identifier(netlist.input_name(cid))
+}
+
+// Input names such as `a[3]` are kept as escaped identifiers rather than renamed,
+// so the ports still match the original design.
+fn identifier(name: &str) -> String {
+    let simple = name.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if simple {
+        name.to_string()
+    } else {
+        format!("\\{} ", name)
+    }
+}
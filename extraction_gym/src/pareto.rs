@@ -0,0 +1,206 @@
+//! Area/delay tradeoffs: extractions that are Pareto-optimal in DAG cost and depth.
+//!
+//! `ParetoExtractor` sweeps a depth bound upwards (an epsilon-constraint sweep).
+//! Layer `h` holds, for every class, the cheapest sub-DAG of depth at most `h`
+//! that `faster_greedy_dag` would build from the sub-DAGs of layer `h - 1`. Only
+//! the parents of the classes a layer improved are evaluated in the next one, so
+//! the sweep costs about as much as a single greedy extraction. Every layer that
+//! improves a root gives an extraction of depth at most `h`. Those and the
+//! `faster_greedy_dag` extraction that no other one beats in both measures make
+//! up the `ParetoFront`.
+
+use crate::choices_file::{self, ChoicesFile};
+use crate::faster_greedy_dag::{CostSet, FasterGreedyDagExtractor};
+use crate::*;
+use serde_json::{json, Value};
+
+/// Extracts the cheapest extraction within `max_depth`, or the cheapest one of the
+/// front without. A `max_depth` below the smallest depth the roots can have gives
+/// the shallowest extraction.
+#[derive(Default)]
+pub struct ParetoExtractor {
+    pub max_depth: Option<u32>,
+}
+
+/// One extraction of a `ParetoFront`, with its depth and DAG cost over the roots.
+#[derive(Clone)]
+pub struct ParetoPoint {
+    pub depth: u32,
+    pub dag_cost: Cost,
+    pub result: ExtractionResult,
+}
+
+/// Extractions none of which is both deeper and more expensive than another, by
+/// increasing depth (and so decreasing DAG cost).
+///
+/// As JSON, `{"version": 1, "points": [...]}` where every point is in the
+/// `choices_file` format, its costs holding the depth and the DAG cost, so one
+/// of them can be picked and extracted offline.
+#[derive(Clone, Default)]
+pub struct ParetoFront {
+    pub points: Vec<ParetoPoint>,
+}
+
+impl ParetoFront {
+    /// Adds `point` unless a point of the front is as shallow and as cheap, and
+    /// drops the points it beats. Returns whether it was added.
+    pub fn insert(&mut self, point: ParetoPoint) -> bool {
+        if self.points.iter().any(|kept| kept.depth <= point.depth && kept.dag_cost <= point.dag_cost) {
+            return false;
+        }
+        self.points.retain(|kept| kept.depth < point.depth || kept.dag_cost < point.dag_cost);
+        let at = self.points.partition_point(|kept| kept.depth < point.depth);
+        self.points.insert(at, point);
+        true
+    }
+
+    /// The cheapest point of depth at most `max_depth`.
+    pub fn cheapest_within(&self, max_depth: u32) -> Option<&ParetoPoint> {
+        self.points.iter().rev().find(|point| point.depth <= max_depth)
+    }
+
+    pub fn to_json(&self, egraph: &EGraph, roots: &[ClassId]) -> Value {
+        let points: Vec<Value> = self
+            .points
+            .iter()
+            .map(|point| ChoicesFile::from_result(egraph, &point.result, roots).to_json())
+            .collect();
+        json!({ "version": choices_file::VERSION, "points": points })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        match value.get("version").and_then(Value::as_u64) {
+            Some(choices_file::VERSION) => {}
+            Some(version) => return Err(format!("unsupported Pareto front version {}", version)),
+            None => return Err("missing version".to_string()),
+        }
+        let points = value.get("points").and_then(Value::as_array).ok_or("missing points")?;
+        let mut front = ParetoFront::default();
+        for (i, point) in points.iter().enumerate() {
+            let file = ChoicesFile::from_json(point).map_err(|err| format!("point {}: {}", i, err))?;
+            let cost = |name: &str| file.costs.get(name).copied().ok_or_else(|| format!("point {}: missing {} cost", i, name));
+            let depth = cost("depth")?.into_inner() as u32;
+            let dag_cost = cost("dag")?;
+            front.points.push(ParetoPoint { depth, dag_cost, result: file.to_result() });
+        }
+        Ok(front)
+    }
+
+    pub fn to_json_file(&self, egraph: &EGraph, roots: &[ClassId], path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json(egraph, roots))?;
+        std::fs::write(path, json)
+    }
+
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let value: Value = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Self::from_json(&value).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl Extractor for ParetoExtractor {
+    fn extract(&self, egraph: &EGraph, roots: &[ClassId]) -> ExtractionResult {
+        let front = self.extract_front(egraph, roots);
+        let point = match self.max_depth {
+            Some(max_depth) => front.cheapest_within(max_depth).or(front.points.first()),
+            None => front.points.last(),
+        };
+        point.map(|point| point.result.clone()).unwrap_or_default()
+    }
+}
+
+impl ParetoExtractor {
+    /// The front of the extractions of `roots` up to `max_depth`, or until deeper
+    /// sub-DAGs stop being cheaper. Empty if the roots have no acyclic extraction.
+    pub fn extract_front(&self, egraph: &EGraph, roots: &[ClassId]) -> ParetoFront {
+        let n2c = |nid: &NodeId| egraph.nid_to_cid(nid);
+        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+        let mut pending = IndexSet::<NodeId>::new();
+        for class in egraph.classes().values() {
+            parents.insert(class.id, Vec::new());
+        }
+        for class in egraph.classes().values() {
+            for node in &class.nodes {
+                for child in &egraph[node].children {
+                    parents[child].push(*node);
+                }
+                if egraph[node].is_leaf() {
+                    pending.insert(*node);
+                }
+            }
+        }
+
+        let mut costs = FxHashMap::<ClassId, CostSet>::with_capacity_and_hasher(egraph.classes().len(), Default::default());
+        // The layers at which the choice of each class changed, and to what.
+        let mut history = FxHashMap::<ClassId, Vec<(u32, NodeId)>>::default();
+        let mut front = ParetoFront::default();
+        let mut depth = 0;
+        while !pending.is_empty() {
+            depth += 1;
+            // Evaluated on the sub-DAGs of the previous layer only.
+            let mut improved = IndexMap::<ClassId, CostSet>::new();
+            for node_id in pending.drain(..) {
+                if !egraph[&node_id].children.iter().all(|child| costs.contains_key(child)) {
+                    continue;
+                }
+                let class_id = *n2c(&node_id);
+                let best = improved.get(&class_id).or(costs.get(&class_id)).map_or(INFINITY, |cost_set| cost_set.total);
+                let cost_set = FasterGreedyDagExtractor::calculate_cost_set(egraph, node_id, &costs, best, None);
+                if cost_set.total < best {
+                    improved.insert(class_id, cost_set);
+                }
+            }
+            let root_improved = roots.iter().any(|root| improved.contains_key(root));
+            for (class_id, cost_set) in improved {
+                history.entry(class_id).or_default().push((depth, cost_set.choice));
+                pending.extend(parents[&class_id].iter().copied());
+                costs.insert(class_id, cost_set);
+            }
+            if root_improved && roots.iter().all(|root| costs.contains_key(root)) {
+                let result = within_depth(egraph, roots, &history, depth);
+                let point = ParetoPoint { depth: result.depth_cost(egraph, roots), dag_cost: result.dag_cost(egraph, roots), result };
+                front.insert(point);
+            }
+            if self.max_depth.is_some_and(|max_depth| depth >= max_depth) && !front.points.is_empty() {
+                break;
+            }
+        }
+        // The layers mix sub-DAGs of different bounds, which can cost more than the
+        // unbounded greedy extraction, so that one competes too.
+        if !front.points.is_empty() {
+            let result = FasterGreedyDagExtractor::default().extract(egraph, roots);
+            let point = ParetoPoint { depth: result.depth_cost(egraph, roots), dag_cost: result.dag_cost(egraph, roots), result };
+            if self.max_depth.map_or(true, |max_depth| point.depth <= max_depth) {
+                front.insert(point);
+            }
+        }
+        front
+    }
+}
+
+/// The extraction of `roots` within `depth`: every class takes its choice of the
+/// smallest layer its parents ask for, whose children are then asked for one
+/// layer less. Depths strictly decrease from parent to child, so it is acyclic.
+fn within_depth(egraph: &EGraph, roots: &[ClassId], history: &FxHashMap<ClassId, Vec<(u32, NodeId)>>, depth: u32) -> ExtractionResult {
+    let choice = |class_id: &ClassId, layer: u32| {
+        let changes = &history[class_id];
+        changes[changes.partition_point(|(changed, _)| *changed <= layer) - 1].1
+    };
+    let mut layers = FxHashMap::<ClassId, u32>::default();
+    let mut todo: Vec<(ClassId, u32)> = roots.iter().map(|root| (*root, depth)).collect();
+    while let Some((class_id, layer)) = todo.pop() {
+        if layers.get(&class_id).is_some_and(|asked| *asked <= layer) {
+            continue;
+        }
+        layers.insert(class_id, layer);
+        for child in &egraph[&choice(&class_id, layer)].children {
+            todo.push((*child, layer - 1));
+        }
+    }
+    let mut result = ExtractionResult::default();
+    for (class_id, layer) in layers {
+        result.choose(class_id, choice(&class_id, layer));
+    }
+    result.choices.sort_keys();
+    result
+}
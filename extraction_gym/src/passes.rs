@@ -0,0 +1,241 @@
+//! Pass schedules of the multi-pass extractors (`faster-greedy-dag-mt*`).
+//!
+//! A pass queues some nodes and runs the work list until it is empty. The first
+//! pass usually starts from the leaves; the following ones queue every node
+//! again, which lets the batches of a parallel extractor revisit the cost sets
+//! they computed from the state at the start of a batch. How many of them pay off
+//! depends on the depth of the e-graph, so the schedule is configurable:
+//!
+//! ```text
+//! leaves,all:stable,all:200
+//! ```
+//!
+//! is a pass from the leaves, then a pass over all nodes that ends the schedule if
+//! it changes no choice, then one that stops after 200 batches. The default,
+//! `leaves,all:converge`, repeats the pass over all nodes until the fixed point,
+//! where no class's best cost changes any more.
+
+use crate::*;
+use crate::queue::UniqueQueue;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+/// The nodes queued at the start of a pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seed {
+    Leaves,
+    AllNodes,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pass {
+    pub seed: Seed,
+    /// Batches after which the pass stops, leaving the nodes still queued to the
+    /// next one. The last pass always runs until the work list is empty.
+    pub max_batches: Option<usize>,
+    /// Skip the remaining passes if this one changes no choice. Nothing is lost:
+    /// with no cost set improved, another pass over all nodes computes the same
+    /// ones again.
+    pub until_stable: bool,
+    /// Repeat the pass until a repetition changes no choice, at most
+    /// `MAX_CONVERGE_PASSES` times, then skip the remaining passes as
+    /// `until_stable` does.
+    pub converge: bool,
+}
+
+/// Repetitions of a `converge` pass that doesn't reach its fixed point.
+pub const MAX_CONVERGE_PASSES: usize = 64;
+
+impl Pass {
+    pub fn leaves() -> Self {
+        Pass { seed: Seed::Leaves, max_batches: None, until_stable: false, converge: false }
+    }
+
+    pub fn all_nodes() -> Self {
+        Pass { seed: Seed::AllNodes, max_batches: None, until_stable: false, converge: false }
+    }
+
+    /// Queues the nodes of the pass, in id order when `deterministic` and in a
+    /// random class order otherwise.
+    pub(crate) fn seed(&self, egraph: &EGraph, deterministic: bool, queue: &mut UniqueQueue<NodeId>) {
+        let mut classes: Vec<&Class> = egraph.classes().values().collect();
+        if deterministic {
+            classes.sort_by_key(|class| class.id);
+        } else {
+            classes.shuffle(&mut rand::thread_rng());
+        }
+        for class in classes {
+            let mut nodes: Vec<&NodeId> = class.nodes.iter().collect();
+            if deterministic {
+                nodes.sort();
+            }
+            for node in nodes {
+                if self.seed == Seed::AllNodes || egraph[node].is_leaf() {
+                    queue.insert(*node);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassSchedule {
+    pub passes: Vec<Pass>,
+}
+
+impl PassSchedule {
+    /// A pass from the leaves, then passes over all nodes until the fixed point.
+    pub fn converging() -> Self {
+        PassSchedule { passes: vec![Pass::leaves(), Pass { converge: true, ..Pass::all_nodes() }] }
+    }
+
+    pub(crate) fn run(&self) -> PassRun<'_> {
+        PassRun { schedule: self, index: 0, repeats: 0, done: false, log: PassLog::default() }
+    }
+}
+
+impl Default for PassSchedule {
+    fn default() -> Self {
+        PassSchedule::converging()
+    }
+}
+
+/// The passes a schedule ran.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PassLog {
+    /// Number of classes whose best cost improved, for each pass run.
+    pub improved: Vec<usize>,
+}
+
+impl PassLog {
+    pub fn passes(&self) -> usize {
+        self.improved.len()
+    }
+
+    /// Whether the last pass changed nothing, i.e. more passes wouldn't either.
+    pub fn converged(&self) -> bool {
+        self.improved.last() == Some(&0)
+    }
+}
+
+impl fmt::Display for PassLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let improved: Vec<String> = self.improved.iter().map(|n| n.to_string()).collect();
+        write!(
+            f,
+            "passes: {} ({}), classes improved per pass: {}",
+            self.passes(),
+            if self.converged() { "converged" } else { "not known to have converged" },
+            improved.join(", ")
+        )
+    }
+}
+
+/// A schedule being run: `next` is the pass to seed next, `end` records how it went.
+pub(crate) struct PassRun<'a> {
+    schedule: &'a PassSchedule,
+    index: usize,
+    repeats: usize,
+    done: bool,
+    log: PassLog,
+}
+
+impl PassRun<'_> {
+    pub(crate) fn next(&self) -> Option<Pass> {
+        self.schedule.passes.get(self.index).copied().filter(|_| !self.done)
+    }
+
+    /// Whether the current pass, which ran `batches` batches, stops with nodes
+    /// queued. The last pass of the schedule always runs its work list dry.
+    pub(crate) fn stops(&self, batches: usize) -> bool {
+        self.index + 1 < self.schedule.passes.len()
+            && self.schedule.passes[self.index].max_batches.is_some_and(|max| batches >= max)
+    }
+
+    /// Ends the current pass, which improved the best cost of `improved` classes and
+    /// left `queued` nodes to the next one.
+    pub(crate) fn end(&mut self, improved: usize, queued: bool) {
+        self.log.improved.push(improved);
+        let pass = self.schedule.passes[self.index];
+        let stable = improved == 0 && !queued;
+        if pass.converge && !stable && self.repeats + 1 < MAX_CONVERGE_PASSES {
+            self.repeats += 1;
+            return;
+        }
+        self.done = stable && (pass.until_stable || pass.converge);
+        self.index += 1;
+        self.repeats = 0;
+    }
+
+    pub(crate) fn finish(self) -> PassLog {
+        self.log
+    }
+}
+
+/// A multi-pass extractor, which can say how many passes it needed.
+pub trait MultiPassExtractor: ParallelExtractor {
+    fn extract_with_passes(&self, egraph: FrozenEGraph, roots: &[ClassId]) -> (ExtractionResult, PassLog);
+
+    /// `Extractor::extract_frozen` with the passes it ran.
+    fn extract_frozen_with_passes(
+        &self,
+        egraph: &EGraph,
+        roots: &[ClassId],
+        frozen: &IndexMap<ClassId, NodeId>,
+    ) -> (ExtractionResult, PassLog) {
+        if frozen.is_empty() {
+            return self.extract_with_passes(FrozenEGraph::new(egraph), roots);
+        }
+        let egraph = freeze_choices(egraph, frozen);
+        self.extract_with_passes(FrozenEGraph::new(&egraph), roots)
+    }
+}
+
+impl std::str::FromStr for PassSchedule {
+    type Err = String;
+
+    fn from_str(schedule: &str) -> Result<Self, String> {
+        let mut passes = Vec::new();
+        for pass in schedule.split(',') {
+            let mut parts = pass.trim().split(':');
+            let mut pass = match parts.next() {
+                Some("leaves") => Pass::leaves(),
+                Some("all") => Pass::all_nodes(),
+                _ => return Err(format!("unknown pass '{}', expected leaves or all", pass)),
+            };
+            for option in parts {
+                if option == "stable" {
+                    pass.until_stable = true;
+                } else if option == "converge" {
+                    pass.converge = true;
+                } else {
+                    let batches = option.parse::<usize>().map_err(|_| format!("unknown pass option '{}', expected stable, converge or a number of batches", option))?;
+                    pass.max_batches = Some(batches.max(1));
+                }
+            }
+            passes.push(pass);
+        }
+        Ok(PassSchedule { passes })
+    }
+}
+
+impl fmt::Display for PassSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pass) in self.passes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", if pass.seed == Seed::Leaves { "leaves" } else { "all" })?;
+            if pass.until_stable {
+                write!(f, ":stable")?;
+            }
+            if pass.converge {
+                write!(f, ":converge")?;
+            }
+            if let Some(batches) = pass.max_batches {
+                write!(f, ":{}", batches)?;
+            }
+        }
+        Ok(())
+    }
+}
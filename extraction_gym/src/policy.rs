@@ -0,0 +1,99 @@
+//! Which cost set of a class wins in the greedy DAG extractors
+//! (`faster_greedy_dag`, `faster_greedy_dag_mt1`, `faster_greedy_dag_mt2`).
+//!
+//! By default a node replaces the choice of its class when its sub-DAG is
+//! strictly cheaper. A `ChoicePolicy` replaces that comparison, e.g. to break the
+//! ties between equally cheap sub-DAGs by depth or by a preferred op, without a
+//! copy of the extractor.
+
+use crate::*;
+use std::sync::Arc;
+
+/// A sub-DAG rooted at `node`, as the policy sees it.
+#[derive(Clone, Copy, Debug)]
+pub struct Candidate<'a> {
+    pub node: &'a Node,
+    /// Its DAG cost.
+    pub total: Cost,
+    /// Its depth in nodes.
+    pub depth: usize,
+    /// Number of classes in it.
+    pub size: usize,
+}
+
+pub trait ChoicePolicy: Send + Sync {
+    /// Whether `candidate` replaces `current` as the choice of their class. This
+    /// has to be a strict order, or the extraction may not terminate; policies that
+    /// only reorder sub-DAGs of the same cost are.
+    fn prefers(&self, candidate: &Candidate, current: &Candidate) -> bool;
+}
+
+/// The cheaper sub-DAG, the first one found among equally cheap ones.
+pub struct StrictCost;
+
+impl ChoicePolicy for StrictCost {
+    fn prefers(&self, candidate: &Candidate, current: &Candidate) -> bool {
+        candidate.total < current.total
+    }
+}
+
+/// The cheaper sub-DAG, then the shallower one.
+pub struct CostThenDepth;
+
+impl ChoicePolicy for CostThenDepth {
+    fn prefers(&self, candidate: &Candidate, current: &Candidate) -> bool {
+        (candidate.total, candidate.depth) < (current.total, current.depth)
+    }
+}
+
+/// The cheaper sub-DAG, then the one of fewer classes.
+pub struct CostThenSize;
+
+impl ChoicePolicy for CostThenSize {
+    fn prefers(&self, candidate: &Candidate, current: &Candidate) -> bool {
+        (candidate.total, candidate.size) < (current.total, current.size)
+    }
+}
+
+/// The cheaper sub-DAG, then the one whose root op comes first in `ops`; the ops
+/// that aren't listed come last.
+pub struct OpPreference {
+    pub ops: Vec<String>,
+}
+
+impl OpPreference {
+    fn rank(&self, op: &str) -> usize {
+        self.ops.iter().position(|preferred| preferred == op).unwrap_or(self.ops.len())
+    }
+}
+
+impl ChoicePolicy for OpPreference {
+    fn prefers(&self, candidate: &Candidate, current: &Candidate) -> bool {
+        (candidate.total, self.rank(&candidate.node.op)) < (current.total, self.rank(&current.node.op))
+    }
+}
+
+/// A policy by name: `cost`, `cost-depth`, `cost-size` or `ops:<op>,<op>,...`.
+pub fn parse_policy(policy: &str) -> Result<Arc<dyn ChoicePolicy>, String> {
+    match policy {
+        "cost" => Ok(Arc::new(StrictCost)),
+        "cost-depth" => Ok(Arc::new(CostThenDepth)),
+        "cost-size" => Ok(Arc::new(CostThenSize)),
+        _ => match policy.strip_prefix("ops:") {
+            Some(ops) if !ops.is_empty() => Ok(Arc::new(OpPreference { ops: ops.split(',').map(String::from).collect() })),
+            _ => Err(format!("unknown policy '{}', expected cost, cost-depth, cost-size or ops:<op>,...", policy)),
+        },
+    }
+}
+
+/// Whether `candidate` becomes the choice of a class whose current one is
+/// `current`, under `policy` or by strict cost without one.
+pub(crate) fn prefers(policy: Option<&dyn ChoicePolicy>, candidate: &Candidate, current: Option<&Candidate>) -> bool {
+    match current {
+        None => candidate.total < INFINITY,
+        Some(current) => match policy {
+            Some(policy) => candidate.total < INFINITY && policy.prefers(candidate, current),
+            None => candidate.total < current.total,
+        },
+    }
+}
@@ -0,0 +1,251 @@
+//! Work lists shared by the extractors.
+//!
+//! The extractors keep the nodes whose cost may have to be recomputed in a
+//! queue that holds every item at most once: re-inserting a pending node is a
+//! no-op. The multi-threaded extractors pop batches that are then processed in
+//! parallel.
+//!
+//! Thanks @Bastacyclop for the original `UniqueQueue` implementation!
+
+use crate::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::hash::Hash;
+
+/// Batch size of `UniqueQueue::pop_batch` unless set with `with_batch_size`.
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+/// FIFO queue in which every item is pending at most once.
+#[derive(Clone)]
+pub struct UniqueQueue<T>
+where
+    T: Eq + Hash + Clone,
+{
+    set: FxHashSet<T>,
+    queue: VecDeque<T>,
+    batch_size: usize,
+}
+
+impl<T> Default for UniqueQueue<T>
+where
+    T: Eq + Hash + Clone,
+{
+    fn default() -> Self {
+        Self::with_batch_size(DEFAULT_BATCH_SIZE)
+    }
+}
+
+impl<T> UniqueQueue<T>
+where
+    T: Eq + Hash + Clone,
+{
+    pub fn with_batch_size(batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batch size must be positive");
+        UniqueQueue {
+            set: Default::default(),
+            queue: VecDeque::new(),
+            batch_size,
+        }
+    }
+
+    pub fn insert(&mut self, t: T) {
+        if self.set.insert(t.clone()) {
+            self.queue.push_back(t);
+        }
+    }
+
+    pub fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for t in iter {
+            self.insert(t);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let res = self.queue.pop_front();
+        res.as_ref().map(|t| self.set.remove(t));
+        res
+    }
+
+    /// Pops up to the batch size items, oldest first.
+    pub fn pop_batch(&mut self) -> Vec<T> {
+        let n = self.batch_size.min(self.queue.len());
+        let batch: Vec<T> = self.queue.drain(..n).collect();
+        for t in &batch {
+            self.set.remove(t);
+        }
+        batch
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let r = self.queue.is_empty();
+        debug_assert_eq!(r, self.set.is_empty());
+        r
+    }
+}
+
+/// Queue that pops the item with the lowest priority first (ties in insertion
+/// order). Every item is pending at most once: inserting a pending item again
+/// only lowers its priority.
+#[derive(Clone)]
+pub struct UniquePriorityQueue<T, P>
+where
+    T: Eq + Hash + Clone,
+    P: Ord + Copy,
+{
+    priorities: FxHashMap<T, P>,
+    // Entries whose priority no longer matches `priorities` are stale and skipped.
+    heap: BinaryHeap<Reverse<(P, u64, Entry<T>)>>,
+    inserted: u64,
+}
+
+/// Heap entry ordered by the other tuple fields only.
+#[derive(Clone)]
+struct Entry<T>(T);
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+impl<T, P> Default for UniquePriorityQueue<T, P>
+where
+    T: Eq + Hash + Clone,
+    P: Ord + Copy,
+{
+    fn default() -> Self {
+        UniquePriorityQueue {
+            priorities: Default::default(),
+            heap: BinaryHeap::new(),
+            inserted: 0,
+        }
+    }
+}
+
+impl<T, P> UniquePriorityQueue<T, P>
+where
+    T: Eq + Hash + Clone,
+    P: Ord + Copy,
+{
+    pub fn insert(&mut self, t: T, priority: P) {
+        match self.priorities.get_mut(&t) {
+            Some(current) if *current <= priority => return,
+            Some(current) => *current = priority,
+            None => {
+                self.priorities.insert(t.clone(), priority);
+            }
+        }
+        self.inserted += 1;
+        self.heap.push(Reverse((priority, self.inserted, Entry(t))));
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        while let Some(Reverse((priority, _, Entry(t)))) = self.heap.pop() {
+            if self.priorities.get(&t) == Some(&priority) {
+                self.priorities.remove(&t);
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.priorities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.priorities.is_empty()
+    }
+}
+
+/// Order in which an extractor processes its pending nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Frontier {
+    /// In the order they became pending.
+    #[default]
+    Fifo,
+    /// Cheapest first: a node is keyed by the cost of the child class whose
+    /// improvement made it pending, so that cheap subterms settle before the
+    /// terms built on them are recomputed. On deep e-graphs this saves many of
+    /// the relaxations FIFO spends on costs that are improved again later.
+    BestFirst,
+}
+
+/// The work list of a `Frontier`; priorities are ignored for `Frontier::Fifo`.
+#[derive(Clone)]
+pub enum Worklist<T, P>
+where
+    T: Eq + Hash + Clone,
+    P: Ord + Copy,
+{
+    Fifo(UniqueQueue<T>),
+    BestFirst(UniquePriorityQueue<T, P>),
+}
+
+impl<T, P> Worklist<T, P>
+where
+    T: Eq + Hash + Clone,
+    P: Ord + Copy,
+{
+    pub fn new(frontier: Frontier) -> Self {
+        match frontier {
+            Frontier::Fifo => Worklist::Fifo(UniqueQueue::default()),
+            Frontier::BestFirst => Worklist::BestFirst(UniquePriorityQueue::default()),
+        }
+    }
+
+    pub fn insert(&mut self, t: T, priority: P) {
+        match self {
+            Worklist::Fifo(queue) => queue.insert(t),
+            Worklist::BestFirst(queue) => queue.insert(t, priority),
+        }
+    }
+
+    pub fn extend<I>(&mut self, iter: I, priority: P)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for t in iter {
+            self.insert(t, priority);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        match self {
+            Worklist::Fifo(queue) => queue.pop(),
+            Worklist::BestFirst(queue) => queue.pop(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Worklist::Fifo(queue) => queue.len(),
+            Worklist::BestFirst(queue) => queue.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,168 @@
+//! Reference counts of the classes an extraction selects, so that the DAG cost
+//! change of swapping one class's choice only walks the classes the swap adds or
+//! drops, not the whole selected DAG. This is the step local search, annealing
+//! and tree search take thousands of times.
+
+use crate::*;
+
+/// How often each class is used by the DAG selected from some roots: once per
+/// root and once per child edge of a selected node (an `x * x` uses `x` twice).
+/// The selected classes are the ones with a count.
+#[derive(Clone, Debug, Default)]
+pub struct RefCounts {
+    counts: FxHashMap<ClassId, usize>,
+    dag_cost: Cost,
+}
+
+impl RefCounts {
+    /// The counts of the DAG `result` selects from `roots`, which has to be
+    /// complete and acyclic, as `ExtractionResult::dag_cost` needs it.
+    pub fn new(result: &ExtractionResult, egraph: &EGraph, roots: &[ClassId]) -> Self {
+        let mut refs = RefCounts::default();
+        let mut todo: Vec<ClassId> = roots.to_vec();
+        while let Some(cid) = todo.pop() {
+            let count = refs.counts.entry(cid).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                let node = &egraph[&result.choices[&cid]];
+                refs.dag_cost += node.cost;
+                todo.extend(node.children.iter().cloned());
+            }
+        }
+        refs
+    }
+
+    pub fn count(&self, class: &ClassId) -> usize {
+        self.counts.get(class).copied().unwrap_or(0)
+    }
+
+    /// DAG cost of the selected classes, `dag_cost` of the result.
+    pub fn dag_cost(&self) -> Cost {
+        self.dag_cost
+    }
+
+    /// The DAG cost change of choosing `new_node` for its class, and the changes
+    /// of the counts. `None` if the swap closes a cycle or selects a class without
+    /// a choice.
+    fn rewire(&self, result: &ExtractionResult, egraph: &EGraph, new_node: &NodeId) -> Option<(Cost, FxHashMap<ClassId, isize>)> {
+        let class = egraph.nid_to_cid(new_node);
+        let mut changes = FxHashMap::<ClassId, isize>::default();
+        let old_node = match result.choices.get(class) {
+            Some(old_node) if self.count(class) > 0 => old_node,
+            // Not selected: the DAG doesn't change.
+            _ => return Some((Cost::default(), changes)),
+        };
+        if old_node == new_node {
+            return Some((Cost::default(), changes));
+        }
+        if closes_cycle(result, egraph, new_node) {
+            return None;
+        }
+        let count = |changes: &FxHashMap<ClassId, isize>, cid: &ClassId| self.count(cid) as isize + changes.get(cid).copied().unwrap_or(0);
+
+        let mut delta = egraph[new_node].cost - egraph[old_node].cost;
+        // The uses of the new node first, so that the classes both nodes use are
+        // never dropped and selected again.
+        let mut todo: Vec<ClassId> = egraph[new_node].children.clone();
+        while let Some(cid) = todo.pop() {
+            let before = count(&changes, &cid);
+            *changes.entry(cid).or_insert(0) += 1;
+            if before == 0 {
+                let node = &egraph[result.choices.get(&cid)?];
+                delta += node.cost;
+                todo.extend(node.children.iter().cloned());
+            }
+        }
+        let mut todo: Vec<ClassId> = egraph[old_node].children.clone();
+        while let Some(cid) = todo.pop() {
+            *changes.entry(cid).or_insert(0) -= 1;
+            if count(&changes, &cid) == 0 {
+                let node = &egraph[&result.choices[&cid]];
+                delta -= node.cost;
+                todo.extend(node.children.iter().cloned());
+            }
+        }
+        Some((delta, changes))
+    }
+}
+
+/// Whether the DAG `result` selects below `new_node` has a cycle, or contains
+/// the class of `new_node`, once it is chosen.
+fn closes_cycle(result: &ExtractionResult, egraph: &EGraph, new_node: &NodeId) -> bool {
+    // On the path while `false`, done once `true`.
+    let mut status = FxHashMap::<ClassId, bool>::default();
+    status.insert(*egraph.nid_to_cid(new_node), false);
+    let mut todo: Vec<(ClassId, bool)> = egraph[new_node].children.iter().map(|cid| (*cid, false)).collect();
+    while let Some((cid, leaving)) = todo.pop() {
+        if leaving {
+            status.insert(cid, true);
+            continue;
+        }
+        match status.get(&cid) {
+            Some(false) => return true,
+            Some(true) => continue,
+            None => {}
+        }
+        status.insert(cid, false);
+        todo.push((cid, true));
+        if let Some(nid) = result.choices.get(&cid) {
+            todo.extend(egraph[nid].children.iter().map(|child| (*child, false)));
+        }
+    }
+    false
+}
+
+impl ExtractionResult {
+    /// Keeps reference counts of the classes selected from `roots` from now on, for
+    /// `swap_cost_delta` and `swap`. `choose` drops them; changing `choices`
+    /// directly leaves them stale.
+    pub fn track_refs(&mut self, egraph: &EGraph, roots: &[ClassId]) {
+        self.refs = Some(RefCounts::new(self, egraph, roots));
+    }
+
+    /// The change of `dag_cost` if `new_node` became the choice of `class`,
+    /// `INFINITY` if that closes a cycle or selects a class without a choice.
+    /// Counts the selected classes from the e-graph roots first unless
+    /// `track_refs` did.
+    pub fn swap_cost_delta(&self, egraph: &EGraph, class: &ClassId, new_node: &NodeId) -> Cost {
+        assert_eq!(egraph.nid_to_cid(new_node), class, "{} is not a node of {}", new_node, class);
+        let counted;
+        let refs = match &self.refs {
+            Some(refs) => refs,
+            None => {
+                counted = RefCounts::new(self, egraph, &egraph.root_eclasses);
+                &counted
+            }
+        };
+        refs.rewire(self, egraph, new_node).map_or(INFINITY, |(delta, _)| delta)
+    }
+
+    /// Chooses `new_node` for `class` unless `swap_cost_delta` is `INFINITY`, and
+    /// returns that delta, keeping tracked reference counts up to date.
+    pub fn swap(&mut self, egraph: &EGraph, class: &ClassId, new_node: &NodeId) -> Cost {
+        let Some(mut refs) = self.refs.take() else {
+            let delta = self.swap_cost_delta(egraph, class, new_node);
+            if delta != INFINITY {
+                self.choices.insert(*class, *new_node);
+            }
+            return delta;
+        };
+        assert_eq!(egraph.nid_to_cid(new_node), class, "{} is not a node of {}", new_node, class);
+        let delta = match refs.rewire(self, egraph, new_node) {
+            Some((delta, changes)) => {
+                for (cid, change) in changes {
+                    match refs.count(&cid) as isize + change {
+                        0 => refs.counts.remove(&cid),
+                        count => refs.counts.insert(cid, count as usize),
+                    };
+                }
+                refs.dag_cost += delta;
+                self.choices.insert(*class, *new_node);
+                delta
+            }
+            None => INFINITY,
+        };
+        self.refs = Some(refs);
+        delta
+    }
+}
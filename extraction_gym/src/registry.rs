@@ -0,0 +1,79 @@
+use crate::*;
+use std::sync::Mutex;
+
+#[derive(PartialEq, Eq)]
+pub enum Optimal {
+    Tree,
+    DAG,
+    Neither,
+}
+
+pub struct ExtractorDetail {
+    pub extractor: Box<dyn Extractor>,
+    pub optimal: Optimal,
+    pub use_for_bench: bool,
+}
+
+impl ExtractorDetail {
+    // Getter for `extractor`
+    pub fn get_extractor(&self) -> &dyn Extractor {
+        &*self.extractor
+    }
+
+    // Getter for `optimal`
+    pub fn get_optimal(&self) -> &Optimal {
+        &self.optimal
+    }
+
+    // Getter for `use_for_bench`
+    pub fn get_use_for_bench(&self) -> bool {
+        self.use_for_bench
+    }
+}
+
+type Factory = Box<dyn Fn() -> ExtractorDetail + Send + Sync>;
+
+static REGISTERED: Mutex<Vec<(&'static str, Factory)>> = Mutex::new(Vec::new());
+
+/// Adds an extractor to the ones the CLI and the server offer by name, next to
+/// the built-in ones (a built-in extractor with the same name is replaced).
+/// `detail` is called every time the extractor list is built, since the list
+/// owns its extractors.
+pub fn register_extractor(name: &str, detail: impl Fn() -> ExtractorDetail + Send + Sync + 'static) {
+    let mut registered = REGISTERED.lock().unwrap();
+    registered.retain(|(registered_name, _)| *registered_name != name);
+    // Names live as long as the process, like the built-in ones.
+    let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+    registered.push((name, Box::new(detail)));
+}
+
+/// The extractors added with `register_extractor`, in registration order.
+pub fn registered_extractors() -> Vec<(&'static str, ExtractorDetail)> {
+    REGISTERED
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, detail)| (*name, detail()))
+        .collect()
+}
+
+/// What an extractor plugin (a `cdylib` loaded by the CLI with `--plugin`) hands
+/// over: named constructors of its extractors. The plugin exports
+///
+/// ```ignore
+/// #[no_mangle]
+/// pub extern "C" fn extraction_gym_plugin() -> *mut Plugin {
+///     Box::into_raw(Box::new(Plugin { extractors: vec![("my-extractor".into(), my_extractor)] }))
+/// }
+/// ```
+///
+/// The boxed extractors cross the library boundary as Rust values, so a plugin
+/// has to be built with the same compiler and `extraction_gym` version as the CLI.
+pub struct Plugin {
+    pub extractors: Vec<(String, fn() -> ExtractorDetail)>,
+}
+
+/// Symbol of the plugin entry point.
+pub const PLUGIN_ENTRY: &[u8] = b"extraction_gym_plugin";
+
+pub type PluginEntry = unsafe extern "C" fn() -> *mut Plugin;
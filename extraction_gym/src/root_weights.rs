@@ -0,0 +1,102 @@
+use crate::*;
+
+/// Relative importance of the roots of a multi-output e-graph, e.g. the
+/// criticality of each output bit.
+///
+/// Weights may also be given to inner classes, such as the outputs gathered by a
+/// concatenation root. Every class gets the largest weight among the weighted
+/// classes and roots it is reachable from, and the cost of its nodes is scaled
+/// by it. Applied to the e-graph data before extraction, the scaled costs are
+/// what `tree_cost`, `dag_cost`, the extractors and the generated LP all work
+/// with; roots without a weight count with 1.
+///
+/// This approximates weighting the cost of each root's cone by the root: a class
+/// is reachable through any node of the e-graph, not only through the nodes an
+/// extraction picks, so an alternative that is never picked still scales up the
+/// classes below it. The weighted costs are the ones reported; `unweighted` gives
+/// the e-graph to report the costs without the weights on.
+#[derive(Clone, Debug, Default)]
+pub struct RootWeights {
+    pub weights: HashMap<ClassId, f64>,
+}
+
+impl RootWeights {
+    /// Reads a JSON object mapping root class ids to their weight, e.g. `{"12": 4.0}`.
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let weights: HashMap<String, f64> = serde_json::from_reader(std::io::BufReader::new(file))?;
+        let mut root_weights = Self::default();
+        for (cid, weight) in weights {
+            let cid = cid.parse::<u32>().map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid class id {}", cid))
+            })?;
+            if weight.is_nan() || weight < 0.0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid weight {} for class {}", weight, cid),
+                ));
+            }
+            root_weights.weights.insert(ClassId::from(cid), weight);
+        }
+        Ok(root_weights)
+    }
+
+    pub fn weight(&self, root: &ClassId) -> f64 {
+        self.weights.get(root).copied().unwrap_or(1.0)
+    }
+
+    /// Weight of every class reachable from a root.
+    pub fn class_weights(&self, data: &Data) -> IndexMap<ClassId, f64> {
+        let mut children: HashMap<ClassId, Vec<ClassId>> = HashMap::new();
+        for node in data.nodes.values() {
+            children.entry(node.eclass).or_default().extend(node.children.iter().copied());
+        }
+
+        // Walking from the heaviest root first, a class keeps the first weight it gets.
+        let mut roots = data.root_eclasses.clone();
+        roots.extend(self.weights.keys().copied());
+        roots.sort_by(|a, b| self.weight(b).total_cmp(&self.weight(a)));
+        let mut class_weights = IndexMap::new();
+        for root in roots {
+            let weight = self.weight(&root);
+            let mut todo = vec![root];
+            while let Some(cid) = todo.pop() {
+                if class_weights.contains_key(&cid) {
+                    continue;
+                }
+                class_weights.insert(cid, weight);
+                if let Some(next) = children.get(&cid) {
+                    todo.extend(next.iter().filter(|c| !class_weights.contains_key(*c)).copied());
+                }
+            }
+        }
+        class_weights
+    }
+
+    /// Scales the node costs in `data` by the weight of their class, and returns
+    /// the costs the scaled nodes had. Areas are left as they were.
+    pub fn apply(&self, data: &mut Data) -> IndexMap<NodeId, Cost> {
+        let class_weights = self.class_weights(data);
+        let mut costs = IndexMap::new();
+        for (nid, node) in data.nodes.iter_mut() {
+            if let Some(weight) = class_weights.get(&node.eclass) {
+                costs.insert(*nid, node.cost);
+                node.area = Some(node.area());
+                node.cost = node.cost * Cost::new(*weight).unwrap();
+            }
+        }
+        costs
+    }
+
+    /// `egraph` with the `costs` that `apply` returned put back, for the costs of
+    /// an extraction without the weights.
+    pub fn unweighted(egraph: &EGraph, costs: &IndexMap<NodeId, Cost>) -> EGraph {
+        let mut unweighted = egraph.clone();
+        for (nid, node) in unweighted.nodes.iter_mut() {
+            if let Some(cost) = costs.get(nid) {
+                node.cost = *cost;
+            }
+        }
+        unweighted
+    }
+}
@@ -0,0 +1,84 @@
+//! Ranking of the classes by how much their choice can move the total cost: the
+//! spread between the tree costs of their best and second best nodes, times the
+//! number of times they are used. The classes at the top are where a wrong greedy
+//! choice costs the most, the ones to look at in a drawing or to hand to the ILP;
+//! the ones with no spread can be left to any heuristic.
+
+use crate::*;
+use crate::faster_bottom_up::FasterBottomUpExtractor;
+use serde_json::{json, Value};
+
+#[derive(Clone, Debug)]
+pub struct Salience {
+    pub class: ClassId,
+    /// Tree costs of the two cheapest nodes, `second` `INFINITY` when the class has
+    /// a single node that can be extracted.
+    pub best: Cost,
+    pub second: Cost,
+    /// Uses by the selected nodes and the roots, or by all the parent nodes and
+    /// the roots without an extraction.
+    pub uses: usize,
+    pub score: f64,
+}
+
+impl Salience {
+    /// `second - best`, 0 without a second node.
+    pub fn spread(&self) -> f64 {
+        if self.second == INFINITY {
+            0.0
+        } else {
+            (self.second - self.best).into_inner()
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "class": self.class.0,
+            "best": self.best.into_inner(),
+            "second": (self.second != INFINITY).then(|| self.second.into_inner()),
+            "spread": self.spread(),
+            "uses": self.uses,
+            "score": self.score,
+        })
+    }
+}
+
+/// The classes with a node that can be extracted, highest score first. With
+/// `result`, the uses are those of its selection, and the classes it doesn't
+/// select score 0.
+pub fn rank(egraph: &EGraph, roots: &[ClassId], result: Option<&ExtractionResult>) -> Vec<Salience> {
+    let tree = FasterBottomUpExtractor::default().extract(egraph, roots);
+    let mut uses: FxHashMap<ClassId, usize> = match result {
+        Some(result) => result.fanout(egraph, roots).into_iter().collect(),
+        None => {
+            let mut uses = FxHashMap::default();
+            for node in egraph.nodes.values() {
+                for child in &node.children {
+                    *uses.entry(*child).or_default() += 1;
+                }
+            }
+            uses
+        }
+    };
+    for root in roots {
+        *uses.entry(*root).or_default() += 1;
+    }
+
+    let mut ranking: Vec<Salience> = egraph
+        .classes()
+        .values()
+        .filter_map(|class| {
+            let mut costs: Vec<Cost> = class.nodes.iter().filter_map(|nid| tree.cost.get(nid).copied()).filter(|cost| *cost != INFINITY).collect();
+            costs.sort();
+            let best = *costs.first()?;
+            let second = costs.get(1).copied().unwrap_or(INFINITY);
+            let uses = uses.get(&class.id).copied().unwrap_or(0);
+            let mut salience = Salience { class: class.id, best, second, uses, score: 0.0 };
+            salience.score = salience.spread() * uses as f64;
+            Some(salience)
+        })
+        .collect();
+    // Ties by class, so that the report doesn't depend on the e-graph's order.
+    ranking.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.class.cmp(&b.class)));
+    ranking
+}
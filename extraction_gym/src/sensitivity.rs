@@ -0,0 +1,86 @@
+//! What-if questions about an extraction: what the best extraction costs, and
+//! which choices it changes, when a node is no longer available.
+
+use crate::*;
+use crate::faster_greedy_dag::FasterGreedyDagExtractor;
+
+#[derive(Clone)]
+pub struct Exclusion {
+    /// The extraction without the node, empty if the roots can't be extracted
+    /// without it.
+    pub result: ExtractionResult,
+    /// DAG cost of `result`, `INFINITY` if there is none.
+    pub dag_cost: Cost,
+    /// `dag_cost` minus the DAG cost of the original extraction.
+    pub delta: Cost,
+    /// The classes either extraction selects that don't select the same node in
+    /// the other, by class id.
+    pub changed: Vec<ClassId>,
+}
+
+impl Exclusion {
+    pub fn feasible(&self) -> bool {
+        self.dag_cost != INFINITY
+    }
+}
+
+/// The cheapest greedy extraction of the e-graph roots without `node_id`. If
+/// `result` doesn't select the node it stays as it is; otherwise the classes whose
+/// selection uses the node are extracted again with the others kept, and the
+/// whole e-graph is extracted again, keeping the cheaper of the two. Removing the
+/// node can leave other nodes without a child class and the roots without a
+/// term, which `EGraph::prune_unreachable` finds.
+pub fn exclude_node(egraph: &EGraph, result: &ExtractionResult, node_id: &NodeId) -> Exclusion {
+    let roots = &egraph.root_eclasses;
+    let before = result.dag_cost(egraph, roots);
+    let active = result.activate_nodes(egraph, roots);
+    if !active.contains(node_id) {
+        return Exclusion { result: result.clone(), dag_cost: before, delta: Cost::default(), changed: vec![] };
+    }
+
+    let mut without = egraph.clone();
+    without.nodes.shift_remove(node_id);
+    without.prune_unreachable(roots);
+    if roots.iter().any(|root| !without.classes().contains_key(root)) {
+        return Exclusion { result: ExtractionResult::default(), dag_cost: INFINITY, delta: INFINITY, changed: vec![] };
+    }
+
+    // The classes whose selected sub-DAG reaches the node's class.
+    let mut parents = FxHashMap::<ClassId, Vec<ClassId>>::default();
+    for nid in &active {
+        for child in &egraph[nid].children {
+            parents.entry(*child).or_default().push(egraph[nid].eclass);
+        }
+    }
+    let mut affected = FxHashSet::<ClassId>::default();
+    let mut todo = vec![*egraph.nid_to_cid(node_id)];
+    while let Some(cid) = todo.pop() {
+        if affected.insert(cid) {
+            todo.extend(parents.get(&cid).into_iter().flatten());
+        }
+    }
+    let frozen: IndexMap<ClassId, NodeId> = active
+        .iter()
+        .filter(|nid| !affected.contains(egraph.nid_to_cid(nid)) && without.nodes.contains_key(*nid))
+        .map(|nid| (*egraph.nid_to_cid(nid), *nid))
+        .collect();
+
+    let extractor = FasterGreedyDagExtractor::default();
+    let best = [extractor.extract_frozen(&without, roots, &frozen), extractor.extract(&without, roots)]
+        .into_iter()
+        .filter(|candidate| candidate.is_valid(&without))
+        .map(|candidate| (candidate.dag_cost(&without, roots), candidate))
+        .min_by_key(|(cost, _)| *cost);
+    let Some((dag_cost, after)) = best else {
+        return Exclusion { result: ExtractionResult::default(), dag_cost: INFINITY, delta: INFINITY, changed: vec![] };
+    };
+
+    let selected = |extraction: &ExtractionResult, graph: &EGraph| -> FxHashMap<ClassId, NodeId> {
+        extraction.activate_nodes(graph, roots).into_iter().map(|nid| (*graph.nid_to_cid(&nid), nid)).collect()
+    };
+    let (old, new) = (selected(result, egraph), selected(&after, &without));
+    let mut changed: Vec<ClassId> = old.keys().chain(new.keys()).filter(|cid| old.get(cid) != new.get(cid)).copied().collect();
+    changed.sort();
+    changed.dedup();
+    Exclusion { result: after, dag_cost, delta: dag_cost - before, changed }
+}
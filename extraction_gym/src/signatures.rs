@@ -0,0 +1,60 @@
+use crate::*;
+
+/// Child types expected by each op, checked against the `type` of the children's
+/// classes (`ClassData::typ`).
+///
+/// Some frontends put ill-typed alternatives into an e-class, e.g. an `add` of a
+/// boolean and an integer next to a well-typed one. Applied to the e-graph data
+/// before extraction, the nodes whose children don't have the expected types are
+/// removed, so neither the extractors nor the generated LP can select them. Ops
+/// without an entry, and children of classes without a type, are not checked; `*`
+/// accepts any type.
+#[derive(Clone, Debug, Default)]
+pub struct Signatures {
+    pub children: HashMap<String, Vec<String>>,
+}
+
+impl Signatures {
+    /// Reads a JSON object mapping ops to their child types, e.g.
+    /// `{"add": ["Int", "Int"], "ite": ["Bool", "*", "*"]}`.
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let children: HashMap<String, Vec<String>> = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Ok(Self { children })
+    }
+
+    /// Whether `node`'s children have the types its op expects.
+    pub fn well_typed(&self, node: &Node, class_data: &IndexMap<ClassId, ClassData>) -> bool {
+        let Some(expected) = self.children.get(&node.op) else {
+            return true;
+        };
+        expected.len() == node.children.len()
+            && node.children.iter().zip(expected).all(|(child, expected)| {
+                expected == "*"
+                    || class_data
+                        .get(child)
+                        .and_then(|data| data.typ.as_ref())
+                        .map_or(true, |typ| typ == expected)
+            })
+    }
+
+    /// Removes the ill-typed nodes from `data`, and then the nodes left with a child
+    /// class without nodes. Returns how many nodes were removed, or the first root
+    /// class that lost all its nodes.
+    pub fn apply(&self, data: &mut Data) -> Result<usize, ClassId> {
+        let before = data.nodes.len();
+        let class_data = data.class_data.clone();
+        data.nodes.retain(|_, node| self.well_typed(node, &class_data));
+        loop {
+            let classes: FxHashSet<ClassId> = data.nodes.values().map(|node| node.eclass).collect();
+            let len = data.nodes.len();
+            data.nodes.retain(|_, node| node.children.iter().all(|child| classes.contains(child)));
+            if data.nodes.len() == len {
+                if let Some(root) = data.root_eclasses.iter().find(|root| !classes.contains(root)) {
+                    return Err(*root);
+                }
+                return Ok(before - data.nodes.len());
+            }
+        }
+    }
+}
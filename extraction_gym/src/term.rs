@@ -0,0 +1,120 @@
+//! The extracted program as a term, see [`ExtractionResult::to_term`].
+
+use crate::*;
+use std::fmt;
+
+/// A node of a `Term`, with its children as indices into `Term::nodes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermNode {
+    pub op: String,
+    pub node: NodeId,
+    pub children: Vec<usize>,
+}
+
+/// The term an extraction selects for one class, as a DAG: every class below it
+/// is one node, whatever the number of its uses. Children come before their
+/// parents, the root is the last node.
+///
+/// Displayed as an s-expression, `(op child ...)` or `op` for a leaf, with a
+/// shared subterm written out at every use; `shared` writes it once instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Term {
+    pub nodes: Vec<TermNode>,
+}
+
+impl ExtractionResult {
+    /// The term rooted at `root`. Panics if a class it needs has no choice, or on a
+    /// cycle, see `validate`.
+    pub fn to_term(&self, egraph: &EGraph, root: ClassId) -> Term {
+        let mut term = Term { nodes: Vec::new() };
+        let mut index = FxHashMap::<ClassId, usize>::default();
+        let mut visiting = FxHashSet::<ClassId>::default();
+        self.add_term_node(egraph, root, &mut term, &mut index, &mut visiting);
+        term
+    }
+
+    fn add_term_node(
+        &self,
+        egraph: &EGraph,
+        cid: ClassId,
+        term: &mut Term,
+        index: &mut FxHashMap<ClassId, usize>,
+        visiting: &mut FxHashSet<ClassId>,
+    ) -> usize {
+        if let Some(&i) = index.get(&cid) {
+            return i;
+        }
+        let node_id = *self.choices.get(&cid).unwrap_or_else(|| panic!("class {} has no choice", cid));
+        assert!(visiting.insert(cid), "the choice of class {} is on a cycle", cid);
+        let node = &egraph[&node_id];
+        let children = node.children.iter().map(|child| self.add_term_node(egraph, *child, term, index, visiting)).collect();
+        visiting.remove(&cid);
+        term.nodes.push(TermNode { op: node.op.clone(), node: node_id, children });
+        index.insert(cid, term.nodes.len() - 1);
+        term.nodes.len() - 1
+    }
+}
+
+impl Term {
+    pub fn root(&self) -> &TermNode {
+        self.nodes.last().expect("a term has a root")
+    }
+
+    /// Displays the term with every subterm used more than once bound by an
+    /// egglog `let` of its own, e.g. `(let t0 (* x y))` then `(+ t0 t0)`, one per
+    /// line. Unlike `Display`, the output grows with the DAG rather than the tree.
+    pub fn shared(&self) -> SharedTerm<'_> {
+        SharedTerm(self)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, i: usize, names: &[Option<String>]) -> fmt::Result {
+        let node = &self.nodes[i];
+        if node.children.is_empty() {
+            return write!(f, "{}", node.op);
+        }
+        write!(f, "({}", node.op)?;
+        for &child in &node.children {
+            write!(f, " ")?;
+            match &names[child] {
+                Some(name) => write!(f, "{}", name)?,
+                None => self.write(f, child, names)?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, self.nodes.len() - 1, &vec![None; self.nodes.len()])
+    }
+}
+
+/// See `Term::shared`.
+pub struct SharedTerm<'a>(&'a Term);
+
+impl fmt::Display for SharedTerm<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let term = self.0;
+        let mut uses = vec![0; term.nodes.len()];
+        for node in &term.nodes {
+            for &child in &node.children {
+                uses[child] += 1;
+            }
+        }
+        // Children come first, so every binding is written before its uses.
+        let mut names = vec![None; term.nodes.len()];
+        let mut bound = 0;
+        for (i, node) in term.nodes.iter().enumerate() {
+            if uses[i] > 1 && !node.children.is_empty() {
+                let name = format!("t{}", bound);
+                bound += 1;
+                write!(f, "(let {} ", name)?;
+                term.write(f, i, &names)?;
+                writeln!(f, ")")?;
+                names[i] = Some(name);
+            }
+        }
+        term.write(f, term.nodes.len() - 1, &names)
+    }
+}
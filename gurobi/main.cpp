@@ -5,6 +5,7 @@
 #include <filesystem>
 #include <fstream>
 #include <iomanip>
+#include <sstream>
 #include <chrono>
 #include <cmath>
 #include "gurobi_c++.h"
@@ -89,8 +90,8 @@ protected:
             // Always log if solution is better, regardless of pool directory
             if (is_better) {
                 best_obj = obj; // Update best objective
-                // Simple log format: time: objective
-                log << elapsed_seconds << ": " << obj << std::endl;
+                // Simple log format: time: objective bound
+                log << elapsed_seconds << ": " << obj << " " << getDoubleInfo(GRB_CB_MIPSOL_OBJBND) << std::endl;
                 
                 // Print to console
                 std::cout << "Improved incumbent found at " << elapsed_seconds 
@@ -139,6 +140,46 @@ protected:
     }
 };
 
+// Computes an irreducible infeasible subsystem of the model and writes its members,
+// one per line: "row <constraint>", "lb <variable>" or "ub <variable>".
+void writeIIS(GRBModel& model, const std::string& iis_file) {
+    model.computeIIS();
+    std::ofstream out(iis_file);
+    if (!out.is_open()) {
+        std::cerr << "Failed to open IIS file: " << iis_file << std::endl;
+        return;
+    }
+
+    GRBConstr* constrs = model.getConstrs();
+    for (int i = 0; i < model.get(GRB_IntAttr_NumConstrs); i++) {
+        if (constrs[i].get(GRB_IntAttr_IISConstr)) {
+            out << "row " << constrs[i].get(GRB_StringAttr_ConstrName) << std::endl;
+        }
+    }
+    delete[] constrs;
+
+    // Indicator constraints are general constraints.
+    GRBGenConstr* gen_constrs = model.getGenConstrs();
+    for (int i = 0; i < model.get(GRB_IntAttr_NumGenConstrs); i++) {
+        if (gen_constrs[i].get(GRB_IntAttr_IISGenConstr)) {
+            out << "row " << gen_constrs[i].get(GRB_StringAttr_GenConstrName) << std::endl;
+        }
+    }
+    delete[] gen_constrs;
+
+    GRBVar* vars = model.getVars();
+    for (int i = 0; i < model.get(GRB_IntAttr_NumVars); i++) {
+        if (vars[i].get(GRB_IntAttr_IISLB)) {
+            out << "lb " << vars[i].get(GRB_StringAttr_VarName) << std::endl;
+        }
+        if (vars[i].get(GRB_IntAttr_IISUB)) {
+            out << "ub " << vars[i].get(GRB_StringAttr_VarName) << std::endl;
+        }
+    }
+    delete[] vars;
+    std::cout << "IIS written to: " << iis_file << std::endl;
+}
+
 int main(int argc, char* argv[]) {
     try {
         // Parse command line arguments
@@ -158,7 +199,7 @@ int main(int argc, char* argv[]) {
         if (missingParams) {
             std::cerr << "Usage: " << argv[0] 
                       << " --lp_file <file> --output_file <file> --log_file <file> "
-                      << "[--mst_file <file>] [--time_limit <seconds>] [--solution_pool_dir <dir>]" 
+                      << "[--mst_file <file>] [--extra_mst_files <file,...>] [--time_limit <seconds>] [--solution_pool_dir <dir>] [--iis_file <file>]" 
                       << std::endl;
             return 1;
         }
@@ -172,6 +213,7 @@ int main(int argc, char* argv[]) {
         std::string mst_file = "";
         double time_limit = GRB_INFINITY;
         std::string solution_pool_dir = "";
+        std::string iis_file = "";
         
         // Process optional parameters
         if (params.find("mst_file") != params.end()) {
@@ -193,6 +235,24 @@ int main(int argc, char* argv[]) {
             }
         }
         
+        if (params.find("iis_file") != params.end()) {
+            iis_file = params["iis_file"];
+        }
+        
+        // Further MIP starts, comma separated
+        std::vector<std::string> extra_mst_files;
+        if (params.find("extra_mst_files") != params.end()) {
+            std::stringstream files(params["extra_mst_files"]);
+            std::string file;
+            while (std::getline(files, file, ',')) {
+                if (std::filesystem::exists(file)) {
+                    extra_mst_files.push_back(file);
+                } else {
+                    std::cerr << "Warning: MST file does not exist: " << file << std::endl;
+                }
+            }
+        }
+        
         if (params.find("solution_pool_dir") != params.end()) {
             solution_pool_dir = params["solution_pool_dir"];
             // Create the directory if it doesn't exist
@@ -238,6 +298,18 @@ int main(int argc, char* argv[]) {
             model.read(mst_file);
         }
         
+        // Every extra start gets its own slot; Gurobi completes and tries each of them
+        if (!extra_mst_files.empty()) {
+            int first = mst_file.empty() ? 0 : 1;
+            model.set(GRB_IntAttr_NumStart, first + (int)extra_mst_files.size());
+            model.update();
+            for (size_t k = 0; k < extra_mst_files.size(); k++) {
+                std::cout << "Loading MIP start " << first + k << " from: " << extra_mst_files[k] << std::endl;
+                model.set(GRB_IntParam_StartNumber, first + (int)k);
+                model.read(extra_mst_files[k]);
+            }
+        }
+        
         // Optimize the model
         std::cout << "Starting optimization..." << std::endl;
         model.optimize();
@@ -255,6 +327,9 @@ int main(int argc, char* argv[]) {
             std::cout << "Time limit reached. Best solution found will be used." << std::endl;
         } else if (status == GRB_INFEASIBLE) {
             std::cout << "Model is infeasible." << std::endl;
+            if (!iis_file.empty()) {
+                writeIIS(model, iis_file);
+            }
         } else if (status == GRB_UNBOUNDED) {
             std::cout << "Model is unbounded." << std::endl;
         } else {
@@ -289,6 +364,11 @@ int main(int argc, char* argv[]) {
         
         // Close log file
         logStream.close();
+
+        // Exit code 2 tells the caller that the model is infeasible, so that it can relax the pruning.
+        if (status == GRB_INFEASIBLE || status == GRB_INF_OR_UNBD) {
+            return 2;
+        }
         
     } catch (GRBException e) {
         std::cerr << "Gurobi error code: " << e.getErrorCode() << std::endl;
@@ -0,0 +1,118 @@
+// On-disk cache of extraction results and solver solutions.
+//
+// Benchmark campaigns rerun the same (e-graph, extractor, bound, solver)
+// combinations over and over; with `--cache <dir>` the CLI looks results up here
+// first and only re-verifies them against the e-graph on a hit.
+//
+// Entries are JSON files named after their key: the hash of the input file
+// contents, the extractor, the ordering mode (random or deterministic) and, for
+// solver solutions, the bound and the solver.
+
+use egraph_serialize::{ClassId, Cost, EGraph as SerializedEGraph, NodeId};
+use extraction_gym::{ExtractionResult, INFINITY};
+use serde_json::{json, Value};
+use std::hash::Hasher;
+use std::path::PathBuf;
+
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+impl ResultCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Stable hash of an input file, used as the e-graph part of the keys.
+    pub fn content_hash(bytes: &[u8]) -> String {
+        let mut hasher = rustc_hash::FxHasher::default();
+        hasher.write(bytes);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Hash of several inputs, each given with the flag it came from. Every input
+    /// is hashed after its flag and length, so the same file given to another
+    /// flag, or bytes moved from one input to the next, change the hash.
+    pub fn inputs_hash(inputs: &[(&str, &[u8])]) -> String {
+        let mut contents = Vec::new();
+        for (flag, bytes) in inputs {
+            contents.extend(flag.as_bytes());
+            contents.push(0);
+            contents.extend((bytes.len() as u64).to_le_bytes());
+            contents.extend(*bytes);
+        }
+        Self::content_hash(&contents)
+    }
+
+    pub fn heuristic_key(egraph_hash: &str, extractor: &str, deterministic: bool) -> String {
+        format!("{}_{}_{}", egraph_hash, extractor, ordering(deterministic))
+    }
+
+    pub fn solution_key(egraph_hash: &str, extractor: &str, deterministic: bool, bound: f32, solver: &str) -> String {
+        format!("{}_{}_{}_{}_{}", egraph_hash, extractor, ordering(deterministic), bound, solver)
+    }
+
+    /// The cached result for `key`, if there is one whose choices all exist in `egraph`.
+    pub fn get(&self, key: &str, egraph: &SerializedEGraph) -> Option<ExtractionResult> {
+        let contents = std::fs::read_to_string(self.path(key)).ok()?;
+        let value: Value = serde_json::from_str(&contents).ok()?;
+        let result = from_json(&value)?;
+        let consistent = result
+            .choices
+            .iter()
+            .all(|(cid, nid)| egraph.nodes.get(nid).is_some_and(|node| node.eclass == *cid));
+        if consistent {
+            Some(result)
+        } else {
+            eprintln!("Warning: Cached result {} does not match the e-graph, ignoring it", key);
+            None
+        }
+    }
+
+    pub fn put(&self, key: &str, result: &ExtractionResult) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path(key), to_json(result).to_string())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+fn ordering(deterministic: bool) -> &'static str {
+    if deterministic {
+        "deterministic"
+    } else {
+        "random"
+    }
+}
+
+fn to_json(result: &ExtractionResult) -> Value {
+    json!({
+        "choices": result.choices.iter().map(|(cid, nid)| json!([cid.0, nid.0[0], nid.0[1]])).collect::<Vec<_>>(),
+        "cost": result.cost.iter().map(|(nid, cost)| json!([nid.0[0], nid.0[1], cost.into_inner()])).collect::<Vec<_>>(),
+        "class_costs": result.class_costs.iter().map(|(cid, cost)| json!([cid.0, cost.into_inner()])).collect::<Vec<_>>(),
+    })
+}
+
+fn from_json(value: &Value) -> Option<ExtractionResult> {
+    let u32_at = |entry: &Value, i: usize| entry.get(i)?.as_u64().map(|v| v as u32);
+    // JSON has no infinity, serde_json writes it as null.
+    let cost_at = |entry: &Value, i: usize| match entry.get(i)? {
+        Value::Null => Some(INFINITY),
+        cost => Cost::new(cost.as_f64()?).ok(),
+    };
+
+    let mut result = ExtractionResult::new_empty();
+    for entry in value.get("choices")?.as_array()? {
+        let cid = ClassId(u32_at(entry, 0)?);
+        result.choose(cid, NodeId([u32_at(entry, 1)?, u32_at(entry, 2)?]));
+    }
+    for entry in value.get("cost")?.as_array()? {
+        result.cost.insert(NodeId([u32_at(entry, 0)?, u32_at(entry, 1)?]), cost_at(entry, 2)?);
+    }
+    for entry in value.get("class_costs")?.as_array()? {
+        result.class_costs.insert(ClassId(u32_at(entry, 0)?), cost_at(entry, 1)?);
+    }
+    Some(result)
+}
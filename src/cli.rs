@@ -0,0 +1,579 @@
+//! The command line of `extraction_tool`.
+//!
+//! `extract`, `gen-lp` and `solve` are the steps of a run on one e-graph. Without
+//! a subcommand, `--pre` selects them as it always has (`extraction_tool
+//! [OPTIONS] --pre <0-5> <INPUT.json>`), which is also how `bench` passes its
+//! options on to each run. The other subcommands are the tools around a run.
+
+use clap::builder::RangedU64ValueParser;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use e_boost::{corpus, ilp_gen, race, regression, synth};
+use extraction_gym::epsilon_greedy::EpsilonSchedule;
+use extraction_gym::passes::PassSchedule;
+use std::fmt::Display;
+
+#[derive(Parser)]
+#[command(
+    name = "extraction_tool",
+    version,
+    about = "E-graph extraction with adaptive heuristics and exact ILP solving",
+    args_conflicts_with_subcommands = true,
+    after_help = "Example: extraction_tool solve --bound 1.1 --solver gurobi input.json"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// E-graph of a run without subcommand
+    #[arg(value_name = "INPUT.json")]
+    pub input: Option<String>,
+    /// Steps of a run without subcommand: 0 solve the files of an earlier LP, 1 or 2 write the LP
+    /// without or with the heuristic's warm start, 3 or 4 also solve it, 5 the heuristic only
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(i32).range(0..=5))]
+    pub pre: i32,
+    #[command(flatten)]
+    pub common: CommonArgs,
+    #[command(flatten)]
+    pub lp: LpArgs,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the heuristic extractor only (--pre 5)
+    Extract(ExtractArgs),
+    /// Write the LP, pruned by the heuristic's result and warm-started from it (--pre 2)
+    GenLp(GenLpArgs),
+    /// Write the LP and solve it (--pre 4)
+    Solve(SolveArgs),
+    /// Run every benchmark of a registry with the given tags and summarize
+    #[command(alias = "batch")]
+    Bench(BenchArgs),
+    /// Split an e-graph into sub-e-graphs of whole classes, one JSON file each
+    Partition(PartitionArgs),
+    /// Extract the tradeoffs between DAG cost and depth (the Pareto front)
+    Pareto(ParetoArgs),
+    /// Compare two extractions of an e-graph: their choices and their costs by op
+    Diff(DiffArgs),
+    /// Serve extractions over HTTP
+    Serve(ServeArgs),
+    /// Extract from an e-graph serialized by egglog
+    Egglog(EgglogArgs),
+    /// Check, or update, the golden costs of the regression suite
+    Regression(RegressionArgs),
+    /// Generate a synthetic arithmetic benchmark
+    Synth(SynthArgs),
+    /// Take the parts of `distribute` runs
+    Worker(WorkerArgs),
+    /// Extract the parts of an e-graph on workers and stitch them together
+    Distribute(DistributeArgs),
+    /// Inspect an extraction interactively
+    Repl(ReplArgs),
+    /// Export per-node features and labels as Parquet (parquet feature)
+    Dataset(DatasetArgs),
+}
+
+/// The flags of every run on one e-graph: its preprocessing, the heuristic and
+/// the outputs.
+#[derive(Args, Clone)]
+pub struct CommonArgs {
+    /// Extractor name
+    #[arg(long, default_value = "faster-greedy-dag-mt1", value_parser = lowercase)]
+    pub extractor: String,
+    /// Load extractors from a shared library (plugins feature)
+    #[arg(long, value_name = "PATH.so")]
+    pub plugin: Vec<String>,
+    /// Rhai cost model: the scripted-cost extractor, and node weights if it has weight() (scripting feature)
+    #[arg(long, value_name = "FILE")]
+    pub cost_script: Option<String>,
+    /// Passes of faster-greedy-dag-mt1/-mt2, e.g. leaves,all:stable,all:200
+    #[arg(long, value_name = "SCHEDULE")]
+    pub passes: Option<PassSchedule>,
+    /// Tie-break of faster-greedy-dag*: cost, cost-depth, cost-size or ops:<op>,... [default: cost]
+    #[arg(long, value_name = "POLICY")]
+    pub choice_policy: Option<String>,
+    /// Visit classes and nodes in id order, so that repeated runs give the same extraction
+    #[arg(long)]
+    pub deterministic: bool,
+    /// Seed of epsilon-greedy; its k-th --starts entry uses seed + k
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Probability of a worse choice in epsilon-greedy, multiplied by d after every pass
+    #[arg(long, value_name = "E[:D]")]
+    pub epsilon: Option<EpsilonSchedule>,
+    /// Report the classes the extraction uses more than n times
+    #[arg(long, value_name = "N")]
+    pub fanout: Option<usize>,
+    /// Add w per use over --fanout to the faster-greedy-dag objective
+    #[arg(long, value_name = "W", requires = "fanout")]
+    pub fanout_weight: Option<f64>,
+    /// Write the classes ranked by cost spread of their best two nodes times their uses, as JSON
+    #[arg(long, value_name = "FILE")]
+    pub salience: Option<String>,
+    /// Also print the tree cost, DAG cost and depth of every root
+    #[arg(long)]
+    pub per_root: bool,
+    /// JSON op -> delay table for faster-weighted-depth and the weighted depth report
+    #[arg(long, value_name = "FILE")]
+    pub delays: Option<String>,
+    /// JSON weights of root classes, which scale the node costs below them
+    #[arg(long, value_name = "FILE")]
+    pub root_weights: Option<String>,
+    /// JSON op -> child types table; nodes whose children's class types differ are not extracted
+    #[arg(long, value_name = "FILE")]
+    pub signatures: Option<String>,
+    /// Partial solution file (N_<class>_<node> 1 lines) of the choices every extraction keeps
+    #[arg(long, value_name = "FILE")]
+    pub freeze: Option<String>,
+    /// Merge the classes whose nodes are the same once their children are, before extraction
+    #[arg(long)]
+    pub merge_classes: bool,
+    /// Remove the classes the roots don't reach and the nodes with a child without nodes, before extraction
+    #[arg(long)]
+    pub prune_unreachable: bool,
+    /// Split the e-graph into at most N parts, run the steps on them in parallel and stitch their results
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub partition: Option<usize>,
+    /// Keep heuristic results and solutions there, keyed by the input and the options, for later runs
+    #[arg(long, value_name = "DIR")]
+    pub cache: Option<String>,
+    /// Benchmark registry to compare with known optima [default: benchmark/corpus.json]
+    #[arg(long, value_name = "FILE")]
+    pub corpus: Option<String>,
+    /// Abort above this resident memory, and prune more to fit the LP under it
+    #[arg(long, value_name = "GB")]
+    pub mem_limit: Option<f64>,
+    /// Save the best extraction so far at this interval, with its history for plotting
+    #[arg(long, value_name = "MINUTES")]
+    pub snapshot: Option<f64>,
+    /// Append this run's costs and runtimes as a row of a CSV file
+    #[arg(long, value_name = "FILE")]
+    pub csv: Option<String>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+/// The files the extraction is written to.
+#[derive(Args, Clone)]
+#[command(next_help_heading = "Outputs")]
+pub struct OutputArgs {
+    /// Write the extracted circuit as a structural Verilog module
+    #[arg(long, value_name = "FILE")]
+    pub verilog: Option<String>,
+    /// Write the extracted circuit as an ASCII AIGER file
+    #[arg(long, value_name = "FILE")]
+    pub aiger: Option<String>,
+    /// JSON mapping of extra ops to AIG gadgets, used with --aiger
+    #[arg(long, value_name = "FILE", requires = "aiger")]
+    pub aig_map: Option<String>,
+    /// Write the extracted circuit as BLIF, one .subckt per node
+    #[arg(long, value_name = "FILE")]
+    pub blif: Option<String>,
+    /// Write the extracted choices and their costs as JSON
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<String>,
+    /// Write the extracted DAG (selected nodes only) as a Graphviz file
+    #[arg(long, value_name = "FILE")]
+    pub dot: Option<String>,
+    /// Write an HTML report: costs by op and root, solver timeline and the drawn DAG
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<String>,
+}
+
+/// The flags of the LP generation.
+#[derive(Args, Clone)]
+#[command(next_help_heading = "LP")]
+pub struct LpArgs {
+    /// Pruning bound on the heuristic's per-class costs, or auto to fit the LP in --lp-budget
+    #[arg(long, default_value = "1.25", value_parser = parse_bound)]
+    pub bound: Bound,
+    /// Binaries the LP of --bound auto may have, e.g. 500k or 2M [default: 2M]
+    #[arg(long, value_name = "N", value_parser = parse_count)]
+    pub lp_budget: Option<usize>,
+    /// ILP cycle encoding: bigM, indicator, lazy or cuts (indicator and lazy need gurobi or cplex)
+    #[arg(long, value_name = "ENC", default_value = "bigM", value_parser = parse_acyclicity)]
+    pub acyclicity: ilp_gen::Acyclicity,
+    /// Add w to the LP objective for every distinct op the extraction uses
+    #[arg(long, value_name = "W")]
+    pub op_weight: Option<f64>,
+    /// Extra heuristics, comma separated, run in parallel as further warm starts
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', value_parser = lowercase)]
+    pub starts: Vec<String>,
+    /// Also write the generated model in lp_solve's LP format
+    #[arg(long, value_name = "FILE")]
+    pub lpsolve: Option<String>,
+    /// Also write the generated model in free MPS format, with the LP's variable names
+    #[arg(long, value_name = "FILE")]
+    pub mps: Option<String>,
+    /// Write the LP section by section without building the model, resumably, and without warm start
+    #[arg(long)]
+    pub stream_lp: bool,
+}
+
+/// The flags of the solver run.
+#[derive(Args, Clone)]
+#[command(next_help_heading = "Solver")]
+pub struct SolverArgs {
+    /// Solver: gurobi, cplex, cpsat, highs or cbc, or race:<name>,<name>...
+    #[arg(long, default_value = "gurobi", value_parser = parse_solver)]
+    pub solver: String,
+    /// Time limit of the solver in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 1800, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: u64,
+    /// Print the solver's incumbent, bound and gap at this interval
+    #[arg(long, value_name = "SECONDS")]
+    pub monitor: Option<u64>,
+    /// Stop the solver once its gap is at most this (0.01 or 1%)
+    #[arg(long, value_name = "GAP", value_parser = parse_gap)]
+    pub target_gap: Option<f64>,
+    /// Gurobi's MIPGap, in process (gurobi feature)
+    #[arg(long, value_name = "GAP")]
+    pub mip_gap: Option<f64>,
+    /// Gurobi's Threads, in process (gurobi feature)
+    #[arg(long, value_name = "N")]
+    pub threads: Option<i32>,
+    /// Save the solver's best incumbent at this interval (gurobi, cplex, cpsat)
+    #[arg(long, value_name = "SECONDS")]
+    pub checkpoint: Option<u64>,
+    /// Warm-start from the last checkpoint with the time it had left
+    #[arg(long)]
+    pub resume_solve: bool,
+    /// Re-solve, pruned by the solution's costs, for as long as the DAG cost improves (with a warm start)
+    #[arg(long, conflicts_with = "op_weight")]
+    pub tighten: bool,
+}
+
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    #[command(flatten)]
+    pub common: CommonArgs,
+}
+
+#[derive(Args)]
+pub struct GenLpArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Write the LP of the whole e-graph, without running the heuristic (--pre 1)
+    #[arg(long)]
+    pub no_warm_start: bool,
+    #[command(flatten)]
+    pub common: CommonArgs,
+    #[command(flatten)]
+    pub lp: LpArgs,
+}
+
+#[derive(Args)]
+pub struct SolveArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Solve the LP of the whole e-graph, without running the heuristic (--pre 3)
+    #[arg(long)]
+    pub no_warm_start: bool,
+    /// Solve the files an earlier gen-lp run of this input, bound and solver wrote, without
+    /// loading the e-graph (--pre 0)
+    #[arg(long, conflicts_with = "no_warm_start")]
+    pub skip_lp: bool,
+    #[command(flatten)]
+    pub common: CommonArgs,
+    #[command(flatten)]
+    pub lp: LpArgs,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Benchmark registry
+    #[arg(long, value_name = "FILE", default_value = corpus::DEFAULT_CORPUS)]
+    pub corpus: String,
+    /// Only the benchmarks with this tag (repeatable); all of them without
+    #[arg(long)]
+    pub tag: Vec<String>,
+    /// Subcommand and options of each run, e.g. `solve --solver highs`, or the options of a run
+    /// without subcommand (`--pre 4 --solver highs`)
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "RUN ARGS")]
+    pub run_args: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct PartitionArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Number of parts at most
+    #[arg(long, default_value_t = 2, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub parts: usize,
+    /// Directory the parts are written to, as <name>_part<i>.json
+    #[arg(long, value_name = "DIR", default_value = "file/partition")]
+    pub out_dir: String,
+}
+
+#[derive(Args)]
+pub struct ParetoArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Depth the sweep stops at
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<u32>,
+    /// Write the front, one choices file per point, as JSON
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Solution or choices file
+    #[arg(value_name = "A.sol")]
+    pub a: String,
+    /// Solution or choices file to compare with
+    #[arg(value_name = "B.sol")]
+    pub b: String,
+    /// E-graph file both were extracted from
+    #[arg(long, value_name = "INPUT.json")]
+    pub egraph: String,
+    /// Write every differing choice and the costs of every op as JSON
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:8080")]
+    pub addr: String,
+}
+
+#[derive(Args)]
+pub struct EgglogArgs {
+    /// E-graph as serialized by egglog
+    #[arg(value_name = "EGGLOG.json")]
+    pub input: String,
+    /// Extractor name
+    #[arg(long, default_value = "faster-greedy-dag-mt1", value_parser = lowercase)]
+    pub extractor: String,
+    /// Refine the heuristic's result with this solver
+    #[arg(long, value_parser = parse_solver)]
+    pub solver: Option<String>,
+    /// Time limit of the solver in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 1800, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: u64,
+    /// Pruning bound of the solver's LP, none when negative
+    #[arg(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    pub bound: f32,
+    /// Write the extraction there rather than to the standard output
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<String>,
+}
+
+#[derive(Args)]
+pub struct RegressionArgs {
+    /// Golden costs
+    #[arg(long, value_name = "FILE", default_value = regression::DEFAULT_GOLDEN)]
+    pub golden: String,
+    /// Tolerance on the costs, the golden file's when not given
+    #[arg(long)]
+    pub epsilon: Option<f64>,
+    /// Write the current costs as the golden ones
+    #[arg(long)]
+    pub update: bool,
+    /// E-graphs to add to the golden file, relative to it
+    #[arg(value_name = "FILE")]
+    pub files: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct SynthArgs {
+    /// Circuit: adder-tree or multiplier
+    #[arg(long, default_value = "multiplier")]
+    pub kind: synth::Kind,
+    /// Width of the operands
+    #[arg(long, default_value_t = 8)]
+    pub bits: usize,
+    /// Number of operands
+    #[arg(long, default_value_t = 2)]
+    pub operands: usize,
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// E-graph file to write
+    #[arg(long, value_name = "FILE")]
+    pub out: String,
+}
+
+#[derive(Args)]
+pub struct WorkerArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:7878")]
+    pub addr: String,
+}
+
+#[derive(Args)]
+pub struct DistributeArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Workers, comma separated
+    #[arg(long, value_name = "HOST:PORT,...", value_delimiter = ',', required = true)]
+    pub workers: Vec<String>,
+    /// Number of parts [default: one per worker]
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub parts: Option<usize>,
+    /// Steps of each part: 3 or 4 to solve it without or with warm start, 5 the heuristic only
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(i32).range(3..=5))]
+    pub pre: i32,
+    /// Extractor name
+    #[arg(long, default_value = "faster-greedy-dag-mt1", value_parser = lowercase)]
+    pub extractor: String,
+    #[arg(long, default_value = "gurobi", value_parser = parse_solver)]
+    pub solver: String,
+    /// Time limit of the solver in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 1800, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: u64,
+    #[arg(long, default_value_t = 1.25)]
+    pub bound: f32,
+    /// Write the stitched choices and their costs as JSON
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ReplArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Extractor of the initial extraction
+    #[arg(long, default_value = "faster-greedy-dag-mt1", value_parser = lowercase)]
+    pub extractor: String,
+    /// Start from these choices instead
+    #[arg(long, value_name = "CHOICES.json", conflicts_with = "extractor")]
+    pub solution: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DatasetArgs {
+    /// E-graph file
+    #[arg(value_name = "INPUT.json")]
+    pub input: String,
+    /// Extractor of the heuristic labels
+    #[arg(long, default_value = "faster-greedy-dag-mt1", value_parser = lowercase)]
+    pub extractor: String,
+    /// Solution file of the optimal labels
+    #[arg(long, value_name = "FILE")]
+    pub solution: Option<String>,
+    #[arg(long, value_name = "DIR", default_value = "file/dataset")]
+    pub out_dir: String,
+}
+
+/// `--bound`: a factor on the heuristic's per-class costs, or `auto`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Factor(f32),
+    /// The bound whose LP fits `--lp-budget`, found by `pruning::auto_bound`.
+    Auto,
+}
+
+/// A run on one e-graph: the `--pre` steps of a subcommand, with the defaults of
+/// the flags it doesn't take.
+pub struct Run {
+    pub input: String,
+    pub pre: i32,
+    pub common: CommonArgs,
+    pub lp: LpArgs,
+    pub solver: SolverArgs,
+}
+
+impl Cli {
+    /// The run without subcommand.
+    pub fn into_run(self) -> Run {
+        let input = self.input.unwrap_or_else(|| usage_error(ErrorKind::MissingRequiredArgument, "no input e-graph given; see --help"));
+        Run { input, pre: self.pre, common: self.common, lp: self.lp, solver: self.solver }
+    }
+}
+
+impl ExtractArgs {
+    pub fn into_run(self) -> Run {
+        Run { input: self.input, pre: 5, common: self.common, lp: defaults(), solver: defaults() }
+    }
+}
+
+impl GenLpArgs {
+    pub fn into_run(self) -> Run {
+        let pre = if self.no_warm_start { 1 } else { 2 };
+        Run { input: self.input, pre, common: self.common, lp: self.lp, solver: defaults() }
+    }
+}
+
+impl SolveArgs {
+    pub fn into_run(self) -> Run {
+        let pre = match (self.skip_lp, self.no_warm_start) {
+            (true, _) => 0,
+            (false, true) => 3,
+            (false, false) => 4,
+        };
+        Run { input: self.input, pre, common: self.common, lp: self.lp, solver: self.solver }
+    }
+}
+
+/// Exits with a usage error, as for the checks clap makes itself.
+pub fn usage_error(kind: ErrorKind, message: impl Display) -> ! {
+    Cli::command().error(kind, message).exit()
+}
+
+/// The values of `T` when none of its flags is given.
+fn defaults<T: Args + FromArgMatches>() -> T {
+    let matches = T::augment_args(clap::Command::new("defaults")).get_matches_from(["defaults"]);
+    T::from_arg_matches(&matches).expect("The defaults of the flags parse")
+}
+
+fn lowercase(value: &str) -> Result<String, String> {
+    Ok(value.to_lowercase())
+}
+
+fn parse_solver(value: &str) -> Result<String, String> {
+    let solver = value.to_lowercase();
+    let known = |name: &str| ["gurobi", "cplex", "cpsat", "highs", "cbc"].contains(&name);
+    let race = race::backends(&solver).map(|backends| backends.len() >= 2 && backends.iter().all(|backend| known(backend)));
+    if known(&solver) || race == Some(true) {
+        Ok(solver)
+    } else {
+        Err("use gurobi, cplex, cpsat, highs, cbc or race: followed by two or more of them".to_string())
+    }
+}
+
+fn parse_bound(value: &str) -> Result<Bound, String> {
+    match value {
+        "auto" => Ok(Bound::Auto),
+        _ => value.parse().map(Bound::Factor).map_err(|_| "a number or auto".to_string()),
+    }
+}
+
+/// A count such as `2000000`, `2M` or `500k`.
+fn parse_count(value: &str) -> Result<usize, String> {
+    let value = value.to_lowercase();
+    let (digits, scale) = match (value.strip_suffix('m'), value.strip_suffix('k')) {
+        (Some(digits), _) => (digits, 1e6),
+        (_, Some(digits)) => (digits, 1e3),
+        _ => (value.as_str(), 1.0),
+    };
+    let count = digits.parse::<f64>().ok().map(|count| count * scale).filter(|count| *count >= 0.0);
+    count.map(|count| count as usize).ok_or_else(|| "a number of binaries such as 2000000 or 2M".to_string())
+}
+
+fn parse_acyclicity(value: &str) -> Result<ilp_gen::Acyclicity, String> {
+    ilp_gen::Acyclicity::from_name(value).ok_or_else(|| "use bigM, indicator, lazy or cuts".to_string())
+}
+
+/// A fraction, or a percentage with `%`.
+fn parse_gap(value: &str) -> Result<f64, String> {
+    match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|percent| percent / 100.0),
+        None => value.parse(),
+    }
+    .map_err(|_| "a fraction such as 0.01, or a percentage such as 1%".to_string())
+}
@@ -0,0 +1,82 @@
+//! Registry of benchmark e-graphs, `benchmark/corpus.json` for the bundled ones:
+//!
+//! ```json
+//! {
+//!   "benchmarks": [
+//!     { "name": "c2670", "path": "E-syn/c2670.json", "nodes": 13205, "classes": 6532,
+//!       "optimal_dag": null, "tags": ["e-syn", "iscas"] }
+//!   ]
+//! }
+//! ```
+//!
+//! Paths are relative to the corpus file. A benchmark that is not distributed with
+//! the repository has a `url` instead of (or next to) its path. `optimal_dag` is
+//! the best known DAG cost, proven optimal, which runs are compared with.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CORPUS: &str = "benchmark/corpus.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Corpus {
+    pub benchmarks: Vec<Benchmark>,
+    /// Directory the paths are relative to, the corpus file's.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Benchmark {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub nodes: Option<usize>,
+    #[serde(default)]
+    pub classes: Option<usize>,
+    #[serde(default)]
+    pub optimal_dag: Option<f64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Corpus {
+    pub fn from_json_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path.as_ref())?;
+        let mut corpus: Corpus = serde_json::from_reader(std::io::BufReader::new(file))?;
+        corpus.dir = path.as_ref().parent().unwrap_or(Path::new(".")).to_path_buf();
+        Ok(corpus)
+    }
+
+    /// The benchmarks that have all of `tags`, all of them if `tags` is empty.
+    pub fn select(&self, tags: &[String]) -> Vec<&Benchmark> {
+        self.benchmarks
+            .iter()
+            .filter(|benchmark| tags.iter().all(|tag| benchmark.tags.contains(tag)))
+            .collect()
+    }
+
+    /// Where `benchmark` is on disk, `None` if it isn't.
+    pub fn file(&self, benchmark: &Benchmark) -> Option<PathBuf> {
+        let path = self.dir.join(benchmark.path.as_ref()?);
+        path.exists().then_some(path)
+    }
+
+    /// The benchmark stored at `input`.
+    pub fn find(&self, input: impl AsRef<Path>) -> Option<&Benchmark> {
+        let input = input.as_ref().canonicalize().ok()?;
+        self.benchmarks
+            .iter()
+            .find(|benchmark| self.file(benchmark).and_then(|path| path.canonicalize().ok()).as_ref() == Some(&input))
+    }
+}
+
+impl Benchmark {
+    /// How far `dag` is above the known optimum, as a fraction.
+    pub fn gap(&self, dag: f64) -> Option<f64> {
+        self.optimal_dag.filter(|optimal| *optimal > 0.0).map(|optimal| (dag - optimal) / optimal)
+    }
+}
@@ -0,0 +1,189 @@
+//! OR-Tools CP-SAT in process (`cpsat` feature, through the `cp_sat` crate), so
+//! that `--solver cpsat` needs neither the `cpsat/cpsat` wrapper nor its
+//! redundancy JSON. The model is the ILP built in memory (`ilp_gen::build_model`):
+//! its rows are the cycle-free extraction constraints the wrapper encodes, with the
+//! pruned nodes fixed through their bounds and the warm start as hints.
+//!
+//! CP-SAT works on integers. The levels, continuous in the ILP, only take integer
+//! values in a selection anyway, the objective is scaled to integer costs, and the
+//! indicator constraints of `--acyclicity indicator` are turned back into big-M
+//! rows. Races, `--checkpoint` and `--monitor` still need the wrapper, and a
+//! signal does not stop the search early.
+
+use crate::ilp_model::{IlpModel, Sense, VarId};
+use crate::solver::{Monitor, SolveFailure, SolverFiles};
+use crate::timeline::{LogFormat, Timeline};
+use cp_sat::builder::{CpModelBuilder, IntVar, LinearExpr};
+use cp_sat::proto::{CpSolverResponse, CpSolverStatus, SatParameters};
+use e_boost_core::solution;
+use extraction_gym::ExtractionResult;
+use std::fs;
+
+/// Largest power of ten the objective is scaled by; costs with more decimals are rounded.
+const MAX_OBJECTIVE_SCALE: i32 = 6;
+
+/// Solves `model` in process, logging to the terminal.
+pub fn solve_model(model: &IlpModel, timeout_secs: u64) -> Result<ExtractionResult, SolveFailure> {
+    let (response, vars) = solve(model, timeout_secs, None);
+    read_solution(model, &response, &vars).map(|(solution, _)| solution)
+}
+
+/// `solve_model` within a `--solver cpsat` run: the final objective and bound go to
+/// `files.log_file` in the wrapper's incumbent format, so that the timeline is read
+/// as for the wrapper, and the values of the node variables to `files.result_file`
+/// for the caches.
+pub fn solve_with_files(model: &IlpModel, files: &SolverFiles, timeout_secs: u64, monitor: Monitor) -> Result<ExtractionResult, SolveFailure> {
+    if monitor.interval.is_some() || monitor.checkpoint.is_some() {
+        eprintln!("Warning: --monitor and --checkpoint need the CP-SAT wrapper, they do not apply in process");
+    }
+    // None may be taken from an earlier run.
+    let _ = fs::remove_file(&files.result_file);
+    let _ = fs::remove_file(&files.log_file);
+
+    println!("Solving the {} variables of {} with CP-SAT in process", model.vars.len(), files.lp_file);
+    println!("-----------------------------------------------------");
+    let (response, vars) = solve(model, timeout_secs, monitor.target_gap);
+    println!("-----------------------------------------------------");
+
+    // Without a solution observer only the last incumbent is known.
+    let scale = objective_scale(model);
+    let log = match response.status() {
+        CpSolverStatus::Optimal | CpSolverStatus::Feasible => {
+            format!("{} {} {}\n", response.wall_time, response.objective_value / scale, response.best_objective_bound / scale)
+        }
+        _ => String::new(),
+    };
+    fs::write(&files.log_file, &log).expect("Unable to write file");
+    let warm_start = model.vars.iter().any(|var| var.hint.is_some());
+    let timeline = Timeline::from_log("cpsat", LogFormat::Incumbents, warm_start, &log);
+    timeline.to_json_file(&files.timeline_file).expect("Unable to write file");
+    println!("Objective timeline ({} points) written to {}", timeline.points.len(), files.timeline_file);
+
+    let (solution, values) = read_solution(model, &response, &vars)?;
+    let mut sol = String::new();
+    for (var, value) in model.vars.iter().zip(&values).filter(|(var, _)| var.name.starts_with("N_")) {
+        sol.push_str(&format!("{} {}\n", var.name, value));
+    }
+    fs::write(&files.result_file, sol).expect("Unable to write file");
+    println!("Solution found with solver: cpsat");
+    Ok(solution)
+}
+
+/// Runs CP-SAT on the integer model of `model`, whose variables are returned in the
+/// order of `model.vars`.
+fn solve(model: &IlpModel, timeout_secs: u64, relative_gap: Option<f64>) -> (CpSolverResponse, Vec<IntVar>) {
+    let (cp_model, vars) = build(model);
+    let params = SatParameters {
+        max_time_in_seconds: Some(timeout_secs as f64),
+        relative_gap_limit: relative_gap,
+        log_search_progress: Some(true),
+        ..Default::default()
+    };
+    let response = cp_model.solve_with_parameters(&params);
+    println!("CP-SAT status: {:?}", response.status());
+    (response, vars)
+}
+
+/// The chosen nodes and the values of all the variables of `model`.
+fn read_solution(model: &IlpModel, response: &CpSolverResponse, vars: &[IntVar]) -> Result<(ExtractionResult, Vec<f64>), SolveFailure> {
+    match response.status() {
+        CpSolverStatus::Optimal | CpSolverStatus::Feasible => {}
+        CpSolverStatus::Infeasible => return Err(SolveFailure::Infeasible),
+        CpSolverStatus::ModelInvalid => panic!("CP-SAT rejected the model: {}", response.solution_info),
+        // Stopped at the time limit before finding a solution.
+        CpSolverStatus::Unknown => return Err(SolveFailure::NoSolution),
+    }
+    let values: Vec<f64> = vars.iter().map(|var| var.solution_value(response) as f64).collect();
+    let solution = solution::from_values(model.vars.iter().map(|var| var.name.as_str()).zip(values.iter().copied()))
+        .unwrap_or_else(|err| panic!("CP-SAT: {}", err));
+    Ok((solution, values))
+}
+
+/// The CP-SAT model of `model`, with the hints as CP-SAT hints.
+fn build(model: &IlpModel) -> (CpModelBuilder, Vec<IntVar>) {
+    let mut cp_model = CpModelBuilder::default();
+    let bounds: Vec<(i64, i64)> = model
+        .vars
+        .iter()
+        .map(|var| {
+            if !var.lower.is_finite() || !var.upper.is_finite() {
+                panic!("CP-SAT needs bounded variables, {} is not", var.name);
+            }
+            (var.lower.ceil() as i64, var.upper.floor() as i64)
+        })
+        .collect();
+    let vars: Vec<IntVar> = bounds.iter().map(|&domain| cp_model.new_int_var([domain])).collect();
+
+    for constraint in &model.constraints {
+        let terms: Vec<(i64, VarId)> =
+            constraint.terms.iter().map(|(var, coeff)| (integer(*coeff, &constraint.name), *var)).collect();
+        let rhs = integer(constraint.rhs, &constraint.name);
+        // The smallest and largest values the left-hand side takes within the bounds.
+        let (min_activity, max_activity) = terms.iter().fold((0, 0), |(min, max), (coeff, var)| {
+            let (lower, upper) = bounds[var.0];
+            (min + (coeff * lower).min(coeff * upper), max + (coeff * lower).max(coeff * upper))
+        });
+        let senses = match (constraint.sense, constraint.indicator) {
+            (Sense::Eq, Some(_)) => vec![Sense::Le, Sense::Ge],
+            (sense, _) => vec![sense],
+        };
+        for sense in senses {
+            let mut row = terms.clone();
+            let mut rhs = rhs;
+            if let Some((var, value)) = constraint.indicator {
+                // Relaxed by as much as the row can be violated when `var != value`:
+                // `terms <= rhs + big_m * off` (`>=` and `-` for `Ge`), with off = 1 - var
+                // for value 1 and off = var for value 0.
+                let (sign, big_m) = match sense {
+                    Sense::Ge => (-1, rhs - min_activity),
+                    _ => (1, max_activity - rhs),
+                };
+                if big_m <= 0 {
+                    continue;
+                }
+                if value == 1.0 {
+                    row.push((sign * big_m, var));
+                    rhs += sign * big_m;
+                } else {
+                    row.push((-sign * big_m, var));
+                }
+            }
+            let lhs: LinearExpr = row.iter().map(|(coeff, var)| (*coeff, vars[var.0])).collect();
+            match sense {
+                Sense::Le => cp_model.add_le(lhs, rhs),
+                Sense::Ge => cp_model.add_ge(lhs, rhs),
+                Sense::Eq => cp_model.add_eq(lhs, rhs),
+            };
+        }
+    }
+
+    let scale = objective_scale(model);
+    let objective: LinearExpr = model.objective.iter().map(|(var, coeff)| ((coeff * scale).round() as i64, vars[var.0])).collect();
+    cp_model.minimize(objective);
+
+    for (var, hint) in vars.iter().zip(&model.vars).filter_map(|(var, variable)| Some((var, variable.hint?))) {
+        cp_model.add_hint(*var, hint.round() as i64);
+    }
+    (cp_model, vars)
+}
+
+/// The power of ten that turns the objective coefficients into integers.
+fn objective_scale(model: &IlpModel) -> f64 {
+    (0..=MAX_OBJECTIVE_SCALE)
+        .map(|digits| 10f64.powi(digits))
+        .find(|scale| model.objective.iter().all(|(_, coeff)| is_integral(coeff * scale)))
+        .unwrap_or(10f64.powi(MAX_OBJECTIVE_SCALE))
+}
+
+fn is_integral(value: f64) -> bool {
+    (value - value.round()).abs() <= 1e-9 * value.abs().max(1.0)
+}
+
+/// `value` as an integer; the constraint rows of the extraction ILP only have integer
+/// coefficients.
+fn integer(value: f64, constraint: &str) -> i64 {
+    if !is_integral(value) {
+        panic!("CP-SAT needs integer coefficients, {} has {}", constraint, value);
+    }
+    value.round() as i64
+}
@@ -0,0 +1,92 @@
+// Parquet export of e-graphs and their extraction labels, for training learned
+// cost models / extractors on the benchmark corpus.
+//
+// cargo run --release --features parquet -- dataset [--extractor <n>] [--solution <file.sol>] [--out-dir <dir>] <input.json>
+//
+// Two tables are written per e-graph:
+//   <name>_nodes.parquet  egraph, node_id, class_id, op, cost, num_children, root_class,
+//                         heuristic (node used by the heuristic extraction),
+//                         optimal (node used by the solver solution, null without --solution)
+//   <name>_edges.parquet  egraph, node_id, child_class, position
+
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, StringArray, UInt32Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use egraph_serialize::{EGraph as SerializedEGraph, NodeId};
+use extraction_gym::ExtractionResult;
+use parquet::arrow::ArrowWriter;
+use rustc_hash::FxHashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+pub fn write_dataset(
+    egraph: &SerializedEGraph,
+    name: &str,
+    heuristic: &ExtractionResult,
+    optimal: Option<&ExtractionResult>,
+    out_dir: &Path,
+) -> anyhow::Result<()> {
+    let roots = &egraph.root_eclasses;
+    let heuristic: FxHashSet<NodeId> = heuristic.activate_nodes(egraph, roots);
+    let optimal: Option<FxHashSet<NodeId>> = optimal.map(|r| r.activate_nodes(egraph, roots));
+    let root_set: FxHashSet<_> = roots.iter().collect();
+
+    let nodes = &egraph.nodes;
+    let node_table = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("egraph", DataType::Utf8, false),
+            Field::new("node_id", DataType::Utf8, false),
+            Field::new("class_id", DataType::UInt32, false),
+            Field::new("op", DataType::Utf8, false),
+            Field::new("cost", DataType::Float64, false),
+            Field::new("num_children", DataType::UInt32, false),
+            Field::new("root_class", DataType::Boolean, false),
+            Field::new("heuristic", DataType::Boolean, false),
+            Field::new("optimal", DataType::Boolean, true),
+        ])),
+        vec![
+            Arc::new(StringArray::from(vec![name; nodes.len()])) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(nodes.keys().map(|nid| nid.to_string()))),
+            Arc::new(UInt32Array::from_iter_values(nodes.values().map(|n| n.eclass.0))),
+            Arc::new(StringArray::from_iter_values(nodes.values().map(|n| n.op.as_str()))),
+            Arc::new(Float64Array::from_iter_values(nodes.values().map(|n| n.cost.into_inner()))),
+            Arc::new(UInt32Array::from_iter_values(nodes.values().map(|n| n.children.len() as u32))),
+            Arc::new(BooleanArray::from_iter(nodes.values().map(|n| Some(root_set.contains(&n.eclass))))),
+            Arc::new(BooleanArray::from_iter(nodes.keys().map(|nid| Some(heuristic.contains(nid))))),
+            Arc::new(BooleanArray::from_iter(nodes.keys().map(|nid| optimal.as_ref().map(|o| o.contains(nid))))),
+        ],
+    )?;
+
+    let edges: Vec<(&NodeId, u32, u32)> = nodes
+        .iter()
+        .flat_map(|(nid, n)| n.children.iter().enumerate().map(move |(i, c)| (nid, c.0, i as u32)))
+        .collect();
+    let edge_table = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("egraph", DataType::Utf8, false),
+            Field::new("node_id", DataType::Utf8, false),
+            Field::new("child_class", DataType::UInt32, false),
+            Field::new("position", DataType::UInt32, false),
+        ])),
+        vec![
+            Arc::new(StringArray::from(vec![name; edges.len()])) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(edges.iter().map(|e| e.0.to_string()))),
+            Arc::new(UInt32Array::from_iter_values(edges.iter().map(|e| e.1))),
+            Arc::new(UInt32Array::from_iter_values(edges.iter().map(|e| e.2))),
+        ],
+    )?;
+
+    std::fs::create_dir_all(out_dir)?;
+    write_parquet(&out_dir.join(format!("{}_nodes.parquet", name)), &node_table)?;
+    write_parquet(&out_dir.join(format!("{}_edges.parquet", name)), &edge_table)?;
+    Ok(())
+}
+
+fn write_parquet(path: &Path, batch: &RecordBatch) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+    println!("{}", path.display());
+    Ok(())
+}
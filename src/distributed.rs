@@ -0,0 +1,343 @@
+//! Coordinator/worker mode: the parts of an e-graph (`e_boost_core::partition`)
+//! are extracted, or solved, on other hosts.
+//!
+//! ```text
+//! extraction_tool worker --addr 0.0.0.0:7878            # on every host
+//! extraction_tool distribute --workers a:7878,b:7878 --parts 8 --pre 4 --solver cpsat input.json
+//! ```
+//!
+//! The protocol is lines over TCP. A task is a header line
+//!
+//! ```json
+//! {"part": 3, "name": "c2670", "extractor": "faster-greedy-dag-mt1", "pre": 4, "solver": "cpsat", "timeout": 600, "bound": 1.25}
+//! ```
+//!
+//! followed by the part's e-graph on one line, as `Data::to_json_writer` writes
+//! it. The worker runs `pipeline::run` on it, with files named after `name`, the
+//! part and the connection (`c2670_part3_c1`), and answers with one line,
+//! `{"part": 3, "runtime": 1.2, "result": <choices file>}` or
+//! `{"part": 3, "error": "..."}`, then waits for the next task. A name that is no
+//! plain file name (with `/`, `\` or `..`) gets an error.
+//!
+//! The coordinator keeps every worker busy with the parts left, one at a time,
+//! and prints the results as they come back. The parts of a worker that goes away
+//! go to the others; the ones that failed or that no worker took are run locally.
+//! The choices of the parts are then stitched together and checked on the whole
+//! e-graph. A partition drops the children outside each part, so the stitched
+//! choices can have cycles: the classes on them are extracted again with
+//! `extractor`, the others kept. Should that still not be valid, the whole
+//! e-graph is extracted locally.
+//!
+//! `run_partitioned` is the same without workers, the parts run on threads of
+//! this process (`--partition` of a run).
+
+use crate::pipeline::{self, PreMode};
+use anyhow::{anyhow, bail, Context};
+use crate::extractor;
+use e_boost_core::partition::{partition, stitch};
+use egraph_serialize::{ClassId, Data, EGraph as SerializedEGraph, NodeId};
+use extraction_gym::choices_file::ChoicesFile;
+use extraction_gym::ExtractionResult;
+use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use rayon::prelude::*;
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Instant;
+
+/// Where a worker writes the LP and solver files of its parts.
+const WORKER_DIR: &str = "file/worker";
+
+#[derive(Serialize, Deserialize)]
+struct Task {
+    part: usize,
+    /// What the files of the part are named after, the input's stem.
+    name: String,
+    extractor: String,
+    /// The `--pre` flag, 3 to 5.
+    pre: i32,
+    solver: String,
+    timeout: u64,
+    bound: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Reply {
+    part: usize,
+    #[serde(default)]
+    runtime: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Runs the tasks of every coordinator that connects, one thread per connection.
+pub fn worker(listener: TcpListener) {
+    // Numbers the connections, so that two coordinators sending the same part
+    // don't write the same files.
+    let connections = AtomicUsize::new(0);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let connection = connections.fetch_add(1, Ordering::Relaxed) + 1;
+                std::thread::spawn(move || {
+                    if let Err(err) = run_tasks(stream, connection) {
+                        eprintln!("Coordinator connection failed: {:#}", err);
+                    }
+                });
+            }
+            Err(err) => eprintln!("Failed to accept a connection: {}", err),
+        }
+    }
+}
+
+fn run_tasks(stream: TcpStream, connection: usize) -> anyhow::Result<()> {
+    let peer = stream.peer_addr()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(());
+        }
+        let mut egraph = String::new();
+        reader.read_line(&mut egraph)?;
+        let task: Task = serde_json::from_str(&header).context("Invalid task header")?;
+        println!("Part {} of {:?} from {}", task.part, task.name, peer);
+        let start = Instant::now();
+        let reply = match run_task(&task, &egraph, connection) {
+            Ok(choices) => Reply { part: task.part, runtime: start.elapsed().as_secs_f64(), result: Some(choices.to_json()), error: None },
+            Err(err) => {
+                eprintln!("Part {} of {:?} failed: {:#}", task.part, task.name, err);
+                Reply { part: task.part, runtime: start.elapsed().as_secs_f64(), result: None, error: Some(format!("{:#}", err)) }
+            }
+        };
+        writeln!(writer, "{}", serde_json::to_string(&reply)?)?;
+    }
+}
+
+fn run_task(task: &Task, egraph: &str, connection: usize) -> anyhow::Result<ChoicesFile> {
+    let name = file_name(task, connection)?;
+    let pre_mode = PreMode::from_flag(task.pre).filter(|mode| mode.flag() >= 3).with_context(|| format!("Invalid pre flag {}", task.pre))?;
+    let options = pipeline::Options {
+        extractor: task.extractor.clone(),
+        bound: task.bound,
+        solver: task.solver.clone(),
+        timeout: task.timeout,
+        pre_mode,
+        out_dir: PathBuf::from(WORKER_DIR),
+        name,
+        in_memory: pre_mode == PreMode::Heuristic,
+        ..pipeline::Options::default()
+    };
+    let data = Data::from_json_reader(egraph.as_bytes()).context("Invalid e-graph")?;
+    let egraph = SerializedEGraph::from_Data(&data)?;
+    // Some extractors still report a failure by panicking.
+    let output = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pipeline::run(&egraph, options)))
+        .map_err(|_| anyhow!("extraction panicked"))??;
+    Ok(ChoicesFile::from_result(&egraph, &output.result, &egraph.root_eclasses))
+}
+
+/// What the worker's files of `task` are named after. The name comes from the
+/// network, so it may not leave `WORKER_DIR`.
+fn file_name(task: &Task, connection: usize) -> anyhow::Result<String> {
+    let name = &task.name;
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        bail!("Invalid task name {:?}", name);
+    }
+    let stem: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+    Ok(format!("{}_part{}_c{}", stem, task.part, connection))
+}
+
+/// A part's extraction and where it ran.
+pub struct PartResult {
+    pub worker: String,
+    pub runtime: f64,
+    pub result: ExtractionResult,
+}
+
+pub struct Distributed {
+    pub result: ExtractionResult,
+    /// Whether `result` is the stitched parts; the whole e-graph was extracted
+    /// locally otherwise.
+    pub stitched: bool,
+    /// Classes on cycles of the stitched parts, extracted again.
+    pub repaired: usize,
+    /// In the order of the parts, `worker` "local" for the ones run here.
+    pub parts: Vec<PartResult>,
+}
+
+/// Splits `data` into `parts` parts, runs `options.pre_mode` on them on `workers`
+/// (`host:port`), and stitches their results into an extraction of the whole
+/// e-graph.
+pub fn distribute(data: &Data, workers: &[String], parts: usize, options: &pipeline::Options) -> anyhow::Result<Distributed> {
+    if options.pre_mode.flag() < 3 {
+        bail!("Workers run the heuristic or the solver, {:?} does neither", options.pre_mode);
+    }
+    let egraph = SerializedEGraph::from_Data(data)?;
+    let part_data = partition(data, 1.0 / parts as f32);
+    let part_egraphs = part_data.iter().map(SerializedEGraph::from_Data).collect::<std::io::Result<Vec<_>>>()?;
+    let count = part_data.len();
+    println!("Split into {} parts for {} workers", count, workers.len());
+
+    let queue = Mutex::new((0..count).collect::<VecDeque<usize>>());
+    let mut done: Vec<Option<PartResult>> = (0..count).map(|_| None).collect();
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for addr in workers {
+            let sender = sender.clone();
+            let (queue, part_data) = (&queue, &part_data);
+            scope.spawn(move || {
+                if let Err(err) = feed(addr, part_data, queue, options, &sender) {
+                    eprintln!("Lost worker {}: {:#}", addr, err);
+                }
+            });
+        }
+        drop(sender);
+        for (addr, reply) in receiver {
+            let part = reply.part;
+            let outcome = match (reply.result, reply.error) {
+                (Some(result), _) => ChoicesFile::from_json(&result).map(|file| file.to_result()),
+                (None, error) => Err(error.unwrap_or_else(|| "no result".to_string())),
+            };
+            match outcome {
+                Ok(result) if result.is_valid(&part_egraphs[part]) => {
+                    println!("Part {}/{} from {}: {} classes in {:.2}s", part + 1, count, addr, result.choices.len(), reply.runtime);
+                    done[part] = Some(PartResult { worker: addr.clone(), runtime: reply.runtime, result });
+                }
+                Ok(_) => eprintln!("Part {}/{} from {}: not a valid extraction of the part", part + 1, count, addr),
+                Err(err) => eprintln!("Part {}/{} failed on {}: {}", part + 1, count, addr, err),
+            }
+        }
+    });
+
+    for (part, slot) in done.iter_mut().enumerate() {
+        if slot.is_some() {
+            continue;
+        }
+        let start = Instant::now();
+        let local = local_options(options, part, &part_egraphs[part]);
+        let result = pipeline::run(&part_egraphs[part], local)?.result;
+        let runtime = start.elapsed().as_secs_f64();
+        println!("Part {}/{} locally: {} classes in {:.2}s", part + 1, count, result.choices.len(), runtime);
+        *slot = Some(PartResult { worker: "local".to_string(), runtime, result });
+    }
+    let parts: Vec<PartResult> = done.into_iter().map(Option::unwrap).collect();
+    merge(&egraph, parts, options)
+}
+
+/// `distribute` without workers: `options.pre_mode` runs on the `parts` parts of
+/// `data` in parallel, each with `options.name` suffixed by `_part<i>`, and their
+/// results are stitched the same way.
+pub fn run_partitioned(data: &Data, parts: usize, options: &pipeline::Options) -> anyhow::Result<Distributed> {
+    if options.pre_mode.flag() < 3 {
+        bail!("The parts are extracted by the heuristic or the solver, {:?} does neither", options.pre_mode);
+    }
+    let egraph = SerializedEGraph::from_Data(data)?;
+    let part_data = partition(data, 1.0 / parts as f32);
+    let count = part_data.len();
+    println!("Split into {} parts", count);
+    let parts = part_data
+        .par_iter()
+        .enumerate()
+        .map(|(part, data)| {
+            let part_egraph = SerializedEGraph::from_Data(data)?;
+            let start = Instant::now();
+            let local = local_options(options, part, &part_egraph);
+            let result = pipeline::run(&part_egraph, local).with_context(|| format!("Part {}/{} failed", part + 1, count))?.result;
+            let runtime = start.elapsed().as_secs_f64();
+            result.validate(&part_egraph).with_context(|| format!("Part {}/{} is not a valid extraction of the part", part + 1, count))?;
+            println!("Part {}/{}: {} classes in {:.2}s", part + 1, count, result.choices.len(), runtime);
+            Ok(PartResult { worker: "local".to_string(), runtime, result })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    merge(&egraph, parts, options)
+}
+
+/// The options of a part, keeping the frozen choices of its classes.
+fn local_options(options: &pipeline::Options, part: usize, part_egraph: &SerializedEGraph) -> pipeline::Options {
+    let frozen = options.frozen.iter().filter(|(_, nid)| part_egraph.nodes.contains_key(*nid)).map(|(cid, nid)| (*cid, *nid)).collect();
+    pipeline::Options { name: format!("{}_part{}", options.name, part), frozen, ..options.clone() }
+}
+
+/// Stitches the results of the parts into an extraction of `egraph`, extracting
+/// the classes on cycles again and the whole `egraph` if that is still invalid.
+fn merge(egraph: &SerializedEGraph, parts: Vec<PartResult>, options: &pipeline::Options) -> anyhow::Result<Distributed> {
+    let (mut result, cyclic) = stitch(egraph, parts.iter().map(|part| &part.result));
+    let repaired = cyclic.len();
+    if !cyclic.is_empty() {
+        let cyclic: HashSet<ClassId> = cyclic.into_iter().collect();
+        let mut frozen: IndexMap<ClassId, NodeId> =
+            result.choices.iter().filter(|(cid, _)| !cyclic.contains(cid)).map(|(cid, nid)| (*cid, *nid)).collect();
+        frozen.extend(options.frozen.iter().map(|(cid, nid)| (*cid, *nid)));
+        let extractors = extractor::extractors();
+        let ed = extractors.get(options.extractor.as_str()).with_context(|| format!("Unknown extractor: {}", options.extractor))?;
+        result = ed.get_extractor().extract_frozen(egraph, &egraph.root_eclasses, &frozen);
+        println!("Extracted the {} classes on cycles of the stitched parts again", repaired);
+    }
+    let stitched = result.is_valid(egraph);
+    if !stitched {
+        eprintln!("The stitched parts are not a valid extraction, extracting the whole e-graph locally");
+        result = pipeline::run(egraph, options.clone())?.result;
+    }
+    result.validate(egraph).context("The extraction of the whole e-graph is invalid")?;
+    Ok(Distributed { result, stitched, repaired, parts })
+}
+
+/// Sends the parts left in `queue` to the worker at `addr` one by one, forwarding
+/// its replies. A part that got no reply goes back to the queue.
+fn feed(
+    addr: &str,
+    parts: &[Data],
+    queue: &Mutex<VecDeque<usize>>,
+    options: &pipeline::Options,
+    replies: &mpsc::Sender<(String, Reply)>,
+) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(addr)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    loop {
+        let Some(part) = queue.lock().unwrap().pop_front() else {
+            return Ok(());
+        };
+        let task = Task {
+            part,
+            name: options.name.clone(),
+            extractor: options.extractor.clone(),
+            pre: options.pre_mode.flag(),
+            solver: options.solver.clone(),
+            timeout: options.timeout,
+            bound: options.bound,
+        };
+        let mut exchange = || -> anyhow::Result<Reply> {
+            writeln!(writer, "{}", serde_json::to_string(&task)?)?;
+            parts[part].to_json_writer(&mut writer)?;
+            writeln!(writer)?;
+            writer.flush()?;
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                bail!("connection closed");
+            }
+            Ok(serde_json::from_str(&line)?)
+        };
+        match exchange() {
+            Ok(reply) if reply.part == part => {
+                let _ = replies.send((addr.to_string(), reply));
+            }
+            Ok(reply) => {
+                queue.lock().unwrap().push_back(part);
+                bail!("reply for part {} to part {}", reply.part, part);
+            }
+            Err(err) => {
+                queue.lock().unwrap().push_back(part);
+                return Err(err);
+            }
+        }
+    }
+}
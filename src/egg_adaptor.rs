@@ -0,0 +1,173 @@
+// Adaptor exposing e-boost as an egg extractor.
+//
+// Mirrors the shape of `egg::Extractor` / `egg::LpExtractor`, so switching a
+// `Runner` user over is a one-line change:
+//
+//     let best = LpExtractor::new(&runner.egraph, AstSize).solve(root);
+//     let best = BoostExtractor::new(&runner.egraph, AstSize).solve(root);
+//
+// By default only the heuristic extractor runs. `with_ilp` adds the exact
+// refinement step: the heuristic result is used as warm start and for bound
+// pruning, and the LP is handed to one of the solver wrappers.
+
+use crate::extractor;
+use crate::solver;
+use egg::{Analysis, AstSize, EGraph, Id, Language, RecExpr};
+use egraph_serialize::{ClassId, Data, EGraph as SerializedEGraph, Node, NodeId};
+use extraction_gym::ExtractionResult;
+use indexmap::IndexMap;
+use ordered_float::NotNan;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Per e-node cost, with the same signature as `egg::LpCostFunction`.
+pub trait NodeCostFunction<L: Language, N: Analysis<L>> {
+    fn node_cost(&mut self, egraph: &EGraph<L, N>, eclass: Id, enode: &L) -> f64;
+}
+
+impl<L: Language, N: Analysis<L>> NodeCostFunction<L, N> for AstSize {
+    fn node_cost(&mut self, _egraph: &EGraph<L, N>, _eclass: Id, _enode: &L) -> f64 {
+        1.0
+    }
+}
+
+impl<L, N, F> NodeCostFunction<L, N> for F
+where
+    L: Language,
+    N: Analysis<L>,
+    F: FnMut(&EGraph<L, N>, Id, &L) -> f64,
+{
+    fn node_cost(&mut self, egraph: &EGraph<L, N>, eclass: Id, enode: &L) -> f64 {
+        self(egraph, eclass, enode)
+    }
+}
+
+/// Wraps an existing `egg::LpCostFunction` so it can be reused as is.
+#[cfg(feature = "egg-lp")]
+pub struct LpCost<CF>(pub CF);
+
+#[cfg(feature = "egg-lp")]
+impl<L, N, CF> NodeCostFunction<L, N> for LpCost<CF>
+where
+    L: Language,
+    N: Analysis<L>,
+    CF: egg::LpCostFunction<L, N>,
+{
+    fn node_cost(&mut self, egraph: &EGraph<L, N>, eclass: Id, enode: &L) -> f64 {
+        self.0.node_cost(egraph, eclass, enode)
+    }
+}
+
+struct IlpOptions {
+    solver: String,
+    timeout_secs: u64,
+    bound: f32,
+}
+
+pub struct BoostExtractor<'a, L: Language, N: Analysis<L>> {
+    egraph: &'a EGraph<L, N>,
+    // Node ids are (canonical class id, position in the egg e-class).
+    serialized: SerializedEGraph,
+    extractor: String,
+    ilp: Option<IlpOptions>,
+}
+
+impl<'a, L, N> BoostExtractor<'a, L, N>
+where
+    L: Language + Display,
+    N: Analysis<L>,
+{
+    /// Converts `egraph` (which must be clean, i.e. rebuilt) using `cost_function`
+    /// for the node costs. The default extractor is faster-greedy-dag-mt1, without ILP.
+    pub fn new<CF>(egraph: &'a EGraph<L, N>, mut cost_function: CF) -> Self
+    where
+        CF: NodeCostFunction<L, N>,
+    {
+        let mut nodes = IndexMap::new();
+        for class in egraph.classes() {
+            let cid = class_id(egraph, class.id);
+            for (i, enode) in class.nodes.iter().enumerate() {
+                let nid = NodeId::from((cid.0, i as u32));
+                let cost = cost_function.node_cost(egraph, class.id, enode);
+                nodes.insert(nid, Node {
+                    op: enode.to_string(),
+                    id: nid,
+                    children: enode.children().iter().map(|&c| class_id(egraph, c)).collect(),
+                    eclass: cid,
+                    cost: NotNan::new(cost).expect("node cost is NaN"),
+                    area: None,
+                });
+            }
+        }
+        let data = Data { nodes, root_eclasses: vec![], class_data: Default::default() };
+        let serialized = SerializedEGraph::from_Data(&data).unwrap();
+
+        Self {
+            egraph,
+            serialized,
+            extractor: String::from("faster-greedy-dag-mt1"),
+            ilp: None,
+        }
+    }
+
+    /// Selects the extraction_gym extractor, by its `--extractor` name.
+    pub fn with_extractor(mut self, name: &str) -> Self {
+        let name = name.to_lowercase();
+        if !extractor::extractors().contains_key(name.as_str()) {
+            panic!("Unknown extractor: {}", name);
+        }
+        self.extractor = name;
+        self
+    }
+
+    /// Refines the heuristic result with `solver` ("gurobi", "cplex", "cpsat", "highs" or "cbc").
+    /// A `bound` >= 1.0 prunes nodes as with `--bound` on the CLI.
+    pub fn with_ilp(mut self, solver: &str, timeout_secs: u64, bound: f32) -> Self {
+        self.ilp = Some(IlpOptions { solver: solver.to_string(), timeout_secs, bound });
+        self
+    }
+
+    /// Extracts `eclass` and returns its DAG cost together with the expression.
+    pub fn find_best(&mut self, eclass: Id) -> (f64, RecExpr<L>) {
+        let root = class_id(self.egraph, eclass);
+        self.serialized.root_eclasses = vec![root];
+        let roots = self.serialized.root_eclasses.clone();
+
+        let extractors = extractor::extractors();
+        let mut result = extractors[self.extractor.as_str()]
+            .get_extractor()
+            .extract(&self.serialized, &roots);
+        result.check(&self.serialized);
+
+        if let Some(ilp) = &self.ilp {
+            let base_name = format!("egg_{}", root);
+            result = solver::refine(&self.serialized, &result, &ilp.solver, ilp.timeout_secs, ilp.bound, &base_name);
+            result.check(&self.serialized);
+        }
+
+        let cost = result.dag_cost(&self.serialized, &roots).into_inner();
+        (cost, self.build_expr(&result, eclass))
+    }
+
+    /// Same as `egg::LpExtractor::solve`.
+    pub fn solve(&mut self, eclass: Id) -> RecExpr<L> {
+        self.find_best(eclass).1
+    }
+
+    fn build_expr(&self, result: &ExtractionResult, eclass: Id) -> RecExpr<L> {
+        let chosen: HashMap<ClassId, &L> = result
+            .choices
+            .iter()
+            .map(|(cid, nid)| {
+                let class = &self.egraph[Id::from(cid.0 as usize)];
+                (*cid, &class.nodes[nid.0[1] as usize])
+            })
+            .collect();
+        let node = |id: Id| (*chosen[&class_id(self.egraph, id)]).clone();
+        node(eclass).build_recexpr(node)
+    }
+}
+
+fn class_id<L: Language, N: Analysis<L>>(egraph: &EGraph<L, N>, id: Id) -> ClassId {
+    ClassId::from(usize::from(egraph.find(id)) as u32)
+}
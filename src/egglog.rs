@@ -0,0 +1,158 @@
+// egglog front-end.
+//
+// cargo run --release -- egglog serialized.json [--extractor <n>] [--solver <s> --timeout <t> --bound <b>] [--out <file>]
+//
+// Reads the JSON written by egglog's `serialize` (string node/class ids,
+// children given as node ids, `:cost` annotations already folded into the node
+// costs), runs the usual extractor (+ optional ILP refinement), and writes one
+// term per root in the s-expression syntax egglog's `extract` prints, together
+// with the chosen node for every class, keyed by egglog's own ids.
+
+use crate::extractor;
+use anyhow::anyhow;
+use crate::solver;
+use egraph_serialize::{ClassId, Data, EGraph as SerializedEGraph, Node, NodeId};
+use extraction_gym::ExtractionResult;
+use indexmap::IndexMap;
+use ordered_float::NotNan;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+pub struct EgglogEGraph {
+    pub nodes: IndexMap<String, EgglogNode>,
+    #[serde(default)]
+    pub root_eclasses: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EgglogNode {
+    pub op: String,
+    #[serde(default)]
+    pub children: Vec<String>,
+    pub eclass: String,
+    #[serde(default = "one")]
+    pub cost: f64,
+    #[serde(default)]
+    pub subsumed: bool,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+pub struct EgglogOptions {
+    pub extractor: String,
+    /// (solver, timeout_secs, bound)
+    pub ilp: Option<(String, u64, f32)>,
+}
+
+/// The e-graph renumbered into e-boost ids, with the way back to egglog ids.
+pub struct Converted {
+    pub egraph: SerializedEGraph,
+    class_names: Vec<String>,
+    node_names: HashMap<NodeId, String>,
+}
+
+impl EgglogEGraph {
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let egraph: Self = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Ok(egraph)
+    }
+
+    /// Subsumed nodes are dropped, since egglog never extracts them. Fails on
+    /// children and roots egglog didn't list, and on classes left without nodes.
+    pub fn convert(&self) -> anyhow::Result<Converted> {
+        // Class ids follow the order in which egglog lists the nodes.
+        let mut class_ids: IndexMap<&str, ClassId> = IndexMap::new();
+        for node in self.nodes.values() {
+            let next = ClassId::from(class_ids.len() as u32);
+            class_ids.entry(node.eclass.as_str()).or_insert(next);
+        }
+
+        let mut class_size: HashMap<ClassId, u32> = HashMap::new();
+        let mut nodes = IndexMap::new();
+        let mut node_names = HashMap::new();
+        for (name, node) in self.nodes.iter().filter(|(_, n)| !n.subsumed) {
+            let cid = class_ids[node.eclass.as_str()];
+            let idx = class_size.entry(cid).or_insert(0);
+            let nid = NodeId::from((cid.0, *idx));
+            *idx += 1;
+            let children = node
+                .children
+                .iter()
+                .map(|child| match self.nodes.get(child) {
+                    Some(child) => Ok(class_ids[child.eclass.as_str()]),
+                    None => Err(anyhow!("Unknown child node {} of {}", child, name)),
+                })
+                .collect::<anyhow::Result<_>>()?;
+            let cost = NotNan::new(node.cost).map_err(|_| anyhow!("Cost of node {} is NaN", name))?;
+            nodes.insert(nid, Node { op: node.op.clone(), id: nid, children, eclass: cid, cost, area: None });
+            node_names.insert(nid, name.clone());
+        }
+
+        let root_eclasses = self
+            .root_eclasses
+            .iter()
+            .map(|root| class_ids.get(root.as_str()).copied().ok_or_else(|| anyhow!("Unknown root e-class {}", root)))
+            .collect::<anyhow::Result<_>>()?;
+        let data = Data { nodes, root_eclasses, class_data: Default::default() };
+        let egraph = SerializedEGraph::from_Data(&data)?;
+        // A class whose nodes are all subsumed is gone, but its parents may remain.
+        egraph.validate().map_err(|err| anyhow!("Invalid e-graph: {}", err))?;
+
+        Ok(Converted {
+            egraph,
+            class_names: class_ids.keys().map(|name| name.to_string()).collect(),
+            node_names,
+        })
+    }
+}
+
+impl Converted {
+    /// Prints the term rooted at `cid` the way egglog's `extract` does.
+    pub fn term(&self, result: &ExtractionResult, cid: ClassId) -> String {
+        result.to_term(&self.egraph, cid).to_string()
+    }
+
+    /// Extraction output keyed by egglog ids.
+    pub fn to_json(&self, result: &ExtractionResult) -> Value {
+        let roots = &self.egraph.root_eclasses;
+        let choices: serde_json::Map<String, Value> = result
+            .choices
+            .iter()
+            .map(|(cid, nid)| {
+                (self.class_names[cid.0 as usize].clone(), Value::String(self.node_names[nid].clone()))
+            })
+            .collect();
+        let extracted: Vec<Value> = roots
+            .iter()
+            .map(|root| json!({ "eclass": self.class_names[root.0 as usize], "term": self.term(result, *root) }))
+            .collect();
+        json!({
+            "dag": result.dag_cost(&self.egraph, roots).into_inner(),
+            "tree": result.tree_cost(&self.egraph, roots).into_inner(),
+            "area": result.area_cost(&self.egraph).into_inner(),
+            "extracted": extracted,
+            "choices": choices,
+        })
+    }
+}
+
+pub fn run(converted: &Converted, options: &EgglogOptions, base_name: &str) -> ExtractionResult {
+    let egraph = &converted.egraph;
+    let extractors = extractor::extractors();
+    let ed = extractors
+        .get(options.extractor.as_str())
+        .unwrap_or_else(|| panic!("Unknown extractor: {}", options.extractor));
+    let mut result = ed.get_extractor().extract(egraph, &egraph.root_eclasses);
+    result.check(egraph);
+
+    if let Some((solver_name, timeout_secs, bound)) = &options.ilp {
+        result = solver::refine(egraph, &result, solver_name, *timeout_secs, *bound, base_name);
+        result.check(egraph);
+    }
+    result
+}
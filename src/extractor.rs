@@ -3,38 +3,16 @@ use extraction_gym::Extractor as extract_Extractor;
 use indexmap::IndexMap;
 
 
-#[derive(PartialEq, Eq)]
-pub enum Optimal {
-    Tree,
-    DAG,
-    Neither,
-}
-
-pub struct ExtractorDetail {
-    extractor: Box<dyn extract_Extractor>,
-    optimal: Optimal,
-    use_for_bench: bool,
-}
-
-impl ExtractorDetail {
-    // Getter for `extractor`
-    pub fn get_extractor(&self) -> &dyn extract_Extractor {
-        &*self.extractor
-    }
-
-    // Getter for `optimal`
-    pub fn get_optimal(&self) -> &Optimal {
-        &self.optimal
-    }
-
-    // Getter for `use_for_bench`
-    pub fn get_use_for_bench(&self) -> bool {
-        self.use_for_bench
-    }
-}
+pub use extraction_gym::registry::{ExtractorDetail, Optimal};
+use extraction_gym::passes::{MultiPassExtractor, PassSchedule};
+use extraction_gym::policy::ChoicePolicy;
+use extraction_gym::queue::Frontier;
+use std::sync::Arc;
 
+/// The built-in extractors followed by the ones added with
+/// `extraction_gym::registry::register_extractor`.
 pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
-    let extractors: IndexMap<&'static str, ExtractorDetail> = [
+    let mut extractors: IndexMap<&'static str, ExtractorDetail> = [
         (
             "bottom-up",
             ExtractorDetail {
@@ -46,11 +24,28 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
         (
             "faster-bottom-up",
             ExtractorDetail {
-                extractor: extraction_gym::faster_bottom_up::FasterBottomUpExtractor.boxed(),
+                extractor: extraction_gym::faster_bottom_up::FasterBottomUpExtractor::default().boxed(),
+                optimal: Optimal::Tree,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-bottom-up-best-first",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_bottom_up::FasterBottomUpExtractor { frontier: Frontier::BestFirst }.boxed(),
                 optimal: Optimal::Tree,
                 use_for_bench: true,
             },
         ),
+        (
+            "gpu-bottom-up",
+            ExtractorDetail {
+                extractor: extraction_gym::gpu_bottom_up::GpuBottomUpExtractor.boxed(),
+                // Tree-optimal up to the 32-bit floats the relaxation uses on the GPU.
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
         (
             "faster-ast-depth-mt",
             ExtractorDetail {
@@ -67,6 +62,46 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
                 use_for_bench: true,
             },
         ),
+        (
+            "faster-ast-size-mt",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_ast_size_mt::FasterAstSizeExtractor.boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-ast-size",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_ast_size::FasterAstSizeExtractor.boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "distinct-ops",
+            ExtractorDetail {
+                extractor: extraction_gym::distinct_ops::DistinctOpsExtractor.boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-weighted-depth",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_weighted_depth::FasterWeightedDepthExtractor::default().boxed(),
+                optimal: Optimal::Tree,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-delay-depth",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_weighted_depth::FasterWeightedDepthExtractor::new(extraction_gym::delay::DelayModel::node_costs()).boxed(),
+                optimal: Optimal::Tree,
+                use_for_bench: true,
+            },
+        ),
         (
             "faster-bottom-up-mt",
             ExtractorDetail {
@@ -78,7 +113,7 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
         (
             "greedy-dag",
             ExtractorDetail {
-                extractor: extraction_gym::greedy_dag::GreedyDagExtractor.boxed(),
+                extractor: extraction_gym::greedy_dag::GreedyDagExtractor::default().boxed(),
                 optimal: Optimal::Neither,
                 use_for_bench: true,
             },
@@ -86,7 +121,39 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
         (
             "faster-greedy-dag",
             ExtractorDetail {
-                extractor: extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor.boxed(),
+                extractor: extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor::default().boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-greedy-dag-best-first",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor { frontier: Frontier::BestFirst, ..Default::default() }.boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "epsilon-greedy",
+            ExtractorDetail {
+                extractor: extraction_gym::epsilon_greedy::EpsilonGreedyDagExtractor::default().boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-greedy-dag-fa",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_greedy_dag_fa::FasterGreedyDagExtractor.boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
+        (
+            "faster-greedy-dag-fa-mt",
+            ExtractorDetail {
+                extractor: extraction_gym::faster_greedy_dag_fa_mt::FasterGreedyDagExtractor.boxed(),
                 optimal: Optimal::Neither,
                 use_for_bench: true,
             },
@@ -94,7 +161,7 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
         (
             "faster-greedy-dag-mt1",
             ExtractorDetail {
-                extractor: extraction_gym::faster_greedy_dag_mt1::FasterGreedyDagExtractor.boxed(),
+                extractor: extraction_gym::faster_greedy_dag_mt1::FasterGreedyDagExtractor::default().boxed(),
                 optimal: Optimal::Neither,
                 use_for_bench: true,
             },
@@ -102,7 +169,7 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
         (
             "faster-greedy-dag-mt2",
             ExtractorDetail {
-                extractor: extraction_gym::faster_greedy_dag_mt2::FasterGreedyDagExtractor.boxed(),
+                extractor: extraction_gym::faster_greedy_dag_mt2::FasterGreedyDagExtractor::default().boxed(),
                 optimal: Optimal::Neither,
                 use_for_bench: true,
             },
@@ -123,6 +190,14 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
                 use_for_bench: true,
             },
         ),
+        (
+            "pareto",
+            ExtractorDetail {
+                extractor: extraction_gym::pareto::ParetoExtractor::default().boxed(),
+                optimal: Optimal::Neither,
+                use_for_bench: true,
+            },
+        ),
         #[cfg(feature = "ilp-cbc")]
         (
             "ilp-cbc-timeout",
@@ -171,5 +246,59 @@ pub fn extractors() -> IndexMap<&'static str, ExtractorDetail> {
     ]
     .into_iter()
     .collect();
+    extractors.extend(extraction_gym::registry::registered_extractors());
     return extractors;
 }
+
+/// Variant of a registered extractor with a fixed visitation order, for the
+/// extractors whose results otherwise vary between runs.
+pub fn deterministic_extractor(name: &str) -> Option<Box<dyn extract_Extractor>> {
+    match name {
+        "greedy-dag" => Some(extraction_gym::greedy_dag::GreedyDagExtractor { deterministic: true }.boxed()),
+        "faster-greedy-dag-mt1" => Some(
+            extraction_gym::faster_greedy_dag_mt1::FasterGreedyDagExtractor { deterministic: true, ..Default::default() }.boxed(),
+        ),
+        "faster-greedy-dag-mt2" => Some(
+            extraction_gym::faster_greedy_dag_mt2::FasterGreedyDagExtractor { deterministic: true, ..Default::default() }.boxed(),
+        ),
+        _ => None,
+    }
+}
+
+/// A registered multi-pass extractor, running the passes of `passes` (its default
+/// schedule if `None`) and choosing by `policy`, for reporting how many passes it
+/// needed.
+pub fn multi_pass_extractor(
+    name: &str,
+    passes: Option<PassSchedule>,
+    deterministic: bool,
+    policy: Option<Arc<dyn ChoicePolicy>>,
+) -> Option<Box<dyn MultiPassExtractor>> {
+    let passes = passes.unwrap_or_default();
+    match name {
+        "faster-greedy-dag-mt1" => Some(Box::new(extraction_gym::faster_greedy_dag_mt1::FasterGreedyDagExtractor { deterministic, passes, policy })),
+        "faster-greedy-dag-mt2" => Some(Box::new(extraction_gym::faster_greedy_dag_mt2::FasterGreedyDagExtractor { deterministic, passes, policy })),
+        _ => None,
+    }
+}
+
+/// A full-adder-aware extractor's result with its secondary choices, from which
+/// the extraction computing the outputs of the adders separately is offered as a
+/// further warm start.
+pub fn dual_extract(
+    name: &str,
+    egraph: &egraph_serialize::EGraph,
+    roots: &[egraph_serialize::ClassId],
+    frozen: &IndexMap<egraph_serialize::ClassId, egraph_serialize::NodeId>,
+) -> Option<faster_greedy_dag_fa::DualExtractionResult> {
+    if name != "faster-greedy-dag-fa" && name != "faster-greedy-dag-fa-mt" {
+        return None;
+    }
+    let frozen_egraph = (!frozen.is_empty()).then(|| freeze_choices(egraph, frozen));
+    let egraph = frozen_egraph.as_ref().unwrap_or(egraph);
+    Some(if name == "faster-greedy-dag-fa" {
+        faster_greedy_dag_fa::FasterGreedyDagExtractor.extract_dual(egraph, roots, Deadline::NONE)
+    } else {
+        faster_greedy_dag_fa_mt::FasterGreedyDagExtractor.extract_dual(egraph_serialize::FrozenEGraph::new(egraph), roots, Deadline::NONE)
+    })
+}
@@ -0,0 +1,210 @@
+//! Gurobi in process, through its C API (`gurobi` feature, which links the `grb`
+//! crate). `--solver gurobi` then loads the LP and the MST warm start with
+//! Gurobi's own readers and takes the solution off the model, instead of spawning
+//! `gurobi/gurobi_solver` (or `gurobi_cl`) and parsing its `.sol` file; and
+//! `pipeline::run` with `in_memory` hands it the `IlpModel` without writing
+//! anything.
+//!
+//! The log still goes to `SolverFiles::log_file`, from which the timeline is read
+//! as for `gurobi_cl`, and the solution is written to `result_file` for the
+//! caches. Races, the solution pool (and so `--checkpoint` and the snapshots of
+//! the incumbents) and `--monitor` still need the wrapper.
+
+use crate::ilp_model::{IlpModel, Sense, VarId, VarKind};
+use crate::interrupt;
+use crate::solver::{Monitor, SolveFailure, SolverFiles};
+use crate::timeline::{LogFormat, Timeline};
+use e_boost_core::solution;
+use extraction_gym::ExtractionResult;
+use grb::callback::Where;
+use grb::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Gurobi parameters besides the time limit, Gurobi's defaults when `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Params {
+    /// Relative gap between the incumbent and the bound at which to stop (`MIPGap`).
+    pub mip_gap: Option<f64>,
+    /// Number of threads (`Threads`), all the cores by default.
+    pub threads: Option<i32>,
+}
+
+static PARAMS: OnceLock<Params> = OnceLock::new();
+
+/// Sets the parameters of the `--solver gurobi` runs, from `--mip-gap` and `--threads`.
+pub fn set_params(params: Params) {
+    if PARAMS.set(params).is_err() {
+        panic!("The Gurobi parameters are already set");
+    }
+}
+
+/// What `set_params` set, or the defaults.
+pub fn params() -> Params {
+    PARAMS.get().copied().unwrap_or_default()
+}
+
+/// Solves `files.lp_file` from `files.mst_file` if there is one, as
+/// `solver::try_run_solver` does with the back end processes. A `--target-gap`
+/// without `params.mip_gap` becomes the MIP gap.
+pub fn solve_files(files: &SolverFiles, timeout_secs: u64, monitor: Monitor, params: Params) -> Result<ExtractionResult, SolveFailure> {
+    if monitor.interval.is_some() || monitor.checkpoint.is_some() {
+        eprintln!("Warning: --monitor and --checkpoint need the Gurobi wrapper, they do not apply in process");
+    }
+    if !files.extra_mst_files.is_empty() {
+        println!("gurobi takes a single warm start in process, the {} extra ones are not used", files.extra_mst_files.len());
+    }
+    let params = Params { mip_gap: params.mip_gap.or(monitor.target_gap), ..params };
+    // None may be taken from an earlier run.
+    let _ = fs::remove_file(&files.result_file);
+    let _ = fs::remove_file(&files.log_file);
+
+    let warm_start = Path::new(&files.mst_file).exists();
+    println!("Solving {} with Gurobi in process", files.lp_file);
+    println!("-----------------------------------------------------");
+    let outcome = solve_lp_file(files, warm_start, timeout_secs, params)
+        .unwrap_or_else(|err| panic!("Gurobi failed on {}: {}", files.lp_file, err));
+    println!("-----------------------------------------------------");
+
+    let log = fs::read_to_string(&files.log_file).unwrap_or_else(|err| {
+        eprintln!("Warning: solver log not read: {}: {}", files.log_file, err);
+        String::new()
+    });
+    let timeline = Timeline::from_log("gurobi", LogFormat::Gurobi, warm_start, &log);
+    timeline.to_json_file(&files.timeline_file).expect("Unable to write file");
+    println!("Objective timeline ({} points) written to {}", timeline.points.len(), files.timeline_file);
+
+    let solution = outcome?;
+    println!("Solution found with solver: gurobi");
+    // As in `solver::finish`, the run left nothing to resume unless a signal cut it short.
+    if !interrupt::requested() {
+        let _ = fs::remove_file(&files.checkpoint_file);
+    }
+    Ok(solution)
+}
+
+fn solve_lp_file(files: &SolverFiles, warm_start: bool, timeout_secs: u64, params: Params) -> grb::Result<Result<ExtractionResult, SolveFailure>> {
+    let env = Env::new("")?;
+    let mut model = Model::read_from(&files.lp_file, &env)?;
+    model.set_param(param::LogFile, files.log_file.clone())?;
+    configure(&mut model, timeout_secs, params)?;
+    if warm_start {
+        model.read(&files.mst_file)?;
+    }
+    let outcome = optimize(&mut model)?;
+    if outcome.is_ok() {
+        model.write(&files.result_file)?;
+    }
+    Ok(outcome)
+}
+
+/// Solves `model` in process from the hints of its variables, without any file:
+/// the log goes to the terminal.
+pub fn solve_model(model: &IlpModel, timeout_secs: u64, params: Params) -> Result<ExtractionResult, SolveFailure> {
+    let solve = || -> grb::Result<_> {
+        let env = Env::new("")?;
+        let mut grb_model = build(model, &env)?;
+        configure(&mut grb_model, timeout_secs, params)?;
+        optimize(&mut grb_model)
+    };
+    solve().unwrap_or_else(|err| panic!("Gurobi failed: {}", err))
+}
+
+/// The Gurobi model of `model`, with the hints as its MIP start.
+fn build(model: &IlpModel, env: &Env) -> grb::Result<Model> {
+    let mut grb_model = Model::with_env("e-boost", env)?;
+    let vars = model
+        .vars
+        .iter()
+        .map(|var| {
+            let kind = match var.kind {
+                VarKind::Binary => VarType::Binary,
+                VarKind::Integer => VarType::Integer,
+                VarKind::Continuous => VarType::Continuous,
+            };
+            grb_model.add_var(&var.name, kind, 0.0, var.lower, var.upper, std::iter::empty())
+        })
+        .collect::<grb::Result<Vec<Var>>>()?;
+    let linear = |terms: &[(VarId, f64)]| {
+        let mut expr = grb::expr::LinExpr::new();
+        for (var, coeff) in terms {
+            expr.add_term(*coeff, vars[var.0]);
+        }
+        expr
+    };
+
+    let mut lazy = Vec::new();
+    for constraint in &model.constraints {
+        let lhs = linear(&constraint.terms);
+        let rhs = constraint.rhs;
+        let row = match constraint.sense {
+            Sense::Le => c!(lhs <= rhs),
+            Sense::Ge => c!(lhs >= rhs),
+            Sense::Eq => c!(lhs == rhs),
+        };
+        match constraint.indicator {
+            Some((var, value)) => {
+                grb_model.add_genconstr_indicator(&constraint.name, vars[var.0], value == 1.0, row)?;
+            }
+            None => {
+                let constr = grb_model.add_constr(&constraint.name, row)?;
+                if constraint.lazy {
+                    lazy.push(constr);
+                }
+            }
+        }
+    }
+    grb_model.set_objective(linear(&model.objective), ModelSense::Minimize)?;
+
+    // Attributes can only be set on variables and constraints once they are added.
+    grb_model.update()?;
+    for constr in &lazy {
+        grb_model.set_obj_attr(attr::Lazy, constr, 1)?;
+    }
+    for (var, hint) in vars.iter().zip(&model.vars).filter_map(|(var, variable)| Some((var, variable.hint?))) {
+        grb_model.set_obj_attr(attr::Start, var, hint)?;
+    }
+    Ok(grb_model)
+}
+
+fn configure(model: &mut Model, timeout_secs: u64, params: Params) -> grb::Result<()> {
+    model.set_param(param::TimeLimit, timeout_secs as f64)?;
+    if let Some(gap) = params.mip_gap {
+        model.set_param(param::MIPGap, gap)?;
+    }
+    if let Some(threads) = params.threads {
+        model.set_param(param::Threads, threads)?;
+    }
+    Ok(())
+}
+
+/// Optimizes `model`, stopping early on a signal, and reads the chosen nodes off
+/// the best solution found.
+fn optimize(model: &mut Model) -> grb::Result<Result<ExtractionResult, SolveFailure>> {
+    // Gurobi keeps its incumbent when terminated, as the wrappers do when interrupted.
+    let mut stop_on_signal = |w: Where| {
+        if interrupt::requested() {
+            match w {
+                Where::Polling(ctx) => ctx.terminate(),
+                Where::MIP(ctx) => ctx.terminate(),
+                _ => {}
+            }
+        }
+        Ok(())
+    };
+    model.optimize_with_callback(&mut stop_on_signal)?;
+
+    if matches!(model.status()?, Status::Infeasible | Status::InfOrUnbd) {
+        return Ok(Err(SolveFailure::Infeasible));
+    }
+    if model.get_attr(attr::SolCount)? == 0 {
+        return Ok(Err(SolveFailure::NoSolution));
+    }
+    let vars = model.get_vars()?.to_vec();
+    let names = model.get_obj_attr_batch(attr::VarName, vars.iter().copied())?;
+    let values = model.get_obj_attr_batch(attr::X, vars)?;
+    let solution = solution::from_values(names.iter().map(String::as_str).zip(values))
+        .unwrap_or_else(|err| panic!("Gurobi: {}", err));
+    Ok(Ok(solution))
+}
@@ -0,0 +1,110 @@
+//! HiGHS in process (`highs` feature, through the `highs` crate), so that the
+//! whole flow runs with open-source dependencies only. `--solver highs` then
+//! builds the ILP in memory (`ilp_gen::build_model`) and hands it to HiGHS
+//! instead of running the `highs` command line on the LP file; the LP file is
+//! still written, for reference and for `--pre 0` runs, which solve it with the
+//! command line as before.
+//!
+//! As on the command line, HiGHS gets no warm start: the pruned nodes are fixed
+//! through their bounds. It has no indicator constraints either, so the model
+//! must use the default big-M cycle encoding. A signal does not stop it early.
+
+use crate::ilp_model::{IlpModel, Sense, VarKind};
+use crate::solver::{Monitor, SolveFailure, SolverFiles};
+use crate::timeline::{LogFormat, Timeline};
+use e_boost_core::solution;
+use extraction_gym::ExtractionResult;
+use highs::{HighsModelStatus, RowProblem};
+use std::fs;
+
+/// Solves `model` in process, logging to the terminal.
+pub fn solve_model(model: &IlpModel, timeout_secs: u64) -> Result<ExtractionResult, SolveFailure> {
+    solve(model, timeout_secs, None, None).map(|(solution, _)| solution)
+}
+
+/// `solve_model` within a `--solver highs` run: the log goes to `files.log_file`,
+/// from which the timeline is read as for the command line, and the values of the
+/// node variables to `files.result_file` for the caches.
+pub fn solve_with_files(model: &IlpModel, files: &SolverFiles, timeout_secs: u64, monitor: Monitor) -> Result<ExtractionResult, SolveFailure> {
+    if monitor.interval.is_some() || monitor.checkpoint.is_some() {
+        eprintln!("Warning: --monitor and --checkpoint do not apply to HiGHS in process");
+    }
+    // None may be taken from an earlier run.
+    let _ = fs::remove_file(&files.result_file);
+    let _ = fs::remove_file(&files.log_file);
+
+    println!("Solving the {} variables of {} with HiGHS in process", model.vars.len(), files.lp_file);
+    println!("-----------------------------------------------------");
+    let outcome = solve(model, timeout_secs, monitor.target_gap, Some(&files.log_file));
+    println!("-----------------------------------------------------");
+
+    let log = fs::read_to_string(&files.log_file).unwrap_or_else(|err| {
+        eprintln!("Warning: solver log not read: {}: {}", files.log_file, err);
+        String::new()
+    });
+    let timeline = Timeline::from_log("highs", LogFormat::Highs, false, &log);
+    timeline.to_json_file(&files.timeline_file).expect("Unable to write file");
+    println!("Objective timeline ({} points) written to {}", timeline.points.len(), files.timeline_file);
+
+    let (solution, values) = outcome?;
+    let mut sol = String::new();
+    for (var, value) in model.vars.iter().zip(&values).filter(|(var, _)| var.name.starts_with("N_")) {
+        sol.push_str(&format!("{} {}\n", var.name, value));
+    }
+    fs::write(&files.result_file, sol).expect("Unable to write file");
+    println!("Solution found with solver: highs");
+    Ok(solution)
+}
+
+/// The chosen nodes and the values of all the variables of `model`.
+fn solve(model: &IlpModel, timeout_secs: u64, mip_gap: Option<f64>, log_file: Option<&str>) -> Result<(ExtractionResult, Vec<f64>), SolveFailure> {
+    let mut costs = vec![0.0; model.vars.len()];
+    for (var, coeff) in &model.objective {
+        costs[var.0] += coeff;
+    }
+    let mut problem = RowProblem::default();
+    let cols: Vec<_> = model
+        .vars
+        .iter()
+        .zip(costs)
+        .map(|(var, cost)| match var.kind {
+            VarKind::Binary | VarKind::Integer => problem.add_integer_column(cost, var.lower..=var.upper),
+            VarKind::Continuous => problem.add_column(cost, var.lower..=var.upper),
+        })
+        .collect();
+
+    for constraint in &model.constraints {
+        if constraint.indicator.is_some() {
+            panic!("HiGHS has no indicator constraints ({}), use --acyclicity bigM", constraint.name);
+        }
+        let row = constraint.terms.iter().map(|(var, coeff)| (cols[var.0], *coeff));
+        match constraint.sense {
+            Sense::Le => problem.add_row(..=constraint.rhs, row),
+            Sense::Ge => problem.add_row(constraint.rhs.., row),
+            Sense::Eq => problem.add_row(constraint.rhs..=constraint.rhs, row),
+        }
+    }
+
+    let mut highs = problem.optimise(highs::Sense::Minimise);
+    highs.set_option("time_limit", timeout_secs as f64);
+    if let Some(gap) = mip_gap {
+        highs.set_option("mip_rel_gap", gap);
+    }
+    if let Some(log_file) = log_file {
+        highs.set_option("log_file", log_file);
+    }
+    let solved = highs.try_solve().unwrap_or_else(|status| panic!("HiGHS failed: {:?}", status));
+
+    match solved.status() {
+        HighsModelStatus::Infeasible | HighsModelStatus::UnboundedOrInfeasible => return Err(SolveFailure::Infeasible),
+        status => println!("HiGHS status: {:?}", status),
+    }
+    let values = solved.get_solution().columns().to_vec();
+    let solution = solution::from_values(model.vars.iter().map(|var| var.name.as_str()).zip(values.iter().copied()))
+        .unwrap_or_else(|err| panic!("HiGHS: {}", err));
+    // Stopped at the time limit before finding a solution.
+    if solution.choices.is_empty() {
+        return Err(SolveFailure::NoSolution);
+    }
+    Ok((solution, values))
+}
@@ -0,0 +1,86 @@
+//! Solver-independent hint file, written next to the LP.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "fixed_zero": ["N_3_1", ...],
+//!   "suggested_one": ["A_0", "N_0_2", ...],
+//!   "objective_estimate": 2385.0
+//! }
+//! ```
+//!
+//! `fixed_zero` lists the binaries the model fixes to 0 (the pruned nodes),
+//! `suggested_one` the binaries a warm start sets to 1 and `objective_estimate`
+//! the objective of that start. A binary in neither list is free, with a
+//! suggested value of 0 when there is a start. Names are the variable names of
+//! the LP. The LP solvers get the pruning through the LP bounds and the start
+//! through the MST file; the back ends that read neither are fed a translation
+//! of this file (see `to_zero_nodes`) and the start in their own format
+//! (`warm_start`), written with it.
+
+use crate::ilp_model::{IlpModel, VarKind};
+use serde::{Deserialize, Serialize};
+
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hints {
+    pub version: u32,
+    #[serde(default)]
+    pub fixed_zero: Vec<String>,
+    #[serde(default)]
+    pub suggested_one: Vec<String>,
+    #[serde(default)]
+    pub objective_estimate: Option<f64>,
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            fixed_zero: Vec::new(),
+            suggested_one: Vec::new(),
+            objective_estimate: None,
+        }
+    }
+}
+
+impl Hints {
+    /// The fixed binaries of `model` and, if its hints are a feasible assignment,
+    /// the binaries they set and their objective.
+    pub fn from_model(model: &IlpModel) -> Self {
+        let binaries = || model.vars.iter().filter(|var| var.kind == VarKind::Binary);
+        let mut hints = Hints {
+            fixed_zero: binaries().filter(|var| var.upper == 0.0).map(|var| var.name.clone()).collect(),
+            ..Default::default()
+        };
+        if model.hint_violations().is_empty() {
+            hints.suggested_one = binaries().filter(|var| var.hint == Some(1.0)).map(|var| var.name.clone()).collect();
+            hints.objective_estimate = Some(model.objective.iter().map(|(var, coef)| coef * model.var(*var).hint.unwrap()).sum());
+        }
+        hints
+    }
+
+    /// The fixed node variables, one name per line, as cpsat reads them with
+    /// `--zero_node_mst`.
+    pub fn to_zero_nodes(&self) -> String {
+        self.fixed_zero.iter().filter(|name| name.starts_with("N_")).map(|name| format!("{}\n", name)).collect()
+    }
+
+    pub fn to_json_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let hints: Hints = serde_json::from_reader(std::io::BufReader::new(file))?;
+        if hints.version != VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported hint file version {}", hints.version),
+            ));
+        }
+        Ok(hints)
+    }
+}
@@ -0,0 +1,123 @@
+//! Infeasibility diagnosis.
+//!
+//! When the model is infeasible, the Gurobi wrapper computes an irreducible
+//! infeasible subsystem (`computeIIS`) and the CPLEX one runs the conflict
+//! refiner. Both write the members of the conflict one per line:
+//!
+//! ```text
+//! row ROOT_12
+//! row FROZEN_7
+//! ub N_9_1
+//! ```
+//!
+//! A member is a constraint or a bound of a variable of the LP. The names are
+//! the ones `ilp_gen::build_model` gives them, which `describe` maps back to the
+//! e-graph: upper bounds of node variables are pruned nodes, `FROZEN_` rows are
+//! frozen choices.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Member {
+    Row(String),
+    LowerBound(String),
+    UpperBound(String),
+}
+
+pub fn parse(text: &str) -> Vec<Member> {
+    text.lines()
+        .filter_map(|line| match line.trim().split_once(' ')? {
+            ("row", name) => Some(Member::Row(name.to_string())),
+            ("lb", name) => Some(Member::LowerBound(name.to_string())),
+            ("ub", name) => Some(Member::UpperBound(name.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The numbers after `prefix` in `name`, e.g. `[12, 3]` for `N_12_3` and `N_`.
+fn ids(name: &str, prefix: &str) -> Option<Vec<u32>> {
+    name.strip_prefix(prefix)?.split('_').map(|id| id.parse().ok()).collect()
+}
+
+/// Describes a row from the ids after its prefix, `None` if they don't fit it.
+type RowDescription = fn(&[u32]) -> Option<String>;
+
+/// The member in e-graph terms.
+pub fn describe(member: &Member) -> String {
+    match member {
+        Member::UpperBound(name) => match ids(name, "N_").as_deref() {
+            Some([class, node]) => format!("node {}.{} is pruned", class, node),
+            _ => format!("upper bound of {}", name),
+        },
+        Member::LowerBound(name) => match ids(name, "L_").as_deref() {
+            Some([class]) => format!("lowest level of class {}", class),
+            _ => format!("lower bound of {}", name),
+        },
+        Member::Row(name) => {
+            let rows: [(&str, RowDescription); 9] = [
+                ("FROZEN_", |ids| Some(format!("class {} is frozen to one node", ids.first()?))),
+                ("ROOT_", |ids| Some(format!("root class {} has to be extracted", ids.first()?))),
+                ("C_ACT_", |ids| Some(format!("class {} is extracted iff one of its nodes is selected", ids.first()?))),
+                ("NODE_CHILD_", |ids| match ids {
+                    [class, node, child] => Some(format!("node {}.{} needs its child class {}", class, node, child)),
+                    _ => None,
+                }),
+                ("INTERSECT_", |ids| match ids {
+                    [class, child] => Some(format!("every node of class {} needs class {}", class, child)),
+                    _ => None,
+                }),
+                ("SELF_LOOP_", |ids| match ids {
+                    [class, node] => Some(format!("node {}.{} is its own child", class, node)),
+                    _ => None,
+                }),
+                ("OPP_", |ids| match ids {
+                    [class, node] => Some(format!("node {}.{} switches its level constraints", class, node)),
+                    _ => None,
+                }),
+                ("LEVEL_", |ids| match ids {
+                    [class, node, child] => Some(format!("node {}.{} sits above its child class {} (no cycles)", class, node, child)),
+                    _ => None,
+                }),
+                ("SYM_", |ids| match ids {
+                    [class, node] => Some(format!("node {}.{} duplicates an earlier node of its class", class, node)),
+                    _ => None,
+                }),
+            ];
+            rows.iter()
+                .find_map(|(prefix, describe)| describe(&ids(name, prefix)?))
+                .unwrap_or_else(|| format!("constraint {}", name))
+        }
+    }
+}
+
+/// The pruned nodes and frozen choices of the conflict, followed by all its members.
+pub fn report(members: &[Member]) -> String {
+    let pruned: Vec<String> = members
+        .iter()
+        .filter_map(|member| match member {
+            Member::UpperBound(name) => ids(name, "N_").filter(|ids| ids.len() == 2).map(|ids| format!("{}.{}", ids[0], ids[1])),
+            _ => None,
+        })
+        .collect();
+    let frozen: Vec<String> = members
+        .iter()
+        .filter_map(|member| match member {
+            Member::Row(name) => ids(name, "FROZEN_").filter(|ids| ids.len() == 1).map(|ids| ids[0].to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let mut report = format!("Conflict of {} constraints and bounds:\n", members.len());
+    if !pruned.is_empty() {
+        report.push_str(&format!("  caused by the pruning of nodes {}\n", pruned.join(", ")));
+    }
+    if !frozen.is_empty() {
+        report.push_str(&format!("  caused by the frozen choices of classes {}\n", frozen.join(", ")));
+    }
+    if pruned.is_empty() && frozen.is_empty() {
+        report.push_str("  caused by neither the pruning nor frozen choices\n");
+    }
+    for member in members {
+        report.push_str(&format!("  - {}\n", describe(member)));
+    }
+    report
+}
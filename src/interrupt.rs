@@ -0,0 +1,36 @@
+//! SIGINT and SIGTERM handling for long runs.
+//!
+//! Once `install` is called, the first signal only asks the run to stop: a running
+//! solver is interrupted and writes its incumbent, and the steps that have not
+//! started are skipped, so the best result so far is still verified and written.
+//! An extractor that is running finishes first. A second signal exits at once.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code of a run stopped by a signal, as shells report SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+pub fn install() {
+    ctrlc::set_handler(|| {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            eprintln!("Interrupted again, exiting");
+            std::process::exit(EXIT_CODE);
+        }
+        eprintln!("Interrupted, keeping the best result so far (interrupt again to exit now)");
+    })
+    .expect("Failed to install the signal handler");
+    INSTALLED.store(true, Ordering::SeqCst);
+}
+
+/// Whether `install` was called, i.e. whether `requested` can become true.
+pub fn installed() -> bool {
+    INSTALLED.load(Ordering::SeqCst)
+}
+
+/// Whether a signal asked the run to stop.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,41 @@
+//! E-boost as a library: the extractors' registry, the solver back ends and the
+//! front ends the `extraction_tool` binary is built from, on top of `e_boost_core`.
+//! `pipeline::run` is the whole extract, prune, ILP, solve and verify flow on an
+//! e-graph in memory.
+
+#[cfg(feature = "parquet")]
+pub mod dataset;
+pub mod cache;
+pub mod corpus;
+#[cfg(feature = "cpsat")]
+pub mod cpsat;
+pub mod distributed;
+pub mod egglog;
+pub mod extractor;
+#[cfg(feature = "gurobi")]
+pub mod gurobi;
+#[cfg(feature = "highs")]
+pub mod highs;
+pub mod hints;
+pub mod iis;
+pub mod interrupt;
+pub mod memory;
+pub mod pipeline;
+pub mod race;
+pub mod repl;
+pub mod regression;
+pub mod report;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod serve;
+pub mod snapshot;
+pub mod solver;
+pub mod summary;
+pub mod synth;
+pub mod timeline;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+#[cfg(feature = "egg")]
+pub mod egg_adaptor;
+
+pub use e_boost_core::{collect_results, ilp_gen, ilp_model, ilp_stream, pruning, solution, warm_start};
@@ -1,354 +1,45 @@
 // cargo build --release
 // cargo run -- --release --bound 1.25 --solver gurobi --timeout 1800 --extractor faster-greedy-dag-mt1 --pre 4 benchmark/BoolE/mul32_map.json
 
+mod cli;
 
 
-mod extractor;
-mod ilp_gen;
-use egg::egraph;
-use rustc_hash::{FxHashMap, FxHashSet};
+#[cfg(feature = "parquet")]
+use e_boost::dataset;
+#[cfg(feature = "gurobi")]
+use e_boost::gurobi;
+#[cfg(feature = "plugins")]
+use e_boost::plugin;
+#[cfg(feature = "scripting")]
+use e_boost::script;
+use e_boost::{cache, pruning, corpus, distributed, egglog, extractor, ilp_gen, interrupt, memory, pipeline, race, regression, repl, report, serve, snapshot, solver, summary, synth, timeline};
+
+use rustc_hash::FxHashSet;
 use extraction_gym::ExtractionResult;
-use indexmap::{IndexMap,IndexSet};
+use extraction_gym::netlist;
+use extraction_gym::choices_file;
+use indexmap::IndexMap;
 use egraph_serialize::EGraph as SerializedEGraph;
+use egraph_serialize::FrozenEGraph;
 use egraph_serialize::ClassId;
 use egraph_serialize::NodeId;
-use egraph_serialize::Node;
 use egraph_serialize::Data;
-use egraph_serialize::Cost;
 use anyhow::Context;
 use core::panic;
-use std::default;
-use std::fmt::format;
-use std::result;
 use std::time::Instant;
 use std::path::PathBuf;
 use std::fs;
 use std::env;
-use serde::{Serialize, Deserialize};
-use serde_json::{to_string_pretty, from_str,Value};
-use std::collections::{HashMap, BTreeMap,HashSet};
-use linked_hash_map::{LinkedHashMap};
-use std::collections::VecDeque;
-use ordered_float::NotNan;
+use serde::Serialize;
+use serde_json::to_string_pretty;
 use std::process::Command;
-use std::fs::File;
-use std::io::Read;
-use std::error::Error;
-use wait_timeout::ChildExt;
-
-
-
-fn remove_redundant_nodes(data: &mut Data, cost_func: &str) {
-    let mut eclass_hashes: HashMap<u32, HashSet<NodeId>> = HashMap::new();
-
-
-    let mut eclass_collect = HashMap::new();
-
-    for (node_id, node) in data.nodes.iter() {
-        let eclass = node.eclass.clone();
-        if !eclass_collect.contains_key(&eclass) {
-            eclass_collect.insert(eclass.clone(), Vec::<NodeId>::new());
-        }
-        eclass_collect.get_mut(&eclass).unwrap().push(node_id.clone());
-    }
-
-    for (eclass, node_ids) in eclass_collect.iter() {
-        let mut grouped: HashMap<Vec<(ClassId, usize)>, Vec<NodeId>> = HashMap::new();
-        for node_id in node_ids.iter() {
-            let node = data.nodes.get(node_id).unwrap();
-            let mut children_hashes: Vec<ClassId> = node.children.iter().cloned().collect();
-            let mut freq_map: BTreeMap<&ClassId, usize> = BTreeMap::new();
-            for elem in &children_hashes {
-                *freq_map.entry(elem).or_insert(0) += 1;
-            }
-            // 2. Convert freq_map to ordered (String, usize) vector
-            //    Using BTreeMap here ensures keys are sorted in dictionary order.
-            let freq_vec: Vec<(ClassId, usize)> = freq_map
-            .into_iter()
-            .map(|(elem, count)| (elem.clone(), count))
-            .collect();
-            // 3. Insert key into corresponding group based on freq_vec
-            grouped.entry(freq_vec).or_insert_with(Vec::new).push(node_id.clone());
-        }
-
-        for (children, node_ids) in grouped.iter() {
-            if node_ids.len() > 1 {
-                for (idx,item) in node_ids.iter().enumerate() {
-                    if let Some(val) = data.nodes.get(item) {
-                        if idx == 0{
-                            continue;
-                        }
-                        else{
-                            data.nodes.remove(item);
-                        }
-                    }
-                    else{
-                        panic!("node not found");
-                    }
-                }
-            }
-        }
-    }
-}
-
-
-fn egraph_partition(data: &mut Data,factor: f32, paritioned_data: &mut Vec<Data>) -> usize {
-    let nodes = &data.nodes;
-    let mut mutable_nodes = nodes.clone();
-    let mut parents = HashMap::new();
-    for (key, node) in mutable_nodes.iter() {
-        for child in node.children.iter() {
-            // let child_eclass = mutable_nodes.get(child).unwrap().eclass.clone();
-            if !parents.contains_key(child) {
-                parents.insert(child.clone(), Vec::<NodeId>::new());
-            }
-            parents.get_mut(&child).unwrap().push(key.clone());
-        }
-    }
-
-
-
-    let start = Instant::now();
-
-    let mut root = Vec::<ClassId>::new();
-    for (key, _) in mutable_nodes.iter() {
-        let key_eclass = mutable_nodes.get(key).unwrap().eclass.clone();
-        if !parents.contains_key(&key_eclass) {
-            root.push(key_eclass);
-        }
-    }
-
-    // println!("Root: {:?}", root);
-    // println!("Root: {:?}", data.root_eclasses);
-    
-    let grownth_duration = start.elapsed();
-    println!("remove_redundant_nodes runtime-{:?}", grownth_duration);
-    
-    if root.len() > 1 {
-        let pseudo_root = Node {
-            op: "pseudo_root".to_string(),
-            id: NodeId::from((u32::MAX, 0)),
-            children: root,
-            eclass: ClassId::from(u32::MAX),
-            cost: NotNan::new(0.0).unwrap(),
-        };
-        mutable_nodes.insert(NodeId::from((u32::MAX, 0)), pseudo_root);
-        root = vec![ClassId::from(u32::MAX)];
-    }
-
-
-    let mut eclass_collect = HashMap::new();
-
-    for (node_id, node) in mutable_nodes.iter() {
-        let eclass = node.eclass.clone();
-        if !eclass_collect.contains_key(&eclass) {
-            eclass_collect.insert(eclass.clone(), Vec::<NodeId>::new());
-        }
-        eclass_collect.get_mut(&eclass).unwrap().push(node_id.clone());
-    }
-
-    let partition_num = ((1.0 / factor).round() as usize); 
-    assert!(mutable_nodes.len() > partition_num);
-    let num = (mutable_nodes.len() as f32 / partition_num as f32);
-    // println!("num: {:?}", num);
-
-    
-    let mut visited = HashSet::new();
-    let mut queue: VecDeque<ClassId> = VecDeque::new();
-    let mut subgraphs = Vec::new();
-    let mut current_subgraph = IndexSet::new();
-    let mut current_count = 0;
-
-    queue.push_back(root[0].clone());
-
-
-
-    while let Some(class_id) = queue.remove(0) {
-        // let class_id = mutable_nodes.get(&_class_id).unwrap().eclass.clone();
-
-        // if visited.contains(&class_id) {
-        //     continue;
-        // }
-        // visited.insert(class_id.clone());
-        current_subgraph.insert(class_id.clone());
-
-        if current_count as f32 >= num {
-            subgraphs.push(current_subgraph.clone());
-            current_subgraph.clear();
-            current_count = 0;
-            if subgraphs.len() == partition_num {
-                break;
-            }
-        }
-
-        if let Some(class_nodes) = eclass_collect.get(&class_id) {
-            for (idx,class_node) in class_nodes.iter().enumerate() {
-                current_count += 1;
-                for child in mutable_nodes.get(class_node).unwrap().children.iter() {
-                    if !visited.contains(child) {
-                        queue.push_back(child.clone());
-                        visited.insert(child);
-                    }
-                }
-            }
-        }
-        else{
-            panic!("class_id not found:{:?}", class_id);
-        }
-    }
-
-    if !current_subgraph.is_empty() {
-        subgraphs.push(current_subgraph);
-    }
-
-
-
-
-    // for (idx, subgraph) in subgraphs.iter().enumerate() {
-    //     println!("subgraph{}: {:?}", idx, subgraph);
-    // }
-
-    let total_length: usize = subgraphs.iter().map(|subgraph| subgraph.len()).sum();
-    let union_subgraphs: HashSet<_> = subgraphs.iter().flat_map(|subgraph| subgraph.iter()).collect();
-    let eclass_keys: HashSet<_> = eclass_collect.keys().collect();
-    assert_eq!(union_subgraphs, eclass_keys);
-
-    let mut subgraph_maps: Vec<IndexMap<NodeId, Node>> = Vec::new();
-    for entry in fs::read_dir("test").expect("Unable to read directory") {
-        let entry = entry.expect("Unable to get entry");
-        let path = entry.path();
-        if path.is_file() && path.file_name().unwrap().to_str().unwrap().starts_with("subgraph_") {
-        fs::remove_file(path).expect("Unable to delete file");
-        }
-    }
-    
-
-
-    // let mut _roots = Vec::<HashSet::<String>>::new();
-    // _roots.push(root.iter().cloned().collect());
-    for (idx,subgraph) in subgraphs.iter().enumerate() {
-        let mut subgraph_map: IndexMap<NodeId, Node> = IndexMap::new();
-        for class_id in subgraph.iter() {
-            if let Some(node_ids) = eclass_collect.get(class_id) {
-                for node_id in node_ids.iter() {
-                    if let Some(node) = mutable_nodes.get(node_id) {
-                        subgraph_map.insert(node_id.clone(), node.clone());
-                    }
-                }
-            }
-        }
-        
-        // let mut roots = HashSet::<String>::new();
-
-        // 1) Collect needed info in a read-only pass
-        let mut to_remove: HashMap<NodeId, Vec<ClassId>> = HashMap::new();
-        let mut subgraph_parents: HashMap<ClassId, Vec<NodeId>> = HashMap::new();
-
-        for (key, node) in subgraph_map.iter() {
-            for child in node.children.iter() {
-                // If child not in map, record for removal
-                if !subgraph_map.contains_key(&NodeId::from((child.return_value(), 0))) {
-                    // roots.insert(nodes.get(child).unwrap().eclass.clone());
-                    to_remove.entry(key.clone())
-                        .or_default()
-                        .push(child.clone());
-                } else {
-                    // child is valid, record parents
-                    // let child_eclass = subgraph_map.get(child).unwrap().eclass.clone();
-                    subgraph_parents.entry(child.clone())
-                        .or_default()
-                        .push(key.clone());
-                }
-            }
-        }
-        // _roots.push(roots.clone());
-
-        // 2) Mutate each node in a second pass, removing invalid children
-        for (key, node) in subgraph_map.iter_mut() {
-            if let Some(children_to_remove) = to_remove.get(key) {
-                node.children.retain(|c| !children_to_remove.contains(c));
-            }
-        }
-
-        let mut subgraph_root = IndexSet::<ClassId>::new();
-        for (key, _) in subgraph_map.iter() {
-            let key_eclass = subgraph_map.get(key).unwrap().eclass.clone();
-            if !subgraph_parents.contains_key(&key_eclass) {
-                // subgraph_root.insert(key_eclass.clone()+".0");
-                subgraph_root.insert(key_eclass);
-            }
-        }
-
-        if subgraph_root.len() > 1 {
-            let pseudo_root = Node {
-                op: format!("pseudo_root_{:?}", idx),
-                id: NodeId::from((u32::MAX, 0)),
-                children: subgraph_root.iter().cloned().collect(),
-                eclass: ClassId::from(u32::MAX),
-                cost: NotNan::new(0.0).unwrap(),
-            };
-            subgraph_map.insert(NodeId::from((u32::MAX, 0)), pseudo_root);
-            subgraph_root = IndexSet::from([ClassId::from(u32::MAX)]);
-        }
-
-        assert_eq!(subgraph_root.len(), 1);
-
-        let new_data = Data {
-            nodes: subgraph_map.clone(),
-            root_eclasses: subgraph_root.iter().cloned().collect(),
-        };
+use clap::Parser;
+use clap::error::ErrorKind;
+use cli::Cli;
 
-        subgraph_maps.push(subgraph_map.clone());
 
 
-        // let new_file_content = serde_json::to_string_pretty(&new_data).expect("Unable to serialize JSON");
-        // fs::write(format!("test/subgraph_{}.json", idx), new_file_content).expect("Unable to write file");
-        new_data.to_json_file(format!("test/subgraph_{}.json", idx));
-        paritioned_data.push(new_data);
-    }
-
-
-    partition_num
-}
-
-
-fn collect_results(cost: HashMap<NodeId,Cost>, bound:f32, zero_node: &mut Vec<NodeId>) {
-    // assert!(bound >= 1.0);
-    let mut collects: HashMap<u32, Vec<(NodeId, NotNan<f64>)>> = HashMap::new();
-    
-    // Collect nodes by category
-    for (node_id, cost) in cost.iter() {
-        let classid = node_id.0[0];
-        match collects.get_mut(&classid) {
-            Some(v) => {
-                v.push((node_id.clone(), cost.clone()));
-            }
-            None => {
-                collects.insert(classid, vec![(node_id.clone(), cost.clone())]);
-            }
-        }
-    }
 
-    // Process each category
-    for (_, costs) in collects.iter() {
-        let mut sorted_costs = costs.clone();
-        // Sort by cost from smallest to largest
-        sorted_costs.sort_by(|a, b| a.1.cmp(&b.1));
-        
-        // Get minimum value and calculate threshold
-        if let Some((_, min_cost)) = sorted_costs.first() {
-            // Calculate threshold: minimum value * bound
-            let threshold = *min_cost * NotNan::new(bound as f64).expect("bound is not NaN");
-            
-            // Add nodes greater than threshold to zero_node
-            for &(ref node_id, cost) in &sorted_costs {
-                if cost > threshold {
-                    zero_node.push(node_id.clone());
-                }
-            }
-        }
-    }
-}
 
 // fn ilp_solver_gurobi(egraph: &SerializedEGraph, warm_start: Option<Vec<NodeId>>) -> Result<ExtractionResult, Box<dyn std::error::Error>> {
 //     ilp_gen::generate_ilp_file(egraph, &egraph.root_eclasses, "lp/total.lp", warm_start);
@@ -412,57 +103,6 @@ fn write_json_result<T: serde::Serialize>(filename: &str, data: &T) {
     let __ = fs::write(filename, json_result);
 }
 
-pub fn parse_cplex_solution(file_path: &str) -> Result<HashMap<String, f64>, Box<dyn Error>> {
-    // 读取文件内容
-    let mut file = File::open(file_path)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    
-    // 查找变量部分
-    let variables_start = contents.find("<variables>")
-        .ok_or("Could not find <variables> tag")?;
-    let variables_end = contents.find("</variables>")
-        .ok_or("Could not find </variables> tag")?;
-    
-    // 提取变量部分
-    let variables_section = &contents[variables_start..variables_end + 12]; // +12 for "</variables>"
-    
-    // 使用正则表达式提取N_开头的变量
-    let mut variables = HashMap::new();
-    let lines: Vec<&str> = variables_section.lines().collect();
-    
-    for line in lines {
-        if line.contains("<variable") && line.contains("name=\"N_") {
-            // 提取名称
-            let name_start = line.find("name=\"")
-                .map(|pos| pos + 6)
-                .ok_or("Could not find name attribute")?;
-            let name_end = line[name_start..].find("\"")
-                .map(|pos| name_start + pos)
-                .ok_or("Could not find end of name attribute")?;
-            let name = &line[name_start..name_end];
-            
-            // 提取值
-            let value_start = line.find("value=\"")
-                .map(|pos| pos + 7)
-                .ok_or("Could not find value attribute")?;
-            let value_end = line[value_start..].find("\"")
-                .map(|pos| value_start + pos)
-                .ok_or("Could not find end of value attribute")?;
-            let value_str = &line[value_start..value_end];
-            let value = value_str.parse::<f64>()?;
-            
-            // 只保存N_开头的变量
-            if name.starts_with("N_") {
-                variables.insert(name.to_string(), value);
-            }
-        }
-    }
-    
-    Ok(variables)
-}
-
-
 // fn ilp_solver_cplex(egraph: &SerializedEGraph, warm_start: Option<Vec<NodeId>>) -> Result<ExtractionResult, Box<dyn std::error::Error>> {
 //     ilp_gen::generate_ilp_file(egraph, &egraph.root_eclasses, "lp/total.lp", warm_start);
 
@@ -518,18 +158,6 @@ impl ExtractionResultttt {
     }
 }
 
-fn gen_gurobi_mst(activated: &FxHashSet<NodeId>, results: &ExtractionResult, filename: &str) {
-    let mut str = String::new();
-    for (cid,nid) in results.choices.iter() {
-        if activated.contains(nid) {
-            str.push_str(&format!("N_{}_{} 1\n", cid.0, nid.0[1]));
-        }
-        else{
-            str.push_str(&format!("A_{} 0\n", cid.0));
-        }
-    }
-    fs::write(filename, str).expect("Unable to write file");
-}
 
 // fn gen_cplex_mst(activated: &FxHashSet<NodeId>, results: &ExtractionResult, filename: &str) {
 //     let mut str = String::new();
@@ -560,128 +188,468 @@ fn gen_gurobi_mst(activated: &FxHashSet<NodeId>, results: &ExtractionResult, fil
 // }
 
 fn main() {
+    let mut cli = Cli::parse();
+    let run = match cli.command.take() {
+        None => cli.into_run(),
+        Some(cli::Command::Extract(args)) => args.into_run(),
+        Some(cli::Command::GenLp(args)) => args.into_run(),
+        Some(cli::Command::Solve(args)) => args.into_run(),
+        Some(cli::Command::Bench(args)) => return bench_command(args),
+        Some(cli::Command::Partition(args)) => return partition_command(args),
+        Some(cli::Command::Pareto(args)) => return pareto_command(args),
+        Some(cli::Command::Diff(args)) => return diff_command(args),
+        Some(cli::Command::Serve(args)) => return serve::serve(&args.addr),
+        Some(cli::Command::Egglog(args)) => return egglog_command(args),
+        Some(cli::Command::Regression(args)) => return regression_command(args),
+        Some(cli::Command::Synth(args)) => return synth_command(args),
+        Some(cli::Command::Worker(args)) => return worker_command(args),
+        Some(cli::Command::Distribute(args)) => return distribute_command(args),
+        Some(cli::Command::Repl(args)) => return repl_command(args),
+        Some(cli::Command::Dataset(args)) => return dataset_command(args),
+    };
+    run_egraph(run);
+}
 
-    // Get command-line arguments
-    let args: Vec<String> = env::args().collect();
-
-    // Initialize variables
-    let mut filename = String::new();
-    let mut extractor = String::from("faster-greedy-dag-mt1");
-    let mut bound: f32 = 1.25;
-    let mut solver = String::from("gurobi"); // Default solver
-    let mut timeout_secs: u64 = 1800; // Default timeout (30 minutes)
-    let mut pre_flag: i32 = 2; // Flag for preprocessing only
-    let mut result= ExtractionResult::new_empty();
-    
-    // Parse command line arguments
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--bound" => {
-                if i + 1 < args.len() {
-                    match args[i + 1].parse::<f32>() {
-                        Ok(value) => {
-                            bound = value;
-                        },
-                        Err(_) => {
-                            panic!("Error: Invalid bound value");
-                        }
-                    }
-                    i += 2;
-                } else {
-                    panic!("Error: Missing value for --bound parameter");
-                }
-            },
-            "--solver" => {
-                if i + 1 < args.len() {
-                    let solver_name = args[i + 1].to_lowercase();
-                    if solver_name == "gurobi" || solver_name == "cplex" || solver_name == "cpsat" {
-                        solver = solver_name;
-                    } else {
-                        panic!("Error: Unknown solver '{}'. Use 'gurobi', 'cplex' or 'cpsat'", args[i + 1]);
-                    }
-                    i += 2;
-                } else {
-                    panic!("Error: Missing value for --solver parameter");
-                }
-            },
-            "--timeout" => {
-                if i + 1 < args.len() {
-                    match args[i + 1].parse::<u64>() {
-                        Ok(value) => {
-                            if value > 0 {
-                                timeout_secs = value;
-                            } else {
-                                panic!("Error: Timeout must be greater than 0 seconds");
-                            }
-                        },
-                        Err(_) => {
-                            panic!("Error: Invalid timeout value");
-                        }
-                    }
-                    i += 2;
-                } else {
-                    panic!("Error: Missing value for --timeout parameter");
-                }
-            },
-            "--extractor" => {
-                if i + 1 < args.len() {
-                    let extractor_name = args[i + 1].to_lowercase();
-                    if extractor::extractors().keys().any(|key| key.to_lowercase() == extractor_name) {
-                        extractor = extractor_name;
-                    } else {
-                        panic!("Error: Unknown solver '{}'. Select from {:?}", args[i + 1], extractor::extractors().keys());
-                    }
-                    i += 2;
-                } else {
-                    panic!("Error: Missing value for --extractor parameter");
-                }
+fn egglog_command(args: cli::EgglogArgs) {
+    let options = egglog::EgglogOptions { extractor: args.extractor, ilp: args.solver.map(|solver| (solver, args.timeout, args.bound)) };
+    let input = args.input;
+
+    let egraph = egglog::EgglogEGraph::from_json_file(&input)
+        .with_context(|| format!("Failed to parse {input}"))
+        .unwrap();
+    let converted = egraph.convert().with_context(|| format!("Failed to convert {input}")).unwrap();
+    let base_name = std::path::Path::new(&input).file_stem().unwrap().to_string_lossy().to_string();
+    let result = egglog::run(&converted, &options, &base_name);
+    let json = to_string_pretty(&converted.to_json(&result)).unwrap();
+    match args.out {
+        Some(path) => fs::write(&path, json).expect("Unable to write file"),
+        None => println!("{}", json),
+    }
+}
+
+fn bench_command(args: cli::BenchArgs) {
+    let corpus_file = args.corpus;
+    let corpus = corpus::Corpus::from_json_file(&corpus_file).unwrap_or_else(|err| panic!("Failed to read {}: {}", corpus_file, err));
+    let selected = corpus.select(&args.tag);
+    if selected.is_empty() {
+        panic!("Error: No benchmark of {} has the tags {:?}", corpus_file, args.tag);
+    }
+    let exe = env::current_exe().expect("Failed to locate the executable");
+    let mut summary = Vec::new();
+    for benchmark in selected {
+        let Some(file) = corpus.file(benchmark) else {
+            match &benchmark.url {
+                Some(url) => eprintln!("Skipping {}: not downloaded, see {}", benchmark.name, url),
+                None => eprintln!("Skipping {}: file not found", benchmark.name),
             }
-            "--pre" => {
-                if i + 1 < args.len() {
-                    match args[i + 1].parse::<i32>() {
-                        Ok(value) => {
-                            if value == 0 || value == 1 || value == 2 || value == 3 || value == 4 || value == 5 {
-                                pre_flag = value;
-                            } else {
-                                panic!("Error: Pre flag must be 0, 1, or 2");
-                            }
-                        },
-                        Err(_) => {
-                            panic!("Error: Invalid pre flag value");
-                        }
-                    }
-                    i += 2;
-                } else {
-                    panic!("Error: Missing value for --pre parameter");
-                }
-            },
-            arg => {
-                // Assume this is the filename
-                filename = arg.to_string();
-                i += 1;
+            summary.push((benchmark, "missing".to_string(), 0.0));
+            continue;
+        };
+        println!("=== {} ({}) ===", benchmark.name, file.display());
+        let start = Instant::now();
+        let status = Command::new(&exe)
+            .args(&args.run_args)
+            .args(["--corpus", corpus_file.as_str()])
+            .arg(&file)
+            .status()
+            .expect("Failed to run benchmark");
+        let outcome = if status.success() { "ok".to_string() } else { format!("failed ({})", status) };
+        summary.push((benchmark, outcome, start.elapsed().as_secs_f64()));
+    }
+    println!("{:<32} {:<12} {:>10} {:>12}", "benchmark", "status", "seconds", "optimal dag");
+    for (benchmark, outcome, seconds) in summary {
+        let optimal = benchmark.optimal_dag.map_or("-".to_string(), |dag| dag.to_string());
+        println!("{:<32} {:<12} {:>10.2} {:>12}", benchmark.name, outcome, seconds, optimal);
+    }
+}
+
+fn partition_command(args: cli::PartitionArgs) {
+    let input = args.input;
+    let data = Data::from_json_file(&input).unwrap_or_else(|err| panic!("Failed to read {}: {}", input, err));
+    let name = std::path::Path::new(&input).file_stem().unwrap().to_string_lossy().to_string();
+    let parts = e_boost_core::partition::partition(&data, 1.0 / args.parts as f32);
+    fs::create_dir_all(&args.out_dir).unwrap_or_else(|err| panic!("Failed to create {}: {}", args.out_dir, err));
+    for (i, part) in parts.iter().enumerate() {
+        let path = format!("{}/{}_part{}.json", args.out_dir, name, i);
+        part.to_json_file(&path).unwrap_or_else(|err| panic!("Failed to write {}: {}", path, err));
+        let classes: FxHashSet<ClassId> = part.nodes.values().map(|node| node.eclass).collect();
+        println!("  {} nodes, {} classes", part.nodes.len(), classes.len());
+    }
+    println!("{} split into {} parts", input, parts.len());
+}
+
+fn pareto_command(args: cli::ParetoArgs) {
+    let input = args.input;
+    let data = Data::from_json_file(&input).unwrap_or_else(|err| panic!("Failed to read {}: {}", input, err));
+    let egraph = SerializedEGraph::from_Data(&data).unwrap();
+    let roots = &egraph.root_eclasses;
+    let start = Instant::now();
+    let front = extraction_gym::pareto::ParetoExtractor { max_depth: args.max_depth }.extract_front(&egraph, roots);
+    println!("{} points in {:.2}s", front.points.len(), start.elapsed().as_secs_f64());
+    for point in &front.points {
+        println!("  depth {:>4}  dag {}", point.depth, point.dag_cost);
+    }
+    if let Some(path) = args.json {
+        front.to_json_file(&egraph, roots, &path).unwrap_or_else(|err| panic!("Failed to write {}: {}", path, err));
+    }
+}
+
+/// A choices file if `path` ends in .json, else a solution file of any of the
+/// back ends.
+fn read_result(path: &str) -> ExtractionResult {
+    if path.ends_with(".json") {
+        return choices_file::ChoicesFile::from_json_file(path)
+            .unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err))
+            .to_result();
+    }
+    e_boost_core::solution::parse_solution(path).unwrap_or_else(|err| panic!("{}: {}", path, err))
+}
+
+fn diff_command(args: cli::DiffArgs) {
+    let input = args.egraph;
+    let data = Data::from_json_file(&input).unwrap_or_else(|err| panic!("Failed to read {}: {}", input, err));
+    let egraph = SerializedEGraph::from_Data(&data).unwrap();
+    let roots = &egraph.root_eclasses;
+    let (a, b) = (read_result(&args.a), read_result(&args.b));
+    for (path, result) in [(&args.a, &a), (&args.b, &b)] {
+        if let Err(err) = result.validate(&egraph) {
+            panic!("Error: {} is not a valid extraction of {}: {}", path, input, err);
+        }
+    }
+    let diff = extraction_gym::diff::compare(&egraph, roots, &a, &b);
+
+    println!("a: {}  tree {}  dag {}  depth {}", args.a, diff.a.tree, diff.a.dag, diff.a.depth);
+    println!("b: {}  tree {}  dag {}  depth {}", args.b, diff.b.tree, diff.b.dag, diff.b.depth);
+    println!("{} classes extracted differently", diff.changed.len());
+    let side = |nid: &Option<NodeId>| match nid {
+        Some(nid) => format!("{} ({})", egraph[nid].op, nid),
+        None => "-".to_string(),
+    };
+    const SHOWN: usize = 20;
+    for change in diff.changed.iter().take(SHOWN) {
+        println!("  class {}: {} -> {}", change.class, side(&change.a), side(&change.b));
+    }
+    if diff.changed.len() > SHOWN {
+        println!("  ... {} more", diff.changed.len() - SHOWN);
+    }
+    // The ops that change the DAG cost most first.
+    let mut deltas: Vec<(&String, f64, f64, i64)> = diff
+        .by_op
+        .iter()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(op, (a, b))| (op, *b.tree - *a.tree, *b.dag - *a.dag, b.depth as i64 - a.depth as i64))
+        .collect();
+    deltas.sort_by(|x, y| y.2.abs().total_cmp(&x.2.abs()).then(y.1.abs().total_cmp(&x.1.abs())));
+    println!("cost deltas (b - a) by op:");
+    println!("  {:<16} {:>12} {:>12} {:>8}", "op", "tree", "dag", "depth");
+    for (op, tree, dag, depth) in deltas {
+        println!("  {:<16} {:>+12} {:>+12} {:>+8}", op, tree, dag, depth);
+    }
+    if let Some(path) = args.json {
+        let json = serde_json::to_string_pretty(&diff.to_json(&egraph)).unwrap();
+        fs::write(&path, json).unwrap_or_else(|err| panic!("Failed to write {}: {}", path, err));
+    }
+}
+
+fn regression_command(args: cli::RegressionArgs) {
+    let golden_file = args.golden;
+    let golden_path = std::path::Path::new(&golden_file);
+    let dir = golden_path.parent().unwrap_or(std::path::Path::new("."));
+    let mut golden = match regression::Golden::from_json_file(golden_path) {
+        Ok(golden) => golden,
+        // --update starts a new golden file from the given e-graphs.
+        Err(_) if args.update => regression::Golden { epsilon: 1e-6, egraphs: Vec::new() },
+        Err(err) => panic!("Failed to read {}: {}", golden_file, err),
+    };
+    if let Some(epsilon) = args.epsilon {
+        golden.epsilon = epsilon;
+    }
+    // Relative to the golden file, like the ones it lists.
+    for file in args.files {
+        if !golden.egraphs.iter().any(|entry| entry.file == file) {
+            golden.egraphs.push(regression::GoldenEGraph { file, extractors: Default::default() });
+        }
+    }
+
+    if args.update {
+        regression::update(&mut golden, dir);
+        golden.to_json_file(golden_path).unwrap_or_else(|err| panic!("Failed to write {}: {}", golden_file, err));
+        println!("Golden costs written to {}", golden_file);
+        return;
+    }
+    let failures = regression::check(&golden, dir);
+    if !failures.is_empty() {
+        eprintln!("{} regressions:", failures.len());
+        for failure in &failures {
+            eprintln!("  {}", failure);
+        }
+        std::process::exit(1);
+    }
+    println!("No regressions");
+}
+
+fn synth_command(args: cli::SynthArgs) {
+    let config = synth::Config { kind: args.kind, bits: args.bits, operands: args.operands, seed: args.seed };
+    let out = args.out;
+
+    let data = synth::generate(&config);
+    let classes: FxHashSet<ClassId> = data.nodes.values().map(|node| node.eclass).collect();
+    data.to_json_file_pretty(&out).unwrap_or_else(|err| panic!("Failed to write {}: {}", out, err));
+    println!(
+        "{} nodes, {} classes, {} outputs",
+        data.nodes.len(),
+        classes.len(),
+        data.root_eclasses.len()
+    );
+}
+
+fn worker_command(args: cli::WorkerArgs) {
+    let addr = args.addr;
+    let listener = std::net::TcpListener::bind(&addr).unwrap_or_else(|err| panic!("Failed to bind {}: {}", addr, err));
+    println!("Worker listening on {}", addr);
+    distributed::worker(listener);
+}
+
+fn distribute_command(args: cli::DistributeArgs) {
+    let input = args.input;
+    let workers = args.workers;
+    let parts = args.parts.unwrap_or(workers.len());
+    let options = pipeline::Options {
+        extractor: args.extractor,
+        bound: args.bound,
+        solver: args.solver,
+        timeout: args.timeout,
+        pre_mode: pipeline::PreMode::from_flag(args.pre).expect("--pre of distribute is 3, 4 or 5"),
+        name: std::path::Path::new(&input).file_stem().unwrap().to_string_lossy().to_string(),
+        ..pipeline::Options::default()
+    };
+
+    let data = Data::from_json_file(&input).unwrap_or_else(|err| panic!("Failed to read {}: {}", input, err));
+    let start = Instant::now();
+    let distributed = distributed::distribute(&data, &workers, parts, &options).unwrap_or_else(|err| panic!("Error: {:#}", err));
+    let egraph = SerializedEGraph::from_Data(&data).unwrap();
+    let roots = &egraph.root_eclasses;
+    println!(
+        "{} result: tree {}, dag {} in {:.2}s",
+        if distributed.stitched { "Stitched" } else { "Local" },
+        distributed.result.tree_cost(&egraph, roots),
+        distributed.result.dag_cost(&egraph, roots),
+        start.elapsed().as_secs_f64()
+    );
+    if let Some(path) = args.json {
+        choices_file::ChoicesFile::from_result(&egraph, &distributed.result, roots)
+            .with_meta("extractor", options.extractor.as_str())
+            .with_meta("workers", workers.len())
+            .with_meta("parts", distributed.parts.len())
+            .with_meta("stitched", distributed.stitched)
+            .with_meta("repaired", distributed.repaired)
+            .to_json_file(&path)
+            .unwrap_or_else(|err| panic!("Failed to write {}: {}", path, err));
+    }
+}
+
+fn repl_command(args: cli::ReplArgs) {
+    let input = args.input;
+    let data = Data::from_json_file(&input).unwrap_or_else(|err| panic!("Failed to read {}: {}", input, err));
+    let egraph = SerializedEGraph::from_Data(&data).unwrap();
+    let result = match args.solution {
+        Some(path) => {
+            let result = choices_file::ChoicesFile::from_json_file(&path)
+                .unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err))
+                .to_result();
+            if !result.is_valid(&egraph) {
+                panic!("Error: {} is not a valid extraction of {}", path, input);
             }
+            result
         }
+        None => {
+            let extractors = extractor::extractors();
+            let ed = extractors.get(args.extractor.as_str()).unwrap_or_else(|| panic!("Unknown extractor: {}", args.extractor));
+            ed.get_extractor().extract(&egraph, &egraph.root_eclasses)
+        }
+    };
+    let mut session = repl::Session::new(&egraph, result);
+    println!(
+        "{}: {} classes, {} nodes, dag {}; type help for the commands",
+        input,
+        egraph.classes().len(),
+        egraph.nodes.len(),
+        session.execute("cost dag").unwrap().trim()
+    );
+    repl::run(&mut session, std::io::stdin().lock(), std::io::stdout()).unwrap();
+}
+
+#[cfg(not(feature = "parquet"))]
+fn dataset_command(_args: cli::DatasetArgs) {
+    panic!("Error: Dataset export needs the `parquet` feature (cargo run --features parquet)");
+}
+
+#[cfg(feature = "parquet")]
+fn dataset_command(args: cli::DatasetArgs) {
+    let input = args.input;
+    let extractor_name = args.extractor;
+    let data = Data::from_json_file(&input)
+        .with_context(|| format!("Failed to parse {input}"))
+        .unwrap();
+    let egraph = SerializedEGraph::from_Data(&data).unwrap();
+    let extractors = extractor::extractors();
+    let ed = extractors
+        .get(extractor_name.as_str())
+        .with_context(|| format!("Unknown extractor: {extractor_name}"))
+        .unwrap();
+    let heuristic = ed.get_extractor().extract(&egraph, &egraph.root_eclasses);
+    heuristic.check(&egraph);
+    let optimal = args.solution.map(|path| {
+        let optimal = solver::parse_solution_file(&path);
+        optimal.check(&egraph);
+        optimal
+    });
+
+    let name = std::path::Path::new(&input).file_stem().unwrap().to_string_lossy().to_string();
+    dataset::write_dataset(&egraph, &name, &heuristic, optimal.as_ref(), std::path::Path::new(&args.out_dir))
+        .unwrap_or_else(|err| panic!("Failed to write dataset: {}", err));
+}
+
+/// A run on one e-graph: the `--pre` steps of `extract`, `gen-lp` or `solve`, or
+/// of the flags without subcommand.
+fn run_egraph(run: cli::Run) {
+    let cli::Run { input: filename, pre: mut pre_flag, common, lp, solver: solver_args } = run;
+    let cli::CommonArgs {
+        extractor,
+        plugin: plugins,
+        cost_script: cost_script_file,
+        passes,
+        choice_policy,
+        deterministic,
+        seed,
+        epsilon,
+        fanout: fanout_limit,
+        fanout_weight,
+        salience: salience_file,
+        per_root,
+        delays: delay_file,
+        root_weights: root_weights_file,
+        signatures: signatures_file,
+        freeze: freeze_file,
+        merge_classes,
+        prune_unreachable,
+        partition: partition_parts,
+        cache: cache_dir,
+        corpus: corpus_file,
+        mem_limit,
+        snapshot: snapshot_minutes,
+        csv: csv_file,
+        output,
+    } = common;
+    let cli::OutputArgs { verilog: verilog_file, aiger: aiger_file, aig_map: aig_map_file, blif: blif_file, json: json_file, dot: dot_file, report: report_file } = output;
+    let cli::LpArgs { bound, mut lp_budget, acyclicity, op_weight, starts: start_extractors, lpsolve: lpsolve_file, mps: mps_file, stream_lp } = lp;
+    let cli::SolverArgs { solver, timeout: mut timeout_secs, monitor, target_gap, mip_gap, threads, checkpoint, resume_solve, tighten } = solver_args;
+    let monitor = solver::Monitor { interval: monitor, target_gap, checkpoint };
+    let mem_limit = mem_limit.map(memory::MemLimit::from_gb);
+    let mut bound = match bound {
+        cli::Bound::Factor(bound) => bound,
+        // Tuned once the heuristic ran.
+        cli::Bound::Auto => {
+            lp_budget.get_or_insert(pruning::DEFAULT_LP_BUDGET);
+            1.25
+        }
+    };
+    let mut result = ExtractionResult::new_empty();
+
+    // Plugins come first, so that --extractor can name their extractors.
+    #[cfg(feature = "plugins")]
+    for path in &plugins {
+        let names = plugin::load_plugin(path).unwrap_or_else(|err| panic!("Failed to load plugin {}: {}", path, err));
+        println!("Loaded plugin {}: {}", path, names.join(", "));
+    }
+    #[cfg(not(feature = "plugins"))]
+    if let Some(path) = plugins.first() {
+        cli::usage_error(ErrorKind::InvalidValue, format!("--plugin {} requires building with the `plugins` feature", path));
+    }
+    // And so does the cost script's.
+    #[cfg(feature = "scripting")]
+    let cost_script = cost_script_file.as_ref().map(|path| {
+        let script = script::load_cost_script(path).unwrap_or_else(|err| panic!("Failed to load cost script {}: {}", path, err));
+        println!("Loaded cost script {}: scripted-cost{}", path, if script.has_weight() { ", weight" } else { "" });
+        script
+    });
+    #[cfg(not(feature = "scripting"))]
+    if let Some(path) = &cost_script_file {
+        panic!("Error: --cost-script {} requires building with the `scripting` feature", path);
     }
+    if !extractor::extractors().keys().any(|key| key.to_lowercase() == extractor) {
+        cli::usage_error(ErrorKind::InvalidValue, format!("unknown extractor '{}', select from {:?}", extractor, extractor::extractors().keys()));
+    }
+    // Ctrl-C (or SIGTERM from a scheduler) stops the run but still writes its results.
+    interrupt::install();
 
     if pre_flag == 1 || pre_flag == 3 {
         bound = -1.0;
     }
 
-    // Check if filename is provided
-    if filename.is_empty() {
-        eprintln!("Error: No input file specified");
-        eprintln!("Usage: {} [OPTIONS] <input.json>", args[0]);
-        eprintln!("Options:");
-        eprintln!("  --bound <value>      Bound value (default: 1.25)");
-        eprintln!("  --solver <name>      Solver: gurobi, cplex, or cpsat (default: gurobi)");
-        eprintln!("  --timeout <seconds>  Timeout in seconds (default: 1800)");
-        eprintln!("  --extractor <name>   Extractor name (default: faster-greedy-dag-mt1)");
-        eprintln!("  --pre <flag>         Pre-processing flag: 0-5 (default: 2)");
-        eprintln!("");
-        eprintln!("Example: {} --bound 1.1 --solver gurobi input.json", args[0]);
-        std::process::exit(1);
+    let backends = race::backends(&solver).unwrap_or_else(|| vec![solver.as_str()]);
+    if matches!(acyclicity, ilp_gen::Acyclicity::Indicator | ilp_gen::Acyclicity::Lazy) && backends.iter().any(|&backend| backend == "highs" || backend == "cbc") {
+        cli::usage_error(
+            ErrorKind::ArgumentConflict,
+            format!("--acyclicity {} needs gurobi or cplex, {} reads neither indicator nor lazy constraints", acyclicity.name(), solver),
+        );
+    }
+    if acyclicity == ilp_gen::Acyclicity::Cuts && pre_flag == 0 {
+        cli::usage_error(ErrorKind::ArgumentConflict, "--acyclicity cuts finds the cycles of a solution in the e-graph, --pre 0 loads none");
+    }
+    // In process (`cpsat` feature), single runs solve the LP's model; races still run the wrapper.
+    if op_weight.is_some() && backends.contains(&"cpsat") && (backends.len() > 1 || !cfg!(feature = "cpsat")) {
+        cli::usage_error(ErrorKind::ArgumentConflict, "--op-weight is an LP objective term, the cpsat wrapper builds its model from the e-graph");
+    }
+    let fanout = fanout_limit.map(|limit| extraction_gym::FanoutPenalty { limit, weight: fanout_weight.unwrap_or(0.0) });
+    if fanout_weight.is_some() && extractor != "faster-greedy-dag" && extractor != "faster-greedy-dag-best-first" {
+        cli::usage_error(ErrorKind::ArgumentConflict, format!("--fanout-weight is a term of the faster-greedy-dag objective, {} has none", extractor));
     }
+    if passes.is_some() && extractor != "faster-greedy-dag-mt1" && extractor != "faster-greedy-dag-mt2" {
+        cli::usage_error(ErrorKind::ArgumentConflict, format!("--passes schedules the passes of faster-greedy-dag-mt1 and -mt2, {} makes a single one", extractor));
+    }
+    let policy = choice_policy.as_ref().map(|policy| {
+        extraction_gym::policy::parse_policy(policy)
+            .unwrap_or_else(|err| cli::usage_error(ErrorKind::InvalidValue, format!("invalid --choice-policy: {}", err)))
+    });
+    if policy.is_some() && !["faster-greedy-dag", "faster-greedy-dag-best-first", "faster-greedy-dag-mt1", "faster-greedy-dag-mt2"].contains(&extractor.as_str()) {
+        cli::usage_error(ErrorKind::ArgumentConflict, format!("--choice-policy picks between the cost sets of the faster-greedy-dag extractors, {} has none", extractor));
+    }
+    if resume_solve && pre_flag != 3 && pre_flag != 4 {
+        cli::usage_error(ErrorKind::ArgumentConflict, "--resume-solve needs a run that generates the LP and solves it (solve, --pre 3 or 4)");
+    }
+    if partition_parts.is_some() && ![3, 4, 5].contains(&pre_flag) {
+        cli::usage_error(ErrorKind::ArgumentConflict, format!("--partition stitches the extractions of the parts, --pre {} extracts nothing", pre_flag));
+    }
+    // The parts run the registered extractor and get the LP and solver options of
+    // `pipeline::Options`, none of the heuristic's own settings.
+    if partition_parts.is_some() {
+        let unsupported = [
+            ("--starts", !start_extractors.is_empty()),
+            ("--resume-solve", resume_solve),
+            ("--tighten", tighten),
+            ("--bound auto", lp_budget.is_some()),
+            ("--stream-lp", stream_lp),
+            ("--lpsolve", lpsolve_file.is_some()),
+            ("--mps", mps_file.is_some()),
+            ("--cache", cache_dir.is_some()),
+            ("--passes", passes.is_some()),
+            ("--choice-policy", choice_policy.is_some()),
+            ("--fanout-weight", fanout_weight.is_some()),
+            ("--delays", delay_file.is_some()),
+            ("--deterministic", deterministic),
+            ("--seed", seed != 0),
+            ("--epsilon", epsilon.is_some()),
+        ];
+        let unsupported: Vec<&str> = unsupported.iter().filter(|(_, set)| *set).map(|(flag, _)| *flag).collect();
+        if !unsupported.is_empty() {
+            cli::usage_error(ErrorKind::ArgumentConflict, format!("the parts of --partition run without {}", unsupported.join(", ")));
+        }
+    }
+    // The cuts of a solution's cycles are no model another solver can take, the exported files get the big-M rows.
+    let mps_acyclicity = if acyclicity == ilp_gen::Acyclicity::Cuts { ilp_gen::Acyclicity::BigM } else { acyclicity };
+    // lp_solve cannot express indicators, the big-M rows encode the same model.
+    let lpsolve_acyclicity = if mps_acyclicity == ilp_gen::Acyclicity::Indicator { ilp_gen::Acyclicity::BigM } else { mps_acyclicity };
 
     let path = std::path::Path::new(&filename);
 
@@ -696,21 +664,39 @@ fn main() {
         .to_string_lossy()
         .to_string();
 
-    let lp_file_path = format!("file/lp/{}_{}.lp", base_name, bound);
-    let mst_file_path = format!("file/start/{}_{}.mst", base_name, bound);
-    let zero_file_path = format!("file/ZeroNode/{}_{}_{}.mst", base_name, bound, solver);
-    let redundancy_file_path = format!("file/redundancy/{}_{}.json", base_name, bound);
-    let result_file = format!("file/result/{}_{}_{}.sol", base_name, bound, solver);
-    let pool = format!("file/pool/{}_{}_{}", base_name, bound, solver);
-    let log_file = format!("file/log/{}_{}_{}.log", base_name, bound, solver);
+    let files = solver::SolverFiles::new(&base_name, bound, &solver);
+    let lp_file_path = files.lp_file.clone();
+    let mst_file_path = files.mst_file.clone();
+    let hint_file_path = files.hint_file.clone();
+    let redundancy_file_path = files.redundancy_file.clone();
+    if let Some(limit) = mem_limit {
+        limit.watch();
+    }
+    #[cfg(feature = "gurobi")]
+    gurobi::set_params(gurobi::Params { mip_gap, threads });
+    #[cfg(not(feature = "gurobi"))]
+    if mip_gap.is_some() || threads.is_some() {
+        panic!("Error: --mip-gap and --threads require building with the `gurobi` feature");
+    }
+    if let Some(minutes) = snapshot_minutes {
+        snapshot::start(&files.snapshot_file, std::time::Duration::from_secs_f64(minutes * 60.0));
+        println!("Saving snapshots to {} every {} minutes", files.snapshot_file, minutes);
+    }
 
-    if extractor != "faster-greedy-dag" && extractor != "faster-greedy-dag-mt1" && extractor != "faster-greedy-dag-mt2" {
+    // Warm start + pruning needs the per-node cost map, fall back to a heuristic run otherwise.
+    let fills_node_costs = extractor::extractors()
+        .get(extractor.as_str())
+        .is_some_and(|ed| ed.get_extractor().fills_node_costs());
+    if !fills_node_costs {
         pre_flag = 5;
     }
 
     println!("Using solver: {}", solver);
     println!("Using extractor: {}", extractor);
-    println!("Using bound value: {}", bound);
+    match lp_budget {
+        Some(budget) => println!("Using bound value: auto, for an LP of at most {} binaries", budget),
+        None => println!("Using bound value: {}", bound),
+    }
     println!("Using timeout: {} seconds", timeout_secs);
     println!("Pre-processing mode: {}", match pre_flag {
         0 => "Solver only (skip LP generation)",
@@ -725,12 +711,21 @@ fn main() {
     });
     println!("LP file path: {}", lp_file_path);
     println!("MST file path: {}", mst_file_path);
-    println!("Zero Node file path: {}", zero_file_path);
+    println!("Hint file path: {}", hint_file_path);
 
 
     let mut zero_node = Vec::<NodeId>::new();
     let mut runtime: f64 = 0.0;
+    // DAG cost and runtime of the heuristic, runtime of the LP, and runtime and timeline of the solver, for --csv and --report.
+    let mut heuristic: Option<(f64, f64)> = None;
+    let mut lp_secs: Option<f64> = None;
+    let mut lp_binaries: Option<usize> = None;
+    let mut solve: Option<(f64, Option<timeline::Timeline>)> = None;
     let mut total_egraph;
+    // The e-graph without the --root-weights, to report the unweighted costs on.
+    let mut unweighted_egraph = None;
+    // Set when --partition ran the steps on the parts, whose stitched result is `result`.
+    let mut partitioned = false;
     
     // Create all necessary directories
     let directories = vec![
@@ -741,7 +736,7 @@ fn main() {
         "file/result",
         "file/log",
         "file/redundancy",
-        &pool,
+        &files.pool_dir,
     ];
     
     for dir in directories {
@@ -750,11 +745,49 @@ fn main() {
         });
     }
 
+    // Set when the input was loaded and --cache is given.
+    let mut egraph_hash: Option<String> = None;
+    let cache = cache_dir.as_ref().map(cache::ResultCache::new);
+    let epsilon_schedule = epsilon.unwrap_or(extraction_gym::epsilon_greedy::EpsilonGreedyDagExtractor::default().epsilon);
+    let epsilon_extractor = |seed: u64| -> Box<dyn extraction_gym::Extractor> {
+        Box::new(extraction_gym::epsilon_greedy::EpsilonGreedyDagExtractor { seed, epsilon: epsilon_schedule, ..Default::default() })
+    };
+    // The weighted-depth extraction also depends on the delay table.
+    let cache_extractor = match &delay_file {
+        Some(path) if extractor == "faster-weighted-depth" || extractor == "faster-delay-depth" => {
+            let delays = fs::read(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err));
+            format!("{}-{}", extractor, cache::ResultCache::content_hash(&delays))
+        }
+        _ if extractor == "epsilon-greedy" => format!("{}-seed{}-eps{}", extractor, seed, epsilon_schedule),
+        _ => match (fanout.filter(|penalty| penalty.weight > 0.0), &passes) {
+            (Some(penalty), _) => format!("{}-fanout{}x{}", extractor, penalty.limit, penalty.weight),
+            (None, Some(passes)) => format!("{}-passes-{}", extractor, passes),
+            (None, None) => extractor.clone(),
+        },
+    };
+    let cache_extractor = match &choice_policy {
+        Some(policy) => format!("{}-policy-{}", cache_extractor, policy),
+        None => cache_extractor,
+    };
+    // Choices that every extraction has to keep, given as a (partial) solution file.
+    let frozen: IndexMap<ClassId, NodeId> = match &freeze_file {
+        Some(path) => solver::parse_solution_file(path).choices,
+        None => IndexMap::new(),
+    };
+    let cache_extractor = match &freeze_file {
+        Some(path) => {
+            let contents = fs::read(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err));
+            format!("{}-frozen-{}", cache_extractor, cache::ResultCache::content_hash(&contents))
+        }
+        None => cache_extractor,
+    };
+
     if pre_flag == 0 {
         println!("Skipping extraction phase (--pre=0 mode)");
         let empty_data = Data {
             nodes: IndexMap::new(),
             root_eclasses: Vec::new(),
+            class_data: IndexMap::new(),
         };
         total_egraph = SerializedEGraph::from_Data(&empty_data)
             .with_context(|| format!("Failed to create empty egraph"))
@@ -768,10 +801,62 @@ fn main() {
             .with_context(|| format!("Failed to parse {filename}"))
             .unwrap();
         // remove_redundant_nodes(&mut data, "dag");
-        data.to_json_file(redundancy_file_path.clone());
-        let mut paritioned_data = Vec::<Data>::new();
- 
+        if let Some(path) = &signatures_file {
+            let signatures = extraction_gym::signatures::Signatures::from_json_file(path)
+                .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path, err));
+            match signatures.apply(&mut data) {
+                Ok(removed) => println!("Removed {} ill-typed nodes", removed),
+                Err(root) => panic!("Error: Root class {} has no well-typed node", root.0),
+            }
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(script) = cost_script.as_ref().filter(|script| script.has_weight()) {
+            script.apply_weights(&mut data);
+        }
+        // The root weights scale the costs of the cost script, and the costs before
+        // are kept to report the extraction without them.
+        let unweighted_costs = root_weights_file.as_ref().map(|path| {
+            let root_weights = extraction_gym::root_weights::RootWeights::from_json_file(path)
+                .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path, err));
+            root_weights.apply(&mut data)
+        });
+        if merge_classes {
+            let merged = e_boost_core::redundancy::merge_duplicate_classes(&mut data);
+            println!("Merged {} duplicate classes, {} nodes left", merged, data.nodes.len());
+        }
+        if prune_unreachable {
+            let mut egraph = SerializedEGraph::from_Data(&data).unwrap();
+            let removed = egraph.prune_unreachable(&data.root_eclasses);
+            if let Some(root) = data.root_eclasses.iter().find(|root| !egraph.classes().contains_key(*root)) {
+                panic!("Error: Root class {} has no node left after pruning", root.0);
+            }
+            println!("Pruned {} unreachable or dead nodes, {} left", removed, egraph.nodes.len());
+            data.nodes = egraph.nodes;
+            data.class_data = egraph.class_data;
+        }
+        data.to_json_file(redundancy_file_path.clone()).expect("Unable to write file");
         total_egraph = SerializedEGraph::from_Data(&data).with_context(|| format!("Failed to get egraph")).unwrap();
+        unweighted_egraph = unweighted_costs.map(|costs| extraction_gym::root_weights::RootWeights::unweighted(&total_egraph, &costs));
+        if cache_dir.is_some() {
+            let read = |path: &std::path::Path| fs::read(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+            // Root weights change the costs, so they are part of the e-graph's identity.
+            // So do the signatures, which remove nodes, and the cost script.
+            let mut files = vec![("input", read(&file_path))];
+            for (flag, path) in [("--root-weights", &root_weights_file), ("--signatures", &signatures_file), ("--cost-script", &cost_script_file)] {
+                if let Some(path) = path {
+                    files.push((flag, read(path.as_ref())));
+                }
+            }
+            let mut inputs: Vec<(&str, &[u8])> = files.iter().map(|(flag, bytes)| (*flag, bytes.as_slice())).collect();
+            // Merging and pruning remove nodes.
+            if merge_classes {
+                inputs.push(("--merge-classes", b""));
+            }
+            if prune_unreachable {
+                inputs.push(("--prune-unreachable", b""));
+            }
+            egraph_hash = Some(cache::ResultCache::inputs_hash(&inputs));
+        }
 
 
         // remove lp and mst file if exist
@@ -783,239 +868,543 @@ fn main() {
             fs::remove_file(&mst_file_path)
                 .unwrap_or_else(|err| eprintln!("Failed to delete {}: {}", mst_file_path, err));
         }
+
+        if let Some(parts) = partition_parts {
+            let options = pipeline::Options {
+                extractor: extractor.clone(),
+                bound,
+                solver: solver.clone(),
+                timeout: timeout_secs,
+                pre_mode: pipeline::PreMode::from_flag(pre_flag).expect("--partition runs --pre 3, 4 or 5"),
+                out_dir: PathBuf::from(&files.dir),
+                name: base_name.clone(),
+                in_memory: false,
+                frozen: frozen.clone(),
+                acyclicity,
+                op_weight,
+                monitor,
+            };
+            let start = Instant::now();
+            let output = distributed::run_partitioned(&data, parts, &options).unwrap_or_else(|err| panic!("Error: {:#}", err));
+            runtime = start.elapsed().as_secs_f64();
+            result = output.result;
+            let dag = result.dag_cost(&total_egraph, &total_egraph.root_eclasses);
+            println!(
+                "{} {} parts: tree:{} dag:{} in {:.2}s, {} classes on cycles extracted again",
+                if output.stitched { "Stitched" } else { "Extracted the whole e-graph instead of" },
+                output.parts.len(),
+                result.tree_cost(&total_egraph, &total_egraph.root_eclasses),
+                dag,
+                runtime,
+                output.repaired
+            );
+            if pre_flag == 5 {
+                heuristic = Some((dag.into_inner(), runtime));
+            } else {
+                solve = Some((runtime, None));
+            }
+            partitioned = true;
+        }
     }
 
-    if pre_flag == 2 || pre_flag == 4 || pre_flag == 5 {
+    let mut delay_model = delay_file.as_ref().map(|path| {
+        extraction_gym::delay::DelayModel::from_json_file(path)
+            .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path, err))
+    });
+    // faster-delay-depth times the nodes the table doesn't cover by their cost, and
+    // its critical path is the depth reported.
+    if extractor == "faster-delay-depth" {
+        delay_model = Some(extraction_gym::delay::DelayModel { cost_as_delay: true, ..delay_model.unwrap_or_default() });
+    }
+
+    // Further warm starts, from the --starts extractors.
+    let mut starts: Vec<ExtractionResult> = Vec::new();
+    // The full-adder-aware extractors' alternative without the adders, a further start.
+    let mut secondary_start: Option<ExtractionResult> = None;
+    if !partitioned && (pre_flag == 2 || pre_flag == 4 || pre_flag == 5) {
         let mut extractors: indexmap::IndexMap<&str, extractor::ExtractorDetail, _> = extractor::extractors();
         extractors.retain(|_, ed| ed.get_use_for_bench());
-        let extractor_name: String = extractor.into();
+        let extractor_name: String = extractor.clone();
         let ed = extractors
             .get(extractor_name.as_str())
             .with_context(|| format!("Unknown extractor: {extractor_name}"))
             .unwrap();
+        // The registered weighted-depth extractors use unit delays or the node costs, rebuild them with the given table.
+        let weighted_depth_extractor = delay_model
+            .clone()
+            .map(extraction_gym::faster_weighted_depth::FasterWeightedDepthExtractor::new);
+        // The registered faster-greedy-dag extractors have no fanout penalty and choose by strict cost.
+        let penalty = fanout.filter(|penalty| penalty.weight > 0.0);
+        let greedy_dag_extractor = ((extractor_name == "faster-greedy-dag" || extractor_name == "faster-greedy-dag-best-first") && (penalty.is_some() || policy.is_some())).then(|| {
+            extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor {
+                frontier: if extractor_name == "faster-greedy-dag-best-first" { extraction_gym::queue::Frontier::BestFirst } else { extraction_gym::queue::Frontier::Fifo },
+                fanout: penalty,
+                policy: policy.clone(),
+            }
+        });
+        let multi_pass_extractor = extractor::multi_pass_extractor(&extractor_name, passes.clone(), deterministic, policy.clone());
+        let configured_extractor = if extractor_name == "epsilon-greedy" {
+            Some(epsilon_extractor(seed))
+        } else if deterministic {
+            extractor::deterministic_extractor(&extractor_name)
+        } else {
+            None
+        };
+        let selected: &dyn extraction_gym::Extractor = match (&weighted_depth_extractor, &greedy_dag_extractor, &configured_extractor) {
+            (Some(weighted), _, _) if extractor_name == "faster-weighted-depth" || extractor_name == "faster-delay-depth" => weighted,
+            (_, Some(greedy_dag), _) => greedy_dag,
+            (_, _, Some(fixed)) => fixed.as_ref(),
+            _ => ed.get_extractor(),
+        };
+        let cache_key = egraph_hash
+            .as_ref()
+            .map(|hash| cache::ResultCache::heuristic_key(hash, &cache_extractor, deterministic));
+        let cached = match (&cache, &cache_key) {
+            (Some(cache), Some(key)) => cache.get(key, &total_egraph),
+            _ => None,
+        };
         let start = Instant::now();
-        result = ed.get_extractor().extract(&total_egraph, &total_egraph.root_eclasses);
+        result = match cached {
+            Some(cached) => {
+                println!("Using cached {} result", extractor_name);
+                cached
+            }
+            None => {
+                let dual = extractor::dual_extract(&extractor_name, &total_egraph, &total_egraph.root_eclasses, &frozen);
+                let extracted = match (dual, &multi_pass_extractor) {
+                    (Some(dual), _) => {
+                        secondary_start = dual.alternative(&total_egraph);
+                        println!("{}: {} classes with a secondary choice", extractor_name, dual.secondary.len());
+                        dual.result
+                    }
+                    (None, Some(multi_pass)) => {
+                        let (extracted, passes) = multi_pass.extract_frozen_with_passes(&total_egraph, &total_egraph.root_eclasses, &frozen);
+                        println!("{}: {}", extractor_name, passes);
+                        extracted
+                    }
+                    (None, None) => selected.extract_frozen(&total_egraph, &total_egraph.root_eclasses, &frozen),
+                };
+                if let (Some(cache), Some(key)) = (&cache, &cache_key) {
+                    cache.put(key, &extracted).unwrap_or_else(|err| eprintln!("Failed to cache result: {}", err));
+                }
+                extracted
+            }
+        };
         let grownth_duration = start.elapsed();
         runtime += grownth_duration.as_secs_f64();
         result.check(&total_egraph);
         let tree = result.tree_cost(&total_egraph, &total_egraph.root_eclasses);
         let dag = result.dag_cost(&total_egraph, &total_egraph.root_eclasses);
         let depth = result.depth_cost(&total_egraph, &total_egraph.root_eclasses);
-        println!("{:<18}: runtime-{} tree:{} dag:{} depth: {}", extractor_name, runtime, tree, dag, depth);
-    }
-
-    if pre_flag == 1 || pre_flag == 2 || pre_flag == 3 || pre_flag == 4 {
-        // Generate MST files based on solver type - only when pre_flag == 1
-        if (pre_flag == 2 || pre_flag == 4) {
-            collect_results(result.cost.clone(), bound, &mut zero_node);
-            println!("zero_node: {:?}", zero_node.len());
-            let activated: FxHashSet<NodeId> = result.activate_nodes(&total_egraph, &total_egraph.root_eclasses);
-            if solver == "gurobi" || solver == "cplex" {
-                gen_gurobi_mst(&activated,&result, &mst_file_path);
-                println!("MST file successfully generated at: {}", mst_file_path);
-            }
-            //  else if solver == "cplex" {
-            //     gen_cplex_mst(&activated,&result, &mst_file_path);
-            //     println!("MST file successfully generated at: {}", mst_file_path);
-            // }
-            else if solver == "cpsat" {
-                let mut str = String::new();
-                for nid in zero_node.iter() {
-                    str.push_str(&format!("N_{}_{}\n", nid.0[0], nid.0[1]));
-                }
-                fs::write(zero_file_path.clone(), str).expect("Unable to write file");
-                println!("Zero Node file successfully generated at: {}", zero_file_path);
-                gen_gurobi_mst(&activated,&result, &mst_file_path);
-                println!("MST file successfully generated at: {}", mst_file_path);
-            }
-            else {
-                panic!("Error: Unknown solver: {}", solver);
+        let area = result.area_cost(&total_egraph);
+        snapshot::offer(&extractor_name, &result, dag.into_inner());
+        heuristic = Some((dag.into_inner(), runtime));
+        match &delay_model {
+            Some(delays) => {
+                let weighted_depth = result.weighted_depth_cost(&total_egraph, &total_egraph.root_eclasses, delays);
+                println!("{:<18}: runtime-{} tree:{} dag:{} depth: {} weighted depth: {} area: {}", extractor_name, runtime, tree, dag, depth, weighted_depth, area);
             }
+            None => println!("{:<18}: runtime-{} tree:{} dag:{} depth: {} area: {}", extractor_name, runtime, tree, dag, depth, area),
+        }
 
-            println!("Generating LP file: {}", lp_file_path);
-            ilp_gen::generate_ilp_file(&total_egraph, &total_egraph.root_eclasses, &lp_file_path, Some(zero_node));
+        if pre_flag != 5 && !start_extractors.is_empty() {
+            let start = Instant::now();
+            let egraph = FrozenEGraph::new(&total_egraph);
+            // Each epsilon-greedy start gets its own seed, so that they differ.
+            let seeded: Vec<Option<Box<dyn extraction_gym::Extractor>>> = start_extractors
+                .iter()
+                .enumerate()
+                .map(|(k, name)| (name == "epsilon-greedy").then(|| epsilon_extractor(seed + k as u64 + 1)))
+                .collect();
+            starts = std::thread::scope(|scope| {
+                let handles: Vec<_> = start_extractors
+                    .iter()
+                    .zip(&seeded)
+                    .map(|(name, seeded)| {
+                        let ed = extractors.get(name.as_str()).with_context(|| format!("Unknown extractor: {name}")).unwrap();
+                        let start_extractor = seeded.as_deref().unwrap_or(ed.get_extractor());
+                        let frozen = &frozen;
+                        scope.spawn(move || start_extractor.extract_frozen(&egraph, &egraph.root_eclasses, frozen))
+                    })
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().expect("Warm start extractor panicked")).collect()
+            });
+            for (name, extracted) in start_extractors.iter().zip(&starts) {
+                extracted.check(&total_egraph);
+                let dag = extracted.dag_cost(&total_egraph, &total_egraph.root_eclasses);
+                snapshot::offer(name, extracted, dag.into_inner());
+                println!("{:<18}: dag:{} (warm start)", name, dag);
+            }
+            println!("Warm starts extracted in {:.2}s", start.elapsed().as_secs_f64());
         }
-        else{
-            ilp_gen::generate_ilp_file(&total_egraph, &total_egraph.root_eclasses, &lp_file_path, None);
+        if let Some(secondary) = secondary_start.take().filter(|_| pre_flag != 5) {
+            let name = format!("{}-secondary", extractor_name);
+            let dag = secondary.dag_cost(&total_egraph, &total_egraph.root_eclasses);
+            snapshot::offer(&name, &secondary, dag.into_inner());
+            println!("{:<18}: dag:{} (warm start)", name, dag);
+            starts.push(secondary);
         }
-        println!("LP file successfully generated at: {}", lp_file_path);
     }
 
-    if pre_flag == 0 || pre_flag == 3 || pre_flag == 4 {
-        println!("Running solver: {}", solver);
-        
-        // Make sure the LP file exists
-        if !std::path::Path::new(&lp_file_path).exists() {
-            panic!("Error: LP file not found: {}", lp_file_path);
+    // The checkpoint replaces the heuristic as the warm start and the time limit
+    // becomes what the interrupted run had left.
+    let checkpoint = if !resume_solve {
+        None
+    } else {
+        match solver::Checkpoint::read(&files.checkpoint_file) {
+            Ok(checkpoint) => {
+                println!("Resuming from {}: objective {}, {} seconds left", files.checkpoint_file, checkpoint.objective, checkpoint.remaining_secs);
+                timeout_secs = checkpoint.remaining_secs.max(1);
+                Some(checkpoint)
+            }
+            Err(err) => {
+                eprintln!("Warning: No checkpoint to resume from: {}: {}", files.checkpoint_file, err);
+                None
+            }
         }
+    };
 
+    // After a signal, the heuristic's result is the one written.
+    let interrupted = interrupt::requested();
+    if interrupted && !partitioned && pre_flag != 5 {
+        println!("Interrupted, skipping the LP and the solver");
+    }
 
-        // Check if MST file exists when in solver-only mode
-        if !std::path::Path::new(&mst_file_path).exists() {
-            eprintln!("Warning: MST file not found: {}", mst_file_path);
-            eprintln!("Continuing without warm start solution");
+    // The LP and the solver run on a `Booster`, from the heuristic's result when
+    // it prunes and warm-starts.
+    let mut booster = pipeline::PreMode::from_flag(pre_flag).filter(|_| !interrupted && !partitioned && pre_flag != 5).map(|pre_mode| {
+        let options = pipeline::Options {
+            extractor: extractor.clone(),
+            bound,
+            solver: solver.clone(),
+            timeout: timeout_secs,
+            pre_mode,
+            name: base_name.clone(),
+            frozen: frozen.clone(),
+            acyclicity,
+            op_weight,
+            monitor,
+            ..pipeline::Options::default()
+        };
+        let mut booster = pipeline::Booster::new(&total_egraph, options).unwrap_or_else(|err| panic!("Error: {:#}", err));
+        if pre_flag == 2 || pre_flag == 4 {
+            booster.set_heuristic(result.clone(), fills_node_costs);
+            for start in &starts {
+                booster.add_start(start.clone());
+            }
         }
-
-        if solver == "cpsat" && !std::path::Path::new(&zero_file_path).exists() {
-            eprintln!("Warning: Zero Node file not found: {}", zero_file_path);
-            eprintln!("Continuing without warm start solution");
+        if let Some(checkpoint) = &checkpoint {
+            booster.resume(checkpoint.solution.clone());
         }
+        booster
+    });
 
-        // Run the selected solver as a child process
-        let mut runtime_solve: f64 = 0.0;
-        let start_solve = Instant::now();
-        let mut child = match solver.as_str() {
-            "gurobi" => {
-                // Using Gurobi
-                let mut cmd = Command::new("gurobi/gurobi_solver");
-                let mut args = vec![
-                    "--lp_file".to_string(),
-                    lp_file_path.clone(),
-                    "--output_file".to_string(), 
-                    result_file.clone(),
-                    "--time_limit".to_string(),
-                    timeout_secs.to_string(),
-                    // "--solution_pool_dir".to_string(),
-                    // pool,
-                    "--log_file".to_string(),
-                    log_file,
-                ];
-                
-                // Add MST file if it exists
-                if std::path::Path::new(&mst_file_path).exists() {
-                    args.insert(0, "--mst_file".to_string());
-                    args.insert(1, mst_file_path.clone());
+    if let Some(booster) = booster.as_mut() {
+        let start_lp = Instant::now();
+        if pre_flag == 2 || pre_flag == 4 {
+            if let Some(budget) = lp_budget {
+                let tuned = pruning::auto_bound(&total_egraph, &result, budget);
+                println!(
+                    "Auto bound {}: about {} binaries of the --lp-budget {} ({} unpruned; {:.2} nodes per class, cost CV {:.2}; {} trial prunes)",
+                    tuned.bound, tuned.binaries, budget, tuned.unpruned, tuned.candidates, tuned.cost_cv, tuned.trials
+                );
+                if tuned.binaries > budget {
+                    eprintln!("Warning: the LP doesn't fit --lp-budget {} even with bound 1", budget);
                 }
-
-                
-                println!("command: {}", args.join(" "));
-                
-                cmd.args(args)
-                    .spawn()
-                    .expect("Failed to start Gurobi solver")
-
-                
-            },
-            "cplex" => {
-                // Using CPLEX
-                let mut cmd = Command::new("cplex/cplex_solver");
-                let mut args = vec![
-                    "--lp_file".to_string(),
-                    lp_file_path.clone(),
-                    "--output_file".to_string(), 
-                    result_file.clone(),
-                    "--time_limit".to_string(),
-                    timeout_secs.to_string(),
-                    // "--solution_pool_dir".to_string(),
-                    // pool,
-                    "--log_file".to_string(),
-                    log_file,
-                ];
-                
-                // Add MST file if it exists
-                if std::path::Path::new(&mst_file_path).exists() {
-                    args.insert(0, "--mst_file".to_string());
-                    args.insert(1, mst_file_path.clone());
+                bound = tuned.bound;
+            }
+            zero_node = booster.pruned(bound);
+            // Prune harder until the LP fits under the memory limit. A streamed LP is
+            // never in memory.
+            if let Some(available) = mem_limit.filter(|_| !stream_lp).and_then(|limit| limit.available()) {
+                let needed = |zero_node: &[NodeId]| (total_egraph.nodes.len() - zero_node.len()) as u64 * memory::LP_BYTES_PER_NODE;
+                while needed(&zero_node) > available && bound > 1.0 {
+                    let lower = if bound < 1.01 { 1.0 } else { 1.0 + (bound - 1.0) / 2.0 };
+                    println!(
+                        "The LP needs about {} MB with bound {}, {} MB are left under --mem-limit: lowering the bound to {}",
+                        needed(&zero_node) >> 20,
+                        bound,
+                        available >> 20,
+                        lower
+                    );
+                    bound = lower;
+                    zero_node = booster.pruned(bound);
                 }
-
-                // clear;cplex/cplex_solver --lp_file file/lp/serialized_egraph_32_1.25.lp --output_file file/result/serialized_egraph_32_1.25_cplex.sol --log_file file/log/serialized_egraph_32_1.25_cplex.log --time_limit 50 --solution_pool_dir pool --mst_file file/start/serialized_egraph_32_1.25_cplex.mst
-
-                println!("command: {}", args.join(" "));
-                
-                cmd.args(args)
-                    .spawn()
-                    .expect("Failed to start CPLEX solver")
-            },
-            "cpsat" => {
-                let mut cmd = Command::new("cpsat/cpsat");
-                let mut args = vec![
-                    "--egraph_json_file".to_string(),
-                    redundancy_file_path.to_string(),
-                    "--output_sol_file".to_string(), 
-                    result_file.clone(),
-                    "--time_limit".to_string(),
-                    timeout_secs.to_string(),
-                    // "--solution_pool_dir".to_string(),
-                    // pool,
-                    "--log_file".to_string(),
-                    log_file,
-                ];
-
-                if std::path::Path::new(&mst_file_path).exists() {
-                    args.insert(0, "--total_gurobi_mst".to_string());
-                    args.insert(1, mst_file_path.clone());
+                if needed(&zero_node) > available {
+                    panic!("Error: The LP needs about {} MB even with bound 1, {} MB are left under --mem-limit", needed(&zero_node) >> 20, available >> 20);
                 }
-
-                if std::path::Path::new(&zero_file_path).exists() {
-                    args.insert(2, "--zero_node_mst".to_string());
-                    args.insert(3, zero_file_path.clone());
+            }
+            booster.set_bound(bound);
+            println!("zero_node: {:?}", zero_node.len());
+        }
+        else{
+            // Without a heuristic there is nothing to prune with.
+            if let Some(available) = mem_limit.filter(|_| !stream_lp).and_then(|limit| limit.available()) {
+                let needed = total_egraph.nodes.len() as u64 * memory::LP_BYTES_PER_NODE;
+                if needed > available {
+                    panic!("Error: The LP needs about {} MB, {} MB are left under --mem-limit; --pre 2 or 4 prune it", needed >> 20, available >> 20);
                 }
-
-                
-                println!("command: {}", args.join(" "));
-
-                cmd.args(args)
-                    .spawn()
-                    .expect("Failed to start CPSAT solver")
-            },
-            _ => {
-                panic!("Error: Unknown solver: {}", solver);
             }
-        };
-
-
-        println!("-----------------------------------------------------");
-        let status = child.wait().expect("Failed to wait for solver process");
-        println!("-----------------------------------------------------");
-
-        let grownth_duration_solve = start_solve.elapsed();
-        runtime_solve += grownth_duration_solve.as_secs_f64();
-
-        if !status.success() {
-            
-            panic!("{} did not exit successfully.", solver);
         }
-
-        if !std::path::Path::new(result_file.as_str()).exists() {
-            panic!("Solver did not produce a solution file");
+        // The MST start and the hint file are written with the LP, from the same model.
+        println!("Generating LP file: {}", lp_file_path);
+        let pruned = (pre_flag == 2 || pre_flag == 4).then(|| zero_node.clone());
+        if let Some(lpsolve_file) = &lpsolve_file {
+            let options = ilp_gen::ModelOptions { pruned: pruned.as_deref(), frozen: Some(&frozen), acyclicity: lpsolve_acyclicity, op_weight };
+            ilp_gen::generate_lpsolve_file(&total_egraph, &total_egraph.root_eclasses, lpsolve_file, options);
         }
-
-        let sol_contents = fs::read_to_string(result_file).expect("Failed to read solution file");
-        if sol_contents.trim().is_empty() {
-            panic!("Solver produced an empty solution file");
+        // The MST of the LP is the warm start of the MPS model too.
+        if let Some(mps_file) = &mps_file {
+            let options = ilp_gen::ModelOptions { pruned: pruned.as_deref(), frozen: Some(&frozen), acyclicity: mps_acyclicity, op_weight };
+            ilp_gen::generate_mps_file(&total_egraph, &total_egraph.root_eclasses, mps_file, options, None);
         }
-        let mut ilp_solution = ExtractionResult::new(IndexMap::new());
-        
-        // Parse the solution file
-        for line in sol_contents.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+        let generated = if stream_lp { booster.stream_lp() } else { booster.generate_lp().map(|_| ()) };
+        generated.unwrap_or_else(|err| panic!("Error: {:#}", err));
+        println!("LP file successfully generated at: {}", lp_file_path);
+        lp_secs = Some(start_lp.elapsed().as_secs_f64());
+        lp_binaries = Some(pruning::lp_binaries(&total_egraph, &total_egraph.root_eclasses, &zero_node));
+    }
 
-            let parts: Vec<_> = line.split_whitespace().collect();
-            if parts.len() == 2 {
-                let var_name = parts[0];
-                if var_name.starts_with("N_") {
-                    let cid = var_name[2..].split('_').next().unwrap().parse::<u32>().unwrap();
-                    let nid = var_name[2..].split('_').nth(1).unwrap().parse::<u32>().unwrap();
-                    let var_value_str = parts[1];
-                    let val = var_value_str.parse::<f64>().expect(format!("Failed to parse solution value: {:?}", var_value_str).as_str()).round() as i32;
-                    if val == 1 {
-                        if !ilp_solution.choices.contains_key(&ClassId::from(cid)) {
-                            ilp_solution.choose(ClassId::from(cid), NodeId::from((cid, nid)));
-                        } else {
-                            panic!("classid already exists");
+    if !interrupted && !partitioned && (pre_flag == 0 || pre_flag == 3 || pre_flag == 4) {
+        let start_solve = Instant::now();
+        // Without a warm start the solution does not depend on the extractor.
+        let cache_key = egraph_hash.as_ref().map(|hash| {
+            let extractor = if pre_flag == 4 { cache_extractor.as_str() } else { "none" };
+            let mut solver = if tighten && pre_flag == 4 { format!("{}-tighten", solver) } else { solver.clone() };
+            // Same optimum, but not the same solution within a time limit.
+            if acyclicity != ilp_gen::Acyclicity::BigM {
+                solver = format!("{}-{}", solver, acyclicity.name());
+            }
+            if let Some(gap) = monitor.target_gap {
+                solver = format!("{}-gap{}", solver, gap);
+            }
+            if let Some(weight) = op_weight {
+                solver = format!("{}-ops{}", solver, weight);
+            }
+            if pre_flag == 4 && !start_extractors.is_empty() {
+                solver = format!("{}-starts:{}", solver, start_extractors.join(","));
+            }
+            cache::ResultCache::solution_key(hash, extractor, deterministic, bound, &solver)
+        });
+        let cached = match (&cache, &cache_key) {
+            (Some(cache), Some(key)) => cache.get(key, &total_egraph),
+            _ => None,
+        };
+        // The timeline of the run that found the solution.
+        let mut solver_timeline = None;
+        let ilp_solution = match cached {
+            Some(cached) => {
+                println!("Using cached {} solution", solver);
+                cached.check(&total_egraph);
+                cached
+            }
+            None => {
+                // Solver-only runs (--pre 0) have no e-graph and no `Booster`.
+                let mut ilp_solution = match booster.as_mut() {
+                    None => solver::run_solver(&solver, &files, timeout_secs, monitor),
+                    Some(booster) => {
+                        // The LP was generated just above. Only --pre 4 warm-starts, unless resumed.
+                        let warm_start = booster.warm_start().cloned();
+                        // The incumbent of an interrupted solver is checked before it replaces the warm start.
+                        match booster.solve() {
+                            Ok(solution) => solution,
+                            Err(err) if interrupt::requested() => {
+                                eprintln!("Warning: {:#}", err);
+                                match warm_start {
+                                    Some(start) => {
+                                        println!("Interrupted without a valid solution, keeping the warm start");
+                                        start
+                                    }
+                                    None => panic!("Error: {} was interrupted without a valid solution", solver),
+                                }
+                            }
+                            Err(err) => panic!("Error: {:#}", err),
                         }
                     }
+                };
+                let files = booster.as_ref().map_or(&files, |booster| booster.files());
+                if tighten && pre_flag == 4 && !interrupt::requested() {
+                    let remaining = timeout_secs.saturating_sub(start_solve.elapsed().as_secs());
+                    let run = solver::SolverRun { solver: &solver, files, timeout_secs: remaining, monitor };
+                    ilp_solution = solver::tighten(&total_egraph, &result, ilp_solution, run, bound, Some(&frozen), acyclicity);
+                }
+                solver_timeline = timeline::Timeline::from_json_file(&files.timeline_file).ok();
+                // An interrupted solve is not what the solver finds within the time limit.
+                if let (Some(cache), Some(key), false) = (&cache, &cache_key, interrupt::requested()) {
+                    cache.put(key, &ilp_solution).unwrap_or_else(|err| eprintln!("Failed to cache solution: {}", err));
                 }
+                ilp_solution
             }
+        };
+        let runtime_solve = start_solve.elapsed().as_secs_f64();
+        solve = Some((runtime_solve, solver_timeline));
+        // Solver-only runs (--pre 0) have no e-graph to evaluate the solution on.
+        if !total_egraph.nodes.is_empty() {
+            let dag = ilp_solution.dag_cost(&total_egraph, &total_egraph.root_eclasses);
+            let area = ilp_solution.area_cost(&total_egraph);
+            snapshot::offer(&solver, &ilp_solution, dag.into_inner());
+            println!("{:<18}: runtime-{} dag:{} area: {}", solver, runtime_solve, dag, area);
         }
+        result = ilp_solution;
+    }
 
-
-
-        // Skip solution checking if we used an empty e-graph
-        println!("Solution found with solver: {}", solver);
+    // Solver-only runs (--pre 0) don't load the e-graph, so there is nothing to map the solution onto.
+    let exportable = !total_egraph.nodes.is_empty() && !result.choices.is_empty();
+    // Compare with the best known result when the input is a registered benchmark.
+    let corpus = match &corpus_file {
+        Some(path) => Some(corpus::Corpus::from_json_file(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err))),
+        None => corpus::Corpus::from_json_file(corpus::DEFAULT_CORPUS).ok(),
+    };
+    let benchmark = corpus.as_ref().and_then(|corpus| corpus.find(&filename));
+    let mut optimum_gap = None;
+    if let Some(benchmark) = benchmark.filter(|_| exportable) {
+        let dag = result.dag_cost(&total_egraph, &total_egraph.root_eclasses).into_inner();
+        if let (Some(optimal), Some(gap)) = (benchmark.optimal_dag, benchmark.gap(dag)) {
+            println!("{}: dag {} against the known optimum {} (gap {:.2}%)", benchmark.name, dag, optimal, gap * 100.0);
+            optimum_gap = Some(gap);
+        }
+    }
+    if (op_weight.is_some() || extractor == "distinct-ops") && exportable {
+        println!("distinct ops: {}", result.distinct_ops(&total_egraph, &total_egraph.root_eclasses).len());
+    }
+    if let (Some(penalty), true) = (&fanout, exportable) {
+        let uses = result.fanout(&total_egraph, &total_egraph.root_eclasses);
+        let over = uses.values().filter(|fanout| **fanout > penalty.limit).count();
+        let excess = extraction_gym::FanoutPenalty { weight: 1.0, ..*penalty }.penalty(uses.values().copied());
+        println!("fanout: max {}, {} classes used more than {} times, {} uses over", uses.values().max().unwrap_or(&0), over, penalty.limit, excess);
+    }
+    if let (Some(path), true) = (&salience_file, exportable) {
+        let ranking = extraction_gym::salience::rank(&total_egraph, &total_egraph.root_eclasses, Some(&result));
+        for salience in ranking.iter().take(5).filter(|salience| salience.score > 0.0) {
+            println!("salience: class {} score {} (spread {} x {} uses)", salience.class.0, salience.score, salience.spread(), salience.uses);
+        }
+        let json = serde_json::Value::Array(ranking.iter().map(|salience| salience.to_json()).collect());
+        fs::write(path, to_string_pretty(&json).unwrap()).unwrap_or_else(|err| panic!("Failed to write {}: {}", path, err));
+        println!("Salience ranking successfully generated at: {}", path);
+    }
+    if let (Some(unweighted), true) = (&unweighted_egraph, exportable) {
+        let roots = &unweighted.root_eclasses;
+        println!("without --root-weights: tree:{} dag:{}", result.tree_cost(unweighted, roots), result.dag_cost(unweighted, roots));
+    }
+    if per_root && exportable {
+        for (root, costs) in result.per_root_costs(&total_egraph) {
+            println!("root {}: tree:{} dag:{} depth: {}", root, costs.tree, costs.dag, costs.depth);
+        }
+    }
+    if let Some(verilog_file) = &verilog_file {
+        if !exportable {
+            eprintln!("Warning: No extraction available, skipping Verilog export");
+        } else {
+            netlist::verilog::write_verilog_file(&total_egraph, &result, &total_egraph.root_eclasses, &base_name, verilog_file)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {}", verilog_file, err));
+            println!("Verilog netlist successfully generated at: {}", verilog_file);
+        }
+    }
+    if let Some(aiger_file) = &aiger_file {
+        if !exportable {
+            eprintln!("Warning: No extraction available, skipping AIGER export");
+        } else {
+            let mapping = match &aig_map_file {
+                Some(path) => netlist::aiger::AigMapping::from_json_file(path)
+                    .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path, err)),
+                None => netlist::aiger::AigMapping::default(),
+            };
+            netlist::aiger::write_aiger_file(&total_egraph, &result, &total_egraph.root_eclasses, &mapping, aiger_file)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {}", aiger_file, err));
+            println!("AIGER file successfully generated at: {}", aiger_file);
+        }
+    }
+    if let Some(blif_file) = &blif_file {
+        if !exportable {
+            eprintln!("Warning: No extraction available, skipping BLIF export");
+        } else {
+            netlist::blif::write_blif_file(&total_egraph, &result, &total_egraph.root_eclasses, &base_name, blif_file)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {}", blif_file, err));
+            println!("BLIF file successfully generated at: {}", blif_file);
+        }
+    }
+    if let Some(json_file) = &json_file {
+        if !exportable {
+            eprintln!("Warning: No extraction available, skipping JSON export");
+        } else {
+            choices_file::ChoicesFile::from_result(&total_egraph, &result, &total_egraph.root_eclasses)
+                .with_meta("input", filename.as_str())
+                .with_meta("extractor", if [2, 4, 5].contains(&pre_flag) { extractor.as_str() } else { "none" })
+                .with_meta("solver", if [0, 3, 4].contains(&pre_flag) { solver.as_str() } else { "none" })
+                .with_meta("bound", bound)
+                .with_meta("interrupted", interrupt::requested())
+                .to_json_file(json_file)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {}", json_file, err));
+            println!("JSON result successfully generated at: {}", json_file);
+        }
+    }
+    if let Some(dot_file) = &dot_file {
+        if !exportable {
+            eprintln!("Warning: No extraction available, skipping DOT export");
+        } else {
+            result
+                .to_dot_file(&total_egraph, dot_file)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {}", dot_file, err));
+            println!("DOT file successfully generated at: {}", dot_file);
+        }
+    }
+    if let Some(report_file) = &report_file {
+        if !exportable {
+            eprintln!("Warning: No extraction available, skipping the HTML report");
+        } else {
+            let seconds = |secs: Option<f64>| secs.map_or_else(|| "-".to_string(), |secs| format!("{:.2} s", secs));
+            report::Report::new(&base_name, &total_egraph, &result)
+                .with_meta("input", &filename)
+                .with_meta("extractor", if [2, 4, 5].contains(&pre_flag) { extractor.as_str() } else { "none" })
+                .with_meta("solver", if [0, 3, 4].contains(&pre_flag) { solver.as_str() } else { "none" })
+                .with_meta("bound", bound)
+                .with_meta("pre", pre_flag)
+                .with_meta("heuristic DAG cost", heuristic.map_or_else(|| "-".to_string(), |(dag, _)| dag.to_string()))
+                .with_meta("heuristic runtime", seconds(heuristic.map(|(_, secs)| secs)))
+                .with_meta("LP generation", seconds(lp_secs))
+                .with_meta("solver runtime", seconds(solve.as_ref().map(|(secs, _)| *secs)))
+                .with_meta("interrupted", interrupt::requested())
+                .with_timeline(solve.as_ref().and_then(|(_, timeline)| timeline.as_ref()))
+                .to_html_file(report_file)
+                .unwrap_or_else(|err| panic!("Failed to write {}: {}", report_file, err));
+            println!("HTML report successfully generated at: {}", report_file);
+        }
+    }
+    if let Some(csv_file) = &csv_file {
+        let roots = &total_egraph.root_eclasses;
+        let costs = exportable.then(|| {
+            let depth = result.depth_cost(&total_egraph, roots) as f64;
+            (result.tree_cost(&total_egraph, roots).into_inner(), result.dag_cost(&total_egraph, roots).into_inner(), depth, result.area_cost(&total_egraph).into_inner())
+        });
+        let record = summary::RunRecord {
+            time: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            benchmark: benchmark.map_or_else(|| base_name.clone(), |benchmark| benchmark.name.clone()),
+            extractor: if [2, 4, 5].contains(&pre_flag) { extractor.clone() } else { "none".to_string() },
+            bound,
+            solver: if [0, 3, 4].contains(&pre_flag) { solver.clone() } else { "none".to_string() },
+            pre: pre_flag,
+            tree: costs.map(|(tree, ..)| tree),
+            dag: costs.map(|(_, dag, ..)| dag),
+            depth: costs.map(|(.., depth, _)| depth),
+            area: costs.map(|(.., area)| area),
+            heuristic_dag: heuristic.map(|(dag, _)| dag),
+            heuristic_secs: heuristic.map(|(_, secs)| secs),
+            lp_secs,
+            lp_binaries,
+            solve_secs: solve.as_ref().map(|(secs, _)| *secs),
+            gap: solve.as_ref().and_then(|(_, timeline)| timeline.as_ref()?.points.last()?.gap),
+            optimum_gap,
+            interrupted: interrupt::requested(),
+        };
+        match record.append_csv(csv_file) {
+            Ok(()) => println!("Run summary appended to {}", csv_file),
+            Err(err) => eprintln!("Warning: Failed to append the run summary to {}: {}", csv_file, err),
+        }
+    }
+    snapshot::flush();
+    if interrupt::requested() {
+        println!("Interrupted: the results above are the best found before the signal");
+        std::process::exit(interrupt::EXIT_CODE);
     }
     
 
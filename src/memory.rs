@@ -0,0 +1,63 @@
+//! `--mem-limit`: the process's resident memory is watched, and the CLI aborts with
+//! a message (after saving the last snapshot) rather than being killed by the OOM
+//! killer when it goes over the limit. Before the LP is generated, its size is
+//! estimated and the pruning bound is lowered until it fits what is left.
+//!
+//! Only the CLI's own memory counts; the solver runs in its own process.
+
+use crate::snapshot;
+use std::time::Duration;
+
+/// Approximate peak memory of `ilp_gen::generate_ilp_file` per unpruned node: the
+/// model and its LP text, about four times the size of the LP file on the E-syn
+/// and E-morphic benchmarks.
+pub const LP_BYTES_PER_NODE: u64 = 1024;
+
+/// How often the watchdog reads the resident memory.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Exit code of a run aborted for its memory use.
+pub const EXIT_CODE: i32 = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MemLimit {
+    pub bytes: u64,
+}
+
+impl MemLimit {
+    pub fn from_gb(gb: f64) -> Self {
+        MemLimit { bytes: (gb * 1e9) as u64 }
+    }
+
+    /// Bytes left under the limit, `None` where the resident memory can't be read.
+    pub fn available(&self) -> Option<u64> {
+        resident_bytes().map(|resident| self.bytes.saturating_sub(resident))
+    }
+
+    /// Aborts the process once its resident memory goes over the limit.
+    pub fn watch(self) {
+        if resident_bytes().is_none() {
+            eprintln!("Warning: the resident memory can't be read on this platform, --mem-limit is only used to size the LP");
+            return;
+        }
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            if let Some(resident) = resident_bytes().filter(|resident| *resident > self.bytes) {
+                eprintln!(
+                    "Error: {:.2} GB resident, over the --mem-limit of {:.2} GB. Try a lower --bound, --pre 5 or a larger limit",
+                    resident as f64 / 1e9,
+                    self.bytes as f64 / 1e9
+                );
+                snapshot::flush();
+                std::process::exit(EXIT_CODE);
+            }
+        });
+    }
+}
+
+/// The resident memory of this process, from `/proc/self/status` (Linux only).
+pub fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?.trim().strip_suffix("kB")?.trim().parse::<u64>().ok()?;
+    Some(kb * 1024)
+}
@@ -0,0 +1,469 @@
+//! The extract, prune, ILP, solve and verify flow of the CLI, for an e-graph in
+//! memory:
+//!
+//! ```no_run
+//! use e_boost::pipeline::{self, Options, PreMode};
+//! # let egraph: egraph_serialize::EGraph = unimplemented!();
+//! let output = pipeline::run(
+//!     &egraph,
+//!     Options { solver: "cpsat".to_string(), timeout: 60, pre_mode: PreMode::WarmSolve, ..Options::default() },
+//! )?;
+//! println!("dag {} in {:.2}s", output.result.dag_cost(&egraph, &egraph.root_eclasses), output.timings.total());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! `Booster` runs the same steps one at a time.
+//!
+//! The solver runs as for the CLI (the wrappers are looked up relative to the
+//! working directory, `highs` and `cbc` on `PATH`), on files under `out_dir`.
+//! With `in_memory`, nothing is written: the model is returned for the caller to
+//! render (`IlpModel::to_lp`, `to_mst`), and a solution obtained elsewhere is read
+//! back with `e_boost_core::solution::read_solution`. The modes that solve run in
+//! memory too with a solver linked in by its feature (`gurobi`, `highs`,
+//! `cpsat`), which is then handed the model in process
+//! (`solver::in_memory_solver`, without retrying a model made infeasible by the
+//! pruning).
+
+use crate::extractor;
+use crate::ilp_gen::{self, Acyclicity, ModelOptions};
+use crate::ilp_model::IlpModel;
+use crate::ilp_stream;
+use crate::solver::{self, Monitor, SolverFiles, SolverRun};
+use crate::warm_start;
+use anyhow::{anyhow, bail, Context};
+use egraph_serialize::{ClassId, Data, EGraph as SerializedEGraph, NodeId};
+use extraction_gym::ExtractionResult;
+use indexmap::IndexMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// What to run, the `--pre` modes of the CLI but the solver-only one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreMode {
+    /// Generate the LP without warm start (`--pre 1`).
+    Lp,
+    /// Run the heuristic and generate the pruned LP with its warm start (`--pre 2`).
+    #[default]
+    WarmLp,
+    /// Generate the LP and solve it (`--pre 3`).
+    Solve,
+    /// Run the heuristic, generate the pruned LP and solve it warm-started (`--pre 4`).
+    WarmSolve,
+    /// Run the heuristic only (`--pre 5`).
+    Heuristic,
+}
+
+impl PreMode {
+    /// The mode of a `--pre` flag, `None` for the solver-only 0.
+    pub fn from_flag(flag: i32) -> Option<Self> {
+        match flag {
+            1 => Some(PreMode::Lp),
+            2 => Some(PreMode::WarmLp),
+            3 => Some(PreMode::Solve),
+            4 => Some(PreMode::WarmSolve),
+            5 => Some(PreMode::Heuristic),
+            _ => None,
+        }
+    }
+
+    pub fn flag(self) -> i32 {
+        match self {
+            PreMode::Lp => 1,
+            PreMode::WarmLp => 2,
+            PreMode::Solve => 3,
+            PreMode::WarmSolve => 4,
+            PreMode::Heuristic => 5,
+        }
+    }
+
+    fn runs_heuristic(self) -> bool {
+        matches!(self, PreMode::WarmLp | PreMode::WarmSolve | PreMode::Heuristic)
+    }
+
+    fn generates_lp(self) -> bool {
+        !matches!(self, PreMode::Heuristic)
+    }
+
+    fn solves(self) -> bool {
+        matches!(self, PreMode::Solve | PreMode::WarmSolve)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub extractor: String,
+    /// Nodes costing more than this times the best cost of their class are pruned.
+    pub bound: f32,
+    pub solver: String,
+    /// Solver time limit, in seconds.
+    pub timeout: u64,
+    pub pre_mode: PreMode,
+    /// Where the LP, warm start, solution and log files are written.
+    pub out_dir: PathBuf,
+    /// What the files are named after.
+    pub name: String,
+    /// Keep the model in `PipelineOutput::model` instead of writing the LP, warm
+    /// start and hint files. The modes that solve need the files, but for the
+    /// solvers of `solver::in_memory_solver`.
+    pub in_memory: bool,
+    /// Choices the heuristic, the LP and the solver keep (`--freeze`). The pruning
+    /// spares them.
+    pub frozen: IndexMap<ClassId, NodeId>,
+    /// How the LP rules out cycles.
+    pub acyclicity: Acyclicity,
+    /// Weight of the distinct operators in the LP's objective (`--op-weight`).
+    pub op_weight: Option<f64>,
+    /// Progress reporting and early stops of the solver.
+    pub monitor: Monitor,
+}
+
+/// The CLI's defaults.
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            extractor: "faster-greedy-dag-mt1".to_string(),
+            bound: 1.25,
+            solver: "gurobi".to_string(),
+            timeout: 1800,
+            pre_mode: PreMode::default(),
+            out_dir: PathBuf::from("file"),
+            name: "egraph".to_string(),
+            in_memory: false,
+            frozen: IndexMap::new(),
+            acyclicity: Acyclicity::default(),
+            op_weight: None,
+            monitor: Monitor::default(),
+        }
+    }
+}
+
+impl Options {
+    /// The model these options generate, with the `pruned` nodes fixed to 0.
+    pub fn model_options<'a>(&'a self, pruned: Option<&'a [NodeId]>) -> ModelOptions<'a> {
+        ModelOptions { pruned, frozen: Some(&self.frozen), acyclicity: self.acyclicity, op_weight: self.op_weight }
+    }
+}
+
+/// Seconds spent in each step, 0 for the steps that didn't run.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+    pub heuristic: f64,
+    pub lp: f64,
+    pub solve: f64,
+}
+
+impl Timings {
+    pub fn total(&self) -> f64 {
+        self.heuristic + self.lp + self.solve
+    }
+}
+
+pub struct PipelineOutput {
+    /// The solver's solution when it ran, the heuristic's otherwise. Empty when
+    /// only the LP was generated without warm start.
+    pub result: ExtractionResult,
+    pub heuristic: Option<ExtractionResult>,
+    /// The generated model. Its hints are the warm start, which was only written
+    /// if they satisfy it (`IlpModel::hint_violations`).
+    pub model: Option<IlpModel>,
+    pub timings: Timings,
+    /// The LP and the solver's outputs, not written `in_memory`.
+    pub files: SolverFiles,
+}
+
+/// The steps of `run` one at a time, for callers that look at the heuristic's
+/// result or the model before going on:
+///
+/// ```no_run
+/// use e_boost::pipeline::{Booster, Options};
+/// # let egraph: egraph_serialize::EGraph = unimplemented!();
+/// let mut booster = Booster::new(&egraph, Options { solver: "highs".to_string(), ..Options::default() })?;
+/// let heuristic = booster.run_heuristic()?;
+/// println!("heuristic dag {}", heuristic.dag_cost(&egraph, &egraph.root_eclasses));
+/// println!("{} variables", booster.generate_lp()?.vars.len());
+/// let solution = booster.solve()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// `options.pre_mode` is not looked at: a step that is skipped is not run. The LP
+/// is pruned by and warm-started from the heuristic's result if it ran first, and
+/// `solve` generates the LP if that didn't happen yet.
+pub struct Booster<'a> {
+    egraph: &'a SerializedEGraph,
+    options: Options,
+    files: SolverFiles,
+    heuristic: Option<ExtractionResult>,
+    /// Whether the heuristic filled the per-node costs the pruning needs.
+    prunable: bool,
+    /// Further warm starts, written as MSTs of their own.
+    starts: Vec<ExtractionResult>,
+    /// The warm start in place of the heuristic's result.
+    resumed: Option<ExtractionResult>,
+    /// The nodes the pruning fixed to 0.
+    zero_node: Vec<NodeId>,
+    model: Option<IlpModel>,
+    /// Whether the LP was streamed, without a model.
+    streamed: bool,
+    timings: Timings,
+}
+
+impl<'a> Booster<'a> {
+    /// Creates the directories of the solver files, unless `options.in_memory`.
+    pub fn new(egraph: &'a SerializedEGraph, options: Options) -> anyhow::Result<Self> {
+        let out_dir = options.out_dir.to_str().context("The output directory is not valid UTF-8")?;
+        let files = SolverFiles::in_dir(out_dir, &options.name, options.bound, &options.solver);
+        if !options.in_memory {
+            files.create_dirs().with_context(|| format!("Failed to create the directories under {}", out_dir))?;
+        }
+        Ok(Booster {
+            egraph,
+            options,
+            files,
+            heuristic: None,
+            prunable: false,
+            starts: Vec::new(),
+            resumed: None,
+            zero_node: Vec::new(),
+            model: None,
+            streamed: false,
+            timings: Timings::default(),
+        })
+    }
+
+    /// Runs `options.extractor`, keeping `options.frozen`, and checks its result.
+    pub fn run_heuristic(&mut self) -> anyhow::Result<ExtractionResult> {
+        let extractors = extractor::extractors();
+        let Some(ed) = extractors.get(self.options.extractor.as_str()) else {
+            bail!("Unknown extractor: {}", self.options.extractor);
+        };
+        let extractor = ed.get_extractor();
+        let start = Instant::now();
+        let result = extractor.extract_frozen(self.egraph, &self.egraph.root_eclasses, &self.options.frozen);
+        self.timings.heuristic = start.elapsed().as_secs_f64();
+        result.validate(self.egraph).with_context(|| format!("{} returned an invalid extraction", self.options.extractor))?;
+        self.set_heuristic(result.clone(), extractor.fills_node_costs());
+        Ok(result)
+    }
+
+    /// Uses `result` as the heuristic's, e.g. that of an extractor configured by the
+    /// caller. `prunable` is whether it has the per-node costs the pruning needs.
+    pub fn set_heuristic(&mut self, result: ExtractionResult, prunable: bool) {
+        self.prunable = prunable;
+        self.heuristic = Some(result);
+    }
+
+    /// Adds a warm start, written next to the heuristic's as an MST of its own.
+    /// The pruning spares its choices. Not written `in_memory` or streamed.
+    pub fn add_start(&mut self, start: ExtractionResult) {
+        self.starts.push(start);
+    }
+
+    /// Warm-starts the LP and the solver from `solution`, e.g. the checkpoint of an
+    /// interrupted solve, in place of the heuristic's result, which still prunes.
+    pub fn resume(&mut self, solution: ExtractionResult) {
+        self.resumed = Some(solution);
+    }
+
+    /// Prunes by `bound` from now on, e.g. one tuned with `pruned`. The files keep
+    /// the names of `options.bound`.
+    pub fn set_bound(&mut self, bound: f32) {
+        self.options.bound = bound;
+    }
+
+    /// The nodes the heuristic's result prunes at `bound` but for the frozen choices
+    /// and those of the other warm starts, none without a heuristic.
+    pub fn pruned(&self, bound: f32) -> Vec<NodeId> {
+        let mut zero_node = Vec::new();
+        let Some(heuristic) = &self.heuristic else {
+            return zero_node;
+        };
+        let egraph = self.egraph;
+        crate::collect_results(egraph, heuristic, bound, &mut zero_node);
+        zero_node.retain(|nid| {
+            let class = &egraph[nid].eclass;
+            self.options.frozen.get(class) != Some(nid) && self.starts.iter().all(|start| start.choices.get(class) != Some(nid))
+        });
+        zero_node
+    }
+
+    /// The warm start of the LP and the solver.
+    pub fn warm_start(&self) -> Option<&ExtractionResult> {
+        self.resumed.as_ref().or(self.heuristic.as_ref())
+    }
+
+    /// Generates the LP, written to `files().lp_file` with its warm starts and
+    /// hints unless `options.in_memory`.
+    pub fn generate_lp(&mut self) -> anyhow::Result<&IlpModel> {
+        let start = Instant::now();
+        let pruned = self.prune()?;
+        let egraph = self.egraph;
+        let roots = &egraph.root_eclasses;
+        let options = &self.options;
+        let model_options = options.model_options(pruned.as_deref());
+        let model = if options.in_memory {
+            ilp_gen::build_model(egraph, roots, model_options, self.warm_start())
+        } else {
+            let files = &self.files;
+            let warm_start = self.warm_start().map(|start| (start, files.mst_file.as_str()));
+            let generated = ilp_gen::generate_ilp_file(egraph, roots, &files.lp_file, model_options, warm_start);
+            // Replaces the hints of an earlier warm-started run.
+            solver::write_hints(&generated, files);
+            write_cplex_start(&generated, files, &options.solver);
+            let mut extra_mst_files = Vec::new();
+            for (k, start) in self.starts.iter().enumerate() {
+                let mst_file = Path::new(&files.mst_file).with_extension(format!("{}.mst", k + 1)).to_string_lossy().to_string();
+                if ilp_gen::generate_mst_file(egraph, roots, &mst_file, model_options, start) {
+                    extra_mst_files.push(mst_file);
+                }
+            }
+            self.write_redundancy()?;
+            self.files.extra_mst_files = extra_mst_files;
+            generated
+        };
+        self.timings.lp = start.elapsed().as_secs_f64();
+        Ok(self.model.insert(model))
+    }
+
+    /// Writes the LP section by section without building its model first, for LPs
+    /// too large for memory: no warm start, and the warm start and hint files of an
+    /// earlier run are removed. `solve` then solves it as written.
+    pub fn stream_lp(&mut self) -> anyhow::Result<()> {
+        if self.options.in_memory {
+            bail!("A streamed LP is written, it can't be kept in memory");
+        }
+        let start = Instant::now();
+        let pruned = self.prune()?;
+        let egraph = self.egraph;
+        let options = &self.options;
+        let files = &self.files;
+        let streamed = ilp_stream::stream_ilp_file(egraph, &egraph.root_eclasses, &files.lp_file, options.model_options(pruned.as_deref()))
+            .with_context(|| format!("Failed to write {}", files.lp_file))?;
+        if streamed.resumed > 0 {
+            println!("{} of the LP's sections were kept from the interrupted run", streamed.resumed);
+        }
+        let _ = fs::remove_file(&files.mst_file);
+        let _ = fs::remove_file(&files.hint_file);
+        println!("The streamed LP has no warm start");
+        self.write_redundancy()?;
+        self.files.extra_mst_files.clear();
+        self.streamed = true;
+        self.timings.lp = start.elapsed().as_secs_f64();
+        Ok(())
+    }
+
+    /// The pruning of the LP, `None` without a heuristic.
+    fn prune(&mut self) -> anyhow::Result<Option<Vec<NodeId>>> {
+        if self.heuristic.is_none() {
+            self.zero_node.clear();
+            return Ok(None);
+        }
+        // Pruning needs the per-node costs.
+        if !self.prunable {
+            return Err(no_node_costs(&self.options.extractor));
+        }
+        self.zero_node = self.pruned(self.options.bound);
+        Ok(Some(self.zero_node.clone()))
+    }
+
+    fn write_redundancy(&self) -> anyhow::Result<()> {
+        if self.options.solver != "cpsat" {
+            return Ok(());
+        }
+        let egraph = self.egraph;
+        let data = Data { nodes: egraph.nodes.clone(), root_eclasses: egraph.root_eclasses.clone(), class_data: egraph.class_data.clone() };
+        data.to_json_file(&self.files.redundancy_file)
+            .with_context(|| format!("Failed to write {}", self.files.redundancy_file))
+    }
+
+    /// Solves the LP with `options.solver` and checks its solution.
+    pub fn solve(&mut self) -> anyhow::Result<ExtractionResult> {
+        let in_memory_solver = solver::in_memory_solver(&self.options.solver);
+        if self.options.in_memory && in_memory_solver.is_none() {
+            bail!("{} runs on files, it can't solve in memory", self.options.solver);
+        }
+        if self.model.is_none() && !self.streamed {
+            self.generate_lp()?;
+        }
+        let egraph = self.egraph;
+        let start = Instant::now();
+        let solution = match in_memory_solver.filter(|_| self.options.in_memory) {
+            Some(solve) => solve(self.model.as_ref().expect("The LP was generated above"), self.options.timeout),
+            None => {
+                let options = &self.options;
+                let run = SolverRun { solver: &options.solver, files: &self.files, timeout_secs: options.timeout, monitor: options.monitor };
+                solver::try_solve_relaxing(egraph, &egraph.root_eclasses, run, options.model_options(Some(&self.zero_node)), self.warm_start())
+            }
+        }
+        .with_context(|| format!("{} failed", self.options.solver))?;
+        self.timings.solve = start.elapsed().as_secs_f64();
+        solution.validate(egraph).with_context(|| format!("{} returned an invalid extraction", self.options.solver))?;
+        Ok(solution)
+    }
+
+    pub fn heuristic(&self) -> Option<&ExtractionResult> {
+        self.heuristic.as_ref()
+    }
+
+    pub fn model(&self) -> Option<&IlpModel> {
+        self.model.as_ref()
+    }
+
+    pub fn timings(&self) -> Timings {
+        self.timings
+    }
+
+    /// The LP and the solver's outputs, not written `in_memory`.
+    pub fn files(&self) -> &SolverFiles {
+        &self.files
+    }
+
+    /// The output of a run whose final extraction is `result`.
+    pub fn into_output(self, result: ExtractionResult) -> PipelineOutput {
+        PipelineOutput { result, heuristic: self.heuristic, model: self.model, timings: self.timings, files: self.files }
+    }
+}
+
+/// The steps of `options.pre_mode`, on a `Booster`.
+pub fn run(egraph: &SerializedEGraph, options: Options) -> anyhow::Result<PipelineOutput> {
+    let pre_mode = options.pre_mode;
+    if options.in_memory && pre_mode.solves() && solver::in_memory_solver(&options.solver).is_none() {
+        bail!("{:?} runs the solver on files, it can't run in memory", pre_mode);
+    }
+    if pre_mode.runs_heuristic() && pre_mode != PreMode::Heuristic {
+        // Fail before running the heuristic for nothing.
+        let fills_node_costs = extractor::extractors().get(options.extractor.as_str()).map(|ed| ed.get_extractor().fills_node_costs());
+        if fills_node_costs == Some(false) {
+            return Err(no_node_costs(&options.extractor));
+        }
+    }
+    let mut booster = Booster::new(egraph, options)?;
+    if pre_mode.runs_heuristic() {
+        booster.run_heuristic()?;
+    }
+    if pre_mode.generates_lp() {
+        booster.generate_lp()?;
+    }
+    let result = if pre_mode.solves() {
+        booster.solve()?
+    } else {
+        booster.heuristic().cloned().unwrap_or_else(ExtractionResult::new_empty)
+    };
+    Ok(booster.into_output(result))
+}
+
+/// Writes the warm start of `model` for the CPLEX interactive optimizer, which reads
+/// its own MST format, when the solver is CPLEX and there is a start. The CPLEX
+/// wrapper reads the MST of the LP.
+fn write_cplex_start(model: &IlpModel, files: &SolverFiles, solver: &str) {
+    if warm_start::Solver::from_name(solver) != Some(warm_start::Solver::Cplex) || !Path::new(&files.mst_file).exists() {
+        return;
+    }
+    let start_file = files.start_file(warm_start::Solver::Cplex);
+    warm_start::write_start(model, warm_start::Solver::Cplex, &start_file).expect("Unable to write file");
+    println!("CPLEX MST file successfully generated at: {}", start_file);
+}
+
+fn no_node_costs(extractor: &str) -> anyhow::Error {
+    anyhow!("{} has no per-node costs to prune with, run it with PreMode::Heuristic", extractor)
+}
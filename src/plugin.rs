@@ -0,0 +1,25 @@
+// Extractors loaded from shared libraries with `--plugin <path>` (`plugins` feature).
+//
+// The library has to export `extraction_gym::registry::PLUGIN_ENTRY`; its
+// extractors are added to the registry under the names the plugin gives them.
+
+use extraction_gym::registry::{register_extractor, Plugin, PluginEntry, PLUGIN_ENTRY};
+
+/// Loads the plugin at `path` and registers its extractors, returning their names.
+pub fn load_plugin(path: &str) -> Result<Vec<String>, libloading::Error> {
+    let plugin: Box<Plugin> = unsafe {
+        let library = libloading::Library::new(path)?;
+        let entry = library.get::<PluginEntry>(PLUGIN_ENTRY)?;
+        let plugin = Box::from_raw(entry());
+        // The registered constructors point into the library, keep it loaded.
+        std::mem::forget(library);
+        plugin
+    };
+
+    let mut names = Vec::new();
+    for (name, detail) in plugin.extractors {
+        register_extractor(&name, detail);
+        names.push(name);
+    }
+    Ok(names)
+}
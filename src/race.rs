@@ -0,0 +1,114 @@
+//! Race mode: `--solver race:gurobi,cpsat` starts every listed back end on the same
+//! LP, warm start and time limit. A back end that finishes before the limit has
+//! proven its answer (an optimum, or an infeasible model) and wins; the others are
+//! killed. When all of them run into the limit, the best incumbent wins.
+//!
+//! Each back end writes its own result, log and timeline, under its name as for a
+//! single-solver run; the winner's result is copied to the race's.
+
+use crate::interrupt;
+use crate::solver::{self, Monitor, Run, SolveFailure, SolverFiles};
+use crate::timeline::Timeline;
+use extraction_gym::ExtractionResult;
+use std::fs;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+/// How long the back ends get past the time limit before they are killed.
+const OVERRUN_SECS: u64 = 60;
+
+/// The back ends of a `race:<solver>,<solver>...` solver, `None` for a single solver.
+pub fn backends(solver: &str) -> Option<Vec<&str>> {
+    solver.strip_prefix("race:").map(|backends| backends.split(',').collect())
+}
+
+pub fn run(backends: &[&str], files: &SolverFiles, timeout_secs: u64, monitor: Monitor) -> Result<ExtractionResult, SolveFailure> {
+    if monitor.interval.is_some() || monitor.target_gap.is_some() || monitor.checkpoint.is_some() {
+        eprintln!("Warning: --monitor, --target-gap and --checkpoint do not apply to races");
+    }
+    println!("Racing {} for {} seconds", backends.join(", "), timeout_secs);
+    let start = Instant::now();
+    let mut running: Vec<(&str, SolverFiles, Run)> = backends
+        .iter()
+        .map(|&backend| {
+            let files = files.for_solver(backend);
+            let run = solver::spawn(backend, &files, timeout_secs, Monitor::default());
+            (backend, files, run)
+        })
+        .collect();
+    let mut timed_out: Vec<(&str, SolverFiles, Run, ExitStatus)> = Vec::new();
+
+    while !running.is_empty() {
+        std::thread::sleep(Duration::from_secs(1));
+        if interrupt::requested() {
+            // As if they all ran into the limit.
+            for (backend, backend_files, mut run) in running.drain(..) {
+                println!("Stopping {}", backend);
+                let status = solver::stop(&mut run.child);
+                timed_out.push((backend, backend_files, run, status));
+            }
+            break;
+        }
+        let mut i = 0;
+        while i < running.len() {
+            let Some(status) = running[i].2.child.try_wait().expect("Failed to wait for solver process") else {
+                i += 1;
+                continue;
+            };
+            let (backend, backend_files, run) = running.remove(i);
+            let elapsed = start.elapsed().as_secs_f64();
+            if start.elapsed().as_secs() >= timeout_secs {
+                println!("{} reached the time limit", backend);
+                timed_out.push((backend, backend_files, run, status));
+            } else if !status.success() && status.code() != Some(solver::INFEASIBLE_EXIT_CODE) {
+                eprintln!("Warning: {} failed after {:.1}s ({}), racing on without it", backend, elapsed, status);
+            } else {
+                println!("{} finished first, after {:.1}s", backend, elapsed);
+                for (loser, _, mut run) in running.drain(..) {
+                    println!("Stopping {}", loser);
+                    let _ = run.child.kill();
+                    let _ = run.child.wait();
+                }
+                return win(backend, files, &backend_files, &run, status, false);
+            }
+        }
+        if start.elapsed().as_secs() >= timeout_secs + OVERRUN_SECS {
+            for (backend, _, run) in running.iter_mut() {
+                eprintln!("Warning: {} overran the time limit, killing it", backend);
+                let _ = run.child.kill();
+            }
+        }
+    }
+
+    // Every back end ran into the limit: the lowest incumbent wins.
+    let best = timed_out
+        .iter()
+        .filter_map(|(backend, backend_files, run, _)| {
+            let log = fs::read_to_string(&backend_files.log_file).ok()?;
+            let timeline = Timeline::from_log(backend, run.log_format, run.warm_start, &log);
+            let incumbent = timeline.points.last()?.incumbent?;
+            println!("{} stopped with incumbent {}", backend, incumbent);
+            Some((incumbent, *backend))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, backend)| backend);
+    // A back end without an incumbent in its log may still have written a solution.
+    let winner = best.or_else(|| timed_out.first().map(|(backend, ..)| *backend)).ok_or(SolveFailure::NoSolution)?;
+    let (backend, backend_files, run, status) = timed_out.iter().find(|(backend, ..)| *backend == winner).unwrap();
+    // It may have been killed after overrunning the limit.
+    win(backend, files, backend_files, run, *status, true)
+}
+
+fn win(
+    backend: &str,
+    files: &SolverFiles,
+    backend_files: &SolverFiles,
+    run: &Run,
+    status: ExitStatus,
+    stopped: bool,
+) -> Result<ExtractionResult, SolveFailure> {
+    let solution = solver::finish(backend, backend_files, run, status, stopped)?;
+    println!("Race won by {}", backend);
+    fs::copy(&backend_files.result_file, &files.result_file).expect("Unable to write file");
+    Ok(solution)
+}
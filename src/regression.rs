@@ -0,0 +1,157 @@
+//! Golden-result regression suite: runs the registered extractors on a set of
+//! e-graphs and compares their tree, DAG and depth costs with stored values.
+//!
+//! ```json
+//! {
+//!   "epsilon": 1e-6,
+//!   "egraphs": [
+//!     { "file": "adder.json", "extractors": { "faster-greedy-dag": { "tree": 9.0, "dag": 7.0, "depth": 3.0 } } }
+//!   ]
+//! }
+//! ```
+//!
+//! Files are relative to the golden file. The bundled suite lives in
+//! `tests/regression/` and runs with `cargo test`; `extraction_tool regression
+//! --golden <file>` checks a corpus of one's own, and `--update` (re)writes the
+//! golden values from the current extractors.
+
+use crate::extractor;
+use egraph_serialize::{Data, EGraph as SerializedEGraph};
+use extraction_gym::ExtractionResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+pub const DEFAULT_GOLDEN: &str = "tests/regression/golden.json";
+
+fn default_epsilon() -> f64 {
+    1e-6
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Golden {
+    #[serde(default = "default_epsilon")]
+    pub epsilon: f64,
+    pub egraphs: Vec<GoldenEGraph>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GoldenEGraph {
+    pub file: String,
+    #[serde(default)]
+    pub extractors: BTreeMap<String, Costs>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Costs {
+    pub tree: f64,
+    pub dag: f64,
+    pub depth: f64,
+}
+
+impl Golden {
+    pub fn from_json_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn to_json_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+    }
+}
+
+/// Costs of `name`'s extraction from `egraph`, or why there are none. The
+/// extractors with a run-dependent result run in their deterministic variant.
+fn extract(egraph: &SerializedEGraph, name: &str) -> Result<Costs, String> {
+    let extractors = extractor::extractors();
+    let deterministic = extractor::deterministic_extractor(name);
+    let selected: &dyn extraction_gym::Extractor = match (&deterministic, extractors.get(name)) {
+        (Some(fixed), _) => fixed.as_ref(),
+        (None, Some(ed)) => ed.get_extractor(),
+        (None, None) => return Err("unknown extractor".to_string()),
+    };
+    let roots = &egraph.root_eclasses;
+    // A panicking extractor fails its entry, not the suite.
+    let result: ExtractionResult = panic::catch_unwind(AssertUnwindSafe(|| selected.extract(egraph, roots)))
+    .map_err(|panic| {
+        panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+            .unwrap_or_else(|| "panicked".to_string())
+    })?;
+    result.validate(egraph).map_err(|err| err.to_string())?;
+    Ok(Costs {
+        tree: result.tree_cost(egraph, roots).into_inner(),
+        dag: result.dag_cost(egraph, roots).into_inner(),
+        depth: result.depth_cost(egraph, roots) as f64,
+    })
+}
+
+fn load(dir: &Path, file: &str) -> Result<SerializedEGraph, String> {
+    let path = dir.join(file);
+    let data = Data::from_json_file(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    SerializedEGraph::from_Data(&data).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Checks every stored cost and returns the mismatches, one line each.
+pub fn check(golden: &Golden, dir: &Path) -> Vec<String> {
+    let mut failures = Vec::new();
+    for entry in &golden.egraphs {
+        let egraph = match load(dir, &entry.file) {
+            Ok(egraph) => egraph,
+            Err(err) => {
+                failures.push(err);
+                continue;
+            }
+        };
+        for (name, expected) in &entry.extractors {
+            let failure = match extract(&egraph, name) {
+                Err(err) => Some(err),
+                Ok(costs) => {
+                    let close = |a: f64, b: f64| (a - b).abs() <= golden.epsilon * a.abs().max(b.abs()).max(1.0);
+                    let matches = close(costs.tree, expected.tree) && close(costs.dag, expected.dag) && close(costs.depth, expected.depth);
+                    (!matches).then(|| {
+                        format!(
+                            "tree {} dag {} depth {}, expected tree {} dag {} depth {}",
+                            costs.tree, costs.dag, costs.depth, expected.tree, expected.dag, expected.depth
+                        )
+                    })
+                }
+            };
+            match failure {
+                Some(failure) => {
+                    println!("FAIL {} {}: {}", entry.file, name, failure);
+                    failures.push(format!("{} {}: {}", entry.file, name, failure));
+                }
+                None => println!("ok   {} {}", entry.file, name),
+            }
+        }
+    }
+    failures
+}
+
+/// Recomputes the stored costs with every benchmarked extractor. Extractors that
+/// fail on an e-graph are left out of its entry.
+pub fn update(golden: &mut Golden, dir: &Path) {
+    let names: Vec<&str> = extractor::extractors()
+        .iter()
+        .filter(|(_, ed)| ed.get_use_for_bench())
+        .map(|(name, _)| *name)
+        .collect();
+    for entry in &mut golden.egraphs {
+        let egraph = load(dir, &entry.file).unwrap_or_else(|err| panic!("{}", err));
+        entry.extractors.clear();
+        for name in &names {
+            match extract(&egraph, name) {
+                Ok(costs) => {
+                    entry.extractors.insert(name.to_string(), costs);
+                }
+                Err(err) => eprintln!("Warning: {} {} left out: {}", entry.file, name, err),
+            }
+        }
+        println!("{}: {} extractors", entry.file, entry.extractors.len());
+    }
+}
@@ -0,0 +1,299 @@
+// Interactive inspection of an extraction.
+//
+// cargo run --release -- repl [--extractor <name> | --solution <choices.json>] input.json
+//
+// The e-graph is loaded once with an extraction of it, from the extractor or a
+// choices file, and stays loaded while its choices are looked at and changed:
+//
+//   class <c>             the nodes of class c, * marking the selected one
+//   node <c.n>            a node with its children's selected nodes
+//   parents <c>           the nodes using class c, * marking the selected ones
+//   why-chosen <c>        the selected node of c against the others: the DAG cost change of each
+//   cost [tree|dag|depth|area]
+//   swap <c> <n | c.n>    select another node for c, rejected if that closes a cycle
+//   undo                  take back the last swap
+//   exclude <c.n>         what the extraction costs without a node (not applied)
+//   extract <name>        start over from a registered extractor
+//   save <file>           write the choices as JSON
+//
+// Node c.n can be given as n where the class is known.
+
+use crate::extractor;
+use egraph_serialize::{ClassId, EGraph as SerializedEGraph, NodeId};
+use extraction_gym::choices_file::ChoicesFile;
+use extraction_gym::{moves, sensitivity, ExtractionResult, INFINITY};
+use indexmap::IndexMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+pub struct Session<'a> {
+    egraph: &'a SerializedEGraph,
+    result: ExtractionResult,
+    undo: Vec<moves::Undo>,
+    parents: IndexMap<ClassId, Vec<NodeId>>,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(egraph: &'a SerializedEGraph, mut result: ExtractionResult) -> Self {
+        let mut parents: IndexMap<ClassId, Vec<NodeId>> = IndexMap::new();
+        for (nid, node) in &egraph.nodes {
+            for child in &node.children {
+                let users = parents.entry(*child).or_default();
+                if users.last() != Some(nid) {
+                    users.push(*nid);
+                }
+            }
+        }
+        result.track_refs(egraph, &egraph.root_eclasses);
+        Session { egraph, result, undo: Vec::new(), parents }
+    }
+
+    pub fn result(&self) -> &ExtractionResult {
+        &self.result
+    }
+
+    /// Runs one command, returning what it prints or why it can't run.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let arg = |i: usize| words.get(i).copied().ok_or_else(|| format!("{} needs more arguments, see help", words[0]));
+        match words.first().copied() {
+            None => Ok(String::new()),
+            Some("class") => self.class(self.parse_class(arg(1)?)?),
+            Some("node") => self.node(self.parse_node(arg(1)?, None)?),
+            Some("parents") => self.parents(self.parse_class(arg(1)?)?),
+            Some("why-chosen") => self.why_chosen(self.parse_class(arg(1)?)?),
+            Some("cost") => self.cost(words.get(1).copied()),
+            Some("swap") => {
+                let class = self.parse_class(arg(1)?)?;
+                let node = self.parse_node(arg(2)?, Some(class))?;
+                self.swap(class, node)
+            }
+            Some("undo") => {
+                let undo = self.undo.pop().ok_or("Nothing to undo")?;
+                let delta = undo.undo(&mut self.result, self.egraph);
+                Ok(format!("dag {:+} -> {}", delta, self.dag()))
+            }
+            Some("exclude") => self.exclude(self.parse_node(arg(1)?, None)?),
+            Some("extract") => {
+                let name = arg(1)?.to_lowercase();
+                let extractors = extractor::extractors();
+                let ed = extractors.get(name.as_str()).ok_or_else(|| format!("Unknown extractor: {}", name))?;
+                let result = ed.get_extractor().extract(self.egraph, &self.egraph.root_eclasses);
+                if !result.is_valid(self.egraph) {
+                    return Err(format!("{} found no valid extraction", name));
+                }
+                *self = Session::new(self.egraph, result);
+                Ok(format!("dag {}", self.dag()))
+            }
+            Some("save") => {
+                let path = arg(1)?;
+                ChoicesFile::from_result(self.egraph, &self.result, &self.egraph.root_eclasses)
+                    .to_json_file(path)
+                    .map_err(|err| format!("Failed to write {}: {}", path, err))?;
+                Ok(format!("Saved to {}", path))
+            }
+            Some("help") => Ok(HELP.to_string()),
+            Some(command) => Err(format!("Unknown command {}, see help", command)),
+        }
+    }
+
+    fn parse_class(&self, word: &str) -> Result<ClassId, String> {
+        let class = ClassId(word.parse().map_err(|_| format!("Invalid class id {}", word))?);
+        if !self.egraph.classes().contains_key(&class) {
+            return Err(format!("No class {}", word));
+        }
+        Ok(class)
+    }
+
+    /// `c.n`, or `n` of `class`.
+    fn parse_node(&self, word: &str, class: Option<ClassId>) -> Result<NodeId, String> {
+        let invalid = || format!("Invalid node id {}", word);
+        let node = match (word.split_once('.'), class) {
+            (Some((c, n)), _) => NodeId([c.parse().map_err(|_| invalid())?, n.parse().map_err(|_| invalid())?]),
+            (None, Some(class)) => NodeId([class.0, word.parse().map_err(|_| invalid())?]),
+            (None, None) => return Err(invalid()),
+        };
+        match (self.egraph.nodes.get(&node), class) {
+            (None, _) => Err(format!("No node {}", node)),
+            (Some(found), Some(class)) if found.eclass != class => Err(format!("{} is not a node of class {}", node, class)),
+            _ => Ok(node),
+        }
+    }
+
+    fn selected(&self, class: &ClassId) -> bool {
+        self.result.refs.as_ref().is_some_and(|refs| refs.count(class) > 0)
+    }
+
+    fn dag(&self) -> f64 {
+        self.result.refs.as_ref().map_or_else(
+            || self.result.dag_cost(self.egraph, &self.egraph.root_eclasses).into_inner(),
+            |refs| refs.dag_cost().into_inner(),
+        )
+    }
+
+    /// One line for `nid`: marker, id, op, cost and children.
+    fn describe(&self, out: &mut String, nid: &NodeId, marked: bool) {
+        let node = &self.egraph[nid];
+        let children: Vec<String> = node.children.iter().map(|child| child.to_string()).collect();
+        let _ = write!(out, "{} {:<10} {:<12} cost {:<6} children [{}]", if marked { "*" } else { " " }, nid.to_string(), node.op, node.cost, children.join(", "));
+        if let Some(tree) = self.result.cost.get(nid) {
+            let _ = write!(out, "  tree {}", tree);
+        }
+        out.push('\n');
+    }
+
+    fn class(&self, class: ClassId) -> Result<String, String> {
+        let nodes = &self.egraph[&class].nodes;
+        let choice = self.result.choices.get(&class);
+        let mut out = match (choice, self.selected(&class)) {
+            (Some(choice), true) => {
+                let uses = self.result.refs.as_ref().map_or(0, |refs| refs.count(&class));
+                format!("class {}: {} nodes, {} selected, used {} times\n", class, nodes.len(), choice, uses)
+            }
+            (Some(choice), false) => format!("class {}: {} nodes, {} chosen but not used by the extraction\n", class, nodes.len(), choice),
+            (None, _) => format!("class {}: {} nodes, no choice\n", class, nodes.len()),
+        };
+        for nid in nodes {
+            self.describe(&mut out, nid, choice == Some(nid));
+        }
+        Ok(out)
+    }
+
+    fn node(&self, nid: NodeId) -> Result<String, String> {
+        let node = &self.egraph[&nid];
+        let chosen = self.result.choices.get(&node.eclass) == Some(&nid);
+        let mut out = format!(
+            "node {} of class {}{}\n",
+            nid,
+            node.eclass,
+            if chosen && self.selected(&node.eclass) { ", selected" } else { "" }
+        );
+        for child in &node.children {
+            match self.result.choices.get(child) {
+                Some(choice) => self.describe(&mut out, choice, false),
+                None => {
+                    let _ = writeln!(out, "  class {} has no choice", child);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn parents(&self, class: ClassId) -> Result<String, String> {
+        let parents = self.parents.get(&class).map_or(&[][..], Vec::as_slice);
+        let mut out = format!("class {}: {} parent nodes{}\n", class, parents.len(), if self.egraph.root_eclasses.contains(&class) { ", a root" } else { "" });
+        for nid in parents {
+            let parent = self.egraph.nid_to_cid(nid);
+            let used = self.result.choices.get(parent) == Some(nid) && self.selected(parent);
+            self.describe(&mut out, nid, used);
+        }
+        Ok(out)
+    }
+
+    fn why_chosen(&self, class: ClassId) -> Result<String, String> {
+        let choice = *self.result.choices.get(&class).ok_or_else(|| format!("Class {} has no choice", class))?;
+        let mut out = String::new();
+        if !self.selected(&class) {
+            let _ = writeln!(out, "{} is chosen for class {}, which the extraction doesn't use", choice, class);
+            return Ok(out);
+        }
+        if self.egraph[&class].nodes.len() == 1 {
+            let _ = writeln!(out, "{} ({}) is the only node of class {}", choice, self.egraph[&choice].op, class);
+            return Ok(out);
+        }
+        let _ = writeln!(out, "{} ({}) is selected; the DAG cost change of selecting each other node instead:", choice, self.egraph[&choice].op);
+        let mut others: Vec<(f64, NodeId)> = self.egraph[&class]
+            .nodes
+            .iter()
+            .filter(|nid| **nid != choice)
+            .map(|nid| (self.result.swap_cost_delta(self.egraph, &class, nid).into_inner(), *nid))
+            .collect();
+        others.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        for (delta, nid) in &others {
+            let node = &self.egraph[nid];
+            if *delta == INFINITY.into_inner() {
+                let _ = writeln!(out, "  {:<10} {:<12} closes a cycle or needs a class without a choice", nid.to_string(), node.op);
+            } else {
+                let _ = writeln!(out, "  {:<10} {:<12} {:+}", nid.to_string(), node.op, delta);
+            }
+        }
+        if let Some((delta, nid)) = others.first().filter(|(delta, _)| *delta < 0.0) {
+            let _ = writeln!(out, "{} would be cheaper by {}", nid, -delta);
+        }
+        Ok(out)
+    }
+
+    fn cost(&self, metric: Option<&str>) -> Result<String, String> {
+        let (egraph, roots) = (self.egraph, &self.egraph.root_eclasses);
+        match metric {
+            None => Ok(format!(
+                "tree {} dag {} depth {} area {}\n",
+                self.result.tree_cost(egraph, roots),
+                self.dag(),
+                self.result.depth_cost(egraph, roots),
+                self.result.area_cost(egraph)
+            )),
+            Some("tree") => Ok(format!("{}\n", self.result.tree_cost(egraph, roots))),
+            Some("dag") => Ok(format!("{}\n", self.dag())),
+            Some("depth") => Ok(format!("{}\n", self.result.depth_cost(egraph, roots))),
+            Some("area") => Ok(format!("{}\n", self.result.area_cost(egraph))),
+            Some(metric) => Err(format!("Unknown cost {}, one of tree, dag, depth or area", metric)),
+        }
+    }
+
+    fn swap(&mut self, class: ClassId, node: NodeId) -> Result<String, String> {
+        if self.result.choices.get(&class) == Some(&node) {
+            return Ok(format!("{} is already the choice of class {}", node, class));
+        }
+        let swap = moves::swap_class(&mut self.result, self.egraph, &node)
+            .ok_or_else(|| format!("Selecting {} closes a cycle or needs a class without a choice", node))?;
+        self.undo.push(swap.undo);
+        Ok(format!("dag {:+} -> {}", swap.delta, self.dag()))
+    }
+
+    fn exclude(&self, node: NodeId) -> Result<String, String> {
+        let exclusion = sensitivity::exclude_node(self.egraph, &self.result, &node);
+        if !exclusion.feasible() {
+            return Ok(format!("The roots can't be extracted without {}", node));
+        }
+        if exclusion.changed.is_empty() {
+            return Ok(format!("dag {} ({:+}), no class changes", exclusion.dag_cost, exclusion.delta));
+        }
+        let changed: Vec<String> = exclusion.changed.iter().map(|class| class.to_string()).collect();
+        Ok(format!("dag {} ({:+}), {} classes change: {}", exclusion.dag_cost, exclusion.delta, changed.len(), changed.join(" ")))
+    }
+}
+
+const HELP: &str = "\
+class <c>             the nodes of class c, * marking the selected one
+node <c.n>            a node with its children's selected nodes
+parents <c>           the nodes using class c, * marking the selected ones
+why-chosen <c>        the DAG cost change of selecting each other node of c
+cost [tree|dag|depth|area]
+swap <c> <n | c.n>    select another node for class c
+undo                  take back the last swap
+exclude <c.n>         what the extraction costs without a node (not applied)
+extract <name>        start over from a registered extractor
+save <file>           write the choices as JSON
+quit";
+
+/// Reads commands from `input` until it ends or `quit`, printing a prompt and the
+/// answers to `output`.
+pub fn run(session: &mut Session, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+        match session.execute(&line) {
+            Ok(answer) if answer.is_empty() => {}
+            Ok(answer) => writeln!(output, "{}", answer.trim_end())?,
+            Err(err) => writeln!(output, "{}", err)?,
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
@@ -0,0 +1,262 @@
+//! Self-contained HTML report of a run (`--report <file>`): the run's settings and
+//! runtimes, the costs of the extraction broken down by op and by root, the
+//! solver's objective timeline, and the extracted DAG drawn as SVG. Nothing is
+//! loaded from elsewhere, so the file can be archived or mailed as it is.
+//!
+//! The DAG is laid out in layers by height above the leaves, leaves at the bottom
+//! as in `--dot`, and hovering a node shows its op, e-class and cost. Larger DAGs
+//! than `MAX_DRAWN_NODES` are left to Graphviz; their DOT source is included.
+
+use crate::timeline::Timeline;
+use egraph_serialize::{EGraph as SerializedEGraph, NodeId};
+use extraction_gym::ExtractionResult;
+use rustc_hash::FxHashMap;
+use std::fmt::{Display, Write};
+
+/// Above this many selected nodes the DAG isn't drawn.
+pub const MAX_DRAWN_NODES: usize = 600;
+
+const NODE_WIDTH: f64 = 120.0;
+const NODE_HEIGHT: f64 = 36.0;
+const H_SPACING: f64 = 20.0;
+const V_SPACING: f64 = 44.0;
+
+pub struct Report<'a> {
+    pub title: String,
+    pub egraph: &'a SerializedEGraph,
+    pub result: &'a ExtractionResult,
+    pub timeline: Option<&'a Timeline>,
+    /// Settings and runtimes, shown in order.
+    pub meta: Vec<(String, String)>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(title: &str, egraph: &'a SerializedEGraph, result: &'a ExtractionResult) -> Self {
+        Report { title: title.to_string(), egraph, result, timeline: None, meta: Vec::new() }
+    }
+
+    pub fn with_meta(mut self, key: &str, value: impl Display) -> Self {
+        self.meta.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_timeline(mut self, timeline: Option<&'a Timeline>) -> Self {
+        self.timeline = timeline;
+        self
+    }
+
+    pub fn to_html(&self) -> String {
+        let egraph = self.egraph;
+        let roots = &egraph.root_eclasses;
+        let mut html = String::new();
+        writeln!(html, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>", escape(&self.title)).unwrap();
+        writeln!(html, "<style>{}</style></head><body>", STYLE).unwrap();
+        writeln!(html, "<h1>{}</h1>", escape(&self.title)).unwrap();
+
+        html.push_str("<h2>Run</h2>\n<table>");
+        for (key, value) in &self.meta {
+            writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape(key), escape(value)).unwrap();
+        }
+        writeln!(html, "<tr><th>e-graph</th><td>{} nodes, {} classes, {} roots</td></tr></table>", egraph.nodes.len(), egraph.classes().len(), roots.len()).unwrap();
+
+        html.push_str("<h2>Costs</h2>\n<table><tr><th>tree</th><th>DAG</th><th>depth</th><th>area</th></tr>");
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr></table>",
+            self.result.tree_cost(egraph, roots),
+            self.result.dag_cost(egraph, roots),
+            self.result.depth_cost(egraph, roots),
+            self.result.area_cost(egraph)
+        )
+        .unwrap();
+
+        let mut active: Vec<NodeId> = self.result.activate_nodes(egraph, roots).into_iter().collect();
+        active.sort();
+        // Count, cost and area of the selected nodes of each op.
+        let mut by_op = FxHashMap::<&str, (usize, f64, f64)>::default();
+        for nid in &active {
+            let node = &egraph[nid];
+            let entry = by_op.entry(node.op.as_str()).or_default();
+            entry.0 += 1;
+            entry.1 += node.cost.into_inner();
+            entry.2 += node.area().into_inner();
+        }
+        let mut by_op: Vec<_> = by_op.into_iter().collect();
+        by_op.sort_by(|(a_op, a), (b_op, b)| b.1.total_cmp(&a.1).then(a_op.cmp(b_op)));
+        let dag = self.result.dag_cost(egraph, roots).into_inner();
+        html.push_str("<h3>By op</h3>\n<table><tr><th>op</th><th>nodes</th><th>cost</th><th>share of DAG cost</th><th>area</th></tr>");
+        for (op, (count, cost, area)) in &by_op {
+            let share = if dag > 0.0 { cost / dag * 100.0 } else { 0.0 };
+            writeln!(html, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1}%</td><td>{}</td></tr>", escape(op), count, cost, share, area).unwrap();
+        }
+        html.push_str("</table>\n");
+        html.push_str("<h3>By root</h3>\n<table><tr><th>root</th><th>tree</th><th>DAG</th><th>depth</th></tr>");
+        for (root, costs) in self.result.per_root_costs(egraph) {
+            writeln!(html, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>", escape(&root.to_string()), costs.tree, costs.dag, costs.depth).unwrap();
+        }
+        html.push_str("</table>\n");
+
+        if let Some(timeline) = self.timeline {
+            writeln!(html, "<h2>Solver timeline ({}{})</h2>", escape(&timeline.solver), if timeline.warm_start { ", warm-started" } else { "" }).unwrap();
+            match timeline_svg(timeline) {
+                Some(svg) => html.push_str(&svg),
+                None => html.push_str("<p>The solver reported no incumbent or bound.</p>\n"),
+            }
+        }
+
+        writeln!(html, "<h2>Extracted DAG ({} nodes)</h2>", active.len()).unwrap();
+        if active.len() <= MAX_DRAWN_NODES {
+            html.push_str(&dag_svg(egraph, &active));
+        } else {
+            writeln!(html, "<p>Too large to draw here (more than {} nodes); render the Graphviz source below, e.g. with <code>sfdp -Tsvg</code>.</p>", MAX_DRAWN_NODES).unwrap();
+        }
+        writeln!(html, "<details><summary>Graphviz source</summary><pre>{}</pre></details>", escape(&self.result.to_dot(egraph))).unwrap();
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    pub fn to_html_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_html())
+    }
+}
+
+const STYLE: &str = "body{font-family:Helvetica,Arial,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:1em}th,td{border:1px solid #ccc;padding:3px 8px;text-align:left}\
+th{background:#f3f3f3}svg{border:1px solid #ddd;background:#fff}.node rect{fill:#fff;stroke:#555}\
+.root rect{fill:#add8e6;stroke-width:2}.node text{font-size:11px;text-anchor:middle}.edge{stroke:#999;fill:none}\
+.node:hover rect{stroke:#d33;stroke-width:2}pre{background:#f7f7f7;padding:1em;overflow:auto;max-height:30em}";
+
+/// The incumbent and bound over time, as steps.
+fn timeline_svg(timeline: &Timeline) -> Option<String> {
+    let values = timeline.points.iter().flat_map(|point| point.incumbent.into_iter().chain(point.bound));
+    let (low, high) = values.fold(None, |range: Option<(f64, f64)>, value| match range {
+        Some((low, high)) => Some((low.min(value), high.max(value))),
+        None => Some((value, value)),
+    })?;
+    let end = timeline.points.last().map_or(0.0, |point| point.time).max(1e-3);
+    let (width, height, margin) = (640.0, 240.0, 50.0);
+    let x = |time: f64| margin + time / end * (width - 2.0 * margin);
+    let y = |value: f64| if high > low { height - margin - (value - low) / (high - low) * (height - 2.0 * margin) } else { height / 2.0 };
+    let steps = |value: fn(&crate::timeline::Point) -> Option<f64>| {
+        let mut points = String::new();
+        let mut last: Option<f64> = None;
+        for point in &timeline.points {
+            if let Some(current) = value(point) {
+                if let Some(last) = last {
+                    write!(points, "{:.1},{:.1} ", x(point.time), y(last)).unwrap();
+                }
+                write!(points, "{:.1},{:.1} ", x(point.time), y(current)).unwrap();
+                last = Some(current);
+            }
+        }
+        if let Some(last) = last {
+            write!(points, "{:.1},{:.1}", x(end), y(last)).unwrap();
+        }
+        points
+    };
+    let mut svg = String::new();
+    writeln!(svg, "<svg width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">", width, height).unwrap();
+    writeln!(svg, "<line x1=\"{m}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"#555\"/><line x1=\"{m}\" y1=\"{m}\" x2=\"{m}\" y2=\"{b}\" stroke=\"#555\"/>", m = margin, b = height - margin, r = width - margin).unwrap();
+    writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"#c33\" stroke-width=\"2\"/>", steps(|point| point.incumbent)).unwrap();
+    writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"#36c\" stroke-width=\"2\" stroke-dasharray=\"5,3\"/>", steps(|point| point.bound)).unwrap();
+    writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{}</text>", margin - 4.0, y(high) + 4.0, high).unwrap();
+    writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{}</text>", margin - 4.0, y(low) + 4.0, low).unwrap();
+    writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\">0 s</text>", margin, height - margin + 16.0).unwrap();
+    writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{:.1} s</text>", width - margin, height - margin + 16.0, end).unwrap();
+    writeln!(svg, "<text x=\"{}\" y=\"20\" font-size=\"12\" fill=\"#c33\">incumbent</text><text x=\"{}\" y=\"20\" font-size=\"12\" fill=\"#36c\">bound</text>", margin, margin + 80.0).unwrap();
+    svg.push_str("</svg>\n");
+    if let Some(gap) = timeline.points.last().and_then(|point| point.gap) {
+        writeln!(svg, "<p>Final gap: {:.2}%</p>", gap * 100.0).unwrap();
+    }
+    Some(svg)
+}
+
+/// The selected nodes in layers by their height above the leaves. Within a layer,
+/// nodes are ordered by the mean position of their children to keep edges short.
+fn dag_svg(egraph: &SerializedEGraph, active: &[NodeId]) -> String {
+    let selected: FxHashMap<_, NodeId> = active.iter().map(|nid| (egraph[nid].eclass, *nid)).collect();
+    let mut height = FxHashMap::<NodeId, usize>::default();
+    fn height_of(egraph: &SerializedEGraph, selected: &FxHashMap<egraph_serialize::ClassId, NodeId>, nid: NodeId, memo: &mut FxHashMap<NodeId, usize>) -> usize {
+        if let Some(height) = memo.get(&nid) {
+            return *height;
+        }
+        // Guards against a cyclic selection, which `check` rejects anyway.
+        memo.insert(nid, 0);
+        let height = egraph[&nid].children.iter().filter_map(|child| selected.get(child)).map(|child| 1 + height_of(egraph, selected, *child, memo)).max().unwrap_or(0);
+        memo.insert(nid, height);
+        height
+    }
+    for nid in active {
+        height_of(egraph, &selected, *nid, &mut height);
+    }
+    let layers = height.values().max().map_or(0, |max| max + 1);
+    let mut layer_nodes: Vec<Vec<NodeId>> = vec![Vec::new(); layers];
+    for nid in active {
+        layer_nodes[height[nid]].push(*nid);
+    }
+    let mut x = FxHashMap::<NodeId, f64>::default();
+    for nodes in layer_nodes.iter_mut() {
+        let key = |nid: &NodeId| {
+            let children: Vec<f64> = egraph[nid].children.iter().filter_map(|child| selected.get(child)).filter_map(|child| x.get(child)).copied().collect();
+            if children.is_empty() { 0.0 } else { children.iter().sum::<f64>() / children.len() as f64 }
+        };
+        let mut keyed: Vec<(f64, NodeId)> = nodes.iter().map(|nid| (key(nid), *nid)).collect();
+        keyed.sort_by(|(a_key, a), (b_key, b)| a_key.total_cmp(b_key).then(a.cmp(b)));
+        *nodes = keyed.into_iter().map(|(_, nid)| nid).collect();
+        for (i, nid) in nodes.iter().enumerate() {
+            x.insert(*nid, i as f64 * (NODE_WIDTH + H_SPACING));
+        }
+    }
+    let widest = layer_nodes.iter().map(|nodes| nodes.len()).max().unwrap_or(0).max(1);
+    let width = widest as f64 * (NODE_WIDTH + H_SPACING) + H_SPACING;
+    let total_height = layers as f64 * (NODE_HEIGHT + V_SPACING) + V_SPACING;
+    // Layers are centred, the highest one at the top.
+    let position = |nid: &NodeId| {
+        let layer = height[nid];
+        let offset = (width - layer_nodes[layer].len() as f64 * (NODE_WIDTH + H_SPACING) + H_SPACING) / 2.0;
+        (offset + x[nid], V_SPACING + (layers - 1 - layer) as f64 * (NODE_HEIGHT + V_SPACING))
+    };
+
+    let mut svg = String::new();
+    writeln!(svg, "<svg width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">", width, total_height).unwrap();
+    for nid in active {
+        let (px, py) = position(nid);
+        for child in egraph[nid].children.iter().filter_map(|child| selected.get(child)) {
+            let (cx, cy) = position(child);
+            writeln!(svg, "<line class=\"edge\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>", px + NODE_WIDTH / 2.0, py + NODE_HEIGHT, cx + NODE_WIDTH / 2.0, cy).unwrap();
+        }
+    }
+    for nid in active {
+        let node = &egraph[nid];
+        let (px, py) = position(nid);
+        let class = if egraph.root_eclasses.contains(&node.eclass) { "node root" } else { "node" };
+        let op: String = if node.op.chars().count() > 16 { node.op.chars().take(15).chain(std::iter::once('…')).collect() } else { node.op.clone() };
+        writeln!(
+            svg,
+            "<g class=\"{}\"><title>{} (class {}, cost {}, area {})</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{}\" rx=\"3\"/><text x=\"{:.1}\" y=\"{:.1}\">{}</text><text x=\"{:.1}\" y=\"{:.1}\">class {} · {}</text></g>",
+            class,
+            escape(&node.op),
+            escape(&node.eclass.to_string()),
+            node.cost,
+            node.area(),
+            px,
+            py,
+            NODE_WIDTH,
+            NODE_HEIGHT,
+            px + NODE_WIDTH / 2.0,
+            py + 15.0,
+            escape(&op),
+            px + NODE_WIDTH / 2.0,
+            py + 29.0,
+            escape(&node.eclass.to_string()),
+            node.cost
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,148 @@
+//! Cost models written as Rhai scripts, loaded with `--cost-script <file>`
+//! (`scripting` feature):
+//!
+//! ```rhai
+//! // The cost of a term with `op` at its root, from the costs of its children's
+//! // terms and its depth (1 for a leaf).
+//! fn cost(op, child_costs, depth) {
+//!     let sum = 0.0;
+//!     for c in child_costs { sum += c; }
+//!     if op == "mul" { 4.0 + sum } else { 1.0 + sum }
+//! }
+//!
+//! // Optional: the node cost the other extractors and the LP objective use.
+//! fn weight(op, cost) { if op == "mul" { 4.0 } else { cost } }
+//! ```
+//!
+//! `cost` drives the `scripted-cost` extractor, a bottom-up extraction that keeps
+//! the cheapest term of every class as the script prices it. A script that also
+//! defines `weight` re-weights the nodes of the e-graph before anything runs on
+//! it, which is how the ILP gets a (linear) share of the model.
+
+use egraph_serialize::{ClassId, Cost, Data, EGraph as SerializedEGraph, NodeId};
+use extraction_gym::queue::UniqueQueue;
+use extraction_gym::registry::{register_extractor, ExtractorDetail, Optimal};
+use extraction_gym::{ExtractionResult, Extractor, INFINITY};
+use indexmap::IndexMap;
+use rhai::{Array, Dynamic, Engine, Scope, AST};
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// How often a class may get a cheaper term. A script whose costs keep falling
+/// around a cycle would never settle otherwise.
+const MAX_UPDATES: usize = 64;
+
+pub struct CostScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl CostScript {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let engine = Engine::new();
+        let ast = engine.compile_file(path.into()).map_err(|err| err.to_string())?;
+        if !ast.iter_functions().any(|function| function.name == "cost" && function.params.len() == 3) {
+            return Err("the script has no cost(op, child_costs, depth) function".to_string());
+        }
+        Ok(CostScript { engine, ast })
+    }
+
+    fn call(&self, function: &str, args: impl rhai::FuncArgs) -> f64 {
+        let value: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, function, args)
+            .unwrap_or_else(|err| panic!("Cost script failed in {}: {}", function, err));
+        value
+            .as_float()
+            .or_else(|_| value.as_int().map(|int| int as f64))
+            .unwrap_or_else(|typ| panic!("Cost script {} returned a {}, not a number", function, typ))
+    }
+
+    pub fn cost(&self, op: &str, child_costs: &[f64], depth: i64) -> f64 {
+        let child_costs: Array = child_costs.iter().map(|cost| Dynamic::from_float(*cost)).collect();
+        self.call("cost", (op.to_string(), child_costs, depth))
+    }
+
+    pub fn has_weight(&self) -> bool {
+        self.ast.iter_functions().any(|function| function.name == "weight" && function.params.len() == 2)
+    }
+
+    /// Replaces the node costs of `data` with the script's `weight`. Areas are left
+    /// as they were.
+    pub fn apply_weights(&self, data: &mut Data) {
+        for node in data.nodes.values_mut() {
+            let weight = self.call("weight", (node.op.clone(), node.cost.into_inner()));
+            node.area = Some(node.area());
+            node.cost = Cost::new(weight).unwrap_or_else(|_| panic!("Cost script weight of {} is NaN", node.op));
+        }
+    }
+}
+
+/// Bottom-up extraction with the costs of a `CostScript`.
+#[derive(Clone)]
+pub struct ScriptedExtractor {
+    pub script: Arc<CostScript>,
+}
+
+impl Extractor for ScriptedExtractor {
+    fn extract(&self, egraph: &SerializedEGraph, _roots: &[ClassId]) -> ExtractionResult {
+        let mut parents = IndexMap::<ClassId, Vec<NodeId>>::with_capacity(egraph.classes().len());
+        let mut pending = UniqueQueue::default();
+        for class in egraph.classes().values() {
+            parents.insert(class.id, Vec::new());
+        }
+        for class in egraph.classes().values() {
+            for node in &class.nodes {
+                for child in &egraph[node].children {
+                    parents[child].push(*node);
+                }
+                if egraph[node].is_leaf() {
+                    pending.insert(*node);
+                }
+            }
+        }
+
+        let mut result = ExtractionResult::default();
+        // The cost and depth of the best term of each class so far.
+        let mut best = FxHashMap::<ClassId, (Cost, i64)>::default();
+        let mut updates = FxHashMap::<ClassId, usize>::default();
+        while let Some(node_id) = pending.pop() {
+            let class_id = egraph.nid_to_cid(&node_id);
+            let node = &egraph[&node_id];
+            let Some(children) = node.children.iter().map(|child| best.get(child).copied()).collect::<Option<Vec<_>>>() else {
+                continue;
+            };
+            let child_costs: Vec<f64> = children.iter().map(|(cost, _)| cost.into_inner()).collect();
+            let depth = 1 + children.iter().map(|(_, depth)| *depth).max().unwrap_or(0);
+            let cost = Cost::new(self.script.cost(&node.op, &child_costs, depth)).unwrap_or(INFINITY);
+            result.record_cost(node_id, cost);
+            let previous = best.get(class_id).map_or(INFINITY, |(cost, _)| *cost);
+            let count = updates.entry(*class_id).or_default();
+            if cost < previous && *count < MAX_UPDATES {
+                *count += 1;
+                result.choose(*class_id, node_id);
+                best.insert(*class_id, (cost, depth));
+                pending.extend(parents[class_id].iter().cloned());
+            }
+        }
+
+        result.class_costs.extend(best.into_iter().map(|(class_id, (cost, _))| (class_id, cost)));
+        result
+    }
+
+    fn fills_node_costs(&self) -> bool {
+        true
+    }
+}
+
+/// Loads the script at `path` and registers its `scripted-cost` extractor.
+pub fn load_cost_script(path: &str) -> Result<Arc<CostScript>, String> {
+    let script = Arc::new(CostScript::from_file(path)?);
+    let registered = script.clone();
+    register_extractor("scripted-cost", move || ExtractorDetail {
+        extractor: ScriptedExtractor { script: registered.clone() }.boxed(),
+        optimal: Optimal::Neither,
+        use_for_bench: true,
+    });
+    Ok(script)
+}
@@ -0,0 +1,294 @@
+// Long-running service mode.
+//
+// cargo run --release -- serve --addr 0.0.0.0:8080
+//
+// Endpoints (all responses are JSON):
+//   GET    /extractors                          list registered extractors
+//   POST   /egraph                              body: e-graph JSON, returns {"id": ...}
+//   DELETE /egraph/<id>                         drop a cached e-graph
+//   POST   /egraph/<id>/extract?extractor=<n>   start an extraction job
+//   GET    /egraph/<id>/status                  state of the last job
+//   GET    /egraph/<id>/result                  choices and costs of the last job
+//   POST   /egraph/<id>/lp?bound=<b>            start writing an LP file (warm-started from the last result if any)
+//   GET    /egraph/<id>/lp                      state of the last LP file
+//
+// Submitted e-graphs stay loaded with their class index built, so repeated
+// extraction requests don't pay the JSON load again.
+
+use crate::extractor;
+use crate::ilp_gen::{self, ModelOptions};
+use egraph_serialize::{Data, NodeId};
+use egraph_serialize::EGraph as SerializedEGraph;
+use extraction_gym::ExtractionResult;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tiny_http::{Method, Request, Response, Server};
+
+enum Job {
+    Idle,
+    Running { extractor: String, started: Instant },
+    Done { extractor: String, result: Arc<ExtractionResult>, runtime: f64 },
+    Failed { extractor: String, error: String },
+}
+
+enum LpJob {
+    Idle,
+    Running { lp_file: String, started: Instant },
+    Done { lp_file: String, pruned_nodes: usize, runtime: f64 },
+    Failed { lp_file: String, error: String },
+}
+
+struct Session {
+    egraph: Arc<SerializedEGraph>,
+    job: Arc<Mutex<Job>>,
+    lp: Arc<Mutex<LpJob>>,
+}
+
+pub fn serve(addr: &str) {
+    let server = Server::http(addr).unwrap_or_else(|err| panic!("Failed to bind {}: {}", addr, err));
+    println!("Serving on http://{}", addr);
+
+    let mut sessions: HashMap<u64, Session> = HashMap::new();
+    let mut next_id: u64 = 0;
+
+    for mut request in server.incoming_requests() {
+        let url = request.url().to_string();
+        let (path, query) = match url.split_once('?') {
+            Some((p, q)) => (p.to_string(), parse_query(q)),
+            None => (url.clone(), HashMap::new()),
+        };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let method = request.method().clone();
+        println!("{} {}", method, url);
+
+        let reply = match (&method, segments.as_slice()) {
+            (Method::Get, ["extractors"]) => {
+                let names: Vec<&str> = extractor::extractors().keys().cloned().collect();
+                Ok(json!({ "extractors": names }))
+            }
+            (Method::Post, ["egraph"]) => Data::from_json_reader(request.as_reader())
+                .map_err(|err| (400, format!("Failed to parse e-graph: {}", err)))
+                .and_then(load)
+                .map(|egraph| {
+                    // `validate` built the class index, so extraction requests find it cached.
+                    let num_classes = egraph.classes().len();
+                    let num_nodes = egraph.nodes.len();
+                    let id = next_id;
+                    next_id += 1;
+                    sessions.insert(id, Session {
+                        egraph: Arc::new(egraph),
+                        job: Arc::new(Mutex::new(Job::Idle)),
+                        lp: Arc::new(Mutex::new(LpJob::Idle)),
+                    });
+                    json!({ "id": id, "nodes": num_nodes, "classes": num_classes })
+                }),
+            (Method::Delete, ["egraph", id]) => match parse_id(id) {
+                Some(id) if sessions.remove(&id).is_some() => Ok(json!({ "id": id, "deleted": true })),
+                _ => Err((404, format!("Unknown e-graph id: {}", id))),
+            },
+            (method, ["egraph", id, action]) => match parse_id(id).and_then(|id| sessions.get(&id).map(|s| (id, s))) {
+                Some((id, session)) => handle_session(method, action, &query, id, session),
+                None => Err((404, format!("Unknown e-graph id: {}", id))),
+            },
+            _ => Err((404, format!("No route for {} {}", method, path))),
+        };
+
+        respond(request, reply);
+    }
+}
+
+fn handle_session(
+    method: &Method,
+    action: &str,
+    query: &HashMap<String, String>,
+    id: u64,
+    session: &Session,
+) -> Result<Value, (u16, String)> {
+    match (method, action) {
+        (Method::Post, "extract") => {
+            let extractor_name = query
+                .get("extractor")
+                .cloned()
+                .unwrap_or_else(|| String::from("faster-greedy-dag-mt1"))
+                .to_lowercase();
+            if !extractor::extractors().contains_key(extractor_name.as_str()) {
+                return Err((400, format!("Unknown extractor: {}", extractor_name)));
+            }
+            {
+                let mut job = session.job.lock().unwrap();
+                if let Job::Running { .. } = *job {
+                    return Err((409, String::from("An extraction job is already running")));
+                }
+                *job = Job::Running { extractor: extractor_name.clone(), started: Instant::now() };
+            }
+
+            let egraph = Arc::clone(&session.egraph);
+            let job = Arc::clone(&session.job);
+            let name = extractor_name.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    let extractors = extractor::extractors();
+                    extractors[name.as_str()].get_extractor().extract(&egraph, &egraph.root_eclasses)
+                }));
+                let runtime = start.elapsed().as_secs_f64();
+                *job.lock().unwrap() = match outcome {
+                    Ok(result) => match result.validate(&egraph) {
+                        Ok(()) => Job::Done { extractor: name, result: Arc::new(result), runtime },
+                        Err(err) => Job::Failed { extractor: name, error: format!("invalid extraction: {}", err) },
+                    },
+                    Err(_) => Job::Failed { extractor: name, error: String::from("extraction panicked") },
+                };
+            });
+            Ok(json!({ "status": "running", "extractor": extractor_name }))
+        }
+        (Method::Get, "status") => Ok(job_status(&session.job.lock().unwrap())),
+        (Method::Get, "result") => match &*session.job.lock().unwrap() {
+            Job::Done { extractor, result, runtime } => {
+                let egraph = &session.egraph;
+                let roots = &egraph.root_eclasses;
+                let choices: serde_json::Map<String, Value> = result
+                    .choices
+                    .iter()
+                    .map(|(cid, nid)| (cid.to_string(), Value::String(nid.to_string())))
+                    .collect();
+                Ok(json!({
+                    "extractor": extractor,
+                    "runtime": runtime,
+                    "tree": result.tree_cost(egraph, roots).into_inner(),
+                    "dag": result.dag_cost(egraph, roots).into_inner(),
+                    "depth": result.depth_cost(egraph, roots),
+                    "area": result.area_cost(egraph).into_inner(),
+                    "choices": choices,
+                }))
+            }
+            job => Err((409, format!("No result available: {}", job_status(job)["status"]))),
+        },
+        (Method::Post, "lp") => {
+            let bound: f32 = match query.get("bound").map(|b| b.parse::<f32>()) {
+                Some(Ok(b)) => b,
+                Some(Err(_)) => return Err((400, String::from("Invalid bound value"))),
+                None => -1.0,
+            };
+            let lp_file = format!("file/lp/serve_{}_{}.lp", id, bound);
+            {
+                let mut lp = session.lp.lock().unwrap();
+                if let LpJob::Running { .. } = *lp {
+                    return Err((409, String::from("An LP file is already being written")));
+                }
+                *lp = LpJob::Running { lp_file: lp_file.clone(), started: Instant::now() };
+            }
+
+            // Like the extraction jobs, the LP is written on its own thread, so the
+            // server keeps answering, and a panic fails the job rather than the server.
+            let egraph = Arc::clone(&session.egraph);
+            let result = match &*session.job.lock().unwrap() {
+                Job::Done { result, .. } if bound >= 1.0 => Some(Arc::clone(result)),
+                _ => None,
+            };
+            let lp = Arc::clone(&session.lp);
+            let path = lp_file.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let _ = std::fs::create_dir_all("file/lp");
+                let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    let warm_start = result.map(|result| {
+                        let mut zero_node = Vec::<NodeId>::new();
+                        crate::collect_results(&egraph, &result, bound, &mut zero_node);
+                        zero_node
+                    });
+                    let pruned = warm_start.as_ref().map_or(0, |z| z.len());
+                    ilp_gen::generate_ilp_file(&egraph, &egraph.root_eclasses, &path, ModelOptions { pruned: warm_start.as_deref(), ..Default::default() }, None);
+                    pruned
+                }));
+                let runtime = start.elapsed().as_secs_f64();
+                *lp.lock().unwrap() = match outcome {
+                    Ok(pruned_nodes) => LpJob::Done { lp_file: path, pruned_nodes, runtime },
+                    Err(_) => LpJob::Failed { lp_file: path, error: String::from("LP generation panicked") },
+                };
+            });
+            Ok(json!({ "status": "running", "lp_file": lp_file }))
+        }
+        (Method::Get, "lp") => Ok(lp_status(&session.lp.lock().unwrap())),
+        _ => Err((404, format!("No route for {} {}", method, action))),
+    }
+}
+
+/// The e-graph of an upload, checked so that the extraction and LP requests on it
+/// don't index missing classes.
+fn load(data: Data) -> Result<SerializedEGraph, (u16, String)> {
+    let egraph = SerializedEGraph::from_Data(&data).map_err(|err| (400, format!("Failed to load e-graph: {}", err)))?;
+    egraph.validate().map_err(|err| (400, format!("Invalid e-graph: {}", err)))?;
+    Ok(egraph)
+}
+
+fn job_status(job: &Job) -> Value {
+    match job {
+        Job::Idle => json!({ "status": "idle" }),
+        Job::Running { extractor, started } => json!({
+            "status": "running",
+            "extractor": extractor,
+            "elapsed": started.elapsed().as_secs_f64(),
+        }),
+        Job::Done { extractor, runtime, .. } => json!({
+            "status": "done",
+            "extractor": extractor,
+            "runtime": runtime,
+        }),
+        Job::Failed { extractor, error } => json!({
+            "status": "failed",
+            "extractor": extractor,
+            "error": error,
+        }),
+    }
+}
+
+fn lp_status(lp: &LpJob) -> Value {
+    match lp {
+        LpJob::Idle => json!({ "status": "idle" }),
+        LpJob::Running { lp_file, started } => json!({
+            "status": "running",
+            "lp_file": lp_file,
+            "elapsed": started.elapsed().as_secs_f64(),
+        }),
+        LpJob::Done { lp_file, pruned_nodes, runtime } => json!({
+            "status": "done",
+            "lp_file": lp_file,
+            "pruned_nodes": pruned_nodes,
+            "runtime": runtime,
+        }),
+        LpJob::Failed { lp_file, error } => json!({
+            "status": "failed",
+            "lp_file": lp_file,
+            "error": error,
+        }),
+    }
+}
+
+fn respond(request: Request, reply: Result<Value, (u16, String)>) {
+    let (code, body) = match reply {
+        Ok(body) => (200, body),
+        Err((code, error)) => (code, json!({ "error": error })),
+    };
+    let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(code)
+        .with_header(header);
+    if let Err(err) = request.respond(response) {
+        eprintln!("Failed to send response: {}", err);
+    }
+}
+
+fn parse_id(id: &str) -> Option<u64> {
+    id.parse::<u64>().ok()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
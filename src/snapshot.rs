@@ -0,0 +1,99 @@
+//! Periodic snapshots of the best extraction so far (`--snapshot <minutes>`).
+//!
+//! The heuristic's result, the warm starts and the solver's incumbents (read from
+//! the solution pool of the Gurobi, CPLEX and CP-SAT wrappers) are offered as they
+//! come. Every interval, the best of them replaces the snapshot, a choices file
+//! written next to it and renamed over it so that it is never seen half written,
+//! and a line is appended to the history next to it (`.jsonl`) for plotting:
+//!
+//! ```json
+//! {"time": 312.4, "dag": 2385.0, "source": "gurobi"}
+//! ```
+//!
+//! `time` is when the result was offered, in seconds since the snapshots started.
+
+use extraction_gym::choices_file::ChoicesFile;
+use extraction_gym::ExtractionResult;
+use egraph_serialize::Cost;
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static SNAPSHOTS: OnceLock<Snapshots> = OnceLock::new();
+
+struct Snapshots {
+    path: PathBuf,
+    start: Instant,
+    best: Mutex<Best>,
+}
+
+#[derive(Default)]
+struct Best {
+    /// DAG cost, seconds since the start, source and choices.
+    result: Option<(f64, f64, String, ChoicesFile)>,
+    written: bool,
+}
+
+/// Starts writing snapshots to `path` every `interval`.
+pub fn start(path: impl Into<PathBuf>, interval: Duration) {
+    let snapshots = Snapshots { path: path.into(), start: Instant::now(), best: Mutex::new(Best::default()) };
+    // The history is this run's.
+    let _ = fs::remove_file(snapshots.path.with_extension("jsonl"));
+    if SNAPSHOTS.set(snapshots).is_err() {
+        panic!("Snapshots are already being written");
+    }
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        flush();
+    });
+}
+
+/// Whether `start` was called.
+pub fn active() -> bool {
+    SNAPSHOTS.get().is_some()
+}
+
+/// Keeps `result`, of DAG cost `dag`, for the next snapshot if it is the best so far.
+pub fn offer(source: &str, result: &ExtractionResult, dag: f64) {
+    let Some(snapshots) = SNAPSHOTS.get() else {
+        return;
+    };
+    let mut best = snapshots.best.lock().unwrap();
+    if best.result.as_ref().is_some_and(|(best_dag, ..)| *best_dag <= dag) {
+        return;
+    }
+    let mut file = ChoicesFile { choices: result.choices.clone(), ..ChoicesFile::default() }.with_meta("source", source);
+    if let Ok(cost) = Cost::new(dag) {
+        file.costs.insert("dag".to_string(), cost);
+    }
+    best.result = Some((dag, snapshots.start.elapsed().as_secs_f64(), source.to_string(), file));
+    best.written = false;
+}
+
+/// Writes the best result now if it isn't yet, e.g. before exiting.
+pub fn flush() {
+    let Some(snapshots) = SNAPSHOTS.get() else {
+        return;
+    };
+    let mut best = snapshots.best.lock().unwrap();
+    let Some((dag, time, source, file)) = best.result.as_ref().filter(|_| !best.written) else {
+        return;
+    };
+    match snapshots.write(*dag, *time, source, file) {
+        Ok(()) => best.written = true,
+        Err(err) => eprintln!("Warning: snapshot not saved: {}: {}", snapshots.path.display(), err),
+    }
+}
+
+impl Snapshots {
+    fn write(&self, dag: f64, time: f64, source: &str, file: &ChoicesFile) -> std::io::Result<()> {
+        let partial = self.path.with_extension("json.partial");
+        file.clone().with_meta("time", time).to_json_file(&partial)?;
+        fs::rename(&partial, &self.path)?;
+        let mut history = OpenOptions::new().create(true).append(true).open(self.path.with_extension("jsonl"))?;
+        writeln!(history, "{}", json!({ "time": time, "dag": dag, "source": source }))
+    }
+}
@@ -0,0 +1,905 @@
+use crate::hints::Hints;
+use crate::iis;
+use crate::interrupt;
+use crate::race;
+use crate::snapshot;
+use crate::ilp_gen::{self, Acyclicity, ModelOptions};
+use crate::ilp_model::IlpModel;
+use e_boost_core::solution::{self, SolutionError};
+use e_boost_core::warm_start::{self, Solver};
+use crate::timeline::{LogFormat, LogTail, Point, Timeline};
+use egraph_serialize::ClassId;
+use egraph_serialize::Cost;
+use egraph_serialize::Data;
+use egraph_serialize::EGraph as SerializedEGraph;
+use egraph_serialize::NodeId;
+use extraction_gym::choices_file::ChoicesFile;
+use extraction_gym::ExtractionResult;
+use indexmap::IndexMap;
+use rustc_hash::FxHashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+use wait_timeout::ChildExt;
+
+/// Files exchanged with the external solver binaries.
+#[derive(Clone)]
+pub struct SolverFiles {
+    /// What the paths are made of, see `in_dir`.
+    pub dir: String,
+    pub base_name: String,
+    pub bound: f32,
+    pub lp_file: String,
+    pub mst_file: String,
+    /// Further MIP starts, tried after `mst_file` by the solvers that take several.
+    pub extra_mst_files: Vec<String>,
+    pub hint_file: String,
+    pub zero_file: String,
+    pub redundancy_file: String,
+    pub result_file: String,
+    pub log_file: String,
+    pub timeline_file: String,
+    pub iis_file: String,
+    pub pool_dir: String,
+    pub checkpoint_file: String,
+    pub snapshot_file: String,
+}
+
+impl SolverFiles {
+    /// The file layout used by the CLI, under `file/`.
+    pub fn new(base_name: &str, bound: f32, solver: &str) -> Self {
+        Self::in_dir("file", base_name, bound, solver)
+    }
+
+    /// The same layout under `dir`.
+    pub fn in_dir(dir: &str, base_name: &str, bound: f32, solver: &str) -> Self {
+        // `race:gurobi,cpsat` names files `race-gurobi-cpsat`.
+        let solver = solver.replace([':', ','], "-");
+        Self {
+            dir: dir.to_string(),
+            base_name: base_name.to_string(),
+            bound,
+            lp_file: format!("{}/lp/{}_{}.lp", dir, base_name, bound),
+            mst_file: format!("{}/start/{}_{}.mst", dir, base_name, bound),
+            extra_mst_files: Vec::new(),
+            hint_file: format!("{}/start/{}_{}.hints.json", dir, base_name, bound),
+            zero_file: format!("{}/ZeroNode/{}_{}_{}.mst", dir, base_name, bound, solver),
+            redundancy_file: format!("{}/redundancy/{}_{}.json", dir, base_name, bound),
+            result_file: format!("{}/result/{}_{}_{}.sol", dir, base_name, bound, solver),
+            log_file: format!("{}/log/{}_{}_{}.log", dir, base_name, bound, solver),
+            timeline_file: format!("{}/result/{}_{}_{}.timeline.json", dir, base_name, bound, solver),
+            iis_file: format!("{}/result/{}_{}_{}.iis", dir, base_name, bound, solver),
+            pool_dir: format!("{}/pool/{}_{}_{}", dir, base_name, bound, solver),
+            checkpoint_file: format!("{}/result/{}_{}_{}.checkpoint.json", dir, base_name, bound, solver),
+            snapshot_file: format!("{}/result/{}_{}_{}.snapshot.json", dir, base_name, bound, solver),
+        }
+    }
+
+    /// The files of `solver` for the same LP and warm starts.
+    pub fn for_solver(&self, solver: &str) -> Self {
+        Self { extra_mst_files: self.extra_mst_files.clone(), ..Self::in_dir(&self.dir, &self.base_name, self.bound, solver) }
+    }
+
+    /// The warm start in the format of `solver`, next to `mst_file` (which is the
+    /// Gurobi one).
+    pub fn start_file(&self, solver: Solver) -> String {
+        Path::new(&self.mst_file).with_extension(solver.extension()).to_string_lossy().to_string()
+    }
+
+    /// Creates the directories the files are in.
+    pub fn create_dirs(&self) -> std::io::Result<()> {
+        for dir in ["lp", "start", "ZeroNode", "redundancy", "result", "log"] {
+            fs::create_dir_all(Path::new(&self.dir).join(dir))?;
+        }
+        fs::create_dir_all(&self.pool_dir)
+    }
+}
+
+/// Progress reporting while the solver runs, read from its log.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Monitor {
+    /// Print the incumbent, bound and gap every that many seconds.
+    pub interval: Option<u64>,
+    /// Interrupt the solver, which then writes its incumbent, once the gap is at
+    /// most this fraction.
+    pub target_gap: Option<f64>,
+    /// Save the best incumbent of the solution pool to `SolverFiles::checkpoint_file`
+    /// every that many seconds (Gurobi, CPLEX and CP-SAT wrappers only).
+    pub checkpoint: Option<u64>,
+}
+
+/// One solver run: the solver, its files, its time limit and its progress reporting.
+#[derive(Clone, Copy)]
+pub struct SolverRun<'a> {
+    pub solver: &'a str,
+    pub files: &'a SolverFiles,
+    pub timeout_secs: u64,
+    pub monitor: Monitor,
+}
+
+/// How long an interrupted solver gets to write its incumbent before it is killed.
+const STOP_GRACE_SECS: u64 = 30;
+
+const GUROBI_WRAPPER: &str = "gurobi/gurobi_solver";
+
+/// Whether `program` is an executable file in one of the `PATH` directories.
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| dir.join(program).is_file())
+    })
+}
+
+/// Why a solver run ended without a solution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolveFailure {
+    Infeasible,
+    NoSolution,
+}
+
+impl std::fmt::Display for SolveFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveFailure::Infeasible => write!(f, "the model is infeasible"),
+            SolveFailure::NoSolution => write!(f, "no solution was found"),
+        }
+    }
+}
+
+impl std::error::Error for SolveFailure {}
+
+/// Exit code of the Gurobi, CPLEX and CP-SAT wrappers for an infeasible model.
+pub(crate) const INFEASIBLE_EXIT_CODE: i32 = 2;
+
+/// How many times `solve_relaxing` regenerates the LP with less pruning.
+const MAX_RELAX_RETRIES: usize = 2;
+
+/// Solves an `IlpModel` in process, without any file.
+pub type ModelSolver = fn(&IlpModel, u64) -> Result<ExtractionResult, SolveFailure>;
+
+/// The in-process solve of `solver`, for the back ends linked in by their feature
+/// (`gurobi`, `highs`, `cpsat`).
+pub fn in_memory_solver(solver: &str) -> Option<ModelSolver> {
+    let solvers: &[(&str, ModelSolver)] = &[
+        #[cfg(feature = "gurobi")]
+        ("gurobi", |model, timeout_secs| crate::gurobi::solve_model(model, timeout_secs, crate::gurobi::params())),
+        #[cfg(feature = "highs")]
+        ("highs", crate::highs::solve_model),
+        #[cfg(feature = "cpsat")]
+        ("cpsat", crate::cpsat::solve_model),
+    ];
+    solvers.iter().find(|(name, _)| *name == solver).map(|(_, solve)| *solve)
+}
+
+/// Solves an `IlpModel` in process within a run, with the log, timeline and result
+/// written to the run's files as the back end processes write them.
+type ModelFileSolver = fn(&IlpModel, &SolverFiles, u64, Monitor) -> Result<ExtractionResult, SolveFailure>;
+
+/// The back ends `try_solve_relaxing` hands the model built in memory rather than
+/// the LP file (or the redundancy JSON).
+fn model_file_solver(solver: &str) -> Option<ModelFileSolver> {
+    let solvers: &[(&str, ModelFileSolver)] = &[
+        #[cfg(feature = "highs")]
+        ("highs", crate::highs::solve_with_files),
+        #[cfg(feature = "cpsat")]
+        ("cpsat", crate::cpsat::solve_with_files),
+    ];
+    solvers.iter().find(|(name, _)| *name == solver).map(|(_, solve)| *solve)
+}
+
+/// Runs the selected solver as a child process on `files.lp_file` (or the redundancy
+/// JSON for cpsat) and returns the parsed solution. `highs` and `cbc` are looked up
+/// on `PATH`; with the `gurobi` feature, `gurobi` runs in process instead (see
+/// `gurobi::solve_files`). Panics if the solver fails or finds no solution.
+pub fn run_solver(solver: &str, files: &SolverFiles, timeout_secs: u64, monitor: Monitor) -> ExtractionResult {
+    try_run_solver(solver, files, timeout_secs, monitor).unwrap_or_else(|failure| panic!("{}: {}", solver, failure))
+}
+
+/// `run_solver`, with an infeasible model or a run without solution as an error.
+pub fn try_run_solver(solver: &str, files: &SolverFiles, timeout_secs: u64, monitor: Monitor) -> Result<ExtractionResult, SolveFailure> {
+    println!("Running solver: {}", solver);
+
+    // Make sure the LP file exists
+    if !Path::new(&files.lp_file).exists() {
+        panic!("Error: LP file not found: {}", files.lp_file);
+    }
+
+    // Check if MST file exists when in solver-only mode
+    if !Path::new(&files.mst_file).exists() {
+        eprintln!("Warning: MST file not found: {}", files.mst_file);
+        eprintln!("Continuing without warm start solution");
+    }
+
+    if let Some(backends) = race::backends(solver) {
+        return race::run(&backends, files, timeout_secs, monitor);
+    }
+    #[cfg(feature = "gurobi")]
+    if solver == "gurobi" {
+        return crate::gurobi::solve_files(files, timeout_secs, monitor, crate::gurobi::params());
+    }
+    let mut run = spawn(solver, files, timeout_secs, monitor);
+
+    println!("-----------------------------------------------------");
+    let (status, stopped) = wait_monitored(&mut run.child, solver, files, timeout_secs, run.log_format, monitor);
+    println!("-----------------------------------------------------");
+
+    finish(solver, files, &run, status, stopped)
+}
+
+/// A solver process started by `spawn`.
+pub(crate) struct Run {
+    pub child: Child,
+    pub log_format: LogFormat,
+    pub warm_start: bool,
+}
+
+/// Starts `solver` on the files `try_run_solver` expects, after removing the outputs
+/// of an earlier run.
+pub(crate) fn spawn(solver: &str, files: &SolverFiles, timeout_secs: u64, monitor: Monitor) -> Run {
+    // cpsat reads neither the bounds nor the MST of the LP, it gets the hint file.
+    let hints = if solver == "cpsat" {
+        match Hints::from_json_file(&files.hint_file) {
+            Ok(hints) => Some(hints),
+            Err(err) => {
+                eprintln!("Warning: Hint file not read: {}: {}", files.hint_file, err);
+                eprintln!("Continuing without warm start solution");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // None may be taken from an earlier run while following this one.
+    let _ = fs::remove_file(&files.result_file);
+    let _ = fs::remove_file(&files.log_file);
+    let _ = fs::remove_file(&files.iis_file);
+    // Only the wrappers write their incumbents to the pool, which then holds this run's.
+    let pooled = (monitor.checkpoint.is_some() || snapshot::active())
+        && match solver {
+            "gurobi" => Path::new(GUROBI_WRAPPER).exists(),
+            "cplex" | "cpsat" => true,
+            _ => false,
+        };
+    if monitor.checkpoint.is_some() && !pooled {
+        eprintln!("Warning: {} writes no solution pool here, no checkpoints are saved", solver);
+    }
+    if pooled {
+        let _ = fs::remove_dir_all(&files.pool_dir);
+        fs::create_dir_all(&files.pool_dir).expect("Unable to create solution pool directory");
+    }
+
+    let takes_extra_starts = match solver {
+        "gurobi" => Path::new(GUROBI_WRAPPER).exists(),
+        "cplex" => true,
+        _ => false,
+    };
+    if !files.extra_mst_files.is_empty() && !takes_extra_starts {
+        println!("{} takes a single warm start, the {} extra ones are not used", solver, files.extra_mst_files.len());
+    }
+
+    let warm_start = match solver {
+        "highs" => false,
+        "cpsat" => {
+            hints.as_ref().is_some_and(|hints| !hints.suggested_one.is_empty()) && Path::new(&files.start_file(Solver::CpSat)).exists()
+        }
+        _ => Path::new(&files.mst_file).exists(),
+    };
+
+    let (child, log_format) = match solver {
+        "gurobi" if !Path::new(GUROBI_WRAPPER).exists() && on_path("gurobi_cl") => {
+            // Stock Gurobi install without the wrapper: drive gurobi_cl directly.
+            println!("{} not found, using gurobi_cl", GUROBI_WRAPPER);
+            let mut args = vec![
+                format!("TimeLimit={}", timeout_secs),
+                format!("ResultFile={}", files.result_file),
+                format!("LogFile={}", files.log_file),
+            ];
+            if Path::new(&files.mst_file).exists() {
+                args.push(format!("InputFile={}", files.mst_file));
+            }
+            args.push(files.lp_file.clone());
+
+            println!("command: gurobi_cl {}", args.join(" "));
+
+            let child = Command::new("gurobi_cl")
+                .args(args)
+                .spawn()
+                .unwrap_or_else(|err| panic!("Failed to start gurobi_cl: {}", err));
+            (child, LogFormat::Gurobi)
+        },
+        "gurobi" | "cplex" => {
+            let binary = if solver == "gurobi" { GUROBI_WRAPPER } else { "cplex/cplex_solver" };
+            let mut cmd = Command::new(binary);
+            let mut args = vec![
+                "--lp_file".to_string(),
+                files.lp_file.clone(),
+                "--output_file".to_string(),
+                files.result_file.clone(),
+                "--time_limit".to_string(),
+                timeout_secs.to_string(),
+                "--log_file".to_string(),
+                files.log_file.clone(),
+                "--iis_file".to_string(),
+                files.iis_file.clone(),
+            ];
+            if pooled {
+                args.extend(["--solution_pool_dir".to_string(), files.pool_dir.clone()]);
+            }
+
+            // Add MST file if it exists
+            if Path::new(&files.mst_file).exists() {
+                args.insert(0, "--mst_file".to_string());
+                args.insert(1, files.mst_file.clone());
+            }
+            if !files.extra_mst_files.is_empty() {
+                args.extend(["--extra_mst_files".to_string(), files.extra_mst_files.join(",")]);
+            }
+
+            println!("command: {}", args.join(" "));
+
+            let child = cmd.args(args)
+                .spawn()
+                .unwrap_or_else(|err| panic!("Failed to start {} solver: {}", solver, err));
+            (child, LogFormat::Incumbents)
+        },
+        "highs" => {
+            // The HiGHS command line has no MIP start option; the pruned nodes are fixed in the LP itself.
+            let options_file = Path::new(&files.log_file).with_extension("opt");
+            fs::write(&options_file, format!("log_file = {}\n", files.log_file)).expect("Unable to write file");
+            let args = vec![
+                "--model_file".to_string(),
+                files.lp_file.clone(),
+                "--solution_file".to_string(),
+                files.result_file.clone(),
+                "--time_limit".to_string(),
+                timeout_secs.to_string(),
+                "--options_file".to_string(),
+                options_file.to_string_lossy().to_string(),
+            ];
+
+            println!("command: highs {}", args.join(" "));
+
+            let child = Command::new("highs")
+                .args(args)
+                .spawn()
+                .expect("Failed to start HiGHS solver");
+            (child, LogFormat::Highs)
+        },
+        "cbc" => {
+            let mut args = vec![files.lp_file.clone(), "-sec".to_string(), timeout_secs.to_string()];
+            if Path::new(&files.mst_file).exists() {
+                let mipstart_file = Path::new(&files.mst_file).with_extension("cbc.mst");
+                write_cbc_mipstart(&files.mst_file, &mipstart_file);
+                args.push("-mipstart".to_string());
+                args.push(mipstart_file.to_string_lossy().to_string());
+            }
+            args.extend(["-solve", "-solu", files.result_file.as_str()].map(String::from));
+
+            println!("command: cbc {}", args.join(" "));
+            println!("CBC output goes to {}", files.log_file);
+
+            let log = fs::File::create(&files.log_file).expect("Unable to create log file");
+            let child = Command::new("cbc")
+                .args(args)
+                .stdout(log)
+                .spawn()
+                .expect("Failed to start CBC solver");
+            (child, LogFormat::Cbc)
+        },
+        "cpsat" => {
+            let mut cmd = Command::new("cpsat/cpsat");
+            let mut args = vec![
+                "--egraph_json_file".to_string(),
+                files.redundancy_file.clone(),
+                "--output_sol_file".to_string(),
+                files.result_file.clone(),
+                "--time_limit".to_string(),
+                timeout_secs.to_string(),
+                "--log_file".to_string(),
+                files.log_file.clone(),
+            ];
+            if pooled {
+                args.extend(["--solution_pool_dir".to_string(), files.pool_dir.clone()]);
+            }
+
+            if let Some(hints) = &hints {
+                fs::write(&files.zero_file, hints.to_zero_nodes()).expect("Unable to write file");
+                args.splice(0..0, ["--zero_node_mst".to_string(), files.zero_file.clone()]);
+                if warm_start {
+                    if let Some(objective) = hints.objective_estimate {
+                        println!("Warm start objective: {}", objective);
+                    }
+                    args.splice(0..0, ["--total_gurobi_mst".to_string(), files.start_file(Solver::CpSat)]);
+                }
+            }
+
+            println!("command: {}", args.join(" "));
+
+            let child = cmd.args(args)
+                .spawn()
+                .expect("Failed to start CPSAT solver");
+            (child, LogFormat::Incumbents)
+        },
+        _ => {
+            panic!("Error: Unknown solver: {}", solver);
+        }
+    };
+
+    Run { child, log_format, warm_start }
+}
+
+/// Reads the log, timeline and solution of a `run` that exited with `status`, after
+/// being interrupted if `stopped`.
+pub(crate) fn finish(solver: &str, files: &SolverFiles, run: &Run, status: ExitStatus, stopped: bool) -> Result<ExtractionResult, SolveFailure> {
+    let log = fs::read_to_string(&files.log_file).unwrap_or_else(|err| {
+        eprintln!("Warning: solver log not read: {}: {}", files.log_file, err);
+        String::new()
+    });
+    if status.code() == Some(INFEASIBLE_EXIT_CODE) || run.log_format.reports_infeasible(&log) {
+        // Written by the Gurobi and CPLEX wrappers.
+        if let Ok(iis) = fs::read_to_string(&files.iis_file) {
+            print!("{}", iis::report(&iis::parse(&iis)));
+        }
+        return Err(SolveFailure::Infeasible);
+    }
+    if !status.success() && !stopped {
+        panic!("{} did not exit successfully.", solver);
+    }
+
+    let timeline = Timeline::from_log(solver, run.log_format, run.warm_start, &log);
+    timeline.to_json_file(&files.timeline_file).expect("Unable to write file");
+    println!("Objective timeline ({} points) written to {}", timeline.points.len(), files.timeline_file);
+
+    if !Path::new(&files.result_file).exists() {
+        return Err(SolveFailure::NoSolution);
+    }
+    let solution = parse_solution_file(&files.result_file);
+    println!("Solution found with solver: {}", solver);
+    // The run finished, there is nothing left to resume, unless it was cut short by a signal.
+    if !interrupt::requested() {
+        let _ = fs::remove_file(&files.checkpoint_file);
+    }
+    Ok(solution)
+}
+
+/// Solves the LP that `run.files` holds, generated from `egraph` with `options`
+/// (see `ilp_gen::generate_ilp_file`). When the solver finds it infeasible, the
+/// pruned nodes that cut the roots off are un-fixed (all of them if the pruning
+/// doesn't explain it), the LP is regenerated and solved again, at most
+/// `MAX_RELAX_RETRIES` times within `run.timeout_secs`.
+pub fn solve_relaxing(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    run: SolverRun,
+    options: ModelOptions,
+    warm_start: Option<&ExtractionResult>,
+) -> ExtractionResult {
+    try_solve_relaxing(egraph, roots, run, options, warm_start).unwrap_or_else(|failure| panic!("{}: {}", run.solver, failure))
+}
+
+/// `solve_relaxing`, with the failure of the last retry as an error. With the
+/// `highs` or `cpsat` feature, that solver is handed the model built in memory
+/// rather than the LP file.
+///
+/// With `Acyclicity::Cuts`, a solution with cycles is not returned: they are cut
+/// off and the LP regenerated and solved again, for as long as `run.timeout_secs`
+/// allows. A solver that runs out of time before finding an acyclic solution has
+/// found none.
+pub fn try_solve_relaxing(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    run: SolverRun,
+    options: ModelOptions,
+    warm_start: Option<&ExtractionResult>,
+) -> Result<ExtractionResult, SolveFailure> {
+    let SolverRun { solver, files, timeout_secs, monitor } = run;
+    let ModelOptions { pruned, frozen, acyclicity, op_weight } = options;
+    let mut pruned = pruned.unwrap_or_default().to_vec();
+    let start = Instant::now();
+    let mut retries = 0;
+    // The cycles of the solutions so far.
+    let mut cuts = Vec::new();
+    let model = |pruned: &[NodeId], cuts: &[Vec<NodeId>]| {
+        let mut model = ilp_gen::build_model(egraph, roots, ModelOptions { pruned: Some(pruned), frozen, acyclicity, op_weight }, warm_start);
+        ilp_gen::add_cycle_cuts(&mut model, cuts);
+        model
+    };
+    loop {
+        let remaining = timeout_secs.saturating_sub(start.elapsed().as_secs()).max(1);
+        let outcome = match model_file_solver(solver) {
+            Some(solve) => solve(&model(&pruned, &cuts), files, remaining, monitor),
+            None => try_run_solver(solver, files, remaining, monitor),
+        };
+        match outcome {
+            Ok(solution) if acyclicity == Acyclicity::Cuts => {
+                let cycles = ilp_gen::solution_cycles(egraph, roots, &solution);
+                if cycles.is_empty() {
+                    return Ok(solution);
+                }
+                if interrupt::requested() || start.elapsed().as_secs() >= timeout_secs {
+                    eprintln!("{}: out of time with {} cycles left in the solution", solver, cycles.len());
+                    return Err(SolveFailure::NoSolution);
+                }
+                cuts.extend(cycles);
+                println!("{}'s solution has cycles, solving again with {} cycles cut off", solver, cuts.len());
+            }
+            Ok(solution) => return Ok(solution),
+            Err(SolveFailure::Infeasible) if retries < MAX_RELAX_RETRIES && !pruned.is_empty() && !interrupt::requested() => {
+                retries += 1;
+                let unfixed = relaxed_pruning(egraph, roots, &pruned, frozen);
+                let classes: FxHashSet<&ClassId> = unfixed.iter().map(|nid| egraph.nid_to_cid(nid)).collect();
+                println!(
+                    "{} found the model infeasible, un-fixing {} pruned nodes in {} classes (retry {} of {})",
+                    solver,
+                    unfixed.len(),
+                    classes.len(),
+                    retries,
+                    MAX_RELAX_RETRIES
+                );
+                pruned.retain(|nid| !unfixed.contains(nid));
+            }
+            Err(failure) => return Err(failure),
+        }
+        let model = model(&pruned, &cuts);
+        ilp_gen::write_model_files(&model, &files.lp_file, warm_start.map(|_| files.mst_file.as_str()));
+        write_hints(&model, files);
+    }
+}
+
+/// The pruned nodes to un-fix: those of the classes that have a term when all nodes
+/// (but the non-frozen ones of frozen classes) are allowed and none without the pruned
+/// nodes, or all of them when no root is cut off by the pruning.
+fn relaxed_pruning(
+    egraph: &SerializedEGraph,
+    roots: &[ClassId],
+    pruned: &[NodeId],
+    frozen: Option<&IndexMap<ClassId, NodeId>>,
+) -> FxHashSet<NodeId> {
+    let pruned_set: FxHashSet<&NodeId> = pruned.iter().collect();
+    let allowed = |nid: &NodeId| frozen.and_then(|frozen| frozen.get(egraph.nid_to_cid(nid))).is_none_or(|frozen| frozen == nid);
+    let with_pruned = derivable_classes(egraph, |nid| allowed(nid) && !pruned_set.contains(nid));
+    if roots.iter().all(|root| with_pruned.contains(root)) {
+        return pruned.iter().cloned().collect();
+    }
+    let without_pruned = derivable_classes(egraph, allowed);
+    pruned
+        .iter()
+        .filter(|nid| {
+            let cid = egraph.nid_to_cid(nid);
+            without_pruned.contains(cid) && !with_pruned.contains(cid)
+        })
+        .cloned()
+        .collect()
+}
+
+/// The classes that have a (finite, acyclic) term made of `allowed` nodes only.
+fn derivable_classes(egraph: &SerializedEGraph, allowed: impl Fn(&NodeId) -> bool) -> FxHashSet<ClassId> {
+    let mut parents: IndexMap<ClassId, Vec<&NodeId>> = IndexMap::new();
+    let mut missing: IndexMap<&NodeId, usize> = IndexMap::new();
+    let mut ready = Vec::new();
+    for (nid, node) in egraph.nodes.iter().filter(|(nid, _)| allowed(nid)) {
+        let children: FxHashSet<&ClassId> = node.children.iter().collect();
+        for child in &children {
+            parents.entry(**child).or_default().push(nid);
+        }
+        if children.is_empty() {
+            ready.push(nid);
+        }
+        missing.insert(nid, children.len());
+    }
+    let mut derivable = FxHashSet::default();
+    while let Some(nid) = ready.pop() {
+        let cid = egraph.nid_to_cid(nid);
+        if !derivable.insert(*cid) {
+            continue;
+        }
+        for parent in parents.get(cid).into_iter().flatten() {
+            let count = missing.get_mut(parent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push(parent);
+            }
+        }
+    }
+    derivable
+}
+
+/// Waits for the solver, reporting its progress, saving checkpoints and stopping it at
+/// the target gap as `monitor` asks, or on a signal. Its incumbents go to the
+/// snapshots when they are written. Returns its exit status and
+/// whether it was stopped.
+fn wait_monitored(
+    child: &mut Child,
+    solver: &str,
+    files: &SolverFiles,
+    timeout_secs: u64,
+    log_format: LogFormat,
+    monitor: Monitor,
+) -> (ExitStatus, bool) {
+    if monitor.interval.is_none() && monitor.target_gap.is_none() && monitor.checkpoint.is_none() && !interrupt::installed() && !snapshot::active() {
+        return (child.wait().expect("Failed to wait for solver process"), false);
+    }
+    let mut tail = LogTail::new(&files.log_file, log_format);
+    let start = Instant::now();
+    let mut reported = start;
+    let mut checkpointed = start;
+    let mut checkpoint_objective = f64::INFINITY;
+    let mut snapshot_objective = f64::INFINITY;
+    loop {
+        if let Some(status) = child.wait_timeout(Duration::from_secs(1)).expect("Failed to wait for solver process") {
+            return (status, false);
+        }
+        if interrupt::requested() {
+            println!("Stopping the solver");
+            return (stop(child), true);
+        }
+        let point = tail.poll();
+        if snapshot::active() {
+            if let Some((objective, path)) = best_pooled(&files.pool_dir).filter(|(objective, _)| *objective < snapshot_objective) {
+                snapshot_objective = objective;
+                snapshot::offer(solver, &parse_solution_file(&path.to_string_lossy()), objective);
+            }
+        }
+        if let Some(interval) = monitor.checkpoint {
+            if checkpointed.elapsed().as_secs() >= interval {
+                checkpointed = Instant::now();
+                if let Some((objective, path)) = best_pooled(&files.pool_dir).filter(|(objective, _)| *objective < checkpoint_objective) {
+                    let remaining_secs = timeout_secs.saturating_sub(start.elapsed().as_secs());
+                    let checkpoint = Checkpoint { solution: parse_solution_file(&path.to_string_lossy()), objective, remaining_secs };
+                    match checkpoint.write(solver, &files.checkpoint_file) {
+                        Ok(()) => {
+                            checkpoint_objective = objective;
+                            println!("[{:.0}s] checkpoint of objective {} saved to {}", start.elapsed().as_secs_f64(), objective, files.checkpoint_file);
+                        }
+                        Err(err) => eprintln!("Warning: checkpoint not saved: {}: {}", files.checkpoint_file, err),
+                    }
+                }
+            }
+        }
+        if let Some(interval) = monitor.interval {
+            if reported.elapsed().as_secs() >= interval {
+                reported = Instant::now();
+                println!("[{:.0}s] {}", start.elapsed().as_secs_f64(), progress(point.as_ref()));
+            }
+        }
+        let gap = point.and_then(|point| point.gap);
+        if let (Some(target), Some(gap)) = (monitor.target_gap, gap) {
+            if gap <= target {
+                println!("Gap {:.2}% reached the target of {:.2}%, stopping the solver", gap * 100.0, target * 100.0);
+                return (stop(child), true);
+            }
+        }
+    }
+}
+
+/// The pooled solution of the lowest objective, from its file name
+/// (`solution_<n>_obj_<objective>_time_<seconds>.sol`). Files written in the last
+/// second are skipped, the solver may not have finished them.
+fn best_pooled(pool_dir: &str) -> Option<(f64, PathBuf)> {
+    fs::read_dir(pool_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let age = entry.metadata().and_then(|metadata| metadata.modified()).ok().and_then(|modified| modified.elapsed().ok());
+            age.is_some_and(|age| age >= Duration::from_secs(1))
+        })
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let objective = name.split_once("_obj_")?.1.split_once("_time_")?.0.parse::<f64>().ok()?;
+            Some((objective, entry.path()))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// The best incumbent of an unfinished run and the part of its time limit it had
+/// left, written as a choices file:
+///
+/// ```json
+/// { "version": 1, "choices": { ... }, "costs": { "dag": 2385.0 },
+///   "meta": { "solver": "gurobi", "remaining_secs": 5400 } }
+/// ```
+#[derive(Clone)]
+pub struct Checkpoint {
+    pub solution: ExtractionResult,
+    pub objective: f64,
+    pub remaining_secs: u64,
+}
+
+impl Checkpoint {
+    pub fn write(&self, solver: &str, path: &str) -> std::io::Result<()> {
+        let mut file = ChoicesFile { choices: self.solution.choices.clone(), ..ChoicesFile::default() }
+            .with_meta("solver", solver)
+            .with_meta("remaining_secs", self.remaining_secs);
+        if let Ok(objective) = Cost::new(self.objective) {
+            file.costs.insert("dag".to_string(), objective);
+        }
+        file.to_json_file(path)
+    }
+
+    pub fn read(path: &str) -> std::io::Result<Self> {
+        let file = ChoicesFile::from_json_file(path)?;
+        let remaining_secs = file.meta.get("remaining_secs").and_then(|secs| secs.as_u64()).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "missing remaining_secs")
+        })?;
+        Ok(Checkpoint {
+            solution: file.to_result(),
+            objective: file.costs.get("dag").map_or(f64::NAN, |cost| cost.into_inner()),
+            remaining_secs,
+        })
+    }
+}
+
+fn progress(point: Option<&Point>) -> String {
+    let value = |value: Option<f64>| value.map_or("-".to_string(), |value| value.to_string());
+    match point {
+        Some(point) => format!(
+            "incumbent {} bound {} gap {}",
+            value(point.incumbent),
+            value(point.bound),
+            point.gap.map_or("-".to_string(), |gap| format!("{:.2}%", gap * 100.0))
+        ),
+        None => "no incumbent yet".to_string(),
+    }
+}
+
+/// Interrupts the solver and waits for it to write its incumbent, killing it after
+/// `STOP_GRACE_SECS`.
+pub(crate) fn stop(child: &mut Child) -> ExitStatus {
+    interrupt(child);
+    match child.wait_timeout(Duration::from_secs(STOP_GRACE_SECS)).expect("Failed to wait for solver process") {
+        Some(status) => status,
+        None => {
+            let _ = child.kill();
+            child.wait().expect("Failed to wait for solver process")
+        }
+    }
+}
+
+/// Asks the solver to stop as Ctrl-C does, after which the solvers write their incumbent.
+fn interrupt(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill").args(["-INT", &child.id().to_string()]).status();
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = child.kill();
+    }
+}
+
+/// Runs the exact step on top of a heuristic `result`: writes the warm start (and,
+/// with `bound` >= 1.0, the pruned nodes), generates the LP and solves it. Used by the
+/// library front-ends; the CLI drives the same steps itself through `--pre`.
+pub fn refine(
+    egraph: &SerializedEGraph,
+    result: &ExtractionResult,
+    solver: &str,
+    timeout_secs: u64,
+    bound: f32,
+    base_name: &str,
+) -> ExtractionResult {
+    let files = SolverFiles::new(base_name, bound, solver);
+    for dir in ["file/lp", "file/start", "file/ZeroNode", "file/redundancy", "file/result", "file/log"] {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    let warm_start = if bound >= 1.0 {
+        let mut zero_node = Vec::<NodeId>::new();
+        crate::collect_results(egraph, result, bound, &mut zero_node);
+        Some(zero_node)
+    } else {
+        None
+    };
+    if solver == "cpsat" {
+        let data = Data {
+            nodes: egraph.nodes.clone(),
+            root_eclasses: egraph.root_eclasses.clone(),
+            class_data: egraph.class_data.clone(),
+        };
+        data.to_json_file(&files.redundancy_file).expect("Unable to write file");
+    }
+    let model = ilp_gen::generate_ilp_file(egraph, &egraph.root_eclasses, &files.lp_file, ModelOptions { pruned: warm_start.as_deref(), ..Default::default() }, Some((result, &files.mst_file)));
+    write_hints(&model, &files);
+
+    let run = SolverRun { solver, files: &files, timeout_secs, monitor: Monitor::default() };
+    solve_relaxing(egraph, &egraph.root_eclasses, run, ModelOptions { pruned: warm_start.as_deref(), ..Default::default() }, Some(result))
+}
+
+/// Keeps re-solving while the solver improves on its incumbent.
+///
+/// After each solve the per-class costs of `heuristic` are lowered to the cost of
+/// the incumbent's sub-DAG below each class, zero-node pruning is recomputed with
+/// them (never dropping a node the incumbent uses), and the smaller LP is solved
+/// again, warm-started from the incumbent. Stops when the DAG cost no longer
+/// improves or `run.timeout_secs` is used up, and returns the best solution. `frozen`
+/// choices are kept in every LP.
+///
+/// The tightened costs are DAG costs, so this fits the extractors whose per-node
+/// costs are DAG costs too (the greedy-dag family).
+pub fn tighten(
+    egraph: &SerializedEGraph,
+    heuristic: &ExtractionResult,
+    incumbent: ExtractionResult,
+    run: SolverRun,
+    bound: f32,
+    frozen: Option<&IndexMap<ClassId, NodeId>>,
+    acyclicity: Acyclicity,
+) -> ExtractionResult {
+    let deadline = Instant::now() + Duration::from_secs(run.timeout_secs);
+    let roots = &egraph.root_eclasses;
+    let mut best_cost = incumbent.dag_cost(egraph, roots);
+    let mut best = incumbent;
+    let mut pruning = heuristic.clone();
+    // The tightened LPs prune nodes the other starts may use, the incumbent is the only one.
+    let files = &SolverFiles { extra_mst_files: Vec::new(), ..run.files.clone() };
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_secs();
+        if remaining == 0 {
+            break;
+        }
+
+        let activated: FxHashSet<NodeId> = best.activate_nodes(egraph, roots);
+        for cid in activated.iter().map(|nid| egraph.nid_to_cid(nid)) {
+            let cost = best.dag_cost(egraph, &[*cid]);
+            let class_cost = pruning.class_costs.entry(*cid).or_insert(cost);
+            if cost < *class_cost {
+                *class_cost = cost;
+            }
+        }
+        let mut zero_node = Vec::<NodeId>::new();
+        crate::collect_results(egraph, &pruning, bound, &mut zero_node);
+        zero_node.retain(|nid| !activated.contains(nid) && frozen.is_none_or(|f| f.get(&egraph[nid].eclass) != Some(nid)));
+        println!("tighten: dag {} with {} nodes pruned", best_cost, zero_node.len());
+
+        let options = ModelOptions { pruned: Some(&zero_node), frozen, acyclicity, op_weight: None };
+        let model = ilp_gen::generate_ilp_file(egraph, roots, &files.lp_file, options, Some((&best, &files.mst_file)));
+        write_hints(&model, files);
+        // The incumbent is never pruned, the LP stays feasible.
+        let solution = solve_relaxing(egraph, roots, SolverRun { files, timeout_secs: remaining, ..run }, options, Some(&best));
+        let cost = solution.dag_cost(egraph, roots);
+        if cost >= best_cost {
+            break;
+        }
+        best_cost = cost;
+        best = solution;
+    }
+
+    best
+}
+
+/// Writes the hint file of `model`, which the back ends without an LP reader are fed,
+/// and the CP-SAT start of its hints if they are a feasible start.
+pub fn write_hints(model: &IlpModel, files: &SolverFiles) {
+    let hints = Hints::from_model(model);
+    hints.to_json_file(&files.hint_file).expect("Unable to write file");
+    println!("Hint file successfully generated at: {}", files.hint_file);
+    let start_file = files.start_file(Solver::CpSat);
+    if hints.suggested_one.is_empty() {
+        let _ = fs::remove_file(&start_file);
+    } else {
+        warm_start::write_start(model, Solver::CpSat, &start_file).expect("Unable to write file");
+    }
+}
+
+/// Rewrites an MST start (`<name> <value>` lines) in the solution format CBC reads
+/// with `-mipstart` (`<index> <name> <value>` lines).
+fn write_cbc_mipstart(mst_file: &str, path: &Path) {
+    let mst = fs::read_to_string(mst_file).expect("Failed to read MST file");
+    let mut str = String::new();
+    for (i, line) in mst.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')).enumerate() {
+        str.push_str(&format!("{} {}\n", i, line.trim()));
+    }
+    fs::write(path, str).expect("Unable to write file");
+}
+
+/// Parses a solution file of any of the back ends, see `solution::parse_solution`.
+pub fn parse_solution_file(result_file: &str) -> ExtractionResult {
+    match solution::parse_solution(result_file) {
+        Ok(solution) => solution,
+        Err(SolutionError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => panic!("Solver did not produce a solution file"),
+        Err(SolutionError::Empty) => panic!("Solver produced an empty solution file"),
+        Err(err) => panic!("{}: {}", result_file, err),
+    }
+}
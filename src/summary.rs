@@ -0,0 +1,129 @@
+//! One CSV row per completed run (`--csv <file>`), for comparing runs across a
+//! campaign without scraping their logs. Rows are appended, so `batch --csv` or
+//! separate runs pointed at the same file build up one table:
+//!
+//! ```text
+//! time,benchmark,extractor,bound,solver,pre,tree,dag,depth,area,heuristic_dag,heuristic_secs,lp_secs,lp_binaries,solve_secs,gap,optimum_gap,interrupted
+//! 1760601600,c2670,faster-greedy-dag-mt1,1.25,gurobi,4,1590,1320,37,1320,1346,0.41,0.88,14012,12.5,0,0.0031,false
+//! ```
+//!
+//! Empty cells are steps that didn't run or values that aren't known: `gap` is the
+//! solver's last gap from its timeline, `optimum_gap` the gap to the known optimum
+//! of a registered benchmark.
+
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+pub const COLUMNS: [&str; 18] = [
+    "time",
+    "benchmark",
+    "extractor",
+    "bound",
+    "solver",
+    "pre",
+    "tree",
+    "dag",
+    "depth",
+    "area",
+    "heuristic_dag",
+    "heuristic_secs",
+    "lp_secs",
+    "lp_binaries",
+    "solve_secs",
+    "gap",
+    "optimum_gap",
+    "interrupted",
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunRecord {
+    /// Seconds since the Unix epoch at the end of the run.
+    pub time: u64,
+    pub benchmark: String,
+    /// `none` where the step didn't run, as in the `--json` meta.
+    pub extractor: String,
+    pub bound: f32,
+    pub solver: String,
+    pub pre: i32,
+    /// Costs of the final extraction.
+    pub tree: Option<f64>,
+    pub dag: Option<f64>,
+    pub depth: Option<f64>,
+    pub area: Option<f64>,
+    pub heuristic_dag: Option<f64>,
+    pub heuristic_secs: Option<f64>,
+    pub lp_secs: Option<f64>,
+    /// Binaries of the generated LP, see `pruning::lp_binaries`.
+    pub lp_binaries: Option<usize>,
+    pub solve_secs: Option<f64>,
+    pub gap: Option<f64>,
+    pub optimum_gap: Option<f64>,
+    pub interrupted: bool,
+}
+
+impl RunRecord {
+    pub fn fields(&self) -> Vec<String> {
+        let number = |value: Option<f64>| value.map_or_else(String::new, |value| value.to_string());
+        vec![
+            self.time.to_string(),
+            self.benchmark.clone(),
+            self.extractor.clone(),
+            self.bound.to_string(),
+            self.solver.clone(),
+            self.pre.to_string(),
+            number(self.tree),
+            number(self.dag),
+            number(self.depth),
+            number(self.area),
+            number(self.heuristic_dag),
+            number(self.heuristic_secs),
+            number(self.lp_secs),
+            self.lp_binaries.map_or_else(String::new, |binaries| binaries.to_string()),
+            number(self.solve_secs),
+            number(self.gap),
+            number(self.optimum_gap),
+            self.interrupted.to_string(),
+        ]
+    }
+
+    /// Appends this run to the CSV file at `path`, with the header if the file is new
+    /// or empty. A file with other columns, e.g. from an older version, is an error
+    /// rather than a table with mismatched rows.
+    pub fn append_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let header = row(COLUMNS.iter().map(|column| column.to_string()));
+        let mut existing = String::new();
+        if let Ok(file) = std::fs::File::open(path) {
+            BufReader::new(file).read_line(&mut existing)?;
+        }
+        let existing = existing.trim_end_matches(['\n', '\r']);
+        if !existing.is_empty() && existing != header {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} has other columns: {}", path.display(), existing)));
+        }
+        // One write per row, so that runs appending at the same time don't interleave.
+        let mut lines = String::new();
+        if existing.is_empty() {
+            lines.push_str(&header);
+            lines.push('\n');
+        }
+        lines.push_str(&row(self.fields()));
+        lines.push('\n');
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(lines.as_bytes())
+    }
+}
+
+/// A CSV line, with the fields that need it quoted.
+fn row(fields: impl IntoIterator<Item = String>) -> String {
+    fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
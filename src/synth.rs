@@ -0,0 +1,204 @@
+//! Synthetic arithmetic e-graphs, shaped like the BoolE multiplier dumps.
+//!
+//! `adder-tree` sums `operands` unsigned numbers of `bits` bits, `multiplier`
+//! multiplies two, starting from the `bits * bits` partial products. Both reduce
+//! their columns Wallace-style with full and half adders and finish with a
+//! ripple-carry adder. Every full adder `(a, b, c)` has the alternatives a
+//! rewrite-saturated BoolE graph gives it:
+//!
+//! ```text
+//! sum   = xor(xor(a, b), c) | xor(a, xor(b, c)) | xor3(a, b, c) | fst(fa(a, b, c))
+//! carry = or(and(a, b), and(xor(a, b), c))     | maj(a, b, c)  | snd(fa(a, b, c))
+//! ```
+//!
+//! Gates cost 1, `xor3` and `maj` 2, `fa` 3 and the projections nothing, so the
+//! paired `fa` is the cheapest implementation only when both outputs are taken
+//! from it. Identical gates are one class: the inputs fan out to many partial
+//! products, and `xor(a, b)` is shared by the sum and the carry. `seed` picks
+//! which bits of a column go into the same adder.
+
+use egraph_serialize::{ClassId, Cost, Data, Node, NodeId};
+use indexmap::IndexMap;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    AdderTree,
+    Multiplier,
+}
+
+impl std::str::FromStr for Kind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, String> {
+        match kind {
+            "adder-tree" => Ok(Kind::AdderTree),
+            "multiplier" => Ok(Kind::Multiplier),
+            _ => Err(format!("unknown kind '{}', expected adder-tree or multiplier", kind)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub kind: Kind,
+    pub bits: usize,
+    /// Numbers summed by an adder tree; a multiplier always has two.
+    pub operands: usize,
+    pub seed: u64,
+}
+
+struct Builder {
+    nodes: IndexMap<NodeId, Node>,
+    classes: u32,
+    /// The class of every (op, children) added so far.
+    memo: HashMap<(String, Vec<ClassId>), ClassId>,
+}
+
+impl Builder {
+    /// A class of the given alternatives, or the existing one with the same first.
+    fn class(&mut self, alternatives: &[(&str, Vec<ClassId>, f64)]) -> ClassId {
+        let (op, children, _) = &alternatives[0];
+        if let Some(class) = self.memo.get(&(op.to_string(), children.clone())) {
+            return *class;
+        }
+        let class = ClassId(self.classes);
+        self.classes += 1;
+        for (i, (op, children, cost)) in alternatives.iter().enumerate() {
+            let id = NodeId([class.0, i as u32]);
+            let node = Node {
+                op: op.to_string(),
+                id,
+                children: children.clone(),
+                eclass: class,
+                cost: Cost::new(*cost).unwrap(),
+                area: None,
+            };
+            self.nodes.insert(id, node);
+            self.memo.insert((op.to_string(), children.clone()), class);
+        }
+        class
+    }
+
+    fn input(&mut self, name: String) -> ClassId {
+        self.class(&[(&name, vec![], 1.0)])
+    }
+
+    /// A commutative two-input gate, with its inputs in a canonical order.
+    fn gate(&mut self, op: &str, a: ClassId, b: ClassId) -> ClassId {
+        self.class(&[(op, vec![a.min(b), a.max(b)], 1.0)])
+    }
+
+    fn half_adder(&mut self, a: ClassId, b: ClassId) -> (ClassId, ClassId) {
+        (self.gate("xor", a, b), self.gate("and", a, b))
+    }
+
+    fn full_adder(&mut self, a: ClassId, b: ClassId, c: ClassId) -> (ClassId, ClassId) {
+        let ab = self.gate("xor", a, b);
+        let bc = self.gate("xor", b, c);
+        let generate = self.gate("and", a, b);
+        let propagate = self.gate("and", ab, c);
+        let fa = self.class(&[("fa", vec![a, b, c], 3.0)]);
+        let sum = self.class(&[
+            ("xor", vec![ab.min(c), ab.max(c)], 1.0),
+            ("xor", vec![a.min(bc), a.max(bc)], 1.0),
+            ("xor3", vec![a, b, c], 2.0),
+            ("fst", vec![fa], 0.0),
+        ]);
+        let carry = self.class(&[
+            ("or", vec![generate.min(propagate), generate.max(propagate)], 1.0),
+            ("maj", vec![a, b, c], 2.0),
+            ("snd", vec![fa], 0.0),
+        ]);
+        (sum, carry)
+    }
+}
+
+fn push(columns: &mut Vec<Vec<ClassId>>, weight: usize, bit: ClassId) {
+    if columns.len() <= weight {
+        columns.resize(weight + 1, Vec::new());
+    }
+    columns[weight].push(bit);
+}
+
+pub fn generate(config: &Config) -> Data {
+    assert!(config.bits > 0, "a synthetic e-graph needs at least one bit");
+    let mut builder = Builder { nodes: IndexMap::new(), classes: 0, memo: HashMap::new() };
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    // The bits of every column, least significant first.
+    let mut columns: Vec<Vec<ClassId>> = Vec::new();
+    match config.kind {
+        Kind::AdderTree => {
+            assert!(config.operands >= 2, "an adder tree needs at least two operands");
+            for operand in 0..config.operands {
+                for i in 0..config.bits {
+                    let bit = builder.input(format!("x{}_{}", operand, i));
+                    push(&mut columns, i, bit);
+                }
+            }
+        }
+        Kind::Multiplier => {
+            let a: Vec<ClassId> = (0..config.bits).map(|i| builder.input(format!("a{}", i))).collect();
+            let b: Vec<ClassId> = (0..config.bits).map(|i| builder.input(format!("b{}", i))).collect();
+            for (i, &a) in a.iter().enumerate() {
+                for (j, &b) in b.iter().enumerate() {
+                    let product = builder.gate("and", a, b);
+                    push(&mut columns, i + j, product);
+                }
+            }
+        }
+    }
+
+    // Wallace reduction down to two rows.
+    while columns.iter().any(|column| column.len() > 2) {
+        let mut next: Vec<Vec<ClassId>> = Vec::new();
+        for (weight, column) in columns.iter().enumerate() {
+            let mut column = column.clone();
+            column.shuffle(&mut rng);
+            let mut bits = column.chunks_exact(3);
+            for chunk in &mut bits {
+                let (sum, carry) = builder.full_adder(chunk[0], chunk[1], chunk[2]);
+                push(&mut next, weight, sum);
+                push(&mut next, weight + 1, carry);
+            }
+            match *bits.remainder() {
+                [a, b] => {
+                    let (sum, carry) = builder.half_adder(a, b);
+                    push(&mut next, weight, sum);
+                    push(&mut next, weight + 1, carry);
+                }
+                [a] => push(&mut next, weight, a),
+                _ => {}
+            }
+        }
+        columns = next;
+    }
+
+    // Ripple-carry adder of the two rows.
+    let mut outputs = Vec::new();
+    let mut carry: Option<ClassId> = None;
+    for column in &columns {
+        let (sum, carry_out) = match (column.as_slice(), carry) {
+            ([], None) => continue,
+            (&[a], None) | (&[], Some(a)) => (a, None),
+            (&[a, b], None) | (&[a], Some(b)) => {
+                let (sum, carry) = builder.half_adder(a, b);
+                (sum, Some(carry))
+            }
+            (&[a, b], Some(c)) => {
+                let (sum, carry) = builder.full_adder(a, b, c);
+                (sum, Some(carry))
+            }
+            _ => unreachable!("the columns are reduced to two rows"),
+        };
+        outputs.push(sum);
+        carry = carry_out;
+    }
+    outputs.extend(carry);
+
+    Data { nodes: builder.nodes, root_eclasses: outputs, class_data: IndexMap::new() }
+}
@@ -0,0 +1,197 @@
+//! Objective timeline of a solver run, parsed from its log.
+//!
+//! ```json
+//! {
+//!   "solver": "gurobi",
+//!   "warm_start": true,
+//!   "points": [{ "time": 0.4, "incumbent": 2385.0, "bound": 2210.5, "gap": 0.073 }, ...]
+//! }
+//! ```
+//!
+//! Every point is one progress line of the log, with the last incumbent and
+//! bound reported up to it (`null` while the solver has none). `gap` is
+//! `|incumbent - bound| / |incumbent|` whenever both are known. The Gurobi,
+//! CPLEX and CP-SAT wrappers log the improving incumbents with the bound at that
+//! time (builds older than the bound column without it); `gurobi_cl`, HiGHS and
+//! CBC write their native logs.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    /// Seconds since the solver started.
+    pub time: f64,
+    pub incumbent: Option<f64>,
+    pub bound: Option<f64>,
+    pub gap: Option<f64>,
+}
+
+impl Point {
+    fn new(time: f64, incumbent: Option<f64>, bound: Option<f64>) -> Self {
+        let gap = match (incumbent, bound) {
+            (Some(incumbent), Some(bound)) if incumbent != 0.0 => Some((incumbent - bound).abs() / incumbent.abs()),
+            (Some(_), Some(0.0)) => Some(0.0),
+            _ => None,
+        };
+        Point { time, incumbent, bound, gap }
+    }
+
+    /// This point with the incumbent and bound it doesn't report taken from `previous`.
+    pub fn after(self, previous: Option<&Point>) -> Point {
+        match previous {
+            Some(previous) => Point::new(self.time, self.incumbent.or(previous.incumbent), self.bound.or(previous.bound)),
+            None => self,
+        }
+    }
+}
+
+/// The log written to `SolverFiles::log_file`, by solver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `<time>: <objective> [<bound>]` (Gurobi and CPLEX wrappers) or
+    /// `<time> <objective> [<bound>]` (CP-SAT) lines, one per improving incumbent.
+    Incumbents,
+    /// Gurobi's node log.
+    Gurobi,
+    /// HiGHS' branch-and-bound log.
+    Highs,
+    /// CBC's standard output.
+    Cbc,
+}
+
+impl LogFormat {
+    /// The point of a progress line, `None` for any other line.
+    pub fn parse_line(&self, line: &str) -> Option<Point> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match self {
+            LogFormat::Incumbents => {
+                let tokens: Vec<&str> = line.split(|c: char| c == ':' || c.is_whitespace()).filter(|t| !t.is_empty()).collect();
+                match tokens[..] {
+                    [time, objective] => Some(Point::new(time.parse().ok()?, Some(objective.parse().ok()?), None)),
+                    [time, objective, bound] => Some(Point::new(time.parse().ok()?, Some(objective.parse().ok()?), number(bound))),
+                    _ => None,
+                }
+            }
+            LogFormat::Gurobi => {
+                // ... | Incumbent BestBd Gap | It/Node Time
+                let n = tokens.len();
+                if n < 7 || !(tokens[n - 3].ends_with('%') || tokens[n - 3] == "-") {
+                    return None;
+                }
+                let time = tokens[n - 1].strip_suffix('s')?.parse().ok()?;
+                Some(Point::new(time, number(tokens[n - 5]), number(tokens[n - 4])))
+            }
+            LogFormat::Highs => {
+                // ... Expl. | BestBound BestSol Gap | Cuts InLp Confl. | LpIters Time
+                let n = tokens.len();
+                if n < 9 || !tokens[n - 9].ends_with('%') {
+                    return None;
+                }
+                let time = tokens[n - 1].strip_suffix('s')?.parse().ok()?;
+                Some(Point::new(time, number(tokens[n - 7]), number(tokens[n - 8])))
+            }
+            LogFormat::Cbc => {
+                // "... (1.23 seconds)"
+                let seconds = tokens.iter().position(|t| *t == "seconds)")?;
+                let time = tokens[seconds.checked_sub(1)?].strip_prefix('(')?.parse().ok()?;
+                let after = |word: &str| tokens.iter().position(|t| *t == word).and_then(|i| tokens.get(i + 1)).and_then(|t| number(t.trim_end_matches(',')));
+                match tokens.first() {
+                    // Integer solution of <objective> found ...
+                    Some(&"Cbc0004I") | Some(&"Cbc0012I") => Some(Point::new(time, after("of"), None)),
+                    // After <n> nodes, <n> on tree, <objective> best solution, best possible <bound> ...
+                    Some(&"Cbc0010I") => {
+                        let best = tokens.iter().position(|t| *t == "best")?;
+                        // CBC reports 1e50 until it has a solution.
+                        let incumbent = number(tokens[best.checked_sub(1)?]).filter(|objective| *objective < 1e50);
+                        Some(Point::new(time, incumbent, after("possible")))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Whether the log reports the model infeasible. The wrappers, whose logs only
+    /// hold incumbents, report it through their exit code instead.
+    pub fn reports_infeasible(&self, log: &str) -> bool {
+        match self {
+            LogFormat::Incumbents => false,
+            LogFormat::Gurobi => log.contains("Model is infeasible"),
+            LogFormat::Highs => log.lines().any(|line| line.starts_with("Model status") && line.contains("Infeasible")),
+            LogFormat::Cbc => log.contains("proven infeasible") || log.contains("Problem is infeasible"),
+        }
+    }
+}
+
+/// A finite number, `None` for the placeholders the logs use (`-`, `inf`, ...).
+fn number(token: &str) -> Option<f64> {
+    token.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub solver: String,
+    pub warm_start: bool,
+    pub points: Vec<Point>,
+}
+
+impl Timeline {
+    pub fn from_log(solver: &str, format: LogFormat, warm_start: bool, log: &str) -> Self {
+        Timeline {
+            solver: solver.to_string(),
+            warm_start,
+            points: log.lines().filter_map(|line| format.parse_line(line)).fold(Vec::new(), |mut points, point| {
+                points.push(point.after(points.last()));
+                points
+            }),
+        }
+    }
+
+    pub fn to_json_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+
+    pub fn from_json_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+}
+
+/// Follows a log while the solver is still writing it.
+pub struct LogTail {
+    path: PathBuf,
+    format: LogFormat,
+    offset: u64,
+    partial: String,
+    last: Option<Point>,
+}
+
+impl LogTail {
+    pub fn new(path: impl Into<PathBuf>, format: LogFormat) -> Self {
+        LogTail { path: path.into(), format, offset: 0, partial: String::new(), last: None }
+    }
+
+    /// Parses the complete lines written since the last call and returns the
+    /// latest point, `None` until there is one.
+    pub fn poll(&mut self) -> Option<Point> {
+        let Ok(mut file) = std::fs::File::open(&self.path) else {
+            return self.last;
+        };
+        let mut bytes = Vec::new();
+        if file.seek(SeekFrom::Start(self.offset)).is_err() || file.read_to_end(&mut bytes).is_err() {
+            return self.last;
+        }
+        self.offset += bytes.len() as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&bytes));
+        while let Some(end) = self.partial.find('\n') {
+            if let Some(point) = self.format.parse_line(&self.partial[..end]) {
+                self.last = Some(point.after(self.last.as_ref()));
+            }
+            self.partial.drain(..=end);
+        }
+        self.last
+    }
+}
@@ -0,0 +1,53 @@
+use e_boost::cache::ResultCache;
+use egraph_serialize::{Cost, EGraphBuilder};
+use extraction_gym::{ExtractionResult, INFINITY};
+
+/// The same file given to another flag, or split differently between the inputs,
+/// is another e-graph.
+#[test]
+fn test_inputs_hash() {
+    let hash = |inputs: &[(&str, &[u8])]| ResultCache::inputs_hash(inputs);
+    let weights = hash(&[("input", b"{}"), ("--root-weights", b"X")]);
+    assert_eq!(weights, hash(&[("input", b"{}"), ("--root-weights", b"X")]));
+    assert_ne!(weights, hash(&[("input", b"{}"), ("--signatures", b"X")]));
+    assert_ne!(weights, hash(&[("input", b"{}"), ("--root-weights", b"Y")]));
+    assert_ne!(hash(&[("input", b"ab"), ("--signatures", b"c")]), hash(&[("input", b"a"), ("--signatures", b"bc")]));
+    assert_ne!(hash(&[("input", b"{}")]), hash(&[("input", b"{}"), ("--merge-classes", b"")]));
+}
+
+#[test]
+fn test_round_trip() {
+    let mut builder = EGraphBuilder::new();
+    let a = builder.leaf("a");
+    let root = builder.new_class();
+    let and = builder.add_node(root, "and", [a, a]);
+    let egraph = builder.root(root).build().unwrap();
+    let leaf = egraph[&a].nodes[0];
+
+    let mut result = ExtractionResult::new_empty();
+    result.choose(a, leaf);
+    result.choose(root, and);
+    result.cost.insert(leaf, Cost::new(1.0).unwrap());
+    result.cost.insert(and, INFINITY);
+    result.class_costs.insert(a, Cost::new(1.5).unwrap());
+    result.class_costs.insert(root, INFINITY);
+
+    let dir = std::env::temp_dir().join(format!("e_boost_cache_{}", std::process::id()));
+    let cache = ResultCache::new(&dir);
+    cache.put("key", &result).unwrap();
+    // Infinite costs are written as null.
+    let text = std::fs::read_to_string(dir.join("key.json")).unwrap();
+    assert!(text.contains("null"));
+    let read = cache.get("key", &egraph).unwrap();
+    assert_eq!(read.choices, result.choices);
+    assert_eq!(read.cost, result.cost);
+    assert_eq!(read.class_costs, result.class_costs);
+
+    // Choices of another e-graph are not used.
+    let mut other = ExtractionResult::new_empty();
+    other.choose(root, leaf);
+    cache.put("other", &other).unwrap();
+    assert!(cache.get("other", &egraph).is_none());
+    assert!(cache.get("missing", &egraph).is_none());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
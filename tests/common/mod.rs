@@ -0,0 +1,8 @@
+use egraph_serialize::{Data, EGraph as SerializedEGraph};
+
+/// The 4-bit multiplier of the regression suite, as read and as an e-graph.
+pub fn mul4() -> (Data, SerializedEGraph) {
+    let data = Data::from_json_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/regression/mul4.json")).unwrap();
+    let egraph = SerializedEGraph::from_Data(&data).unwrap();
+    (data, egraph)
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "parquet")]
+
+use arrow::array::{Array, BooleanArray, StringArray, UInt32Array};
+use arrow::record_batch::RecordBatch;
+use e_boost::dataset::write_dataset;
+use egraph_serialize::EGraphBuilder;
+use extraction_gym::ExtractionResult;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::path::Path;
+
+fn read(path: &Path) -> RecordBatch {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap()).unwrap().build().unwrap();
+    let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+    arrow::compute::concat_batches(&batches[0].schema(), &batches).unwrap()
+}
+
+fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+    batch.column_by_name(name).unwrap().as_any().downcast_ref::<T>().unwrap()
+}
+
+/// `or(a, b)` or the cheaper `not(a)` in the root, the heuristic taking the
+/// first and the solver the second.
+#[test]
+fn test_write_read_back() {
+    let mut builder = EGraphBuilder::new();
+    let (a, b) = (builder.leaf("a"), builder.leaf("b"));
+    let root = builder.new_class();
+    let or = builder.add_node(root, "or", [a, b]);
+    let not = builder.add_node(root, "not", [a]);
+    let egraph = builder.root(root).build().unwrap();
+    let mut heuristic = ExtractionResult::new_empty();
+    let mut optimal = ExtractionResult::new_empty();
+    for class in [a, b] {
+        heuristic.choose(class, egraph[&class].nodes[0]);
+        optimal.choose(class, egraph[&class].nodes[0]);
+    }
+    heuristic.choose(root, or);
+    optimal.choose(root, not);
+
+    let dir = std::env::temp_dir().join(format!("e_boost_dataset_{}", std::process::id()));
+    write_dataset(&egraph, "g", &heuristic, Some(&optimal), &dir).unwrap();
+
+    let nodes = read(&dir.join("g_nodes.parquet"));
+    assert_eq!(nodes.num_rows(), 4);
+    let ids: Vec<&str> = column::<StringArray>(&nodes, "node_id").iter().map(Option::unwrap).collect();
+    let expected: Vec<String> = egraph.nodes.keys().map(|nid| nid.to_string()).collect();
+    assert_eq!(ids, expected);
+    assert!(column::<StringArray>(&nodes, "egraph").iter().all(|name| name == Some("g")));
+    let flags = |name: &str| column::<BooleanArray>(&nodes, name).iter().collect::<Vec<_>>();
+    let on = |used: &[bool]| used.iter().map(|used| Some(*used)).collect::<Vec<_>>();
+    // Nodes a, b, or, not.
+    assert_eq!(flags("root_class"), on(&[false, false, true, true]));
+    assert_eq!(flags("heuristic"), on(&[true, true, true, false]));
+    assert_eq!(flags("optimal"), on(&[true, false, false, true]));
+    let children: Vec<u32> = column::<UInt32Array>(&nodes, "num_children").values().to_vec();
+    assert_eq!(children, [0, 0, 2, 1]);
+
+    let edges = read(&dir.join("g_edges.parquet"));
+    let child_class: Vec<u32> = column::<UInt32Array>(&edges, "child_class").values().to_vec();
+    let position: Vec<u32> = column::<UInt32Array>(&edges, "position").values().to_vec();
+    assert_eq!(child_class, [a.0, b.0, a.0]);
+    assert_eq!(position, [0, 1, 0]);
+
+    // Without a solution the optimal column is null.
+    write_dataset(&egraph, "g", &heuristic, None, &dir).unwrap();
+    let nodes = read(&dir.join("g_nodes.parquet"));
+    assert_eq!(column::<BooleanArray>(&nodes, "optimal").null_count(), 4);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,37 @@
+use e_boost::extractor;
+use std::time::Duration;
+
+mod common;
+
+/// The extractors that check `extract_with_deadline`'s budget.
+const ANYTIME: [&str; 12] = [
+    "greedy-dag",
+    "global-greedy-dag",
+    "epsilon-greedy",
+    "faster-greedy-dag",
+    "faster-greedy-dag-best-first",
+    "faster-greedy-dag-fa",
+    "faster-greedy-dag-fa-mt",
+    "faster-greedy-dag-mt1",
+    "faster-greedy-dag-mt2",
+    "faster-bottom-up-mt",
+    "faster-ast-size-mt",
+    "faster-ast-depth-mt",
+];
+
+#[test]
+fn test_extract_with_deadline() {
+    let (_, egraph) = common::mul4();
+    let roots = &egraph.root_eclasses;
+    let extractors = extractor::extractors();
+    for name in ANYTIME {
+        let extractor = extractors[name].get_extractor();
+        // Out of time before the first node.
+        let cut_short = extractor.extract_with_deadline(&egraph, roots, Duration::ZERO);
+        assert!(cut_short.choices.is_empty(), "{} kept going", name);
+        assert!(!cut_short.is_valid(&egraph));
+
+        let result = extractor.extract_with_deadline(&egraph, roots, Duration::from_secs(3600));
+        assert!(result.is_valid(&egraph), "{} stopped early", name);
+    }
+}
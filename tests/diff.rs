@@ -0,0 +1,50 @@
+use egraph_serialize::{EGraphBuilder, NodeId};
+use extraction_gym::diff::{self, ChoiceChange};
+use extraction_gym::ExtractionResult;
+
+/// `(+ (* x y) (* x y))` against `(shl x)`, of the same class.
+#[test]
+fn test_compare() {
+    let mut builder = EGraphBuilder::new();
+    let x = builder.leaf("x");
+    let y = builder.leaf("y");
+    let product = builder.term("*", [x, y]);
+    let root = builder.new_class();
+    let sum = builder.add_node(root, "+", [product, product]);
+    let shift = builder.add_node(root, "shl", [x]);
+    let egraph = builder.root(root).build().unwrap();
+    let leaves = [x, y, product].into_iter().map(|cid| (cid, NodeId::from((cid.0, 0))));
+    let a = ExtractionResult::new(leaves.clone().chain([(root, sum)]).collect());
+    let b = ExtractionResult::new(leaves.chain([(root, shift)]).collect());
+
+    let diff = diff::compare(&egraph, &egraph.root_eclasses, &a, &b);
+    assert_eq!((diff.a.tree.into_inner(), diff.a.dag.into_inner(), diff.a.depth), (7.0, 4.0, 3));
+    assert_eq!((diff.b.tree.into_inner(), diff.b.dag.into_inner(), diff.b.depth), (2.0, 2.0, 2));
+    // `b` chooses the product too, but doesn't use it.
+    assert_eq!(
+        diff.changed,
+        vec![
+            ChoiceChange { class: y, a: Some(NodeId::from((y.0, 0))), b: None },
+            ChoiceChange { class: product, a: Some(NodeId::from((product.0, 0))), b: None },
+            ChoiceChange { class: root, a: Some(sum), b: Some(shift) },
+        ]
+    );
+
+    let ops: Vec<&str> = diff.by_op.keys().map(String::as_str).collect();
+    assert_eq!(ops, ["*", "+", "shl", "x", "y"]);
+    let (a_product, b_product) = diff.by_op["*"];
+    assert_eq!((a_product.tree.into_inner(), a_product.dag.into_inner(), a_product.depth), (2.0, 1.0, 1));
+    assert_eq!(b_product, Default::default());
+    let (a_x, b_x) = diff.by_op["x"];
+    assert_eq!((a_x.tree.into_inner(), b_x.tree.into_inner()), (2.0, 1.0));
+    assert_eq!(a_x.dag, b_x.dag);
+    // The ops add up to the costs.
+    let a_depth: u32 = diff.by_op.values().map(|(a, _)| a.depth).sum();
+    let b_tree: f64 = diff.by_op.values().map(|(_, b)| b.tree.into_inner()).sum();
+    assert_eq!((a_depth, b_tree), (3, 2.0));
+
+    assert!(diff::compare(&egraph, &egraph.root_eclasses, &a, &a).changed.is_empty());
+    let json = diff.to_json(&egraph);
+    assert_eq!(json["changed"][2]["b"]["op"], "shl");
+    assert_eq!(json["by_op"]["shl"]["b"]["dag"], 1.0);
+}
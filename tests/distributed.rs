@@ -0,0 +1,164 @@
+use e_boost::{distributed, extractor};
+use e_boost::pipeline::{Options, PreMode};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+
+mod common;
+
+/// Two connections to an in-process worker share the parts, the third address
+/// has no worker.
+#[test]
+fn test_distribute_to_workers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || distributed::worker(listener));
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+    let (data, egraph) = common::mul4();
+    let options = Options {
+        extractor: "faster-greedy-dag".to_string(),
+        pre_mode: PreMode::Heuristic,
+        out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("distributed"),
+        name: "mul4".to_string(),
+        ..Options::default()
+    };
+    let output = distributed::distribute(&data, &[addr.clone(), addr, unused], 4, &options).unwrap();
+
+    output.result.check(&egraph);
+    assert!(output.stitched);
+    assert_eq!(output.parts.len(), 4);
+    assert!(output.parts.iter().all(|part| part.worker != "local"));
+}
+
+/// The parts run on threads here, and the stitched result is an extraction of
+/// the whole e-graph.
+#[test]
+fn test_run_partitioned() {
+    let (data, egraph) = common::mul4();
+    let options = Options {
+        extractor: "faster-greedy-dag".to_string(),
+        pre_mode: PreMode::Heuristic,
+        out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("partitioned"),
+        name: "mul4".to_string(),
+        ..Options::default()
+    };
+    let output = distributed::run_partitioned(&data, 4, &options).unwrap();
+
+    output.result.check(&egraph);
+    assert!(output.stitched);
+    assert!(output.parts.len() > 1);
+    assert!(output.parts.iter().all(|part| part.worker == "local"));
+    assert!(distributed::run_partitioned(&data, 4, &Options { pre_mode: PreMode::WarmLp, ..options }).is_err());
+}
+
+/// The frozen choices reach the parts, through the options of the run.
+#[test]
+fn test_run_partitioned_frozen() {
+    let (data, egraph) = common::mul4();
+    let options = Options {
+        extractor: "faster-greedy-dag".to_string(),
+        pre_mode: PreMode::Heuristic,
+        out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("partitioned-frozen"),
+        name: "mul4".to_string(),
+        ..Options::default()
+    };
+    let unfrozen = distributed::run_partitioned(&data, 4, &options).unwrap().result;
+    // A node the parts don't pick by themselves, which still leaves a valid extraction.
+    let extractors = extractor::extractors();
+    let greedy = extractors.get("faster-greedy-dag").unwrap().get_extractor();
+    let (class, node, frozen) = egraph
+        .classes()
+        .values()
+        .flat_map(|class| class.nodes.iter().filter(|&node| unfrozen.choices.get(&class.id).is_some_and(|chosen| chosen != node)).map(|node| (class.id, *node)))
+        .map(|(class, node)| (class, node, [(class, node)].into_iter().collect()))
+        .find(|(_, _, frozen)| greedy.extract_frozen(&egraph, &egraph.root_eclasses, frozen).is_valid(&egraph))
+        .unwrap();
+
+    let output = distributed::run_partitioned(&data, 4, &Options { frozen, ..options }).unwrap();
+    output.result.check(&egraph);
+    assert_eq!(output.result.choices.get(&class), Some(&node));
+    assert!(output.parts.iter().any(|part| part.result.choices.get(&class) == Some(&node)));
+}
+
+/// Sends one task to the worker at `addr` and reads its reply.
+fn send_task(addr: &str, data: &egraph_serialize::Data, name: &str) -> Value {
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let task = json!({"part": 0, "name": name, "extractor": "faster-greedy-dag", "pre": 5, "solver": "gurobi", "timeout": 10, "bound": 1.25});
+    writeln!(writer, "{}", task).unwrap();
+    data.to_json_writer(&mut writer).unwrap();
+    writeln!(writer).unwrap();
+    let mut reply = String::new();
+    reader.read_line(&mut reply).unwrap();
+    serde_json::from_str(&reply).unwrap()
+}
+
+/// The worker names its files itself: a task name that would leave its
+/// directory gets an error.
+#[test]
+fn test_worker_task_names() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || distributed::worker(listener));
+    let (data, _) = common::mul4();
+
+    let reply = send_task(&addr, &data, "mul4");
+    assert!(reply["result"].is_object(), "{}", reply);
+    for name in ["../mul4", "/tmp/mul4", "a\\b", ".."] {
+        let reply = send_task(&addr, &data, name);
+        assert!(reply["result"].is_null());
+        assert!(reply["error"].as_str().unwrap().contains("Invalid task name"), "{}", reply);
+    }
+}
+
+/// A fake worker that answers every task with `reply(part)`, or hangs up when
+/// that is `None`.
+fn fake_worker(reply: fn(usize) -> Option<Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let (mut header, mut egraph) = (String::new(), String::new());
+            while reader.read_line(&mut header).unwrap() > 0 && reader.read_line(&mut egraph).unwrap() > 0 {
+                let task: Value = serde_json::from_str(&header).unwrap();
+                let Some(reply) = reply(task["part"].as_u64().unwrap() as usize) else {
+                    break;
+                };
+                writeln!(writer, "{}", reply).unwrap();
+                header.clear();
+                egraph.clear();
+            }
+        }
+    });
+    addr
+}
+
+/// Parts that failed, got a reply for another part or no reply at all are run
+/// locally.
+#[test]
+fn test_distribute_requeues() {
+    let failing = fake_worker(|part| Some(json!({"part": part, "error": "out of memory"})));
+    let confused = fake_worker(|part| Some(json!({"part": part + 1, "result": null})));
+    let hanging_up = fake_worker(|_| None);
+
+    let (data, egraph) = common::mul4();
+    let options = Options {
+        extractor: "faster-greedy-dag".to_string(),
+        pre_mode: PreMode::Heuristic,
+        out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("requeued"),
+        name: "mul4".to_string(),
+        ..Options::default()
+    };
+    for workers in [[failing.clone()], [confused], [hanging_up]] {
+        let output = distributed::distribute(&data, &workers, 4, &options).unwrap();
+        output.result.check(&egraph);
+        assert!(output.stitched);
+        assert!(output.parts.iter().all(|part| part.worker == "local"));
+    }
+}
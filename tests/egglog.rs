@@ -0,0 +1,39 @@
+use e_boost::egglog::{self, EgglogEGraph, EgglogOptions};
+use serde_json::json;
+
+fn fixture() -> EgglogEGraph {
+    EgglogEGraph::from_json_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/egglog/double.json")).unwrap()
+}
+
+/// `(Mul (Var "x") (Num 2))` rewritten to an addition, and to a shift that is
+/// cheaper but subsumed.
+#[test]
+fn test_extract_fixture() {
+    let converted = fixture().convert().unwrap();
+    assert_eq!(converted.egraph.nodes.len(), 6);
+    let options = EgglogOptions { extractor: String::from("faster-greedy-dag-mt1"), ilp: None };
+    let result = egglog::run(&converted, &options, "double");
+    let output = converted.to_json(&result);
+    assert_eq!(output["extracted"], json!([{ "eclass": "Math-2", "term": "(Add (Var \"x\") (Var \"x\"))" }]));
+    assert_eq!(output["choices"]["Math-2"], "function-3-Add");
+    assert_eq!(output["choices"]["Math-0"], "function-0-Var");
+    assert_eq!((output["dag"].as_f64(), output["tree"].as_f64()), (Some(3.0), Some(5.0)));
+}
+
+#[test]
+fn test_convert_errors() {
+    let mut egraph = fixture();
+    egraph.root_eclasses.push(String::from("Math-9"));
+    assert_eq!(egraph.convert().err().unwrap().to_string(), "Unknown root e-class Math-9");
+
+    let mut egraph = fixture();
+    egraph.nodes["function-3-Add"].children[1] = String::from("function-9-Var");
+    assert_eq!(egraph.convert().err().unwrap().to_string(), "Unknown child node function-9-Var of function-3-Add");
+
+    // The root's nodes all subsumed.
+    let mut egraph = fixture();
+    for name in ["function-2-Mul", "function-3-Add"] {
+        egraph.nodes[name].subsumed = true;
+    }
+    assert!(egraph.convert().err().unwrap().to_string().starts_with("Invalid e-graph"));
+}
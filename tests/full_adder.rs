@@ -0,0 +1,59 @@
+use egraph_serialize::{ClassId, EGraph, EGraphBuilder, FrozenEGraph, NodeId};
+use extraction_gym::faster_greedy_dag_fa::{self, DualExtractionResult};
+use extraction_gym::{faster_greedy_dag_fa_mt, Deadline};
+
+/// The two outputs of a full adder of `a`, `b` and `c`: the sum `xor3` or `fst`
+/// of `fa`, and the carry `maj` or `snd` of it. Returns the e-graph, its output
+/// classes and the nodes `[fst, snd, xor3, maj]`.
+fn adder(fa_cost: f64) -> (EGraph, [ClassId; 2], [NodeId; 4]) {
+    let mut builder = EGraphBuilder::new();
+    let inputs = [builder.leaf("a"), builder.leaf("b"), builder.leaf("c")];
+    let fa = builder.new_class();
+    builder.add_node_with_cost(fa, "fa", inputs, fa_cost);
+    let (sum, carry) = (builder.new_class(), builder.new_class());
+    let xor3 = builder.add_node_with_cost(sum, "xor3", inputs, 2.0);
+    let fst = builder.add_node_with_cost(sum, "fst", [fa], 0.0);
+    let maj = builder.add_node_with_cost(carry, "maj", inputs, 2.0);
+    let snd = builder.add_node_with_cost(carry, "snd", [fa], 0.0);
+    let egraph = builder.root(sum).root(carry).build().unwrap();
+    (egraph, [sum, carry], [fst, snd, xor3, maj])
+}
+
+fn extract_both(egraph: &EGraph) -> [DualExtractionResult; 2] {
+    let roots = &egraph.root_eclasses;
+    [
+        faster_greedy_dag_fa::FasterGreedyDagExtractor.extract_dual(egraph, roots, Deadline::NONE),
+        faster_greedy_dag_fa_mt::FasterGreedyDagExtractor.extract_dual(FrozenEGraph::new(egraph), roots, Deadline::NONE),
+    ]
+}
+
+/// One adder (3) is cheaper than the two outputs apart (2 + 2): both outputs
+/// take its projections, and the separate gates are the secondary choices.
+#[test]
+fn test_adder_pair_primary_and_secondary() {
+    let (egraph, [sum, carry], [fst, snd, xor3, maj]) = adder(3.0);
+    let roots = &egraph.root_eclasses;
+    for dual in extract_both(&egraph) {
+        dual.result.check(&egraph);
+        assert_eq!((dual.result.choices[&sum], dual.result.choices[&carry]), (fst, snd));
+        assert_eq!(dual.result.dag_cost(&egraph, roots).into_inner(), 3.0 + 3.0);
+        let secondary: Vec<(ClassId, NodeId)> = dual.secondary.iter().map(|(cid, (nid, _))| (*cid, *nid)).collect();
+        assert_eq!(secondary, [(sum, xor3), (carry, maj)]);
+
+        let alternative = dual.alternative(&egraph).unwrap();
+        assert_eq!((alternative.choices[&sum], alternative.choices[&carry]), (xor3, maj));
+        assert_eq!(alternative.dag_cost(&egraph, roots).into_inner(), 4.0 + 3.0);
+    }
+}
+
+/// An adder dearer than the two outputs apart is given up by both of them, and
+/// there is no alternative left.
+#[test]
+fn test_adder_pair_given_up() {
+    let (egraph, [sum, carry], [_, _, xor3, maj]) = adder(5.0);
+    for dual in extract_both(&egraph) {
+        dual.result.check(&egraph);
+        assert_eq!((dual.result.choices[&sum], dual.result.choices[&carry]), (xor3, maj));
+        assert!(dual.alternative(&egraph).is_none());
+    }
+}
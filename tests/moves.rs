@@ -0,0 +1,83 @@
+use egraph_serialize::{Data, EGraph as SerializedEGraph};
+use extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor;
+use extraction_gym::faster_greedy_dag_fa::AdderPairs;
+use extraction_gym::{moves, ExtractionResult, Extractor};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn egraph(name: &str) -> SerializedEGraph {
+    let path = format!("{}/tests/regression/{}", env!("CARGO_MANIFEST_DIR"), name);
+    SerializedEGraph::from_Data(&Data::from_json_file(path).unwrap()).unwrap()
+}
+
+fn greedy(egraph: &SerializedEGraph) -> ExtractionResult {
+    FasterGreedyDagExtractor::default().extract(egraph, &egraph.root_eclasses)
+}
+
+fn dag_cost(result: &ExtractionResult, egraph: &SerializedEGraph) -> f64 {
+    result.dag_cost(egraph, &egraph.root_eclasses).into_inner()
+}
+
+/// Every single-class swap either is rejected or changes the DAG cost by its
+/// delta, and its undo restores the choices.
+#[test]
+fn test_swap_deltas() {
+    let egraph = egraph("mul4.json");
+    let mut result = greedy(&egraph);
+    let before = dag_cost(&result, &egraph);
+    let choices = result.choices.clone();
+    for class in egraph.classes().values() {
+        for node in &class.nodes {
+            let Some(swap) = moves::swap_class(&mut result, &egraph, node) else {
+                continue;
+            };
+            assert!(result.is_valid(&egraph));
+            assert!((dag_cost(&result, &egraph) - before - swap.delta.into_inner()).abs() < 1e-9);
+            let undone = swap.undo.undo(&mut result, &egraph);
+            assert_eq!(undone, -swap.delta);
+            assert_eq!(result.choices, choices);
+        }
+    }
+}
+
+/// Greedy DAG extraction computes the outputs of the adders separately, taking
+/// an adder for both is cheaper.
+#[test]
+fn test_exchange_adder() {
+    let egraph = egraph("adder.json");
+    let pairs = AdderPairs::new(&egraph);
+    let mut result = greedy(&egraph);
+    let before = dag_cost(&result, &egraph);
+    let fst = egraph.nodes.iter().find(|(_, node)| node.op == "fst").map(|(id, _)| *id).unwrap();
+
+    let exchange = moves::exchange_adder(&mut result, &egraph, &pairs, &fst).unwrap();
+    assert!(exchange.delta.into_inner() < 0.0);
+    assert!(result.is_valid(&egraph));
+    assert_eq!(dag_cost(&result, &egraph), before + exchange.delta.into_inner());
+
+    let back = moves::exchange_adder(&mut result, &egraph, &pairs, &fst).unwrap();
+    assert_eq!(back.delta, -exchange.delta);
+    assert_eq!(dag_cost(&result, &egraph), before);
+}
+
+#[test]
+fn test_region_moves() {
+    let egraph = egraph("mul4.json");
+    let root = egraph.root_eclasses[0];
+    let mut result = greedy(&egraph);
+    let before = dag_cost(&result, &egraph);
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let restart = moves::random_restart(&mut result, &egraph, &root, 3, &mut rng);
+    assert!(result.is_valid(&egraph));
+    assert!((dag_cost(&result, &egraph) - before - restart.delta.into_inner()).abs() < 1e-9);
+
+    let restarted = dag_cost(&result, &egraph);
+    let reextract = moves::reextract(&mut result, &egraph, &root, 3, &FasterGreedyDagExtractor::default()).unwrap();
+    assert!(result.is_valid(&egraph));
+    assert!((dag_cost(&result, &egraph) - restarted - reextract.delta.into_inner()).abs() < 1e-9);
+
+    reextract.undo.undo(&mut result, &egraph);
+    restart.undo.undo(&mut result, &egraph);
+    assert_eq!(dag_cost(&result, &egraph), before);
+}
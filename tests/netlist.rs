@@ -0,0 +1,167 @@
+use egraph_serialize::{EGraph, EGraphBuilder};
+use extraction_gym::netlist::aiger::{self, AigMapping};
+use extraction_gym::netlist::{blif, verilog, Gate, Netlist};
+use extraction_gym::ExtractionResult;
+
+/// A full adder of `a`, `b` and `cin` from two-input gates, its sum and carry
+/// concatenated into the root.
+fn full_adder() -> EGraph {
+    let mut builder = EGraphBuilder::new();
+    let (a, b, cin) = (builder.leaf("a"), builder.leaf("b"), builder.leaf("cin"));
+    let ab = builder.term("xor", [a, b]);
+    let sum = builder.term("xor", [ab, cin]);
+    let (and_ab, and_c) = (builder.term("and", [a, b]), builder.term("and", [ab, cin]));
+    let carry = builder.term("or", [and_ab, and_c]);
+    let root = builder.term("&", [sum, carry]);
+    builder.root(root).build().unwrap()
+}
+
+/// The same adder from the cells `fa_sum` and `fa_carry`.
+fn full_adder_cells() -> EGraph {
+    let mut builder = EGraphBuilder::new();
+    let inputs = [builder.leaf("a"), builder.leaf("b"), builder.leaf("cin")];
+    let (sum, carry) = (builder.term("fa_sum", inputs), builder.term("fa_carry", inputs));
+    let root = builder.term("&", [sum, carry]);
+    builder.root(root).build().unwrap()
+}
+
+/// Every class takes its first node.
+fn extract(egraph: &EGraph) -> ExtractionResult {
+    let mut result = ExtractionResult::new_empty();
+    for class in egraph.classes().values() {
+        result.choose(class.id, class.nodes[0]);
+    }
+    result
+}
+
+fn golden(name: &str) -> String {
+    std::fs::read_to_string(format!("{}/tests/netlist/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+#[test]
+fn test_gate_from_op() {
+    let mut builder = EGraphBuilder::new();
+    let (a, b, c) = (builder.leaf("a"), builder.leaf("b"), builder.leaf("c"));
+    let cases = [
+        (builder.leaf("0"), Gate::Const(false)),
+        (builder.leaf("true"), Gate::Const(true)),
+        (a, Gate::Input(String::from("a"))),
+        (builder.term("!", [a]), Gate::Not),
+        (builder.term("id", [a]), Gate::Buf),
+        (builder.term("AND2", [a, b]), Gate::And),
+        (builder.term("+", [a, b, c]), Gate::Or),
+        (builder.term("xnor", [a, b]), Gate::Xnor),
+        (builder.term("maj3", [a, b, c]), Gate::Maj),
+        // A majority needs three inputs, and an inverter one.
+        (builder.term("maj", [a, b]), Gate::Cell(String::from("maj"))),
+        (builder.term("not", [a, b]), Gate::Cell(String::from("not"))),
+        (builder.term("fa", [a, b, c]), Gate::Cell(String::from("fa"))),
+    ];
+    let egraph = builder.build().unwrap();
+    for (class, gate) in cases {
+        assert_eq!(Gate::from_node(&egraph[&egraph[&class].nodes[0]]), gate);
+    }
+}
+
+#[test]
+fn test_full_adder_verilog() {
+    let egraph = full_adder();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    assert_eq!(verilog::to_verilog(&netlist, "full_adder"), golden("full_adder.v"));
+}
+
+/// Two inputs of the same op are two ports, not one.
+#[test]
+fn test_duplicate_input_ops() {
+    let mut builder = EGraphBuilder::new();
+    let (x, y, z) = (builder.leaf("x"), builder.leaf("x"), builder.leaf("z"));
+    let root = builder.term("and", [x, y, z]);
+    let egraph = builder.root(root).build().unwrap();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    let names: Vec<&str> = netlist.inputs.iter().map(|cid| netlist.input_name(*cid)).collect();
+    assert_eq!(names, [format!("x_{}", x), format!("x_{}", y), String::from("z")]);
+    assert!(verilog::to_verilog(&netlist, "m").contains(&format!("assign n{} = x_{} & x_{} & z;", root, x, y)));
+}
+
+#[test]
+fn test_full_adder_aiger() {
+    let egraph = full_adder();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    assert_eq!(aiger::to_aiger(&netlist, &AigMapping::default()).unwrap(), golden("full_adder.aag"));
+}
+
+/// The cells through gadgets give the same AIG, the XOR of `a` and `b` shared
+/// between the sum and the carry.
+#[test]
+fn test_full_adder_gadgets() {
+    let egraph = full_adder_cells();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    let err = aiger::to_aiger(&netlist, &AigMapping::default()).unwrap_err();
+    assert_eq!(err, "op 'fa_sum' has no AIG gadget");
+
+    let mut mapping = AigMapping::default();
+    mapping.insert("fa_sum", "$0 ^ $1 ^ $2").unwrap();
+    mapping.insert("fa_carry", "($0 & $1) | ($2 & ($0 ^ $1))").unwrap();
+    assert_eq!(aiger::to_aiger(&netlist, &mapping).unwrap(), golden("full_adder.aag"));
+
+    mapping.insert("fa_carry", "$0 & $3").unwrap();
+    assert_eq!(aiger::to_aiger(&netlist, &mapping).unwrap_err(), "gadget uses $3 but the node has 3 inputs");
+}
+
+#[test]
+fn test_gadget_syntax() {
+    let mut mapping = AigMapping::default();
+    for expr in ["!(!$0 | 1) & (0 ^ $12)", "$0", "((($1)))"] {
+        assert!(mapping.insert("ok", expr).is_ok(), "{}", expr);
+    }
+    for (expr, err) in [
+        ("$0 & ", "unexpected end or token at 5"),
+        ("($0 | $1", "expected ')' at 8"),
+        ("$0 $1", "unexpected '$' at 3"),
+        ("$x", "expected input index at 1"),
+        ("a & b", "unexpected end or token at 0"),
+    ] {
+        assert_eq!(mapping.insert("bad", expr).unwrap_err(), err);
+    }
+}
+
+/// Equal ANDs up to input order are hashed to one gate, and an AND of a signal
+/// with its complement folds to the constant 0.
+#[test]
+fn test_aig_structural_hashing() {
+    let mut builder = EGraphBuilder::new();
+    let (a, b) = (builder.leaf("a"), builder.leaf("b"));
+    let not_a = builder.term("!", [a]);
+    let (ab, ba, none) = (builder.term("and", [a, b]), builder.term("*", [b, a]), builder.term("and", [a, not_a]));
+    let root = builder.term("&", [ab, ba, none]);
+    let egraph = builder.root(root).build().unwrap();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    let aag = aiger::to_aiger(&netlist, &AigMapping::default()).unwrap();
+    assert_eq!(aag, "aag 3 2 0 3 1\n2\n4\n6\n6\n0\n6 4 2\ni0 a\ni1 b\no0 po0\no1 po1\no2 po2\n");
+}
+
+#[test]
+fn test_full_adder_blif() {
+    let egraph = full_adder();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    assert_eq!(blif::to_blif(&netlist, "full_adder"), golden("full_adder.blif"));
+}
+
+/// Ops without a primitive gate are instances of black boxes.
+#[test]
+fn test_blif_black_boxes() {
+    let egraph = full_adder_cells();
+    let result = extract(&egraph);
+    let netlist = Netlist::new(&egraph, &result, &egraph.root_eclasses);
+    let text = blif::to_blif(&netlist, "full_adder");
+    for cell in ["fa_sum", "fa_carry"] {
+        assert!(text.contains(&format!(".subckt {} i0=a i1=b i2=cin", cell)));
+        assert!(text.contains(&format!(".model {}\n.inputs i0 i1 i2\n.outputs o\n.blackbox\n.end\n", cell)));
+    }
+}
@@ -0,0 +1,21 @@
+module full_adder (a, b, cin, po0, po1);
+  input a;
+  input b;
+  input cin;
+  output po0;
+  output po1;
+  wire n3;
+  wire n4;
+  wire n5;
+  wire n6;
+  wire n7;
+
+  assign n3 = a ^ b;
+  assign n4 = n3 ^ cin;
+  assign n5 = a & b;
+  assign n6 = n3 & cin;
+  assign n7 = n5 | n6;
+
+  assign po0 = n4;
+  assign po1 = n7;
+endmodule
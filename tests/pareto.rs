@@ -0,0 +1,56 @@
+use egraph_serialize::{Data, EGraph as SerializedEGraph};
+use extraction_gym::faster_ast_depth::FasterAstSizeExtractor as FasterAstDepthExtractor;
+use extraction_gym::pareto::{ParetoExtractor, ParetoFront};
+use extraction_gym::Extractor;
+
+fn egraph(name: &str) -> SerializedEGraph {
+    let data = Data::from_json_file(format!("{}/tests/regression/{}.json", env!("CARGO_MANIFEST_DIR"), name)).unwrap();
+    SerializedEGraph::from_Data(&data).unwrap()
+}
+
+#[test]
+fn test_front() {
+    for name in ["adder", "mul4", "shared"] {
+        let egraph = egraph(name);
+        let roots = &egraph.root_eclasses;
+        let front = ParetoExtractor::default().extract_front(&egraph, roots);
+        assert!(!front.points.is_empty(), "{}", name);
+        for point in &front.points {
+            point.result.check(&egraph);
+            assert_eq!(point.depth, point.result.depth_cost(&egraph, roots));
+            assert_eq!(point.dag_cost, point.result.dag_cost(&egraph, roots));
+        }
+        for pair in front.points.windows(2) {
+            assert!(pair[0].depth < pair[1].depth && pair[0].dag_cost > pair[1].dag_cost, "{}", name);
+        }
+        // The sweep starts at the smallest depth.
+        let shallowest = FasterAstDepthExtractor.extract(&egraph, roots).depth_cost(&egraph, roots);
+        assert_eq!(front.points[0].depth, shallowest, "{}", name);
+
+        let bounded = ParetoExtractor { max_depth: Some(shallowest) }.extract(&egraph, roots);
+        assert_eq!(bounded.choices, front.points[0].result.choices);
+        let cheapest = ParetoExtractor::default().extract(&egraph, roots);
+        assert_eq!(cheapest.choices, front.points.last().unwrap().result.choices);
+
+        let read = ParetoFront::from_json(&front.to_json(&egraph, roots)).unwrap();
+        assert_eq!(read.points.len(), front.points.len());
+        for (read, point) in read.points.iter().zip(&front.points) {
+            assert_eq!((read.depth, read.dag_cost), (point.depth, point.dag_cost));
+            assert_eq!(read.result.choices, point.result.choices);
+        }
+    }
+}
+
+#[test]
+fn test_insert_keeps_the_undominated() {
+    let egraph = egraph("adder");
+    let result = ParetoExtractor::default().extract(&egraph, &egraph.root_eclasses);
+    let point = |depth, dag_cost: f64| extraction_gym::pareto::ParetoPoint { depth, dag_cost: dag_cost.try_into().unwrap(), result: result.clone() };
+    let mut front = ParetoFront::default();
+    assert!(front.insert(point(5, 10.0)));
+    assert!(front.insert(point(3, 12.0)));
+    assert!(!front.insert(point(6, 10.0)));
+    assert!(front.insert(point(4, 10.0)));
+    let points: Vec<u32> = front.points.iter().map(|point| point.depth).collect();
+    assert_eq!(points, [3, 4]);
+}
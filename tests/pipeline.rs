@@ -0,0 +1,103 @@
+use e_boost::pipeline::{self, Booster, Options, PreMode};
+use egraph_serialize::{Data, EGraph as SerializedEGraph};
+use std::path::{Path, PathBuf};
+
+fn adder() -> SerializedEGraph {
+    let data = Data::from_json_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/regression/adder.json")).unwrap();
+    SerializedEGraph::from_Data(&data).unwrap()
+}
+
+fn options(pre_mode: PreMode) -> Options {
+    Options {
+        extractor: "faster-greedy-dag".to_string(),
+        pre_mode,
+        out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("pipeline"),
+        name: "adder".to_string(),
+        ..Options::default()
+    }
+}
+
+#[test]
+fn test_heuristic_only() {
+    let egraph = adder();
+    let output = pipeline::run(&egraph, options(PreMode::Heuristic)).unwrap();
+    output.result.check(&egraph);
+    assert!(output.heuristic.is_some());
+    assert_eq!(output.timings.lp, 0.0);
+    assert_eq!(output.timings.solve, 0.0);
+}
+
+/// The pruned LP and its warm start, without a solver.
+#[test]
+fn test_warm_lp() {
+    let egraph = adder();
+    let output = pipeline::run(&egraph, options(PreMode::WarmLp)).unwrap();
+    assert!(Path::new(&output.files.lp_file).exists());
+    assert!(Path::new(&output.files.mst_file).exists());
+    assert!(output.files.lp_file.starts_with(env!("CARGO_TARGET_TMPDIR")));
+}
+
+/// The same LP, without touching the output directory.
+#[test]
+fn test_in_memory_lp() {
+    let egraph = adder();
+    let options = Options { out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("in-memory"), in_memory: true, ..options(PreMode::WarmLp) };
+    let output = pipeline::run(&egraph, options.clone()).unwrap();
+    let model = output.model.unwrap();
+    assert!(model.hint_violations().is_empty());
+    assert!(model.to_lp().contains("N_"));
+    assert!(!options.out_dir.exists());
+
+    let solve = Options { pre_mode: PreMode::WarmSolve, ..options };
+    assert!(pipeline::run(&egraph, solve).is_err());
+}
+
+/// The steps of `WarmLp` one at a time.
+#[test]
+fn test_booster_steps() {
+    let egraph = adder();
+    let options = Options { out_dir: PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("booster"), in_memory: true, ..options(PreMode::WarmLp) };
+    let mut booster = Booster::new(&egraph, options).unwrap();
+    let heuristic = booster.run_heuristic().unwrap();
+    heuristic.check(&egraph);
+    let model = booster.generate_lp().unwrap();
+    assert!(model.hint_violations().is_empty());
+    assert_eq!(booster.timings().solve, 0.0);
+
+    let output = booster.into_output(heuristic.clone());
+    assert_eq!(output.heuristic.unwrap().choices, heuristic.choices);
+    assert!(output.model.is_some());
+}
+
+#[test]
+fn test_unknown_extractor() {
+    let options = Options { extractor: "no-such-extractor".to_string(), ..options(PreMode::Heuristic) };
+    assert!(pipeline::run(&adder(), options).is_err());
+}
+
+/// The frozen choices and the extra warm starts survive the heuristic and the
+/// pruning.
+#[test]
+fn test_booster_frozen() {
+    let egraph = adder();
+    let mut booster = Booster::new(&egraph, Options { in_memory: true, ..options(PreMode::WarmLp) }).unwrap();
+    let heuristic = booster.run_heuristic().unwrap();
+    let (class, other) = egraph
+        .classes()
+        .values()
+        .find_map(|class| Some((class.id, *class.nodes.iter().find(|&node| heuristic.choices.get(&class.id) != Some(node))?)))
+        .unwrap();
+
+    let frozen = [(class, other)].into_iter().collect();
+    let mut booster = Booster::new(&egraph, Options { in_memory: true, frozen, ..options(PreMode::WarmLp) }).unwrap();
+    let result = booster.run_heuristic().unwrap();
+    assert_eq!(result.choices.get(&class), Some(&other));
+    assert!(!booster.pruned(1.0).contains(&other));
+    assert!(booster.generate_lp().unwrap().hint_violations().is_empty());
+
+    let mut booster = Booster::new(&egraph, Options { in_memory: true, ..options(PreMode::WarmLp) }).unwrap();
+    booster.set_heuristic(heuristic, true);
+    booster.add_start(result.clone());
+    let pruned = booster.pruned(1.0);
+    assert!(result.choices.values().all(|node| !pruned.contains(node)));
+}
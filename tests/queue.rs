@@ -0,0 +1,81 @@
+use extraction_gym::queue::{Frontier, UniquePriorityQueue, UniqueQueue, Worklist};
+
+fn drain<T>(pop: impl FnMut() -> Option<T>) -> Vec<T> {
+    std::iter::from_fn(pop).collect()
+}
+
+#[test]
+fn test_unique_queue_dedup() {
+    let mut queue = UniqueQueue::default();
+    queue.extend([1, 2, 1, 3, 2]);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.pop(), Some(1));
+    // Popped, so pending again.
+    queue.insert(1);
+    assert_eq!(drain(|| queue.pop()), [2, 3, 1]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_unique_queue_batches() {
+    let mut queue = UniqueQueue::with_batch_size(2);
+    queue.extend([4, 0, 4, 1, 2, 3]);
+    assert_eq!(queue.pop_batch(), [4, 0]);
+    queue.insert(4);
+    queue.insert(1);
+    assert_eq!(queue.pop_batch(), [1, 2]);
+    assert_eq!(queue.pop_batch(), [3, 4]);
+    assert!(queue.pop_batch().is_empty());
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_priority_queue_lowers_only() {
+    let mut queue = UniquePriorityQueue::default();
+    queue.insert('a', 5);
+    queue.insert('b', 3);
+    queue.insert('c', 3);
+    // Lowered below `b` and `c`, then not raised again.
+    queue.insert('a', 1);
+    queue.insert('a', 9);
+    // Not raised, and still behind `b` of the same priority.
+    queue.insert('c', 4);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(drain(|| queue.pop()), ['a', 'b', 'c']);
+}
+
+#[test]
+fn test_priority_queue_skips_stale_entries() {
+    let mut queue = UniquePriorityQueue::default();
+    for priority in [8, 6, 4, 2] {
+        queue.insert('a', priority);
+    }
+    queue.insert('b', 3);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop(), Some('a'));
+    assert_eq!(queue.len(), 1);
+    // The entries of `a` at 4, 6 and 8 are left in the heap and skipped.
+    assert_eq!(queue.pop(), Some('b'));
+    assert_eq!(queue.pop(), None);
+    assert!(queue.is_empty());
+
+    // Pending again after a pop, at any priority.
+    queue.insert('a', 7);
+    queue.insert('c', 6);
+    assert_eq!(drain(|| queue.pop()), ['c', 'a']);
+}
+
+#[test]
+fn test_worklist_frontiers() {
+    let mut fifo = Worklist::new(Frontier::Fifo);
+    let mut best_first = Worklist::new(Frontier::BestFirst);
+    for worklist in [&mut fifo, &mut best_first] {
+        worklist.extend([1, 2], 5);
+        worklist.insert(3, 0);
+        worklist.insert(1, 9);
+        assert_eq!(worklist.len(), 3);
+    }
+    assert_eq!(drain(|| fifo.pop()), [1, 2, 3]);
+    assert_eq!(drain(|| best_first.pop()), [3, 1, 2]);
+    assert!(fifo.is_empty() && best_first.is_empty());
+}
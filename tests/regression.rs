@@ -0,0 +1,12 @@
+use std::process::Command;
+
+/// The bundled golden costs, through the `regression` subcommand.
+#[test]
+fn test_golden_costs() {
+    let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/regression/golden.json");
+    let status = Command::new(env!("CARGO_BIN_EXE_extraction_tool"))
+        .args(["regression", "--golden", golden])
+        .status()
+        .expect("Failed to run extraction_tool");
+    assert!(status.success());
+}
@@ -0,0 +1,39 @@
+use e_boost::repl::Session;
+use extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor;
+use extraction_gym::Extractor;
+
+mod common;
+
+/// Swapping every alternative of the root class in turn keeps the DAG cost the
+/// session reports equal to the one computed from scratch, and undo restores it.
+#[test]
+fn test_repl_swap_and_undo() {
+    let (_, egraph) = common::mul4();
+    let roots = &egraph.root_eclasses;
+    let result = FasterGreedyDagExtractor::default().extract(&egraph, roots);
+    let before = result.dag_cost(&egraph, roots);
+    let mut session = Session::new(&egraph, result);
+
+    assert_eq!(session.execute("cost dag").unwrap().trim(), before.to_string());
+    assert!(session.execute("class 999999").is_err());
+    assert!(session.execute("frobnicate").is_err());
+    assert!(session.execute("undo").is_err());
+
+    let mut swapped = 0;
+    for class in egraph.classes().values().filter(|class| class.nodes.len() > 1) {
+        let why = session.execute(&format!("why-chosen {}", class.id)).unwrap();
+        assert!(why.contains(&session.result().choices[&class.id].to_string()));
+        for nid in &class.nodes {
+            if session.execute(&format!("swap {} {}", class.id, nid)).is_ok() {
+                session.result().check(&egraph);
+                assert_eq!(session.execute("cost dag").unwrap().trim(), session.result().dag_cost(&egraph, roots).to_string());
+                swapped += 1;
+            }
+        }
+    }
+    assert!(swapped > 0);
+    for _ in 0..swapped {
+        let _ = session.execute("undo");
+    }
+    assert_eq!(session.result().dag_cost(&egraph, roots), before);
+}
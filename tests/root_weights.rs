@@ -0,0 +1,44 @@
+use egraph_serialize::{Data, EGraph, EGraphBuilder};
+use extraction_gym::root_weights::RootWeights;
+use extraction_gym::ExtractionResult;
+
+/// Two roots sharing the class `shared`, the first of weight 4. The first root
+/// has a dearer alternative `alt` over `t`, the second one an input `u` of its
+/// own.
+#[test]
+fn test_shared_subgraph() {
+    let mut builder = EGraphBuilder::new();
+    let (a, b, t, u) = (builder.leaf("a"), builder.leaf("b"), builder.leaf("t"), builder.leaf("u"));
+    let shared = builder.term("and", [a, b]);
+    let (first, second) = (builder.new_class(), builder.new_class());
+    builder.add_node(first, "out1", [shared]);
+    builder.add_node_with_cost(first, "alt", [t], 10.0);
+    builder.add_node(second, "out2", [shared, u]);
+    let egraph = builder.root(first).root(second).build().unwrap();
+    let mut data = Data { nodes: egraph.nodes.clone(), root_eclasses: egraph.root_eclasses.clone(), class_data: egraph.class_data.clone() };
+
+    let root_weights = RootWeights { weights: [(first, 4.0)].into_iter().collect() };
+    let class_weights = root_weights.class_weights(&data);
+    // The shared classes take the larger weight.
+    for class in [first, shared, a, b] {
+        assert_eq!(class_weights[&class], 4.0);
+    }
+    for class in [second, u] {
+        assert_eq!(class_weights[&class], 1.0);
+    }
+    // Reached through an alternative no extraction picks.
+    assert_eq!(class_weights[&t], 4.0);
+
+    let costs = root_weights.apply(&mut data);
+    let weighted = EGraph::from_Data(&data).unwrap();
+    let mut result = ExtractionResult::new_empty();
+    for class in weighted.classes().values() {
+        let node = class.nodes.iter().find(|node| weighted[*node].op != "alt").unwrap();
+        result.choose(class.id, *node);
+    }
+    let roots = &weighted.root_eclasses;
+    assert_eq!(result.dag_cost(&weighted, roots).into_inner(), 4.0 * 4.0 + 2.0);
+    let unweighted = RootWeights::unweighted(&weighted, &costs);
+    assert_eq!(result.dag_cost(&unweighted, roots).into_inner(), 6.0);
+    assert!(unweighted.nodes.values().all(|node| node.cost == egraph[&node.id].cost));
+}
@@ -0,0 +1,32 @@
+use extraction_gym::faster_greedy_dag::FasterGreedyDagExtractor;
+use extraction_gym::{sensitivity, Extractor};
+
+mod common;
+
+#[test]
+fn test_exclude_node() {
+    let (_, egraph) = common::mul4();
+    let roots = &egraph.root_eclasses;
+    let result = FasterGreedyDagExtractor::default().extract(&egraph, roots);
+    let active = result.activate_nodes(&egraph, roots);
+
+    // A node the extraction doesn't use changes nothing.
+    let unused = egraph.nodes.keys().find(|nid| !active.contains(*nid)).unwrap();
+    let exclusion = sensitivity::exclude_node(&egraph, &result, unused);
+    assert_eq!(exclusion.delta.into_inner(), 0.0);
+    assert!(exclusion.changed.is_empty());
+
+    let mut tested = 0;
+    for nid in active.iter().filter(|nid| egraph.nid_to_class(nid).nodes.len() > 1) {
+        let exclusion = sensitivity::exclude_node(&egraph, &result, nid);
+        if !exclusion.feasible() {
+            continue;
+        }
+        tested += 1;
+        assert!(exclusion.result.is_valid(&egraph));
+        assert!(!exclusion.result.activate_nodes(&egraph, roots).contains(nid));
+        assert!(exclusion.changed.contains(egraph.nid_to_cid(nid)));
+        assert_eq!(exclusion.dag_cost, exclusion.result.dag_cost(&egraph, roots));
+    }
+    assert!(tested > 0);
+}
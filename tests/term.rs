@@ -0,0 +1,32 @@
+use egraph_serialize::{EGraphBuilder, NodeId};
+use extraction_gym::ExtractionResult;
+
+/// `(+ (* x y) (* x y))`, with the product shared.
+#[test]
+fn test_to_term() {
+    let mut builder = EGraphBuilder::new();
+    let x = builder.leaf("x");
+    let y = builder.leaf("y");
+    let product = builder.term("*", [x, y]);
+    let sum = builder.term("+", [product, product]);
+    let egraph = builder.root(sum).build().unwrap();
+    let choices = [x, y, product, sum].into_iter().map(|cid| (cid, NodeId::from((cid.0, 0)))).collect();
+    let result = ExtractionResult::new(choices);
+
+    let term = result.to_term(&egraph, sum);
+    assert_eq!(term.nodes.len(), 4);
+    assert_eq!(term.root().op, "+");
+    assert_eq!(term.to_string(), "(+ (* x y) (* x y))");
+    assert_eq!(term.shared().to_string(), "(let t0 (* x y))\n(+ t0 t0)");
+    assert_eq!(result.to_term(&egraph, x).shared().to_string(), "x");
+}
+
+#[test]
+#[should_panic(expected = "has no choice")]
+fn test_to_term_missing_choice() {
+    let mut builder = EGraphBuilder::new();
+    let x = builder.leaf("x");
+    let f = builder.term("f", [x]);
+    let egraph = builder.root(f).build().unwrap();
+    ExtractionResult::new([(f, NodeId::from((f.0, 0)))].into_iter().collect()).to_term(&egraph, f);
+}
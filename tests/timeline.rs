@@ -0,0 +1,131 @@
+use e_boost::timeline::{LogFormat, Point, Timeline};
+
+fn point(time: f64, incumbent: Option<f64>, bound: Option<f64>, gap: Option<f64>) -> Point {
+    Point { time, incumbent, bound, gap }
+}
+
+fn assert_points(format: LogFormat, log: &str, expected: &[Option<Point>]) {
+    let points: Vec<Option<Point>> = log.lines().map(|line| format.parse_line(line)).collect();
+    assert_eq!(points.len(), expected.len());
+    for (line, (point, expected)) in log.lines().zip(points.iter().zip(expected)) {
+        match (point, expected) {
+            (Some(point), Some(expected)) => {
+                assert_eq!((point.time, point.incumbent, point.bound), (expected.time, expected.incumbent, expected.bound), "{}", line);
+                match (point.gap, expected.gap) {
+                    (Some(gap), Some(expected)) => assert!((gap - expected).abs() < 1e-4, "{}: gap {}", line, gap),
+                    (gap, expected) => assert_eq!(gap, expected, "{}", line),
+                }
+            }
+            (point, expected) => assert_eq!(point.is_some(), expected.is_some(), "{}", line),
+        }
+    }
+}
+
+/// Gurobi's node log, with the heuristic (`H`) and node (`*`) incumbents and
+/// `-` before the first one.
+#[test]
+fn test_gurobi() {
+    let log = "\
+    Nodes    |    Current Node    |     Objective Bounds      |     Work
+ Expl Unexpl |  Obj  Depth IntInf | Incumbent    BestBd   Gap | It/Node Time
+
+     0     0 2210.50000    0  412          - 2210.50000      -     -    0s
+H    0     0                    2385.0000000 2210.50000  7.32%     -    0s
+     0     2 2210.50000    0  412 2385.00000 2210.50000  7.32%     -    1s
+*   12     4              10    2301.0000000 2250.00000  2.22%  45.3    3s
+Explored 40 nodes (5120 simplex iterations) in 4.12 seconds (2.01 work units)";
+    assert_points(
+        LogFormat::Gurobi,
+        log,
+        &[
+            None,
+            None,
+            None,
+            Some(point(0.0, None, Some(2210.5), None)),
+            Some(point(0.0, Some(2385.0), Some(2210.5), Some(0.0732))),
+            Some(point(1.0, Some(2385.0), Some(2210.5), Some(0.0732))),
+            Some(point(3.0, Some(2301.0), Some(2250.0), Some(0.0222))),
+            None,
+        ],
+    );
+}
+
+#[test]
+fn test_highs() {
+    let log = "\
+        Nodes      |    B&B Tree     |            Objective Bounds              |  Dynamic Constraints |       Work      
+     Proc. InQueue |  Leaves   Expl. | BestBound       BestSol              Gap |   Cuts   InLp Confl. | LpIters     Time
+
+         0       0         0   0.00%   2210.5          inf                  inf        0      0      0         0     0.1s
+ T       0       0         0   0.00%   2210.5          2385               7.32%        0      0      0       412     0.4s
+         5       2         1  50.00%   2250            2301               2.22%      120     40     12      1500     2.3s
+Solving report";
+    assert_points(
+        LogFormat::Highs,
+        log,
+        &[
+            None,
+            None,
+            None,
+            Some(point(0.1, None, Some(2210.5), None)),
+            Some(point(0.4, Some(2385.0), Some(2210.5), Some(0.0732))),
+            Some(point(2.3, Some(2301.0), Some(2250.0), Some(0.0222))),
+            None,
+        ],
+    );
+}
+
+/// CBC reports an incumbent of 1e50 until it has one.
+#[test]
+fn test_cbc() {
+    let log = "\
+Cbc0038I Full problem 1234 rows 567 columns, reduced to 10 rows 12 columns
+Cbc0010I After 0 nodes, 1 on tree, 1e+50 best solution, best possible 2210.5 (0.45 seconds)
+Cbc0012I Integer solution of 2385 found by DiveCoefficient after 412 iterations and 0 nodes (0.61 seconds)
+Cbc0010I After 100 nodes, 12 on tree, 2301 best solution, best possible 2250 (3.10 seconds)
+Cbc0004I Integer solution of 2301 found after 1500 iterations and 112 nodes (3.35 seconds)";
+    assert_points(
+        LogFormat::Cbc,
+        log,
+        &[
+            None,
+            Some(point(0.45, None, Some(2210.5), None)),
+            Some(point(0.61, Some(2385.0), None, None)),
+            Some(point(3.1, Some(2301.0), Some(2250.0), Some(0.0222))),
+            Some(point(3.35, Some(2301.0), None, None)),
+        ],
+    );
+}
+
+/// The wrappers' incumbent lines, with and without the bound.
+#[test]
+fn test_incumbents() {
+    let log = "\
+0.523: 2385
+1.3: 2301 2250
+2.75 2260 2260
+4.1: 0 0
+Optimal objective 2260";
+    assert_points(
+        LogFormat::Incumbents,
+        log,
+        &[
+            Some(point(0.523, Some(2385.0), None, None)),
+            Some(point(1.3, Some(2301.0), Some(2250.0), Some(0.0222))),
+            Some(point(2.75, Some(2260.0), Some(2260.0), Some(0.0))),
+            Some(point(4.1, Some(0.0), Some(0.0), Some(0.0))),
+            None,
+        ],
+    );
+}
+
+/// Points carry the last incumbent and bound forward.
+#[test]
+fn test_from_log() {
+    let log = "\
+Cbc0012I Integer solution of 2385 found by DiveCoefficient after 412 iterations and 0 nodes (0.61 seconds)
+Cbc0010I After 100 nodes, 12 on tree, 1e+50 best solution, best possible 2250 (3.10 seconds)";
+    let timeline = Timeline::from_log("cbc", LogFormat::Cbc, false, log);
+    let last = timeline.points[1];
+    assert_eq!((last.time, last.incumbent, last.bound), (3.1, Some(2385.0), Some(2250.0)));
+}
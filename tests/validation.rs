@@ -0,0 +1,74 @@
+use egraph_serialize::{ClassId, EGraph, EGraphBuilder, NodeId, ValidationError};
+use extraction_gym::ExtractionResult;
+use indexmap::IndexMap;
+
+/// `f(x, y)` with `x` also `g(f)`, which closes a cycle.
+fn egraph() -> (EGraph, [ClassId; 3]) {
+    let mut builder = EGraphBuilder::new();
+    let x = builder.leaf("x");
+    let y = builder.leaf("y");
+    let f = builder.term("f", [x, y]);
+    builder.add_node(x, "g", [f]);
+    (builder.root(f).build().unwrap(), [x, y, f])
+}
+
+fn result(choices: &[(ClassId, (u32, u32))]) -> ExtractionResult {
+    ExtractionResult::new(choices.iter().map(|(cid, nid)| (*cid, NodeId::from(*nid))).collect::<IndexMap<_, _>>())
+}
+
+#[test]
+fn test_validate_reports_the_offending_choice() {
+    let (egraph, [x, y, f]) = egraph();
+    let f_node = NodeId::from((f.0, 0));
+
+    assert_eq!(result(&[(f, (f.0, 0)), (x, (x.0, 0)), (y, (y.0, 0))]).validate(&egraph), Ok(()));
+    assert_eq!(result(&[]).validate(&egraph), Err(ValidationError::MissingChoice { class: f, parent: None }));
+    assert_eq!(
+        result(&[(f, (f.0, 0)), (x, (x.0, 0))]).validate(&egraph),
+        Err(ValidationError::MissingChoice { class: y, parent: Some(f_node) })
+    );
+    assert_eq!(
+        result(&[(f, (f.0, 0)), (x, (y.0, 0))]).validate(&egraph),
+        Err(ValidationError::WrongClass { class: x, node: NodeId::from((y.0, 0)) })
+    );
+    assert_eq!(
+        result(&[(f, (f.0, 0)), (x, (x.0, 7))]).validate(&egraph),
+        Err(ValidationError::UnknownNode(NodeId::from((x.0, 7))))
+    );
+    let cyclic = result(&[(f, (f.0, 0)), (x, (x.0, 1)), (y, (y.0, 0))]);
+    assert_eq!(cyclic.validate(&egraph), Err(ValidationError::Cycle(vec![f, x])));
+    assert!(!cyclic.is_valid(&egraph));
+
+    let mut rootless = egraph.clone();
+    rootless.root_eclasses.clear();
+    assert_eq!(result(&[]).validate(&rootless), Err(ValidationError::NoRoots));
+}
+
+#[test]
+fn test_fallible_egraph_access() {
+    let (mut egraph, [x, _, f]) = egraph();
+    let node = egraph[&NodeId::from((f.0, 0))].clone();
+
+    assert_eq!(egraph.try_add_node(node.id, node.clone()), Err(ValidationError::DuplicateNode(node.id)));
+    assert_eq!(egraph.get_node(&node.id), Ok(&node));
+    assert_eq!(egraph.get_node(&NodeId::from((9, 0))), Err(ValidationError::UnknownNode(NodeId::from((9, 0)))));
+    assert_eq!(egraph.get_class(&x).map(|class| class.nodes.len()), Ok(2));
+    assert_eq!(egraph.get_class(&ClassId::from(9)).err(), Some(ValidationError::UnknownClass(ClassId::from(9))));
+    assert_eq!(egraph.try_add_node(NodeId::from((9, 0)), node), Ok(()));
+}
+
+#[test]
+fn test_validate_egraph() {
+    let (mut egraph, [_, _, f]) = egraph();
+    assert_eq!(egraph.validate(), Ok(()));
+
+    let mut dangling = egraph.clone();
+    dangling.nodes[0].children.push(ClassId::from(9));
+    assert_eq!(dangling.validate(), Err(ValidationError::UnknownClass(ClassId::from(9))));
+    egraph.root_eclasses = vec![ClassId::from(8)];
+    assert_eq!(egraph.validate(), Err(ValidationError::UnknownClass(ClassId::from(8))));
+    egraph.root_eclasses.clear();
+    assert_eq!(egraph.validate(), Err(ValidationError::NoRoots));
+    egraph.root_eclasses.push(f);
+    assert_eq!(egraph.validate(), Ok(()));
+}